@@ -0,0 +1,269 @@
+use crate::Result;
+use crate::config::AdminConfig;
+use crate::storage::StorageBackend;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 管理员令牌的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AdminScope {
+    /// 查看房间列表和房间详情
+    #[serde(rename = "rooms:read")]
+    RoomsRead,
+    /// 删除、批量清理房间
+    #[serde(rename = "rooms:manage")]
+    RoomsManage,
+    /// 设置玩家的每日游戏上限
+    #[serde(rename = "players:manage")]
+    PlayersManage,
+    /// 向所有房间和大厅发布公告
+    #[serde(rename = "server:announce")]
+    ServerAnnounce,
+    /// 查看管理操作审计日志
+    #[serde(rename = "audit:read")]
+    AuditRead,
+}
+
+impl std::str::FromStr for AdminScope {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "rooms:read" => Ok(AdminScope::RoomsRead),
+            "rooms:manage" => Ok(AdminScope::RoomsManage),
+            "players:manage" => Ok(AdminScope::PlayersManage),
+            "server:announce" => Ok(AdminScope::ServerAnnounce),
+            "audit:read" => Ok(AdminScope::AuditRead),
+            other => Err(format!("未知的权限范围: {}", other)),
+        }
+    }
+}
+
+/// 管理员令牌，与玩家会话相互独立
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminToken {
+    pub token: String,
+    /// 签发令牌的管理员用户名
+    pub username: String,
+    pub scopes: Vec<AdminScope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl AdminToken {
+    /// 令牌是否包含指定权限范围
+    pub fn allows(&self, scope: AdminScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+/// 写入审计日志的管理操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdminAction {
+    IssueToken,
+    RevokeToken,
+    DeleteRoom,
+    CleanupRooms,
+    KickPlayer,
+    EndGame,
+    FreezeRoom,
+    UnfreezeRoom,
+    Announce,
+    SetPlayLimit,
+    MutePlayer,
+    UnmutePlayer,
+    ViewRoomState,
+}
+
+/// 一条管理操作审计记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// 执行操作的管理员用户名
+    pub admin: String,
+    pub action: AdminAction,
+    /// 操作对象，如房间ID、玩家ID，全局操作为空
+    pub target: String,
+    /// 操作参数和结果摘要
+    #[serde(default)]
+    pub detail: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 限流计数表最多保留的键数，防止大量不同的键撑大内存
+const RATE_LIMIT_MAX_ENTRIES: usize = 10_000;
+
+/// 固定时间窗口计数器
+struct RateWindow {
+    started_at: Instant,
+    count: u32,
+}
+
+/// 管理员令牌的签发、校验与管理接口限流
+pub struct AdminAuth {
+    storage: Arc<dyn StorageBackend>,
+    rate_limits: DashMap<String, RateWindow>,
+    config: AdminConfig,
+}
+
+impl AdminAuth {
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        AdminAuth {
+            storage,
+            rate_limits: DashMap::new(),
+            config: crate::config::Config::get().admin.clone(),
+        }
+    }
+
+    /// 为管理员签发新令牌，未指定有效期时使用默认值，超过上限时截断
+    pub async fn issue_token(
+        &self,
+        username: &str,
+        scopes: Vec<AdminScope>,
+        ttl_minutes: Option<u64>,
+    ) -> Result<AdminToken> {
+        if scopes.is_empty() {
+            return Err(crate::Error::Auth("至少需要一个权限范围".to_string()));
+        }
+
+        let ttl_minutes = ttl_minutes
+            .unwrap_or(self.config.token_ttl_minutes)
+            .clamp(1, self.config.max_token_ttl_minutes.max(1));
+        let now = Utc::now();
+        let token = AdminToken {
+            token: format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple()),
+            username: username.to_string(),
+            scopes,
+            created_at: now,
+            expires_at: now + chrono::Duration::minutes(ttl_minutes as i64),
+        };
+
+        self.storage
+            .save_admin_token(&token)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        Ok(token)
+    }
+
+    /// 校验令牌及其权限范围，只有存在的令牌才计入该令牌的请求次数，
+    /// 逐个猜测令牌由按客户端IP的限流拦截
+    pub async fn authorize(&self, token: &str, scope: AdminScope) -> Result<AdminToken> {
+        let admin_token = self
+            .storage
+            .get_admin_token(token)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?
+            .ok_or_else(|| crate::Error::Auth("无效的管理员令牌".to_string()))?;
+
+        if admin_token.is_expired() {
+            return Err(crate::Error::Auth("管理员令牌已过期".to_string()));
+        }
+
+        self.check_rate_limit(&token_rate_key(token))?;
+
+        // 签发后被移出管理员列表的用户，其令牌立即失效
+        if !crate::config::Config::get().is_admin(&admin_token.username) {
+            return Err(crate::Error::Auth("令牌所属用户已不是管理员".to_string()));
+        }
+
+        if !admin_token.allows(scope) {
+            return Err(crate::Error::Auth("管理员令牌权限不足".to_string()));
+        }
+
+        Ok(admin_token)
+    }
+
+    /// 吊销令牌
+    pub async fn revoke(&self, token: &str) -> Result<()> {
+        let revoked = self
+            .storage
+            .get_admin_token(token)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        if revoked.is_some() {
+            self.check_rate_limit(&token_rate_key(token))?;
+        }
+        self.storage
+            .delete_admin_token(token)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        self.rate_limits.remove(&token_rate_key(token));
+
+        if let Some(revoked) = revoked {
+            self.audit(
+                &revoked.username,
+                AdminAction::RevokeToken,
+                "",
+                serde_json::json!({ "scopes": revoked.scopes }),
+            )
+            .await;
+        }
+        Ok(())
+    }
+
+    /// 追加一条审计记录，写入失败只记录日志，不影响已完成的操作
+    pub async fn audit(&self, admin: &str, action: AdminAction, target: &str, detail: serde_json::Value) {
+        let record = AuditRecord {
+            admin: admin.to_string(),
+            action,
+            target: target.to_string(),
+            detail,
+            created_at: Utc::now(),
+        };
+        if let Err(e) = self
+            .storage
+            .append_audit_record(&record, self.config.audit_log_max_len)
+            .await
+        {
+            tracing::error!("写入管理操作审计日志失败 {:?}: {}", record, e);
+        }
+    }
+
+    /// 按键（客户端IP、有效令牌或用户名）限制管理接口请求频率
+    pub fn check_rate_limit(&self, key: &str) -> Result<()> {
+        let window = Duration::from_secs(self.config.rate_limit_window);
+        let now = Instant::now();
+
+        // 计数表达到上限时才清理过期窗口，清理后仍然已满则拒绝新的键
+        if self.rate_limits.len() >= RATE_LIMIT_MAX_ENTRIES && !self.rate_limits.contains_key(key) {
+            self.rate_limits
+                .retain(|_, entry| now.duration_since(entry.started_at) <= window);
+            if self.rate_limits.len() >= RATE_LIMIT_MAX_ENTRIES {
+                return Err(crate::Error::Auth("管理接口请求过于频繁，请稍后再试".to_string()));
+            }
+        }
+
+        let mut entry = self
+            .rate_limits
+            .entry(key.to_string())
+            .or_insert(RateWindow {
+                started_at: now,
+                count: 0,
+            });
+
+        // 窗口过期后重新计数
+        if now.duration_since(entry.started_at) > window {
+            entry.started_at = now;
+            entry.count = 0;
+        }
+
+        if entry.count >= self.config.rate_limit_max_requests {
+            return Err(crate::Error::Auth("管理接口请求过于频繁，请稍后再试".to_string()));
+        }
+        entry.count += 1;
+        Ok(())
+    }
+}
+
+/// 有效令牌在限流计数表中的键
+fn token_rate_key(token: &str) -> String {
+    format!("token:{}", token)
+}
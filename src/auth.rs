@@ -0,0 +1,427 @@
+use crate::Result;
+use crate::config::OAuthProviderConfig;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tracing::debug;
+use url::Url;
+
+/// 摸鱼派登录方式的标识
+pub const FISHPI_PROVIDER_ID: &str = "fishpi";
+
+/// 外部登录方式确认的用户身份
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    /// 用户在该登录方式中的唯一ID
+    pub external_id: String,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub avatar: Option<String>,
+}
+
+/// 登录方式：负责跳转到外部登录页、校验回调并取得用户身份
+#[axum::async_trait]
+pub trait AuthProvider: Send + Sync {
+    /// 登录方式标识，出现在登录和回调地址中
+    fn id(&self) -> &str;
+
+    /// 展示给用户的名称
+    fn display_name(&self) -> &str;
+
+    /// 生成跳转到外部登录页的地址，登录完成后回到 redirect_uri 并带回 state
+    fn login_url(&self, redirect_uri: &str, state: &str) -> Result<String>;
+
+    /// 校验回调参数，返回登录用户的身份
+    async fn verify_callback(&self, params: &HashMap<String, String>, redirect_uri: &str) -> Result<ExternalIdentity>;
+
+    /// 按外部ID重新获取用户资料，用户信息缓存丢失时使用；不支持时返回 None，用户需要重新登录
+    async fn fetch_identity(&self, _external_id: &str) -> Result<Option<ExternalIdentity>> {
+        Ok(None)
+    }
+
+    /// 外部身份对应的本地用户ID，默认加上登录方式前缀，避免不同登录方式的ID冲突
+    fn local_user_id(&self, external_id: &str) -> String {
+        format!("{}:{}", self.id(), external_id)
+    }
+
+    /// 外部身份对应的本地用户名，默认加上登录方式前缀，避免冒用管理员用户名
+    fn local_username(&self, username: &str) -> String {
+        format!("{}:{}", self.id(), username)
+    }
+}
+
+fn http_client() -> Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36")
+        .build()
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))
+}
+
+/// 摸鱼派用户信息响应
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct FishpiUserInfoResponse {
+    pub msg: String,
+    pub code: i32,
+    pub data: FishpiUserInfoData,
+}
+
+#[derive(Debug, Deserialize)]
+struct FishpiUserInfoData {
+    #[serde(rename = "userAvatarURL")]
+    pub user_avatar_url: Option<String>,
+    #[serde(rename = "userNickname")]
+    pub user_nickname: Option<String>,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+}
+
+/// 摸鱼派 OpenID 2.0 登录，本地用户ID和用户名直接使用摸鱼派的ID和用户名
+pub struct FishpiProvider {
+    base_url: String,
+}
+
+impl FishpiProvider {
+    pub fn new() -> Self {
+        FishpiProvider {
+            base_url: "https://fishpi.cn".to_string(),
+        }
+    }
+
+    /// 检查response_nonce是否有效
+    fn is_response_nonce_valid(&self, response_nonce: &str) -> bool {
+        // response_nonce格式: 2025-06-19T03:52:20Z8241ed4a70
+        if let Some(timestamp_str) = response_nonce.split('Z').next()
+            && let Ok(timestamp) = DateTime::parse_from_rfc3339(&format!("{}Z", timestamp_str))
+        {
+            let now = Utc::now();
+            let diff = now.signed_duration_since(timestamp.naive_utc().and_utc());
+
+            // 检查是否在5分钟内
+            if diff.num_minutes() <= 5 {
+                debug!("response_nonce时间有效，距离现在{}分钟", diff.num_minutes());
+                return true;
+            } else {
+                debug!("response_nonce已过期，距离现在{}分钟", diff.num_minutes());
+                return false;
+            }
+        }
+
+        debug!("无法解析response_nonce时间戳: {}", response_nonce);
+        false
+    }
+
+    /// 验证OpenID签名
+    async fn verify_signature(&self, params: &HashMap<String, String>) -> Result<()> {
+        // 构建验证请求参数
+        let mut verify_params = HashMap::new();
+
+        // 获取openid.signed参数，确定哪些参数被签名了
+        let signed_params = if let Some(signed_str) = params.get("openid.signed") {
+            signed_str.split(',').collect::<Vec<&str>>()
+        } else {
+            return Err(crate::Error::Auth("缺少openid.signed参数".to_string()));
+        };
+
+        // 添加所有必需的参数
+        verify_params.insert(
+            "openid.ns".to_string(),
+            "http://specs.openid.net/auth/2.0".to_string(),
+        );
+        verify_params.insert(
+            "openid.mode".to_string(),
+            "check_authentication".to_string(),
+        );
+
+        // 添加所有被签名的参数
+        for (key, value) in params {
+            if key == "openid.signed" || key == "openid.sig" {
+                verify_params.insert(key.clone(), value.clone());
+            } else if key == "openid.mode" {
+                // 跳过，已经设置为check_authentication
+                continue;
+            } else if let Some(param_name) = key.strip_prefix("openid.") {
+                // 检查这个参数是否在签名列表中
+                if signed_params.contains(&param_name) {
+                    verify_params.insert(key.clone(), value.clone());
+                }
+            }
+        }
+
+        // 发送验证请求到摸鱼派
+        let verify_url = format!("{}/openid/verify", self.base_url);
+
+        // 构建请求体，使用JSON格式
+        let query_string = serde_json::to_string(&verify_params)
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        // 发送POST请求
+        let response = http_client()?
+            .post(&verify_url)
+            .header("Content-Type", "application/json")
+            .body(query_string)
+            .send()
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        if !response.status().is_success() {
+            return Err(crate::Error::Auth(format!(
+                "验证请求失败，状态码: {}",
+                response.status()
+            )));
+        }
+
+        let response_text = response
+            .text()
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        // 解析响应
+        let is_valid = response_text
+            .lines()
+            .find_map(|line| line.strip_prefix("is_valid:"))
+            .is_some_and(|value| value.trim() == "true");
+
+        if is_valid {
+            Ok(())
+        } else {
+            Err(crate::Error::Auth("签名验证失败".to_string()))
+        }
+    }
+}
+
+impl Default for FishpiProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[axum::async_trait]
+impl AuthProvider for FishpiProvider {
+    fn id(&self) -> &str {
+        FISHPI_PROVIDER_ID
+    }
+
+    fn display_name(&self) -> &str {
+        "摸鱼派"
+    }
+
+    /// 生成摸鱼派登录URL，OpenID 2.0 没有 state 参数，改为附加在 return_to 中
+    fn login_url(&self, redirect_uri: &str, state: &str) -> Result<String> {
+        let mut return_to = Url::parse(redirect_uri).map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+        return_to.query_pairs_mut().append_pair("state", state);
+        let realm = crate::config::Config::get().auth.domain.clone();
+
+        let mut url = Url::parse(&format!("{}/openid/login", self.base_url))
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        // 使用url crate的query_pairs_mut来确保正确的URL编码
+        url.query_pairs_mut()
+            .append_pair("openid.ns", "http://specs.openid.net/auth/2.0")
+            .append_pair("openid.mode", "checkid_setup")
+            .append_pair("openid.return_to", return_to.as_str())
+            .append_pair("openid.realm", &realm)
+            .append_pair(
+                "openid.claimed_id",
+                "http://specs.openid.net/auth/2.0/identifier_select",
+            )
+            .append_pair(
+                "openid.identity",
+                "http://specs.openid.net/auth/2.0/identifier_select",
+            );
+
+        debug!("生成的登录URL: {}", url);
+
+        Ok(url.to_string())
+    }
+
+    /// 验证摸鱼派OpenID响应，成功后获取用户资料
+    async fn verify_callback(&self, params: &HashMap<String, String>, _redirect_uri: &str) -> Result<ExternalIdentity> {
+        // 检查是否是成功的响应
+        match params.get("openid.mode").map(String::as_str) {
+            Some("id_res") => {}
+            Some(_) => return Err(crate::Error::Auth("无效的OpenID模式".to_string())),
+            None => return Err(crate::Error::Auth("缺少openid.mode参数".to_string())),
+        }
+
+        // 检查response_nonce的有效期
+        let response_nonce = params
+            .get("openid.response_nonce")
+            .ok_or_else(|| crate::Error::Auth("缺少response_nonce参数".to_string()))?;
+        if !self.is_response_nonce_valid(response_nonce) {
+            return Err(crate::Error::Auth("response_nonce已过期或无效".to_string()));
+        }
+
+        // 进行签名校验
+        self.verify_signature(params).await?;
+
+        // 提取用户ID：/openid/id/123456
+        let claimed_id = params
+            .get("openid.claimed_id")
+            .ok_or_else(|| crate::Error::Auth("缺少openid.claimed_id参数".to_string()))?;
+        let user_id = claimed_id
+            .split('/')
+            .next_back()
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| crate::Error::Auth("无法从claimed_id中提取用户ID".to_string()))?;
+
+        self.fetch_identity(user_id)
+            .await?
+            .ok_or_else(|| crate::Error::Auth("获取用户信息失败".to_string()))
+    }
+
+    /// 从摸鱼派API获取用户信息
+    async fn fetch_identity(&self, external_id: &str) -> Result<Option<ExternalIdentity>> {
+        let url = format!(
+            "{}/api/user/getInfoById?userId={}",
+            self.base_url, external_id
+        );
+
+        let response = http_client()?
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        let status = response.status();
+        let response_text = response
+            .text()
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        if !status.is_success() {
+            return Err(crate::Error::Auth("获取用户信息失败".to_string()));
+        }
+
+        let fishpi_user: FishpiUserInfoResponse = serde_json::from_str(&response_text)
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+        Ok(Some(ExternalIdentity {
+            external_id: external_id.to_string(),
+            nickname: fishpi_user.data.user_nickname
+                .filter(|nickname| !nickname.trim().is_empty())
+                .or(Some(fishpi_user.data.user_name.clone())),
+            username: fishpi_user.data.user_name,
+            avatar: fishpi_user.data.user_avatar_url,
+        }))
+    }
+
+    fn local_user_id(&self, external_id: &str) -> String {
+        external_id.to_string()
+    }
+
+    fn local_username(&self, username: &str) -> String {
+        username.to_string()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+/// 通用 OAuth2 授权码登录，OIDC 提供方同样通过 userinfo 接口获取用户资料
+pub struct OAuth2Provider {
+    config: OAuthProviderConfig,
+}
+
+impl OAuth2Provider {
+    pub fn new(config: OAuthProviderConfig) -> Self {
+        OAuth2Provider { config }
+    }
+
+    /// 按配置的字段名从 userinfo 响应中取出字符串，数字ID同样转成字符串
+    fn field(info: &serde_json::Value, name: &str) -> Option<String> {
+        match info.get(name)? {
+            serde_json::Value::String(value) if !value.trim().is_empty() => Some(value.clone()),
+            serde_json::Value::Number(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[axum::async_trait]
+impl AuthProvider for OAuth2Provider {
+    fn id(&self) -> &str {
+        &self.config.id
+    }
+
+    fn display_name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn login_url(&self, redirect_uri: &str, state: &str) -> Result<String> {
+        let mut url = Url::parse(&self.config.authorize_url).map_err(|e| crate::Error::Config(e.to_string()))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", &self.config.scopes.join(" "))
+            .append_pair("state", state);
+        Ok(url.to_string())
+    }
+
+    async fn verify_callback(&self, params: &HashMap<String, String>, redirect_uri: &str) -> Result<ExternalIdentity> {
+        if let Some(error) = params.get("error") {
+            return Err(crate::Error::Auth(format!("{} 登录失败: {}", self.config.name, error)));
+        }
+        let code = params
+            .get("code")
+            .ok_or_else(|| crate::Error::Auth("缺少授权码".to_string()))?;
+
+        let client = http_client()?;
+        let token: TokenResponse = client
+            .post(&self.config.token_url)
+            .header("Accept", "application/json")
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", redirect_uri),
+                ("client_id", self.config.client_id.as_str()),
+                ("client_secret", self.config.client_secret.as_str()),
+            ])
+            .send()
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?
+            .error_for_status()
+            .map_err(|e| crate::Error::Auth(format!("换取访问令牌失败: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| crate::Error::Auth(format!("无法解析访问令牌: {}", e)))?;
+
+        let info: serde_json::Value = client
+            .get(&self.config.userinfo_url)
+            .header("Accept", "application/json")
+            .bearer_auth(&token.access_token)
+            .send()
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?
+            .error_for_status()
+            .map_err(|e| crate::Error::Auth(format!("获取用户信息失败: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| crate::Error::Auth(format!("无法解析用户信息: {}", e)))?;
+
+        let external_id = Self::field(&info, &self.config.id_field)
+            .ok_or_else(|| crate::Error::Auth(format!("用户信息缺少字段 {}", self.config.id_field)))?;
+        let username = Self::field(&info, &self.config.username_field).unwrap_or_else(|| external_id.clone());
+        let nickname = self
+            .config
+            .nickname_field
+            .as_deref()
+            .and_then(|name| Self::field(&info, name))
+            .or_else(|| Some(username.clone()));
+        let avatar = self
+            .config
+            .avatar_field
+            .as_deref()
+            .and_then(|name| Self::field(&info, name));
+
+        Ok(ExternalIdentity {
+            external_id,
+            username,
+            nickname,
+            avatar,
+        })
+    }
+}
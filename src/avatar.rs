@@ -0,0 +1,159 @@
+use crate::Result;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// 用户头像的代理地址，启用头像代理后写入 `User.avatar`
+pub fn proxied_url(user_id: &str) -> String {
+    format!(
+        "{}/avatars/{}",
+        crate::config::Config::get().auth.domain.trim_end_matches('/'),
+        urlencoding::encode(user_id)
+    )
+}
+
+/// 头像地址是否允许代理：只接受 https，且域名在 `avatar.allowed_hosts` 中
+pub fn is_allowed(url: &str) -> bool {
+    allowed_host(url).is_some()
+}
+
+fn allowed_host(url: &str) -> Option<(String, u16)> {
+    let url = url::Url::parse(url).ok()?;
+    if url.scheme() != "https" {
+        return None;
+    }
+    let host = url.host_str()?.to_string();
+    let allowed = crate::config::Config::get().avatar.allowed_hosts.contains(&host);
+    allowed.then(|| (host, url.port_or_known_default().unwrap_or(443)))
+}
+
+/// 是否为公网地址，内网、回环、链路本地等地址不允许作为头像来源
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_unspecified()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // fc00::/7 唯一本地地址、fe80::/10 链路本地地址
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// 下载外部头像，返回内容类型和图片数据；非图片或超过大小上限的内容会被拒绝
+///
+/// 只请求允许的 https 主机，解析出的地址必须是公网地址，并固定使用校验过的地址连接，不跟随重定向。
+pub async fn fetch(url: &str) -> Result<(String, Vec<u8>)> {
+    let config = &crate::config::Config::get().avatar;
+    let Some((host, port)) = allowed_host(url) else {
+        return Err(crate::Error::Network(anyhow::anyhow!("头像地址不在允许的范围内: {}", url)));
+    };
+
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?
+        .collect();
+    let Some(addr) = addrs.first().copied() else {
+        return Err(crate::Error::Network(anyhow::anyhow!("无法解析头像主机: {}", host)));
+    };
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(crate::Error::Network(anyhow::anyhow!("头像主机 {} 解析到了内网地址", host)));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.fetch_timeout.max(1)))
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+    if !response.status().is_success() {
+        return Err(crate::Error::Network(anyhow::anyhow!("下载头像失败: HTTP {}", response.status())));
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    if !content_type.starts_with("image/") {
+        return Err(crate::Error::Network(anyhow::anyhow!("头像不是图片: {}", content_type)));
+    }
+    if response.content_length().is_some_and(|len| len > config.max_bytes) {
+        return Err(crate::Error::Network(anyhow::anyhow!("头像超过大小上限")));
+    }
+
+    // 分块读取，没有 Content-Length 的响应超过上限时立即中止
+    let mut data = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?
+    {
+        if (data.len() + chunk.len()) as u64 > config.max_bytes {
+            return Err(crate::Error::Network(anyhow::anyhow!("头像超过大小上限")));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok((content_type, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn rejects_internal_addresses() {
+        for addr in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_ip(ip(addr)), "{} 不应视为公网地址", addr);
+        }
+    }
+
+    #[test]
+    fn accepts_public_addresses() {
+        for addr in ["1.1.1.1", "203.0.114.1", "100.128.0.1", "2606:4700::1111", "::ffff:8.8.8.8"] {
+            assert!(is_public_ip(ip(addr)), "{} 应视为公网地址", addr);
+        }
+    }
+}
@@ -89,7 +89,7 @@ fn main() {
                 .unwrap()
                 .parse()
                 .expect("相似度必须是数字");
-            let difficulty = Difficulty::from_str(args.value_of("difficulty").unwrap());
+            let difficulty: Difficulty = args.value_of("difficulty").unwrap().parse().unwrap();
 
             let word_pair = WordPair {
                 civilian_word: civilian.to_string(),
@@ -120,6 +120,11 @@ fn main() {
             for (category, count) in &stats.category_stats {
                 println!("    {}: {}", category, count);
             }
+            println!("  分类抽取权重（最近抽取次数）:");
+            for (category, weight) in &stats.category_weights {
+                let uses = stats.recent_usage.get(category).copied().unwrap_or(0);
+                println!("    {}: {:.2}（{}）", category, weight, uses);
+            }
         }
         Some(("validate", _)) => {
             let errors = word_bank.validate();
@@ -0,0 +1,299 @@
+use crate::Result;
+use crate::game::PlayerId;
+use crate::message::{ClientMessage, SequencedMessage};
+use crate::room::Room;
+use rand::prelude::*;
+use std::collections::HashSet;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// 电脑玩家名称
+const BOT_NAMES: [&str; 8] = ["阿福", "小白", "大黄", "豆豆", "团子", "可乐", "布丁", "汤圆"];
+
+/// 电脑玩家行动前的最短和最长停顿（毫秒），模拟真人的思考时间
+const BOT_THINK_TIME_MS: std::ops::RangeInclusive<u64> = 1500..=4000;
+
+/// 有分类可参考时的描述模板，{} 为词语所属分类
+const CATEGORY_TEMPLATES: [&str; 4] = [
+    "这是一种和{}有关的东西",
+    "说到{}，很多人会想到它",
+    "在{}里面算是比较常见的",
+    "我觉得它属于{}这一类",
+];
+
+/// 困难电脑玩家额外给出词语字数的描述模板，{category} 为分类，{length} 为字数
+const DETAILED_TEMPLATES: [&str; 3] = [
+    "和{category}有关，是个{length}个字的词",
+    "{category}里挺常见的，{length}个字",
+    "想想{category}，一个{length}字的东西",
+];
+
+/// 通用描述模板，白板或找不到分类时使用
+const GENERIC_TEMPLATES: [&str; 5] = [
+    "这个东西在日常生活中挺常见的",
+    "我觉得大部分人都接触过它",
+    "它和我们的生活息息相关",
+    "说起它，我第一反应是很实用",
+    "不同的人对它的感受可能不太一样",
+];
+
+/// 电脑玩家难度，影响描述的含糊程度和投票的准确度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BotDifficulty {
+    /// 只给通用描述，随机投票
+    Easy,
+    /// 描述提及词语分类，多数时候根据描述推断投票
+    #[default]
+    Medium,
+    /// 描述提及分类和字数，总是根据描述推断投票
+    Hard,
+}
+
+impl std::str::FromStr for BotDifficulty {
+    type Err = std::convert::Infallible;
+
+    /// 解析难度，无法识别时默认为普通
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "easy" => BotDifficulty::Easy,
+            "hard" => BotDifficulty::Hard,
+            _ => BotDifficulty::Medium,
+        })
+    }
+}
+
+impl BotDifficulty {
+    /// 投票时根据描述推断而不是随机选择的概率
+    fn inference_rate(&self) -> f64 {
+        match self {
+            BotDifficulty::Easy => 0.0,
+            BotDifficulty::Medium => 0.6,
+            BotDifficulty::Hard => 1.0,
+        }
+    }
+
+    /// 显示在电脑玩家名称后的难度标记
+    fn label(&self) -> &'static str {
+        match self {
+            BotDifficulty::Easy => "简单",
+            BotDifficulty::Medium => "普通",
+            BotDifficulty::Hard => "困难",
+        }
+    }
+}
+
+/// 房主添加电脑玩家：校验后以与真实玩家相同的消息通道加入房间，返回实际加入的数量
+pub async fn add_bots(room: &Arc<Room>, host_id: &PlayerId, count: usize, difficulty: Option<&str>) -> Result<usize> {
+    let difficulty: BotDifficulty = difficulty.and_then(|s| s.parse().ok()).unwrap_or_default();
+    room.check_bot_seats(host_id, count).await?;
+
+    let taken: HashSet<String> = room
+        .players_with_roles()
+        .await
+        .into_iter()
+        .map(|player| player.name)
+        .collect();
+    let mut names: Vec<String> = BOT_NAMES
+        .iter()
+        .map(|name| format!("电脑·{}（{}）", name, difficulty.label()))
+        .filter(|name| !taken.contains(name))
+        .collect();
+    names.shuffle(&mut rand::rng());
+
+    let mut added = 0;
+    for index in 0..count {
+        let name = names
+            .pop()
+            .unwrap_or_else(|| format!("电脑·{}号（{}）", taken.len() + index + 1, difficulty.label()));
+        let id: PlayerId = format!("ai-bot-{}-{}", room.id(), uuid::Uuid::new_v4().simple());
+        let (tx, rx) = mpsc::channel::<SequencedMessage>(100);
+        let rx = room.player_feed(id.clone(), &tx, rx);
+        room.register_bot(id.clone());
+
+        let join = ClientMessage::Join {
+            player_name: name.to_string(),
+            capabilities: None,
+            invite_code: None,
+        };
+        if let Err(e) = room.handle_message(id.clone(), join, Some(tx)).await {
+            error!("电脑玩家 {} 加入房间失败: {}", name, e);
+            room.release_external_bot(&id);
+            continue;
+        }
+
+        let bot = AiBot {
+            id,
+            difficulty,
+            room: Arc::downgrade(room),
+            ready_sent: false,
+            described: false,
+            voted: false,
+        };
+        tokio::spawn(bot.run(rx));
+        added += 1;
+    }
+    Ok(added)
+}
+
+/// 自动准备、按模板描述并根据描述启发式投票的电脑玩家
+struct AiBot {
+    id: PlayerId,
+    difficulty: BotDifficulty,
+    room: Weak<Room>,
+    ready_sent: bool,
+    described: bool,
+    voted: bool,
+}
+
+impl AiBot {
+    async fn run(mut self, mut rx: mpsc::Receiver<SequencedMessage>) {
+        while let Some(SequencedMessage { message, .. }) = rx.recv().await {
+            if message.type_ != "state_update" {
+                continue;
+            }
+            let Some(room) = self.room.upgrade() else {
+                break;
+            };
+            self.on_state_update(&room, &message.data);
+        }
+        debug!("电脑玩家 {} 已退出", self.id);
+    }
+
+    fn on_state_update(&mut self, room: &Arc<Room>, data: &serde_json::Value) {
+        let state = data["state"].as_str().unwrap_or_default();
+        let me = data["players"]
+            .as_array()
+            .and_then(|players| players.iter().find(|p| p["id"] == self.id.as_str()))
+            .cloned()
+            .unwrap_or_default();
+
+        if state != "Lobby" {
+            self.ready_sent = false;
+        }
+        if state != "DescribePhase" {
+            self.described = false;
+        }
+        if state != "VotePhase" {
+            self.voted = false;
+        }
+
+        match state {
+            "Lobby" if !self.ready_sent && !me["is_ready"].as_bool().unwrap_or(false) => {
+                self.ready_sent = true;
+                self.act(room, ClientMessage::Ready {});
+            }
+            "DescribePhase" if !self.described && data["current_player"] == self.id.as_str() => {
+                self.described = true;
+                let content = describe(room, me["word"].as_str(), self.difficulty);
+                self.act(room, ClientMessage::Describe { content });
+            }
+            "VotePhase" if !self.voted && me["is_alive"].as_bool().unwrap_or(false) => {
+                self.voted = true;
+                if let Some(target) = self.choose_vote_target(data) {
+                    self.act(room, ClientMessage::Vote { target_id: target });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 稍作停顿后以普通玩家消息的形式执行操作
+    fn act(&self, room: &Arc<Room>, message: ClientMessage) {
+        let room = room.clone();
+        let bot_id = self.id.clone();
+        let think_time = Duration::from_millis(rand::rng().random_range(BOT_THINK_TIME_MS));
+        tokio::spawn(async move {
+            tokio::time::sleep(think_time).await;
+            if let Err(e) = room.handle_message(bot_id.clone(), message, None).await {
+                debug!("电脑玩家 {} 操作失败: {}", bot_id, e);
+            }
+        });
+    }
+
+    /// 启发式投票：电脑玩家不知道任何人的身份，按难度决定随机投票还是投给描述与其他人描述重合字最少（最“不合群”）的玩家
+    fn choose_vote_target(&self, data: &serde_json::Value) -> Option<PlayerId> {
+        let pk_candidates: Vec<&str> = data["pk_candidates"]
+            .as_array()
+            .map(|ids| ids.iter().filter_map(|id| id.as_str()).collect())
+            .unwrap_or_default();
+        let candidates: Vec<&str> = data["players"]
+            .as_array()?
+            .iter()
+            .filter(|p| p["is_alive"].as_bool().unwrap_or(false))
+            .filter_map(|p| p["id"].as_str())
+            .filter(|id| *id != self.id && (pk_candidates.is_empty() || pk_candidates.contains(id)))
+            .collect();
+
+        let descriptions: Vec<(&str, &str)> = data["descriptions"]
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| Some((item["player_id"].as_str()?, item["content"].as_str()?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let scored: Vec<(&str, usize)> = candidates
+            .iter()
+            .map(|candidate| {
+                let own: HashSet<char> = descriptions
+                    .iter()
+                    .filter(|(id, _)| id == candidate)
+                    .flat_map(|(_, content)| content.chars())
+                    .collect();
+                let others: HashSet<char> = descriptions
+                    .iter()
+                    .filter(|(id, _)| id != candidate)
+                    .flat_map(|(_, content)| content.chars())
+                    .collect();
+                (*candidate, own.intersection(&others).count())
+            })
+            .collect();
+
+        let mut rng = rand::rng();
+        if !rng.random_bool(self.difficulty.inference_rate()) {
+            return candidates.choose(&mut rng).map(|id| id.to_string());
+        }
+
+        let lowest = scored.iter().map(|(_, score)| *score).min()?;
+        let suspects: Vec<&str> = scored
+            .into_iter()
+            .filter(|(_, score)| *score == lowest)
+            .map(|(id, _)| id)
+            .collect();
+        suspects.choose(&mut rng).map(|id| id.to_string())
+    }
+}
+
+/// 根据词语所属分类生成不直接暴露词语的模板描述，简单难度、白板或找不到分类时使用通用描述
+fn describe(room: &Room, word: Option<&str>, difficulty: BotDifficulty) -> String {
+    let mut rng = rand::rng();
+    let word = word.filter(|_| difficulty != BotDifficulty::Easy);
+    let category = word
+        .filter(|word| !word.is_empty())
+        .and_then(|word| room.word_bank().category_of(word).map(|category| (word, category)))
+        // 分类名里包含词语中的字时不使用，避免描述暴露词语
+        .filter(|(word, category)| !word.chars().any(|c| category.contains(c)));
+
+    match category {
+        Some((word, category)) if difficulty == BotDifficulty::Hard => DETAILED_TEMPLATES
+            .choose(&mut rng)
+            .copied()
+            .unwrap_or(DETAILED_TEMPLATES[0])
+            .replace("{category}", category)
+            .replace("{length}", &word.chars().count().to_string()),
+        Some((_, category)) => CATEGORY_TEMPLATES
+            .choose(&mut rng)
+            .copied()
+            .unwrap_or(CATEGORY_TEMPLATES[0])
+            .replace("{}", category),
+        None => GENERIC_TEMPLATES
+            .choose(&mut rng)
+            .copied()
+            .unwrap_or(GENERIC_TEMPLATES[0])
+            .to_string(),
+    }
+}
@@ -0,0 +1,253 @@
+use crate::Result;
+use crate::game::Role;
+use crate::storage::GameResult;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// 摸鱼派聊天室接口的通用响应
+#[derive(Debug, Deserialize)]
+struct ChatroomResponse {
+    code: i32,
+    #[serde(default)]
+    msg: Option<String>,
+}
+
+/// 以配置的机器人账号向摸鱼派聊天室发送一条消息，内容支持 Markdown
+pub async fn send_message(content: &str) -> Result<()> {
+    let config = &crate::config::Config::get().chatroom;
+    if config.api_key.is_empty() {
+        return Err(crate::Error::Config("未配置摸鱼派聊天室的 api_key".to_string()));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+    let response: ChatroomResponse = client
+        .post(format!("{}/chat-room/send", config.base_url.trim_end_matches('/')))
+        .json(&serde_json::json!({
+            "apiKey": config.api_key,
+            "content": content,
+        }))
+        .send()
+        .await
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?
+        .json()
+        .await
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+
+    if response.code != 0 {
+        return Err(crate::Error::Network(anyhow::anyhow!(
+            "摸鱼派聊天室发送失败: {}",
+            response.msg.unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
+
+/// 转义 Markdown 特殊字符，玩家昵称等用户输入原样显示，不会被渲染成链接或格式
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_control() {
+            escaped.push(' ');
+            continue;
+        }
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '{' | '}' | '[' | ']' | '(' | ')' | '#' | '+' | '-' | '.' | '!' | '|' | '<' | '>' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 把一局的结果整理为聊天室消息：获胜阵营、双方词语和玩家身份
+///
+/// `hidden` 中的玩家（设置了隐藏个人资料）不出现在玩家列表中。
+pub fn format_game_result(result: &GameResult, hidden: &HashSet<String>) -> String {
+    let word_of = |role: Role| {
+        result
+            .players
+            .iter()
+            .find(|player| player.role == Some(role))
+            .and_then(|player| player.word.clone())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let winner = match result.winner {
+        Role::Civilian => "平民",
+        _ => "卧底",
+    };
+    let players: Vec<String> = result
+        .players
+        .iter()
+        .filter(|player| !hidden.contains(&player.id))
+        .map(|player| {
+            let role = player.role.map_or_else(|| "观战".to_string(), |role| role.to_string());
+            let won = player.role.is_some_and(|role| role.wins_with(result.winner));
+            format!("{}（{}{}）", escape_markdown(&player.name), role, if won { "·胜" } else { "" })
+        })
+        .collect();
+
+    format!(
+        "🕵️ 谁是卧底 · 房间 {} 对局结束，**{}阵营**获胜！\n\n平民词：{}　卧底词：{}\n\n玩家：{}\n\n[来玩一局]({})",
+        result.room_id,
+        winner,
+        word_of(Role::Civilian),
+        word_of(Role::Undercover),
+        players.join("、"),
+        crate::config::Config::get().feed.join_url(&result.room_id),
+    )
+}
+
+/// 聊天室中的一条用户消息
+#[derive(Debug, Clone)]
+pub struct ChatroomMessage {
+    /// 发送者的摸鱼派用户ID
+    pub user_id: String,
+    pub user_name: String,
+    /// 消息的 Markdown 原文
+    pub content: String,
+}
+
+/// 聊天室中的游戏指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatCommand {
+    /// 以发送者为房主创建房间
+    CreateRoom,
+    /// 推荐一个正在等人的公开房间
+    FindRoom,
+    Help,
+}
+
+/// 解析 `卧底 开房` 这样的指令，前缀取自配置，不是指令时返回 None
+pub fn parse_command(content: &str) -> Option<ChatCommand> {
+    let prefix = &crate::config::Config::get().chatroom.command_prefix;
+    let rest = content.trim().strip_prefix(prefix.as_str())?;
+    // 前缀后必须是空白或直接结束，避免误触发 "卧底游戏真好玩" 这样的消息
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    match rest.trim() {
+        "开房" | "创建" => Some(ChatCommand::CreateRoom),
+        "加入" | "房间" | "匹配" => Some(ChatCommand::FindRoom),
+        "" | "帮助" => Some(ChatCommand::Help),
+        _ => None,
+    }
+}
+
+/// 解析聊天室 WebSocket 推送，只保留普通用户消息
+fn parse_message(text: &str) -> Option<ChatroomMessage> {
+    let value: serde_json::Value = serde_json::from_str(text).ok()?;
+    if value["type"] != "msg" {
+        return None;
+    }
+    let user_id = match &value["userOId"] {
+        serde_json::Value::Number(id) => id.to_string(),
+        serde_json::Value::String(id) => id.clone(),
+        _ => return None,
+    };
+    Some(ChatroomMessage {
+        user_id,
+        user_name: value["userName"].as_str()?.to_string(),
+        content: value["md"].as_str().or(value["content"].as_str())?.to_string(),
+    })
+}
+
+/// 聊天室心跳间隔，长时间没有数据时服务端会断开连接
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(180);
+
+/// 持续监听摸鱼派聊天室，把用户消息转发给 `messages`，断线后按配置的间隔重连，接收端关闭时退出
+pub async fn listen(messages: tokio::sync::mpsc::Sender<ChatroomMessage>) {
+    let config = &crate::config::Config::get().chatroom;
+    loop {
+        match connect_and_read(&messages).await {
+            Ok(()) => tracing::info!("摸鱼派聊天室连接已断开"),
+            Err(e) => tracing::warn!("摸鱼派聊天室连接失败: {}", e),
+        }
+        if messages.is_closed() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_secs(config.reconnect_interval.max(1))).await;
+    }
+}
+
+/// 建立一次聊天室连接并读取消息直到断开
+async fn connect_and_read(messages: &tokio::sync::mpsc::Sender<ChatroomMessage>) -> Result<()> {
+    let config = &crate::config::Config::get().chatroom;
+    let base_url = config.base_url.trim_end_matches('/');
+    let (scheme, host_part) = match base_url.split_once("://") {
+        Some(("https", rest)) => ("wss", rest),
+        Some(("http", rest)) => ("ws", rest),
+        _ => return Err(crate::Error::Config(format!("无效的摸鱼派地址: {}", base_url))),
+    };
+    let url = format!(
+        "{}://{}/chat-room-channel?apiKey={}",
+        scheme,
+        host_part,
+        urlencoding::encode(&config.api_key)
+    );
+
+    // wss 连接使用内置的 webpki 根证书校验服务器证书
+    let (ws, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+    read_messages(ws, messages).await
+}
+
+async fn read_messages<S>(
+    mut ws: tokio_tungstenite::WebSocketStream<S>,
+    messages: &tokio::sync::mpsc::Sender<ChatroomMessage>,
+) -> Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    use futures_util::{SinkExt, StreamExt};
+    use tokio_tungstenite::tungstenite::Message;
+
+    tracing::info!("已连接摸鱼派聊天室");
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    heartbeat.tick().await;
+    loop {
+        tokio::select! {
+            _ = heartbeat.tick() => {
+                ws.send(Message::Text("-hb-".to_string()))
+                    .await
+                    .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+            }
+            frame = ws.next() => {
+                let Some(frame) = frame else {
+                    return Ok(());
+                };
+                match frame.map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))? {
+                    Message::Text(text) => {
+                        if let Some(message) = parse_message(&text)
+                            && messages.send(message).await.is_err()
+                        {
+                            return Ok(());
+                        }
+                    }
+                    Message::Close(_) => return Ok(()),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_markdown_neutralises_links_and_formatting() {
+        assert_eq!(escape_markdown("[点我](https://evil.example)"), "\\[点我\\]\\(https://evil\\.example\\)");
+        assert_eq!(escape_markdown("**粗体**_斜体_`代码`"), "\\*\\*粗体\\*\\*\\_斜体\\_\\`代码\\`");
+        assert_eq!(escape_markdown("<img src=x>"), "\\<img src=x\\>");
+        assert_eq!(escape_markdown("换行\n# 标题"), "换行 \\# 标题");
+        assert_eq!(escape_markdown("普通昵称"), "普通昵称");
+    }
+}
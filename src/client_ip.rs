@@ -0,0 +1,392 @@
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::{HeaderMap, request::Parts};
+use dashmap::DashMap;
+use once_cell::sync::OnceCell;
+use std::convert::Infallible;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+static TRUSTED_PROXIES: OnceCell<TrustedProxies> = OnceCell::new();
+
+/// 限流计数表最多保留的IP数，防止大量不同的IP撑大内存
+const RATE_LIMIT_MAX_ENTRIES: usize = 10_000;
+
+/// 请求来源的真实客户端IP
+///
+/// 直连地址在可信代理列表中时，依次采用 X-Forwarded-For、X-Real-IP 中的地址，
+/// 否则直接使用直连地址，防止客户端伪造请求头。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientIp(pub IpAddr);
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        Ok(ClientIp(resolve(peer, &parts.headers, TrustedProxies::global())))
+    }
+}
+
+impl std::fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// 可信反向代理列表，支持单个地址和 CIDR 网段
+#[derive(Debug, Default)]
+pub struct TrustedProxies {
+    networks: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// 解析配置中的代理列表，无法识别的条目记录警告后跳过
+    pub fn parse(entries: &[String]) -> Self {
+        let networks = entries
+            .iter()
+            .filter_map(|entry| {
+                let parsed = parse_network(entry.trim());
+                if parsed.is_none() {
+                    warn!("忽略无法识别的可信代理地址: {}", entry);
+                }
+                parsed
+            })
+            .collect();
+        TrustedProxies { networks }
+    }
+
+    /// 全局配置中的可信代理列表
+    pub fn global() -> &'static TrustedProxies {
+        TRUSTED_PROXIES
+            .get_or_init(|| Self::parse(&crate::config::Config::get().server.trusted_proxies))
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.networks
+            .iter()
+            .any(|(network, prefix)| in_network(ip, *network, *prefix))
+    }
+}
+
+fn parse_network(entry: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match entry.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+        None => (entry, None),
+    };
+    let parsed = addr.parse::<IpAddr>().ok()?;
+    let addr = parsed.to_canonical();
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    // IPv4 映射的 IPv6 网段（::ffff:a.b.c.d/n）按 IPv4 网段匹配，前缀长度去掉映射前缀的 96 位
+    let prefix = match prefix {
+        Some(prefix) if parsed.is_ipv6() && addr.is_ipv4() => prefix.checked_sub(96)?,
+        Some(prefix) => prefix,
+        None => max,
+    };
+    (prefix <= max).then_some((addr, prefix))
+}
+
+fn in_network(ip: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// 解析转发头中的单个地址，兼容带端口的写法
+fn parse_forwarded_addr(value: &str) -> Option<IpAddr> {
+    let value = value.trim();
+    value
+        .parse::<IpAddr>()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .map(|ip| ip.to_canonical())
+}
+
+/// 根据直连地址和转发头确定客户端IP
+///
+/// X-Forwarded-For 从右向左跳过可信代理，取第一个不可信的地址；
+/// 链路上全部是可信代理时取最左侧的地址。
+pub fn resolve(peer: IpAddr, headers: &HeaderMap, trusted: &TrustedProxies) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !trusted.contains(peer) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(parse_forwarded_addr)
+        .collect();
+    if let Some(leftmost) = forwarded.first() {
+        return forwarded
+            .iter()
+            .rev()
+            .find(|ip| !trusted.contains(**ip))
+            .copied()
+            .unwrap_or(*leftmost);
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_forwarded_addr)
+        .unwrap_or(peer)
+}
+
+/// 按客户端IP统计同时在线的连接数
+pub struct IpConnections {
+    counts: Arc<DashMap<IpAddr, usize>>,
+    /// 每个IP的连接上限，0 表示不限制
+    limit: usize,
+}
+
+/// 连接占用的名额，连接结束时释放
+pub struct IpConnectionGuard {
+    counts: Arc<DashMap<IpAddr, usize>>,
+    ip: IpAddr,
+}
+
+impl IpConnections {
+    pub fn new(limit: usize) -> Self {
+        IpConnections {
+            counts: Arc::new(DashMap::new()),
+            limit,
+        }
+    }
+
+    pub fn from_config() -> Self {
+        Self::new(crate::config::Config::get().websocket.max_connections_per_ip)
+    }
+
+    /// 为新连接占用名额，该IP的连接数已达上限时返回 None
+    pub fn try_acquire(&self, ip: IpAddr) -> Option<IpConnectionGuard> {
+        let mut count = self.counts.entry(ip).or_insert(0);
+        if self.limit > 0 && *count >= self.limit {
+            return None;
+        }
+        *count += 1;
+        Some(IpConnectionGuard {
+            counts: self.counts.clone(),
+            ip,
+        })
+    }
+}
+
+impl Drop for IpConnectionGuard {
+    fn drop(&mut self) {
+        if let Some(mut count) = self.counts.get_mut(&self.ip) {
+            *count = count.saturating_sub(1);
+        }
+        self.counts.remove_if(&self.ip, |_, count| *count == 0);
+    }
+}
+
+/// 按客户端IP的固定时间窗口限流，用于无需登录即可调用的接口
+pub struct IpRateLimiter {
+    windows: DashMap<IpAddr, (Instant, u32)>,
+    window: Duration,
+    /// 每个窗口内允许的请求次数，0 表示不限制
+    max_requests: u32,
+}
+
+impl IpRateLimiter {
+    pub fn new(window: Duration, max_requests: u32) -> Self {
+        IpRateLimiter {
+            windows: DashMap::new(),
+            window,
+            max_requests,
+        }
+    }
+
+    /// 记录一次请求，该IP在当前窗口内的次数已达上限时返回 false
+    pub fn check(&self, ip: IpAddr) -> bool {
+        if self.max_requests == 0 {
+            return true;
+        }
+        let now = Instant::now();
+
+        // 计数表达到上限时才清理过期窗口，清理后仍然已满则拒绝新的IP
+        if self.windows.len() >= RATE_LIMIT_MAX_ENTRIES && !self.windows.contains_key(&ip) {
+            self.windows
+                .retain(|_, (started_at, _)| now.duration_since(*started_at) <= self.window);
+            if self.windows.len() >= RATE_LIMIT_MAX_ENTRIES {
+                return false;
+            }
+        }
+
+        let mut entry = self.windows.entry(ip).or_insert((now, 0));
+        let (started_at, count) = entry.value_mut();
+        if now.duration_since(*started_at) > self.window {
+            *started_at = now;
+            *count = 0;
+        }
+        if *count >= self.max_requests {
+            return false;
+        }
+        *count += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    fn proxies(entries: &[&str]) -> TrustedProxies {
+        TrustedProxies::parse(&entries.iter().map(|entry| entry.to_string()).collect::<Vec<_>>())
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn parse_network_accepts_addresses_and_cidrs() {
+        assert_eq!(parse_network("10.0.0.1"), Some((ip("10.0.0.1"), 32)));
+        assert_eq!(parse_network("10.0.0.0/8"), Some((ip("10.0.0.0"), 8)));
+        assert_eq!(parse_network("0.0.0.0/0"), Some((ip("0.0.0.0"), 0)));
+        assert_eq!(parse_network("fd00::/8"), Some((ip("fd00::"), 8)));
+        assert_eq!(parse_network("::1"), Some((ip("::1"), 128)));
+    }
+
+    #[test]
+    fn parse_network_rejects_invalid_entries() {
+        assert_eq!(parse_network("10.0.0.0/33"), None);
+        assert_eq!(parse_network("fd00::/129"), None);
+        assert_eq!(parse_network("10.0.0.0/-1"), None);
+        assert_eq!(parse_network("10.0.0.0/"), None);
+        assert_eq!(parse_network("not-an-ip"), None);
+        assert_eq!(parse_network("10.0.0.0/8/8"), None);
+    }
+
+    #[test]
+    fn parse_network_maps_ipv4_mapped_networks() {
+        assert_eq!(parse_network("::ffff:10.0.0.1"), Some((ip("10.0.0.1"), 32)));
+        assert_eq!(parse_network("::ffff:10.0.0.0/104"), Some((ip("10.0.0.0"), 8)));
+        assert_eq!(parse_network("::ffff:10.0.0.0/64"), None);
+    }
+
+    #[test]
+    fn in_network_handles_prefix_edges() {
+        assert!(in_network(ip("203.0.113.7"), ip("0.0.0.0"), 0));
+        assert!(in_network(ip("10.0.0.1"), ip("10.0.0.1"), 32));
+        assert!(!in_network(ip("10.0.0.2"), ip("10.0.0.1"), 32));
+        assert!(in_network(ip("10.255.1.2"), ip("10.0.0.0"), 8));
+        assert!(!in_network(ip("11.0.0.1"), ip("10.0.0.0"), 8));
+        assert!(in_network(ip("2001:db8::1"), ip("::"), 0));
+        assert!(in_network(ip("::1"), ip("::1"), 128));
+        assert!(!in_network(ip("::2"), ip("::1"), 128));
+        // 地址族不同时不匹配，即使前缀为 0
+        assert!(!in_network(ip("10.0.0.1"), ip("::"), 0));
+        assert!(!in_network(ip("::1"), ip("0.0.0.0"), 0));
+    }
+
+    #[test]
+    fn contains_matches_ipv4_mapped_peers() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        assert!(trusted.contains(ip("::ffff:10.1.2.3")));
+        assert!(!trusted.contains(ip("::ffff:192.168.0.1")));
+    }
+
+    #[test]
+    fn untrusted_peer_ignores_forwarded_headers() {
+        let trusted = proxies(&["10.0.0.1"]);
+        let headers = headers(&[("x-forwarded-for", "1.2.3.4"), ("x-real-ip", "5.6.7.8")]);
+        assert_eq!(resolve(ip("203.0.113.9"), &headers, &trusted), ip("203.0.113.9"));
+    }
+
+    #[test]
+    fn no_trusted_proxies_always_uses_peer() {
+        let trusted = proxies(&[]);
+        let headers = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn spoofed_forwarded_for_is_skipped() {
+        // 客户端自带的 X-Forwarded-For 在最左侧，代理追加真实地址
+        let trusted = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "6.6.6.6, 1.2.3.4, 10.0.0.2")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn all_trusted_chain_uses_leftmost() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "10.0.0.5, 10.0.0.2")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("10.0.0.5"));
+    }
+
+    #[test]
+    fn multiple_forwarded_headers_are_joined_in_order() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        let headers = headers(&[("x-forwarded-for", "6.6.6.6"), ("x-forwarded-for", "1.2.3.4, 10.0.0.2")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &headers, &trusted), ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn forwarded_addresses_with_ports_and_mapped_ipv6() {
+        let trusted = proxies(&["10.0.0.0/8"]);
+        let with_port = headers(&[("x-forwarded-for", "1.2.3.4:5678")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &with_port, &trusted), ip("1.2.3.4"));
+        let bracketed = headers(&[("x-forwarded-for", "[2001:db8::1]:443")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &bracketed, &trusted), ip("2001:db8::1"));
+        let mapped = headers(&[("x-forwarded-for", "::ffff:1.2.3.4")]);
+        assert_eq!(resolve(ip("::ffff:10.0.0.1"), &mapped, &trusted), ip("1.2.3.4"));
+    }
+
+    #[test]
+    fn falls_back_to_real_ip_then_peer() {
+        let trusted = proxies(&["10.0.0.1"]);
+        let real_ip = headers(&[("x-real-ip", "1.2.3.4")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &real_ip, &trusted), ip("1.2.3.4"));
+        let garbage = headers(&[("x-forwarded-for", "unknown, not-an-ip"), ("x-real-ip", "also bad")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &garbage, &trusted), ip("10.0.0.1"));
+    }
+
+    #[test]
+    fn rate_limiter_counts_per_ip() {
+        let limiter = IpRateLimiter::new(Duration::from_secs(60), 2);
+        assert!(limiter.check(ip("1.2.3.4")));
+        assert!(limiter.check(ip("1.2.3.4")));
+        assert!(!limiter.check(ip("1.2.3.4")));
+        assert!(limiter.check(ip("5.6.7.8")));
+    }
+
+    #[test]
+    fn rate_limiter_resets_after_window_and_zero_disables() {
+        let limiter = IpRateLimiter::new(Duration::ZERO, 1);
+        assert!(limiter.check(ip("1.2.3.4")));
+        std::thread::sleep(Duration::from_millis(2));
+        assert!(limiter.check(ip("1.2.3.4")));
+
+        let unlimited = IpRateLimiter::new(Duration::from_secs(60), 0);
+        assert!((0..100).all(|_| unlimited.check(ip("1.2.3.4"))));
+    }
+}
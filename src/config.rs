@@ -1,6 +1,6 @@
 use anyhow::Result;
 use once_cell::sync::OnceCell;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::time::Duration;
 
@@ -12,12 +12,28 @@ pub struct Config {
     pub websocket: WebSocketConfig,
     pub game: GameConfig,
     pub redis: RedisConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
     pub log: LogConfig,
     pub security: SecurityConfig,
     pub auth: AuthConfig,
     pub cors: CorsConfig,
     pub word_bank: WordBankConfig,
     pub admin: AdminConfig,
+    #[serde(default)]
+    pub stickers: StickerConfig,
+    #[serde(default)]
+    pub bots: BotConfig,
+    #[serde(default)]
+    pub cluster: ClusterConfig,
+    #[serde(default)]
+    pub stats: StatsConfig,
+    #[serde(default)]
+    pub feed: FeedConfig,
+    #[serde(default)]
+    pub avatar: AvatarConfig,
+    #[serde(default)]
+    pub chatroom: ChatroomConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +43,10 @@ pub struct ServerConfig {
     pub workers: usize,
     pub http_port: Option<u16>, // HTTP服务器端口，如果为None则使用port
     pub ws_port: Option<u16>,   // WebSocket服务器端口，如果为None则使用port
+    pub tls_cert_path: Option<String>, // TLS证书（PEM）路径，与tls_key_path同时设置时直接提供https/wss
+    pub tls_key_path: Option<String>,  // TLS私钥（PEM）路径
+    #[serde(default)]
+    pub trusted_proxies: Vec<String>, // 可信反向代理的地址或网段，只有来自这些地址的转发头才会被采用
 }
 
 #[derive(Debug, Deserialize)]
@@ -34,6 +54,65 @@ pub struct WebSocketConfig {
     pub path: String,
     pub ping_interval: u64,
     pub ping_timeout: u64,
+    /// 高延迟阈值（毫秒），平均延迟持续超过该值的玩家会获得更长的断线重连宽限时间
+    #[serde(default = "default_high_latency_threshold")]
+    pub high_latency_threshold: u64,
+    /// 单条入站消息（帧）的最大字节数，超出时断开连接
+    #[serde(default = "default_max_message_size")]
+    pub max_message_size: usize,
+    /// 消息 data 字段序列化后的最大字节数
+    #[serde(default = "default_max_data_size")]
+    pub max_data_size: usize,
+    /// 聊天内容的最大字符数
+    #[serde(default = "default_max_chat_length")]
+    pub max_chat_length: usize,
+    /// 描述内容和猜词的最大字符数
+    #[serde(default = "default_max_description_length")]
+    pub max_description_length: usize,
+    /// 增量更新客户端重新接收一次完整状态的间隔（秒），0 表示只在首次发送完整状态
+    #[serde(default = "default_full_state_sync_interval")]
+    pub full_state_sync_interval: u64,
+    /// 每名玩家缓存的最近未确认消息条数，用于重连后补发，0 表示不缓存
+    #[serde(default = "default_replay_buffer_size")]
+    pub replay_buffer_size: usize,
+    /// 房间消息的合并窗口（毫秒），窗口内发往同一玩家的多条消息一并发出，0 表示立即发送
+    #[serde(default = "default_batch_window")]
+    pub batch_window: u64,
+    /// 同一客户端IP同时保持的WebSocket连接数上限，0 表示不限制
+    #[serde(default)]
+    pub max_connections_per_ip: usize,
+}
+
+fn default_high_latency_threshold() -> u64 {
+    500
+}
+
+fn default_max_message_size() -> usize {
+    16 * 1024
+}
+
+fn default_max_data_size() -> usize {
+    8 * 1024
+}
+
+fn default_max_chat_length() -> usize {
+    200
+}
+
+fn default_max_description_length() -> usize {
+    100
+}
+
+fn default_full_state_sync_interval() -> u64 {
+    60
+}
+
+fn default_replay_buffer_size() -> usize {
+    100
+}
+
+fn default_batch_window() -> u64 {
+    20
 }
 
 #[derive(Debug, Deserialize)]
@@ -43,12 +122,155 @@ pub struct GameConfig {
     pub describe_time_limit: u64,
     pub vote_time_limit: u64,
     pub round_delay: u64,
+    /// 断线重连宽限时间（秒），在此时间内重连将保留玩家的准备状态
+    #[serde(default = "default_reconnect_grace_period")]
+    pub reconnect_grace_period: u64,
+    /// 对局中断线玩家的宽限时间（秒），超过后自动跳过其描述、投票和猜词
+    #[serde(default = "default_in_game_grace_period")]
+    pub in_game_grace_period: u64,
+    /// 卧底被淘汰后猜平民词的时间限制（秒）
+    #[serde(default = "default_guess_time_limit")]
+    pub guess_time_limit: u64,
+    /// 游戏结束后猜词小游戏的时间限制（秒）
+    #[serde(default = "default_word_quiz_time_limit")]
+    pub word_quiz_time_limit: u64,
+    /// 描述中出现自己词语的字符占比达到该值时拒绝（0.0-1.0，为0时不按字符检查），直接包含整个词语时总是拒绝
+    #[serde(default = "default_word_char_overlap")]
+    pub word_char_overlap: f32,
+    /// 角色分配表，为空时使用内置标准表
+    #[serde(default)]
+    pub role_table: Vec<RoleTableEntry>,
+    /// 大厅或游戏结束后无人操作超过该时间（秒）时关闭房间
+    #[serde(default = "default_lobby_idle_timeout")]
+    pub lobby_idle_timeout: u64,
+    /// 同一玩家的加入、离开、重连通知在该时间（秒）内最多广播一次，0 表示不限制
+    #[serde(default = "default_presence_debounce")]
+    pub presence_debounce: u64,
+    /// 大厅中未准备的玩家超过该时间（秒）没有操作时自动移出房间，0 表示不移出
+    #[serde(default = "default_afk_timeout")]
+    pub afk_timeout: u64,
+    /// 关闭前的提醒时间点（距离关闭的秒数）
+    #[serde(default = "default_idle_warning_thresholds")]
+    pub idle_warning_thresholds: Vec<u64>,
+    /// 全服同时进行的对局数量上限，0 表示不限制
+    #[serde(default)]
+    pub max_concurrent_games: usize,
+    /// 房间邀请码的默认有效期（秒）
+    #[serde(default = "default_invite_ttl")]
+    pub invite_ttl: u64,
+    /// 房间邀请码的最长有效期（秒）
+    #[serde(default = "default_max_invite_ttl")]
+    pub max_invite_ttl: u64,
+}
+
+/// 角色分配表的一档：玩家人数达到 players 时使用的卧底和白板数量
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleTableEntry {
+    pub players: usize,
+    pub undercovers: usize,
+    /// 白板数量，仅在房间开启白板时生效
+    #[serde(default)]
+    pub blanks: usize,
+}
+
+fn default_reconnect_grace_period() -> u64 {
+    30
+}
+
+fn default_in_game_grace_period() -> u64 {
+    60
+}
+
+pub(crate) fn default_word_char_overlap() -> f32 {
+    1.0
+}
+
+pub(crate) fn default_guess_time_limit() -> u64 {
+    30
+}
+
+fn default_word_quiz_time_limit() -> u64 {
+    20
+}
+
+fn default_lobby_idle_timeout() -> u64 {
+    900
+}
+
+fn default_presence_debounce() -> u64 {
+    10
+}
+
+fn default_afk_timeout() -> u64 {
+    300
+}
+
+fn default_idle_warning_thresholds() -> Vec<u64> {
+    vec![120, 60]
+}
+
+fn default_invite_ttl() -> u64 {
+    3600
+}
+
+fn default_max_invite_ttl() -> u64 {
+    86400
 }
 
 #[derive(Debug, Deserialize)]
 pub struct RedisConfig {
     pub url: String,
     pub pool_size: u32,
+    /// Redis 不可用期间最多缓存的待补写写操作数量，超出时丢弃最早的
+    #[serde(default = "default_max_pending_writes")]
+    pub max_pending_writes: usize,
+    /// 降级期间检查 Redis 是否恢复的间隔（秒）
+    #[serde(default = "default_reconcile_interval")]
+    pub reconcile_interval: u64,
+}
+
+fn default_max_pending_writes() -> usize {
+    5000
+}
+
+fn default_reconcile_interval() -> u64 {
+    5
+}
+
+/// 存储后端
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    /// Redis，支持降级缓存和主备部署
+    #[default]
+    Redis,
+    /// SQLite 数据库文件，保存对局结果、玩家统计和词库等长期数据，不支持主备部署
+    Sqlite,
+    /// 进程内存，重启后数据丢失，用于测试和单机演示
+    Memory,
+}
+
+/// 存储配置
+#[derive(Debug, Deserialize)]
+pub struct StorageConfig {
+    #[serde(default)]
+    pub backend: StorageKind,
+    /// SQLite 数据库文件路径，不存在时自动创建
+    #[serde(default = "default_sqlite_path")]
+    pub sqlite_path: String,
+}
+
+fn default_sqlite_path() -> String {
+    "data/undercover.db".to_string()
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            backend: StorageKind::default(),
+            sqlite_path: default_sqlite_path(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -61,6 +283,36 @@ pub struct LogConfig {
 pub struct SecurityConfig {
     pub rate_limits: RateLimitConfig,
     pub word_filter: WordFilterConfig,
+    #[serde(default)]
+    pub moderation: ModerationConfig,
+}
+
+/// 房间内敏感词违规的自动处理配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModerationConfig {
+    /// 一局内累计违规达到该次数时自动禁言，为0时不禁言
+    #[serde(default = "default_strike_limit")]
+    pub strike_limit: u32,
+    /// 自动禁言时长（分钟）
+    #[serde(default = "default_mute_minutes")]
+    pub mute_minutes: u64,
+}
+
+fn default_strike_limit() -> u32 {
+    3
+}
+
+fn default_mute_minutes() -> u64 {
+    5
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        ModerationConfig {
+            strike_limit: default_strike_limit(),
+            mute_minutes: default_mute_minutes(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -78,12 +330,117 @@ pub struct WordFilterConfig {
     pub sensitive_words: Vec<String>,
     pub custom_words: Vec<String>,
     pub replacement: String,
+    /// 命中敏感词时的处理方式：replace（替换）、reject（拒绝）、drop（静默丢弃）
+    #[serde(default)]
+    pub action: crate::security::FilterAction,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct AuthConfig {
     pub domain: String,
     pub ws_domain: Option<String>, // WebSocket域名，如果为None则使用domain
+    /// 是否允许不登录摸鱼派以游客身份游玩
+    #[serde(default)]
+    pub guest_enabled: bool,
+    /// 游客身份的有效期（小时），到期后需要重新进入
+    #[serde(default = "default_guest_session_hours")]
+    pub guest_session_hours: u64,
+    /// 游客昵称最大长度（字符数）
+    #[serde(default = "default_guest_nickname_max_length")]
+    pub guest_nickname_max_length: usize,
+    /// 同一IP创建游客身份的限流窗口（秒）
+    #[serde(default = "default_guest_rate_limit_window")]
+    pub guest_rate_limit_window: u64,
+    /// 同一IP在一个窗口内最多创建的游客身份数，0 表示不限制
+    #[serde(default = "default_guest_rate_limit_max")]
+    pub guest_rate_limit_max: u32,
+    /// 同一IP发起登录的限流窗口（秒）
+    #[serde(default = "default_login_rate_limit_window")]
+    pub login_rate_limit_window: u64,
+    /// 同一IP在一个窗口内最多发起的登录数，0 表示不限制
+    #[serde(default = "default_login_rate_limit_max")]
+    pub login_rate_limit_max: u32,
+    /// JWT 签名密钥（HS256），配置后登录时同时签发令牌，可代替URL中的会话ID
+    #[serde(default)]
+    pub jwt_secret: Option<String>,
+    /// JWT 有效期（分钟），过期后可用仍有效的会话重新获取
+    #[serde(default = "default_jwt_ttl_minutes")]
+    pub jwt_ttl_minutes: u64,
+    /// 摸鱼派之外的 OAuth2 / OIDC 登录方式
+    #[serde(default)]
+    pub providers: Vec<OAuthProviderConfig>,
+}
+
+fn default_jwt_ttl_minutes() -> u64 {
+    1440
+}
+
+/// OAuth2 / OIDC 登录方式配置，使用授权码流程并从 userinfo 接口读取用户资料
+#[derive(Debug, Deserialize, Clone)]
+pub struct OAuthProviderConfig {
+    /// 登录方式标识，只能包含小写字母、数字、- 和 _，用于登录地址和用户ID前缀
+    pub id: String,
+    /// 展示名称
+    pub name: String,
+    pub authorize_url: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// userinfo 中作为唯一ID的字段
+    #[serde(default = "default_oauth_id_field")]
+    pub id_field: String,
+    /// userinfo 中的用户名字段，缺失时使用唯一ID
+    #[serde(default = "default_oauth_username_field")]
+    pub username_field: String,
+    /// userinfo 中的昵称字段
+    #[serde(default = "default_oauth_nickname_field")]
+    pub nickname_field: Option<String>,
+    /// userinfo 中的头像字段
+    #[serde(default = "default_oauth_avatar_field")]
+    pub avatar_field: Option<String>,
+}
+
+fn default_oauth_id_field() -> String {
+    "sub".to_string()
+}
+
+fn default_oauth_username_field() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_oauth_nickname_field() -> Option<String> {
+    Some("name".to_string())
+}
+
+fn default_oauth_avatar_field() -> Option<String> {
+    Some("picture".to_string())
+}
+
+fn default_guest_session_hours() -> u64 {
+    24
+}
+
+fn default_guest_nickname_max_length() -> usize {
+    16
+}
+
+fn default_guest_rate_limit_window() -> u64 {
+    3600
+}
+
+fn default_guest_rate_limit_max() -> u32 {
+    10
+}
+
+fn default_login_rate_limit_window() -> u64 {
+    60
+}
+
+fn default_login_rate_limit_max() -> u32 {
+    20
 }
 
 #[derive(Debug, Deserialize)]
@@ -98,12 +455,380 @@ pub struct WordBankConfig {
     pub min_similarity: f32,
     pub max_words_per_category: usize,
     pub enable_categories: bool,
+    /// 统计全服分类使用情况的时间窗口（小时）
+    #[serde(default = "default_usage_window_hours")]
+    pub usage_window_hours: u64,
+    /// 分类在窗口内每被抽取一次，权重按 1 / (1 + 惩罚系数 × 次数) 降低；为0时不降低
+    #[serde(default = "default_usage_penalty")]
+    pub usage_penalty: f64,
+}
+
+fn default_usage_window_hours() -> u64 {
+    6
+}
+
+fn default_usage_penalty() -> f64 {
+    0.5
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct AdminConfig {
     /// 管理员用户名列表
     pub admin_usernames: Vec<String>,
+    /// 管理员令牌默认有效期（分钟）
+    #[serde(default = "default_admin_token_ttl")]
+    pub token_ttl_minutes: u64,
+    /// 管理员令牌最长有效期（分钟）
+    #[serde(default = "default_admin_max_token_ttl")]
+    pub max_token_ttl_minutes: u64,
+    /// 管理接口限流时间窗口（秒）
+    #[serde(default = "default_admin_rate_limit_window")]
+    pub rate_limit_window: u64,
+    /// 管理接口时间窗口内允许的最大请求数
+    #[serde(default = "default_admin_rate_limit_max_requests")]
+    pub rate_limit_max_requests: u32,
+    /// 审计日志最多保留的记录数，超出后丢弃最早的记录
+    #[serde(default = "default_admin_audit_log_max_len")]
+    pub audit_log_max_len: usize,
+}
+
+fn default_admin_token_ttl() -> u64 {
+    60
+}
+
+fn default_admin_max_token_ttl() -> u64 {
+    1440
+}
+
+fn default_admin_rate_limit_window() -> u64 {
+    60
+}
+
+fn default_admin_audit_log_max_len() -> usize {
+    10000
+}
+
+fn default_admin_rate_limit_max_requests() -> u32 {
+    30
+}
+
+/// 房间自定义表情包配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct StickerConfig {
+    /// 允许的表情图片域名，表情URL必须为https且域名在此列表中
+    #[serde(default = "default_sticker_allowed_hosts")]
+    pub allowed_hosts: Vec<String>,
+    /// 每个房间最多登记的表情数量
+    #[serde(default = "default_max_stickers_per_room")]
+    pub max_per_room: usize,
+    /// 表情URL最大长度
+    #[serde(default = "default_max_sticker_url_length")]
+    pub max_url_length: usize,
+    /// 发送表情限流时间窗口（秒）
+    #[serde(default = "default_sticker_rate_limit_window")]
+    pub rate_limit_window: u64,
+    /// 时间窗口内每名玩家允许发送的最大表情数
+    #[serde(default = "default_sticker_rate_limit_max")]
+    pub rate_limit_max: u32,
+}
+
+impl Default for StickerConfig {
+    fn default() -> Self {
+        StickerConfig {
+            allowed_hosts: default_sticker_allowed_hosts(),
+            max_per_room: default_max_stickers_per_room(),
+            max_url_length: default_max_sticker_url_length(),
+            rate_limit_window: default_sticker_rate_limit_window(),
+            rate_limit_max: default_sticker_rate_limit_max(),
+        }
+    }
+}
+
+fn default_sticker_allowed_hosts() -> Vec<String> {
+    vec!["file.fishpi.cn".to_string()]
+}
+
+fn default_max_stickers_per_room() -> usize {
+    16
+}
+
+fn default_max_sticker_url_length() -> usize {
+    256
+}
+
+fn default_sticker_rate_limit_window() -> u64 {
+    10
+}
+
+fn default_sticker_rate_limit_max() -> u32 {
+    3
+}
+
+/// 外部机器人接入配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct BotConfig {
+    /// 允许接入的机器人及其API密钥
+    #[serde(default)]
+    pub api_keys: Vec<BotApiKey>,
+    /// 每个房间最多容纳的机器人数量
+    #[serde(default = "default_max_bots_per_room")]
+    pub max_per_room: usize,
+}
+
+/// 外部机器人的API密钥，name 用于生成机器人的玩家ID和昵称
+#[derive(Debug, Deserialize, Clone)]
+pub struct BotApiKey {
+    pub name: String,
+    pub key: String,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        BotConfig {
+            api_keys: Vec::new(),
+            max_per_room: default_max_bots_per_room(),
+        }
+    }
+}
+
+impl BotConfig {
+    /// 根据API密钥查找机器人，密钥按固定时间比较
+    pub fn find(&self, key: &str) -> Option<&BotApiKey> {
+        self.api_keys
+            .iter()
+            .find(|bot| !bot.key.is_empty() && crate::security::constant_time_eq(bot.key.as_bytes(), key.as_bytes()))
+    }
+}
+
+fn default_max_bots_per_room() -> usize {
+    4
+}
+
+/// 主备部署配置：主实例通过Redis持续写入房间检查点，备用实例在主实例失联后接管
+#[derive(Debug, Deserialize, Clone)]
+pub struct ClusterConfig {
+    /// 是否启用主备模式
+    #[serde(default)]
+    pub enabled: bool,
+    /// 实例标识，为空时启动时随机生成
+    #[serde(default)]
+    pub instance_id: String,
+    /// 主实例写入房间检查点的间隔（秒）
+    #[serde(default = "default_checkpoint_interval")]
+    pub checkpoint_interval: u64,
+    /// 主实例租约有效期（秒），主实例超过该时长未续约时由备用实例接管
+    #[serde(default = "default_lease_ttl")]
+    pub lease_ttl: u64,
+    /// 接管时忽略超过该时长（秒）未更新的检查点
+    #[serde(default = "default_checkpoint_max_age")]
+    pub checkpoint_max_age: u64,
+}
+
+fn default_checkpoint_interval() -> u64 {
+    2
+}
+
+fn default_lease_ttl() -> u64 {
+    10
+}
+
+fn default_checkpoint_max_age() -> u64 {
+    600
+}
+
+/// 每日对局统计汇总配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct StatsConfig {
+    /// 检查并汇总前一天对局记录的间隔（秒）
+    #[serde(default = "default_aggregate_interval")]
+    pub aggregate_interval: u64,
+    /// 每日汇总保留天数
+    #[serde(default = "default_stats_retention_days")]
+    pub retention_days: u32,
+}
+
+fn default_aggregate_interval() -> u64 {
+    3600
+}
+
+fn default_stats_retention_days() -> u32 {
+    90
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        StatsConfig {
+            aggregate_interval: default_aggregate_interval(),
+            retention_days: default_stats_retention_days(),
+        }
+    }
+}
+
+/// 公开房间目录订阅源配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct FeedConfig {
+    /// 游戏页面的公网地址，用于生成加入链接，为空时使用相对链接
+    #[serde(default)]
+    pub public_url: String,
+    /// 订阅源允许客户端和代理缓存的时间（秒）
+    #[serde(default = "default_feed_cache_ttl")]
+    pub cache_ttl: u64,
+    /// 订阅源最多列出的房间数量
+    #[serde(default = "default_feed_max_items")]
+    pub max_items: usize,
+}
+
+fn default_feed_cache_ttl() -> u64 {
+    30
+}
+
+fn default_feed_max_items() -> usize {
+    50
+}
+
+impl FeedConfig {
+    /// 房间的加入链接
+    pub fn join_url(&self, room_id: &str) -> String {
+        format!("{}/?room_id={}", self.public_url.trim_end_matches('/'), urlencoding::encode(room_id))
+    }
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        FeedConfig {
+            public_url: String::new(),
+            cache_ttl: default_feed_cache_ttl(),
+            max_items: default_feed_max_items(),
+        }
+    }
+}
+
+/// 摸鱼派聊天室集成配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct ChatroomConfig {
+    /// 摸鱼派地址
+    #[serde(default = "default_chatroom_base_url")]
+    pub base_url: String,
+    /// 发送消息使用的机器人账号 API Key，为空时不启用聊天室集成
+    #[serde(default)]
+    pub api_key: String,
+    /// 公开房间的对局结束后向聊天室发送战报
+    #[serde(default)]
+    pub post_game_results: bool,
+    /// 发送战报的最少玩家数（含机器人）
+    #[serde(default = "default_chatroom_min_players")]
+    pub min_players: usize,
+    /// 监听聊天室中的游戏指令，如 "卧底 开房"
+    #[serde(default)]
+    pub commands_enabled: bool,
+    /// 游戏指令的前缀
+    #[serde(default = "default_chatroom_command_prefix")]
+    pub command_prefix: String,
+    /// 同一用户两次开房指令的最短间隔（秒）
+    #[serde(default = "default_chatroom_command_cooldown")]
+    pub command_cooldown: u64,
+    /// 聊天室连接断开后的重连间隔（秒）
+    #[serde(default = "default_chatroom_reconnect_interval")]
+    pub reconnect_interval: u64,
+}
+
+fn default_chatroom_base_url() -> String {
+    "https://fishpi.cn".to_string()
+}
+
+fn default_chatroom_min_players() -> usize {
+    4
+}
+
+fn default_chatroom_command_prefix() -> String {
+    "卧底".to_string()
+}
+
+fn default_chatroom_command_cooldown() -> u64 {
+    60
+}
+
+fn default_chatroom_reconnect_interval() -> u64 {
+    10
+}
+
+impl Default for ChatroomConfig {
+    fn default() -> Self {
+        ChatroomConfig {
+            base_url: default_chatroom_base_url(),
+            api_key: String::new(),
+            post_game_results: false,
+            min_players: default_chatroom_min_players(),
+            commands_enabled: false,
+            command_prefix: default_chatroom_command_prefix(),
+            command_cooldown: default_chatroom_command_cooldown(),
+            reconnect_interval: default_chatroom_reconnect_interval(),
+        }
+    }
+}
+
+/// 头像代理配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct AvatarConfig {
+    /// 是否通过 /avatars/{user_id} 代理外部头像，启用后用户头像地址改写为代理地址
+    #[serde(default = "default_avatar_enabled")]
+    pub enabled: bool,
+    /// 头像在 Redis 中的缓存时间（秒），同时用作浏览器缓存时间
+    #[serde(default = "default_avatar_cache_ttl")]
+    pub cache_ttl: u64,
+    /// 单个头像的大小上限（字节）
+    #[serde(default = "default_avatar_max_bytes")]
+    pub max_bytes: u64,
+    /// 下载外部头像的超时时间（秒）
+    #[serde(default = "default_avatar_fetch_timeout")]
+    pub fetch_timeout: u64,
+    /// 允许代理的头像域名，头像URL必须为https且域名在此列表中，否则不经代理
+    #[serde(default = "default_avatar_allowed_hosts")]
+    pub allowed_hosts: Vec<String>,
+}
+
+fn default_avatar_enabled() -> bool {
+    true
+}
+
+fn default_avatar_cache_ttl() -> u64 {
+    86400
+}
+
+fn default_avatar_max_bytes() -> u64 {
+    512 * 1024
+}
+
+fn default_avatar_fetch_timeout() -> u64 {
+    5
+}
+
+fn default_avatar_allowed_hosts() -> Vec<String> {
+    vec!["file.fishpi.cn".to_string()]
+}
+
+impl Default for AvatarConfig {
+    fn default() -> Self {
+        AvatarConfig {
+            enabled: default_avatar_enabled(),
+            cache_ttl: default_avatar_cache_ttl(),
+            max_bytes: default_avatar_max_bytes(),
+            fetch_timeout: default_avatar_fetch_timeout(),
+            allowed_hosts: default_avatar_allowed_hosts(),
+        }
+    }
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        ClusterConfig {
+            enabled: false,
+            instance_id: String::new(),
+            checkpoint_interval: default_checkpoint_interval(),
+            lease_ttl: default_lease_ttl(),
+            checkpoint_max_age: default_checkpoint_max_age(),
+        }
+    }
 }
 
 impl Config {
@@ -130,6 +855,11 @@ impl Config {
         CONFIG.get().expect("配置未初始化，请先调用 Config::init()")
     }
 
+    /// 获取全局配置实例，未初始化时返回 None（嵌入游戏引擎、不读取配置文件时使用）
+    pub fn try_get() -> Option<&'static Config> {
+        CONFIG.get()
+    }
+
     pub fn server_addr(&self) -> SocketAddr {
         format!("{}:{}", self.server.host, self.server.port)
             .parse()
@@ -170,6 +900,54 @@ impl Config {
         Duration::from_secs(self.game.round_delay)
     }
 
+    pub fn guess_time_limit(&self) -> Duration {
+        Duration::from_secs(self.game.guess_time_limit)
+    }
+
+    pub fn word_quiz_time_limit(&self) -> Duration {
+        Duration::from_secs(self.game.word_quiz_time_limit)
+    }
+
+    pub fn invite_ttl(&self) -> Duration {
+        Duration::from_secs(self.game.invite_ttl)
+    }
+
+    pub fn max_invite_ttl(&self) -> Duration {
+        Duration::from_secs(self.game.max_invite_ttl)
+    }
+
+    pub fn reconnect_grace_period(&self) -> Duration {
+        Duration::from_secs(self.game.reconnect_grace_period)
+    }
+
+    pub fn in_game_grace_period(&self) -> Duration {
+        Duration::from_secs(self.game.in_game_grace_period)
+    }
+
+    pub fn lobby_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.game.lobby_idle_timeout)
+    }
+
+    pub fn presence_debounce(&self) -> Duration {
+        Duration::from_secs(self.game.presence_debounce)
+    }
+
+    pub fn afk_timeout(&self) -> Duration {
+        Duration::from_secs(self.game.afk_timeout)
+    }
+
+    pub fn full_state_sync_interval(&self) -> Duration {
+        Duration::from_secs(self.websocket.full_state_sync_interval)
+    }
+
+    pub fn batch_window(&self) -> Duration {
+        Duration::from_millis(self.websocket.batch_window)
+    }
+
+    pub fn high_latency_threshold(&self) -> Duration {
+        Duration::from_millis(self.websocket.high_latency_threshold)
+    }
+
     pub fn log_filter(&self) -> String {
         format!("fishpi_undercover={}", self.log.level)
     }
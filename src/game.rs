@@ -1,5 +1,7 @@
-use crate::message::GameStateType;
-use crate::word_bank::WordBank;
+use crate::config::RoleTableEntry;
+use crate::message::{ErrorCode, GameStateType};
+use crate::security::FilterAction;
+use crate::word_bank::{Difficulty, WordBank};
 use chrono::{DateTime, Utc};
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -7,6 +9,7 @@ use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::sync::Arc;
 use std::time::Duration;
+use tracing::warn;
 
 /// 玩家ID类型
 pub type PlayerId = String;
@@ -16,6 +19,8 @@ pub type PlayerId = String;
 pub enum Role {
     Undercover,
     Civilian,
+    /// 白板：没有词语，需要靠伪装存活，与卧底同一阵营
+    Blank,
 }
 
 impl fmt::Display for Role {
@@ -23,6 +28,17 @@ impl fmt::Display for Role {
         match self {
             Role::Undercover => write!(f, "卧底"),
             Role::Civilian => write!(f, "平民"),
+            Role::Blank => write!(f, "白板"),
+        }
+    }
+}
+
+impl Role {
+    /// 检查该角色是否属于获胜阵营（白板与卧底同一阵营）
+    pub fn wins_with(&self, winner: Role) -> bool {
+        match self {
+            Role::Blank => winner != Role::Civilian,
+            role => *role == winner,
         }
     }
 }
@@ -38,6 +54,13 @@ pub struct Player {
     pub last_action: DateTime<Utc>,
 }
 
+/// 房主为房间登记的自定义表情
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sticker {
+    pub id: String,
+    pub url: String,
+}
+
 /// 聊天消息
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -45,9 +68,566 @@ pub struct ChatMessage {
     pub player_name: String,
     pub content: String,
     pub timestamp: DateTime<Utc>,
+    /// 表情消息附带的表情，普通文字消息为空
+    #[serde(default)]
+    pub sticker: Option<Sticker>,
+}
+
+/// 玩家操作类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Describe,
+    Vote,
+    Chat,
+    EliminatedChat,
+    GuessWord,
+    Concede,
+    Sticker,
+    /// 发言包含敏感词被记违规，内容为本局累计违规次数
+    Strike,
+    /// 违规次数达到上限被自动禁言，内容为禁言分钟数
+    Mute,
+}
+
+/// 对局中的一条玩家操作记录，用于赛后复盘和争议处理
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameAction {
+    pub player_id: PlayerId,
+    pub kind: ActionKind,
+    /// 描述、聊天内容或猜测的词语，投票时为被投票玩家ID
+    pub content: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// 单局最多保留的操作记录条数，超出时丢弃最早的记录
+pub const MAX_GAME_ACTIONS: usize = 500;
+
+/// 弃票时使用的投票目标，计入投票完成人数但不计入淘汰票数
+pub const ABSTAIN_VOTE: &str = "skip";
+
+/// 对局计时统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GameClockStats {
+    /// 对局总时长（毫秒）
+    pub total_ms: u64,
+    /// 各阶段累计时长（毫秒），键为阶段名称
+    pub phase_ms: HashMap<GameStateType, u64>,
+    /// 每名玩家平均每次描述用时（毫秒）
+    pub average_describe_ms: HashMap<PlayerId, u64>,
+}
+
+/// 对局计时器，根据状态机记录的各阶段开始时间累计阶段耗时
+#[derive(Debug, Clone, Default)]
+pub struct GameClock {
+    started_at: Option<DateTime<Utc>>,
+    ended_at: Option<DateTime<Utc>>,
+    /// 当前计时片段：(阶段, 描述中的玩家, 开始时间)
+    segment: Option<(GameStateType, Option<PlayerId>, DateTime<Utc>)>,
+    phase_ms: HashMap<GameStateType, u64>,
+    /// 每名玩家的描述总用时（毫秒）和描述次数
+    describe_ms: HashMap<PlayerId, (u64, u32)>,
+}
+
+impl GameClock {
+    /// 开始为新的一局计时
+    pub fn start(now: DateTime<Utc>) -> Self {
+        GameClock {
+            started_at: Some(now),
+            ..Default::default()
+        }
+    }
+
+    /// 观察当前状态，阶段或描述玩家变化时结算上一个片段
+    pub fn observe(&mut self, state: &GameState, now: DateTime<Utc>) {
+        if self.started_at.is_none() || self.ended_at.is_some() {
+            return;
+        }
+
+        let state_type = state.get_state_type();
+        let next = state
+            .get_phase_timing()
+            .map(|(player, started_at)| (state_type.clone(), player, started_at));
+        if next.is_some() && next == self.segment {
+            return;
+        }
+
+        if let Some((phase, player, started_at)) = self.segment.take() {
+            // 片段在下一阶段开始时结束，没有下一阶段时以当前时间为准
+            let ended_at = next.as_ref().map_or(now, |(_, _, at)| *at);
+            let elapsed = (ended_at - started_at).num_milliseconds().max(0) as u64;
+            *self.phase_ms.entry(phase).or_default() += elapsed;
+            if let Some(player) = player {
+                let entry = self.describe_ms.entry(player).or_default();
+                entry.0 += elapsed;
+                entry.1 += 1;
+            }
+        }
+        self.segment = next;
+
+        if state_type == GameStateType::GameOver {
+            self.ended_at = Some(now);
+        }
+    }
+
+    /// 生成计时统计，对局未结束时以当前时间计算总时长
+    pub fn stats(&self, now: DateTime<Utc>) -> GameClockStats {
+        let total_ms = self.started_at.map_or(0, |started_at| {
+            (self.ended_at.unwrap_or(now) - started_at).num_milliseconds().max(0) as u64
+        });
+
+        GameClockStats {
+            total_ms,
+            phase_ms: self.phase_ms.clone(),
+            average_describe_ms: self
+                .describe_ms
+                .iter()
+                .map(|(player, (total, count))| (player.clone(), total / (*count).max(1) as u64))
+                .collect(),
+        }
+    }
+}
+
+/// 角色分配方案
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RolePlan {
+    pub civilians: usize,
+    pub undercovers: usize,
+    pub blanks: usize,
+}
+
+/// 内置标准角色分配表：(适用的最少玩家数, 卧底数, 白板数)
+const DEFAULT_ROLE_TABLE: [(usize, usize, usize); 4] = [(0, 1, 1), (7, 2, 1), (9, 3, 1), (10, 3, 2)];
+
+impl RolePlan {
+    /// 根据玩家人数和是否开启白板，从角色分配表中得出各角色人数，分配表为空时使用内置标准表
+    pub fn for_players(
+        player_count: usize,
+        blank_role: bool,
+        role_table: &[RoleTableEntry],
+    ) -> Result<RolePlan, String> {
+        let (undercovers, blanks) = if role_table.is_empty() {
+            DEFAULT_ROLE_TABLE
+                .iter()
+                .rev()
+                .find(|(players, ..)| *players <= player_count)
+                .map(|(_, undercovers, blanks)| (*undercovers, *blanks))
+                .unwrap_or((1, 0))
+        } else {
+            // 选择适用人数不超过当前人数的最大一档，人数低于所有档位时使用最小一档
+            let mut entries: Vec<_> = role_table.iter().collect();
+            entries.sort_by_key(|entry| entry.players);
+            entries
+                .iter()
+                .rev()
+                .find(|entry| entry.players <= player_count)
+                .or(entries.first())
+                .map(|entry| (entry.undercovers, entry.blanks))
+                .unwrap_or((1, 0))
+        };
+
+        let blanks = if blank_role { blanks } else { 0 };
+        let special = undercovers + blanks;
+        if undercovers == 0 || special > player_count.saturating_sub(special) {
+            return Err(format!(
+                "{} 名玩家无法分配 {} 名卧底和 {} 名白板",
+                player_count, undercovers, blanks
+            ));
+        }
+
+        Ok(RolePlan {
+            civilians: player_count - special,
+            undercovers,
+            blanks,
+        })
+    }
+}
+
+/// 房主可调整的房间设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    /// 淘汰揭晓延迟（秒），为0时票数和淘汰结果同时公布
+    pub reveal_delay_secs: u64,
+    /// 分段揭晓时是否同时公开被淘汰玩家的身份
+    pub reveal_role: bool,
+    /// 是否加入白板角色
+    pub blank_role: bool,
+    /// 是否允许投票给自己
+    pub allow_self_vote: bool,
+    /// 是否在大厅中展示玩家延迟
+    pub show_latency: bool,
+    /// 平民词与卧底词允许的最大字数差，为空时不限制
+    pub max_word_length_diff: Option<usize>,
+    /// 卧底被淘汰时是否可以猜平民词，猜中则卧底方反败为胜
+    pub undercover_guess: bool,
+    /// 平票时是否进入PK：平票玩家补充描述，其他玩家在他们之间重新投票
+    pub pk_on_tie: bool,
+    /// 抽词使用的词库分类，为空时从全部词库中随机抽取
+    pub word_category: Option<String>,
+    /// 抽词难度，为空时不限难度
+    pub word_difficulty: Option<Difficulty>,
+    /// 是否为排位赛，开启时每局结束后更新玩家等级分
+    pub ranked: bool,
+    /// 玩家被淘汰时是否私下告知其本轮完整投票明细（不受通知偏好影响）
+    pub vote_recap: bool,
+    /// 游戏结束后是否先进行猜词小游戏：平民猜卧底词、卧底和白板猜平民词，猜中可获得系列赛加分
+    pub word_quiz: bool,
+    /// 命中敏感词时的处理方式，为空时使用全局配置
+    pub sensitive_word_action: Option<FilterAction>,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            reveal_delay_secs: 0,
+            reveal_role: false,
+            blank_role: false,
+            allow_self_vote: true,
+            show_latency: false,
+            max_word_length_diff: None,
+            undercover_guess: false,
+            pk_on_tie: false,
+            word_category: None,
+            word_difficulty: None,
+            ranked: true,
+            vote_recap: false,
+            word_quiz: false,
+            sensitive_word_action: None,
+        }
+    }
+}
+
+impl GameSettings {
+    /// 淘汰揭晓延迟上限（秒）
+    pub const MAX_REVEAL_DELAY_SECS: u64 = 10;
+
+    /// 开启白板时所需的最少玩家数
+    pub const MIN_PLAYERS_WITH_BLANK: usize = 4;
+
+    /// 校验设置是否合法
+    pub fn validate(&self) -> Result<(), String> {
+        if self.reveal_delay_secs > Self::MAX_REVEAL_DELAY_SECS {
+            return Err(format!(
+                "淘汰揭晓延迟不能超过 {} 秒",
+                Self::MAX_REVEAL_DELAY_SECS
+            ));
+        }
+        Ok(())
+    }
+
+    /// 获取淘汰揭晓延迟，未开启分段揭晓时返回None
+    pub fn reveal_delay(&self) -> Option<Duration> {
+        (self.reveal_delay_secs > 0).then(|| Duration::from_secs(self.reveal_delay_secs))
+    }
+}
+
+/// 房间级别的游戏参数，在创建房间时确定，未指定的项沿用全局配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameParams {
+    pub min_players: usize,
+    pub max_players: usize,
+    /// 每位玩家的描述时间（秒）
+    pub describe_time_limit: u64,
+    /// 投票时间（秒）
+    pub vote_time_limit: u64,
+    /// 结果展示后进入下一轮的间隔（秒）
+    pub round_delay: u64,
+    /// 卧底被淘汰后猜平民词的时间限制（秒）
+    #[serde(default = "crate::config::default_guess_time_limit")]
+    pub guess_time_limit: u64,
+    /// 描述中出现自己词语的字符占比达到该值时拒绝，为0时不按字符检查
+    #[serde(default = "crate::config::default_word_char_overlap")]
+    pub word_char_overlap: f32,
+    /// 角色分配表，为空时使用内置标准表
+    #[serde(default)]
+    pub role_table: Vec<RoleTableEntry>,
+}
+
+impl GameParams {
+    /// 房间最少玩家数下限
+    pub const MIN_PLAYERS: usize = 3;
+
+    /// 描述、投票时间的允许范围（秒）
+    pub const TIME_LIMIT_RANGE: std::ops::RangeInclusive<u64> = 10..=300;
+
+    /// 轮次间隔的允许范围（秒）
+    pub const ROUND_DELAY_RANGE: std::ops::RangeInclusive<u64> = 1..=60;
+
+    /// 使用全局配置中的默认参数
+    pub fn from_config() -> Self {
+        let game = &crate::config::Config::get().game;
+        GameParams {
+            min_players: game.min_players,
+            max_players: game.max_players,
+            describe_time_limit: game.describe_time_limit,
+            vote_time_limit: game.vote_time_limit,
+            round_delay: game.round_delay,
+            guess_time_limit: game.guess_time_limit,
+            word_char_overlap: game.word_char_overlap,
+            role_table: game.role_table.clone(),
+        }
+    }
+
+    /// 校验参数是否合法，房间人数上限不能超过全局配置
+    pub fn validate(&self) -> Result<(), String> {
+        let max_allowed = crate::config::Config::get().game.max_players;
+        if self.min_players < Self::MIN_PLAYERS {
+            return Err(format!("最少玩家数不能小于 {}", Self::MIN_PLAYERS));
+        }
+        if self.min_players > self.max_players {
+            return Err("最少玩家数不能大于最多玩家数".to_string());
+        }
+        if self.max_players > max_allowed {
+            return Err(format!("最多玩家数不能超过 {}", max_allowed));
+        }
+        if !Self::TIME_LIMIT_RANGE.contains(&self.describe_time_limit)
+            || !Self::TIME_LIMIT_RANGE.contains(&self.vote_time_limit)
+        {
+            return Err(format!(
+                "描述和投票时间需在 {}-{} 秒之间",
+                Self::TIME_LIMIT_RANGE.start(),
+                Self::TIME_LIMIT_RANGE.end()
+            ));
+        }
+        if !Self::ROUND_DELAY_RANGE.contains(&self.round_delay) {
+            return Err(format!(
+                "轮次间隔需在 {}-{} 秒之间",
+                Self::ROUND_DELAY_RANGE.start(),
+                Self::ROUND_DELAY_RANGE.end()
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn describe_time_limit(&self) -> Duration {
+        Duration::from_secs(self.describe_time_limit)
+    }
+
+    pub fn vote_time_limit(&self) -> Duration {
+        Duration::from_secs(self.vote_time_limit)
+    }
+
+    pub fn guess_time_limit(&self) -> Duration {
+        Duration::from_secs(self.guess_time_limit)
+    }
+
+    pub fn round_delay(&self) -> Duration {
+        Duration::from_secs(self.round_delay)
+    }
+}
+
+impl Default for GameParams {
+    /// 与示例配置相同的参数，供不加载配置文件的场景使用
+    fn default() -> Self {
+        GameParams {
+            min_players: 4,
+            max_players: 12,
+            describe_time_limit: 60,
+            vote_time_limit: 60,
+            round_delay: 5,
+            guess_time_limit: crate::config::default_guess_time_limit(),
+            word_char_overlap: crate::config::default_word_char_overlap(),
+            role_table: Vec::new(),
+        }
+    }
+}
+
+/// 不经过房间和 WebSocket，直接构造一局已分配好身份和词语的游戏
+///
+/// 座位顺序即添加玩家的顺序，卧底和白板由调用方指定，不读取全局配置和词库，
+/// 适合模拟器、聊天机器人或测试代码嵌入游戏引擎。构造出的状态处于第一轮描述阶段，
+/// 之后通过 `GameState` 的 `add_description`、`advance_describe_phase`、`add_vote`、
+/// `process_result_phase` 等方法推进。
+///
+/// # 示例
+///
+/// ```
+/// use fishpi_undercover::game::{GameBuilder, GameEvent, GameParams, GameSettings, Role};
+///
+/// let params = GameParams::default();
+/// let settings = GameSettings::default();
+/// let mut game = GameBuilder::new()
+///     .players([("a", "阿狸"), ("b", "布丁"), ("c", "彩虹"), ("d", "多多")])
+///     .undercovers(["d"])
+///     .word_pair("苹果", "梨")
+///     .params(params.clone())
+///     .build()
+///     .unwrap();
+///
+/// // 按座位顺序依次描述，最后一位描述完进入投票阶段
+/// for id in ["a", "b", "c", "d"] {
+///     game.add_description(id.to_string(), format!("{} 的描述", id), &params).unwrap();
+///     game.advance_describe_phase(&params).unwrap();
+/// }
+///
+/// // 全员投给卧底，卧底出局后平民获胜
+/// for voter in ["a", "b", "c", "d"] {
+///     game.add_vote(voter.to_string(), "d".to_string(), &settings, &params).unwrap();
+/// }
+/// assert_eq!(game.get_eliminated_player().as_deref(), Some("d"));
+/// let event = game.process_result_phase(&settings, &params).unwrap();
+/// assert!(matches!(event, GameEvent::GameOver(Role::Civilian)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GameBuilder {
+    players: Vec<(PlayerId, String)>,
+    undercovers: Vec<PlayerId>,
+    blanks: Vec<PlayerId>,
+    word_pair: Option<(String, String)>,
+    host: Option<PlayerId>,
+    params: GameParams,
+}
+
+impl GameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 按座位顺序添加玩家：(玩家ID, 昵称)
+    pub fn players<I, K, N>(mut self, players: I) -> Self
+    where
+        I: IntoIterator<Item = (K, N)>,
+        K: Into<PlayerId>,
+        N: Into<String>,
+    {
+        self.players
+            .extend(players.into_iter().map(|(id, name)| (id.into(), name.into())));
+        self
+    }
+
+    /// 指定卧底玩家
+    pub fn undercovers<I, K>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<PlayerId>,
+    {
+        self.undercovers.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    /// 指定白板玩家，不指定时本局没有白板
+    pub fn blanks<I, K>(mut self, ids: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<PlayerId>,
+    {
+        self.blanks.extend(ids.into_iter().map(Into::into));
+        self
+    }
+
+    /// 设置本局的平民词和卧底词
+    pub fn word_pair(mut self, civilian_word: impl Into<String>, undercover_word: impl Into<String>) -> Self {
+        self.word_pair = Some((civilian_word.into(), undercover_word.into()));
+        self
+    }
+
+    /// 设置房主，不设置时为第一位玩家
+    pub fn host(mut self, host: impl Into<PlayerId>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// 设置计时参数，不设置时使用 `GameParams::default()`
+    pub fn params(mut self, params: GameParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// 校验配置并生成处于第一轮描述阶段的游戏状态
+    pub fn build(self) -> Result<GameState, String> {
+        if self.players.len() < GameParams::MIN_PLAYERS {
+            return Err(format!("至少需要 {} 名玩家", GameParams::MIN_PLAYERS));
+        }
+        let mut seen = HashSet::new();
+        if let Some((id, _)) = self.players.iter().find(|(id, _)| !seen.insert(id)) {
+            return Err(format!("玩家 {} 重复", id));
+        }
+
+        let (civilian_word, undercover_word) = self.word_pair.ok_or_else(|| "没有设置词对".to_string())?;
+        if self.undercovers.is_empty() {
+            return Err("至少需要一名卧底".to_string());
+        }
+        if !self.blanks.is_empty() && self.players.len() < GameSettings::MIN_PLAYERS_WITH_BLANK {
+            return Err(format!(
+                "开启白板需要至少 {} 名玩家",
+                GameSettings::MIN_PLAYERS_WITH_BLANK
+            ));
+        }
+        for id in self.undercovers.iter().chain(&self.blanks) {
+            if !seen.contains(id) {
+                return Err(format!("玩家 {} 不在本局中", id));
+            }
+        }
+        if let Some(id) = self.undercovers.iter().find(|id| self.blanks.contains(id)) {
+            return Err(format!("玩家 {} 不能同时是卧底和白板", id));
+        }
+
+        let special: HashSet<&PlayerId> = self.undercovers.iter().chain(&self.blanks).collect();
+        if special.len() > self.players.len() - special.len() {
+            return Err(format!(
+                "{} 名玩家无法分配 {} 名卧底和 {} 名白板",
+                self.players.len(),
+                self.undercovers.len(),
+                self.blanks.len()
+            ));
+        }
+
+        let host = self.host.unwrap_or_else(|| self.players[0].0.clone());
+        if !seen.contains(&host) {
+            return Err(format!("房主 {} 不在本局中", host));
+        }
+
+        let now = Utc::now();
+        let players = self
+            .players
+            .iter()
+            .map(|(id, name)| {
+                let (role, word) = if self.undercovers.contains(id) {
+                    (Role::Undercover, Some(undercover_word.clone()))
+                } else if self.blanks.contains(id) {
+                    (Role::Blank, None)
+                } else {
+                    (Role::Civilian, Some(civilian_word.clone()))
+                };
+                Player {
+                    id: id.clone(),
+                    name: name.clone(),
+                    role: Some(role),
+                    word,
+                    is_alive: true,
+                    last_action: now,
+                }
+            })
+            .collect();
+
+        Ok(GameState::DescribePhase {
+            players,
+            current_player_index: 0,
+            descriptions: HashMap::new(),
+            current_player_start_time: now,
+            player_duration: self.params.describe_time_limit(),
+            remaining_time: self.params.describe_time_limit(),
+            chat_messages: Vec::new(),
+            eliminated_chat_messages: Vec::new(),
+            host,
+            pk_candidates: Vec::new(),
+        })
+    }
+}
+
+/// 被淘汰卧底的猜词结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WordGuess {
+    pub player_id: PlayerId,
+    /// 猜测的词语，超时未猜时为空
+    pub guess: Option<String>,
+    pub correct: bool,
 }
 
 /// 游戏状态
+///
+/// 聊天记录不随状态快照序列化，单独持久化在按房间划分的Redis列表中，加载状态后通过 `restore_chat` 恢复。
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GameState {
     Lobby {
@@ -55,7 +635,10 @@ pub enum GameState {
         min_players: usize,
         max_players: usize,
         ready_players: HashSet<PlayerId>,
+        /// 聊天记录单独持久化在Redis列表中，不随状态快照序列化
+        #[serde(skip)]
         chat_messages: Vec<ChatMessage>,
+        #[serde(skip)]
         eliminated_chat_messages: Vec<ChatMessage>,
         host: PlayerId,
     },
@@ -69,9 +652,14 @@ pub enum GameState {
         current_player_start_time: DateTime<Utc>,
         player_duration: Duration,
         remaining_time: Duration,
+        #[serde(skip)]
         chat_messages: Vec<ChatMessage>,
+        #[serde(skip)]
         eliminated_chat_messages: Vec<ChatMessage>,
         host: PlayerId,
+        /// PK环节中参与补充描述的平票玩家，普通回合为空
+        #[serde(default)]
+        pk_candidates: Vec<PlayerId>,
     },
     VotePhase {
         players: Vec<Player>,
@@ -80,9 +668,14 @@ pub enum GameState {
         start_time: DateTime<Utc>,
         duration: Duration,
         remaining_time: Duration,
+        #[serde(skip)]
         chat_messages: Vec<ChatMessage>,
+        #[serde(skip)]
         eliminated_chat_messages: Vec<ChatMessage>,
         host: PlayerId,
+        /// PK环节中可以被投票的平票玩家，普通回合为空
+        #[serde(default)]
+        pk_candidates: Vec<PlayerId>,
     },
     ResultPhase {
         players: Vec<Player>,
@@ -91,28 +684,94 @@ pub enum GameState {
         next_round_delay: Duration,
         remaining_time: Duration,
         start_time: DateTime<Utc>,
+        #[serde(skip)]
+        chat_messages: Vec<ChatMessage>,
+        #[serde(skip)]
+        eliminated_chat_messages: Vec<ChatMessage>,
+        host: PlayerId,
+        /// 平票时得票最多的玩家
+        #[serde(default)]
+        tied: Vec<PlayerId>,
+        /// 本次结果是否来自PK投票
+        #[serde(default)]
+        pk_round: bool,
+    },
+    /// 卧底被淘汰后的猜词阶段
+    GuessPhase {
+        players: Vec<Player>,
+        guesser: PlayerId,
+        guess: Option<WordGuess>,
+        start_time: DateTime<Utc>,
+        duration: Duration,
+        remaining_time: Duration,
+        #[serde(skip)]
         chat_messages: Vec<ChatMessage>,
+        #[serde(skip)]
         eliminated_chat_messages: Vec<ChatMessage>,
         host: PlayerId,
     },
     GameOver {
         winner: Role,
         players: Vec<Player>,
+        #[serde(skip)]
         chat_messages: Vec<ChatMessage>,
+        #[serde(skip)]
         eliminated_chat_messages: Vec<ChatMessage>,
         host: PlayerId,
+        /// 卧底猜词结果，本局没有猜词时为空
+        #[serde(default)]
+        word_guess: Option<WordGuess>,
+        /// 主动认输的卧底，本局没有认输时为空
+        #[serde(default)]
+        conceded_by: Option<PlayerId>,
     },
 }
 
+/// 玩家离开房间的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaveReason {
+    /// 主动离开
+    Voluntary,
+    /// 被房主踢出
+    Kicked,
+    /// 被管理员移出房间
+    Banned,
+    /// 断线且超过重连宽限时间
+    ConnectionLost,
+    /// 长时间未操作
+    AfkTimeout,
+    /// 加入了其他房间
+    SwitchedRoom,
+}
+
+impl LeaveReason {
+    /// 用于通知消息的描述
+    pub fn describe(&self) -> &'static str {
+        match self {
+            LeaveReason::Voluntary => "离开了游戏",
+            LeaveReason::Kicked => "被踢出了房间",
+            LeaveReason::Banned => "被管理员移出了房间",
+            LeaveReason::ConnectionLost => "连接断开，已移出房间",
+            LeaveReason::AfkTimeout => "长时间未操作，已移出房间",
+            LeaveReason::SwitchedRoom => "加入了其他房间",
+        }
+    }
+}
+
 /// 游戏事件
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GameEvent {
     PlayerJoined(Player),
-    PlayerLeft(Player),
+    PlayerLeft(Player, LeaveReason),
+    /// 玩家准备，附带当前准备人数是否达到开局下限
     PlayerReady(PlayerId, bool),
-    GameStarted(Vec<Player>),
+    /// 玩家取消准备，附带当前准备人数是否达到开局下限
+    PlayerUnready(PlayerId, bool),
+    GameStarted(Vec<Player>, RolePlan),
     DescriptionAdded(PlayerId, String),
-    NextPlayer(PlayerId),
+    /// 轮到下一位玩家描述，附带其开始描述的时间
+    NextPlayer(PlayerId, DateTime<Utc>),
     DescribePhaseComplete,
     VoteAdded(PlayerId, PlayerId),
     VotePhaseComplete(HashMap<PlayerId, PlayerId>),
@@ -125,6 +784,21 @@ pub enum GameEvent {
     GameReset,
     CountdownUpdate(Duration),
     PlayerKicked(Player, PlayerId),
+    GuessPhaseStarted(PlayerId),
+    WordGuessed(WordGuess),
+    PkRoundStarted(Vec<PlayerId>),
+    UndercoverConceded(PlayerId),
+}
+
+/// 游戏状态事件日志中的一条记录
+///
+/// 能在上一个状态上无歧义重放的事件只记录事件本身，阶段切换等其余状态变化记录完整快照，
+/// 写入快照时之前的记录会被丢弃。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "data", rename_all = "snake_case")]
+pub enum StateRecord {
+    Snapshot(GameState),
+    Event(GameEvent),
 }
 
 /// 超时检测结果
@@ -134,6 +808,7 @@ pub enum TimeoutResult {
     DescribeTimeout(PlayerId), 
     VoteTimeout,
     ResultTimeout,
+    GuessTimeout,
 }
 
 impl GameState {
@@ -150,17 +825,87 @@ impl GameState {
         }
     }
 
+    /// 在当前状态上重放事件，与产生该事件的操作效果相同，无法重放时返回 false 且不修改状态
+    ///
+    /// 只有阶段内的增量变化（加入、准备、描述、轮到下一位、投票）可以重放，
+    /// 阶段切换等其余变化需要写入完整快照。聊天记录单独持久化，不经过状态事件日志。
+    pub fn apply_event(&mut self, event: &GameEvent) -> bool {
+        match (self, event) {
+            (GameState::Lobby { players, ready_players, .. }, GameEvent::PlayerJoined(player)) => {
+                if !players.contains_key(&player.id) {
+                    ready_players.remove(&player.id);
+                    players.insert(player.id.clone(), player.clone());
+                }
+                true
+            }
+            (GameState::Lobby { players, ready_players, .. }, GameEvent::PlayerReady(player_id, _)) => {
+                if !players.contains_key(player_id) {
+                    return false;
+                }
+                ready_players.insert(player_id.clone());
+                true
+            }
+            (GameState::Lobby { players, ready_players, .. }, GameEvent::PlayerUnready(player_id, _)) => {
+                if !players.contains_key(player_id) {
+                    return false;
+                }
+                ready_players.remove(player_id);
+                true
+            }
+            (GameState::DescribePhase { descriptions, .. }, GameEvent::DescriptionAdded(player_id, description)) => {
+                descriptions.insert(player_id.clone(), description.clone());
+                true
+            }
+            (
+                GameState::DescribePhase { players, current_player_index, current_player_start_time, .. },
+                GameEvent::NextPlayer(player_id, start_time),
+            ) => match players.iter().position(|p| p.id == *player_id) {
+                Some(index) => {
+                    *current_player_index = index;
+                    *current_player_start_time = *start_time;
+                    true
+                }
+                None => false,
+            },
+            (GameState::VotePhase { votes, .. }, GameEvent::VoteAdded(voter, target)) => {
+                votes.insert(voter.clone(), target.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 按顺序重放状态事件日志：从最近一次快照开始依次应用之后的事件，没有快照时返回 None
+    pub fn replay(records: impl IntoIterator<Item = StateRecord>) -> Option<GameState> {
+        let mut state: Option<GameState> = None;
+        for record in records {
+            match record {
+                StateRecord::Snapshot(snapshot) => state = Some(snapshot),
+                StateRecord::Event(event) => {
+                    let Some(current) = state.as_mut() else {
+                        continue;
+                    };
+                    if !current.apply_event(&event) {
+                        warn!("事件 {:?} 无法在当前状态上重放，已跳过", event);
+                    }
+                }
+            }
+        }
+        state
+    }
+
     /// 重置游戏状态（从GameOver状态重置到Lobby状态）
-    pub fn reset_game(&mut self) -> Result<GameEvent, String> {
+    pub fn reset_game(&mut self, params: &GameParams) -> Result<GameEvent, String> {
         match self {
-            GameState::GameOver { players, chat_messages, .. } => {
-                // 使用全局配置中的min_players和max_players设置
-                let config = crate::config::Config::get();
-                let min_players = config.game.min_players;
-                let max_players = config.game.max_players;
-
-                // 获取当前房主（第一个玩家）
-                let host = if let Some(first_player) = players.first() {
+            GameState::GameOver { players, chat_messages, host, .. } => {
+                // 使用房间参数中的min_players和max_players设置
+                let min_players = params.min_players;
+                let max_players = params.max_players;
+
+                // 保留当前房主，房主已不在房间时由第一个玩家接任
+                let host = if players.iter().any(|p| p.id == *host) {
+                    host.clone()
+                } else if let Some(first_player) = players.first() {
                     first_player.id.clone()
                 } else {
                     return Err("没有玩家可以成为房主".to_string());
@@ -193,6 +938,55 @@ impl GameState {
         }
     }
 
+    /// 中止对局，不计胜负，所有玩家回到大厅；卡在任意阶段（含游戏结束）的房间都可以中止
+    pub fn abort_game(&mut self, params: &GameParams) -> Result<(), String> {
+        if matches!(self, GameState::Lobby { .. }) {
+            return Err("房间已在大厅，没有可以结束的对局".to_string());
+        }
+
+        let players = self.get_players();
+        let host = self
+            .get_host()
+            .or_else(|| players.first().map(|p| p.id.clone()))
+            .ok_or_else(|| "没有玩家可以成为房主".to_string())?;
+        let chat_messages = self.get_chat_messages().unwrap_or_default();
+
+        let players = players
+            .into_iter()
+            .map(|mut player| {
+                player.role = None;
+                player.word = None;
+                player.is_alive = true;
+                player.last_action = Utc::now();
+                (player.id.clone(), player)
+            })
+            .collect();
+
+        *self = GameState::Lobby {
+            players,
+            min_players: params.min_players,
+            max_players: params.max_players,
+            ready_players: HashSet::new(),
+            chat_messages,
+            eliminated_chat_messages: Vec::new(),
+            host,
+        };
+        Ok(())
+    }
+
+    /// 转移房主
+    pub fn set_host(&mut self, new_host: PlayerId) {
+        match self {
+            GameState::Lobby { host, .. }
+            | GameState::DescribePhase { host, .. }
+            | GameState::VotePhase { host, .. }
+            | GameState::ResultPhase { host, .. }
+            | GameState::GuessPhase { host, .. }
+            | GameState::GameOver { host, .. } => *host = new_host,
+            GameState::RoleAssignment { .. } => {}
+        }
+    }
+
     /// 添加玩家
     pub fn add_player(&mut self, player: Player) -> Result<GameEvent, String> {
         match self {
@@ -224,7 +1018,7 @@ impl GameState {
     }
 
     /// 移除玩家
-    pub fn remove_player(&mut self, player_id: PlayerId) -> Result<GameEvent, String> {
+    pub fn remove_player(&mut self, player_id: PlayerId, reason: LeaveReason) -> Result<GameEvent, String> {
         match self {
             GameState::Lobby {
                 players,
@@ -238,7 +1032,7 @@ impl GameState {
                 
                 players.remove(&player_id);
                 ready_players.remove(&player_id);
-                Ok(GameEvent::PlayerLeft(player))
+                Ok(GameEvent::PlayerLeft(player, reason))
             }
             _ => Err("游戏已经开始".to_string()),
         }
@@ -282,7 +1076,7 @@ impl GameState {
     }
 
     /// 玩家准备
-    pub fn player_ready(&mut self, player_id: PlayerId) -> Result<GameEvent, String> {
+    pub fn player_ready(&mut self, player_id: PlayerId, params: &GameParams) -> Result<GameEvent, String> {
         match self {
             GameState::Lobby {
                 players,
@@ -300,7 +1094,7 @@ impl GameState {
                 if ready_players.contains(&player_id) {
                     ready_players.remove(&player_id);
                     let can_start = ready_players.len() >= *min_players;
-                    return Ok(GameEvent::PlayerReady(player_id_clone, can_start));
+                    return Ok(GameEvent::PlayerUnready(player_id_clone, can_start));
                 }
                 
                 // 玩家未准备，设置为准备状态
@@ -310,7 +1104,7 @@ impl GameState {
             }
             GameState::GameOver { .. } => {
                 // 游戏结束后，先重置游戏状态
-                self.reset_game()?;
+                self.reset_game(params)?;
                 // 然后直接处理准备逻辑，避免递归调用
                 match self {
                     GameState::Lobby {
@@ -329,7 +1123,7 @@ impl GameState {
                         if ready_players.contains(&player_id) {
                             ready_players.remove(&player_id);
                             let can_start = ready_players.len() >= *min_players;
-                            return Ok(GameEvent::PlayerReady(player_id_clone, can_start));
+                            return Ok(GameEvent::PlayerUnready(player_id_clone, can_start));
                         }
                         
                         // 玩家未准备，设置为准备状态
@@ -349,6 +1143,8 @@ impl GameState {
         &mut self,
         word_bank: Arc<WordBank>,
         player_order: &[PlayerId],
+        settings: &GameSettings,
+        params: &GameParams,
     ) -> Result<GameEvent, String> {
         match self {
             GameState::Lobby {
@@ -368,11 +1164,15 @@ impl GameState {
                     .filter_map(|id| players.get(id).cloned())
                     .collect();
 
-                let undercover_count = if players_vec.len() <= 6 {
-                    1
-                } else {
-                    (players_vec.len() as f32 * 0.25).ceil() as usize
-                };
+                if settings.blank_role && players_vec.len() < GameSettings::MIN_PLAYERS_WITH_BLANK {
+                    return Err(format!(
+                        "开启白板需要至少 {} 名玩家",
+                        GameSettings::MIN_PLAYERS_WITH_BLANK
+                    ));
+                }
+
+                let plan = RolePlan::for_players(players_vec.len(), settings.blank_role, &params.role_table)?;
+                let undercover_count = plan.undercovers;
 
                 let mut rng = rand::rng();
                 let mut indices: Vec<usize> = (0..players_vec.len()).collect();
@@ -388,12 +1188,33 @@ impl GameState {
                     }
                 }
 
-                if let Some(word_pair) = word_bank.get_random_word_pair() {
+                // 白板从剩余玩家中抽取
+                for &index in indices.iter().skip(undercover_count).take(plan.blanks) {
+                    players_vec[index].role = Some(Role::Blank);
+                }
+
+                // 分类、难度和字数差条件同时生效，没有同时满足的词对时随机抽取
+                let word_pair = word_bank
+                    .get_word_pair_matching(
+                        settings.word_category.as_deref(),
+                        settings.word_difficulty.as_ref(),
+                        settings.max_word_length_diff,
+                    )
+                    .or_else(|| {
+                        warn!(
+                            "没有满足条件的词对（分类 {:?}，难度 {:?}，字数差 {:?}），改为随机抽取",
+                            settings.word_category, settings.word_difficulty, settings.max_word_length_diff
+                        );
+                        word_bank.get_random_word_pair()
+                    });
+
+                if let Some(word_pair) = word_pair {
                     for player in &mut players_vec {
-                        player.word = Some(match player.role {
-                            Some(Role::Undercover) => word_pair.undercover_word.clone(),
-                            _ => word_pair.civilian_word.clone(),
-                        });
+                        player.word = match player.role {
+                            Some(Role::Undercover) => Some(word_pair.undercover_word.clone()),
+                            Some(Role::Blank) => None,
+                            _ => Some(word_pair.civilian_word.clone()),
+                        };
                     }
                 } else {
                     return Err("无法获取词语".to_string());
@@ -404,11 +1225,12 @@ impl GameState {
                     current_player_index: 0,
                     descriptions: HashMap::new(),
                     current_player_start_time: Utc::now(),
-                    player_duration: crate::config::Config::get().describe_time_limit(),
-                    remaining_time: crate::config::Config::get().describe_time_limit(),
+                    player_duration: params.describe_time_limit(),
+                    remaining_time: params.describe_time_limit(),
                     chat_messages: chat_messages.clone(),
                     eliminated_chat_messages: Vec::new(),
                     host: host.clone(),
+                    pk_candidates: Vec::new(),
                 };
 
                 // 创建不包含角色信息的玩家列表用于事件
@@ -421,7 +1243,7 @@ impl GameState {
                     last_action: p.last_action,
                 }).collect();
 
-                Ok(GameEvent::GameStarted(players_without_roles))
+                Ok(GameEvent::GameStarted(players_without_roles, plan))
             }
             _ => Err("游戏已经开始".to_string()),
         }
@@ -432,7 +1254,8 @@ impl GameState {
         &mut self,
         player_id: PlayerId,
         description: String,
-    ) -> Result<GameEvent, String> {
+        params: &GameParams,
+    ) -> crate::Result<GameEvent> {
         match self {
             GameState::DescribePhase {
                 players,
@@ -441,16 +1264,25 @@ impl GameState {
                 ..
             } => {
                 if *current_player_index >= players.len() {
-                    return Err("描述阶段已结束".to_string());
+                    return Err(crate::Error::Rule(ErrorCode::InvalidState, "描述阶段已结束".to_string()));
                 }
 
                 let current_player = &players[*current_player_index];
                 if current_player.id != player_id {
-                    return Err("还没轮到您描述".to_string());
+                    return Err(crate::Error::Rule(ErrorCode::NotYourTurn, "还没轮到您描述".to_string()));
                 }
 
                 if !current_player.is_alive {
-                    return Err("您已被淘汰".to_string());
+                    return Err(crate::Error::Rule(ErrorCode::InvalidAction, "您已被淘汰".to_string()));
+                }
+
+                let overlap = params.word_char_overlap;
+                if current_player
+                    .word
+                    .as_deref()
+                    .is_some_and(|word| Self::reveals_word(&description, word, overlap))
+                {
+                    return Err(crate::Error::Rule(ErrorCode::WordRevealed, "不能直接说出词语".to_string()));
                 }
 
                 descriptions.insert(player_id.clone(), description.clone());
@@ -458,12 +1290,28 @@ impl GameState {
                 // 返回 DescriptionAdded 事件，让调用者处理后续逻辑
                 Ok(GameEvent::DescriptionAdded(player_id, description))
             }
-            _ => Err("当前不是描述阶段".to_string()),
+            _ => Err(crate::Error::Rule(ErrorCode::InvalidState, "当前不是描述阶段".to_string())),
+        }
+    }
+
+    /// 描述是否说出了词语：直接包含整个词语，或词语中出现在描述里的字符占比达到阈值
+    fn reveals_word(description: &str, word: &str, overlap: f32) -> bool {
+        let description = description.to_lowercase();
+        let word = word.trim().to_lowercase();
+        if word.is_empty() {
+            return false;
+        }
+        if description.contains(&word) {
+            return true;
         }
+
+        let chars: std::collections::HashSet<char> = word.chars().filter(|c| !c.is_whitespace()).collect();
+        let matched = chars.iter().filter(|c| description.contains(**c)).count();
+        overlap > 0.0 && chars.len() > 1 && matched as f32 / chars.len() as f32 >= overlap
     }
 
     /// 推进描述阶段（移动到下一个玩家或结束阶段）
-    pub fn advance_describe_phase(&mut self) -> Result<GameEvent, String> {
+    pub fn advance_describe_phase(&mut self, params: &GameParams) -> Result<GameEvent, String> {
         match self {
             GameState::DescribePhase {
                 players,
@@ -472,13 +1320,14 @@ impl GameState {
                 current_player_start_time,
                 chat_messages,
                 host,
+                pk_candidates,
                 ..
             } => {
                 let next_alive_index = players
                     .iter()
                     .enumerate()
                     .skip(*current_player_index + 1)
-                    .find(|(_, p)| p.is_alive)
+                    .find(|(_, p)| Self::should_describe(p, pk_candidates))
                     .map(|(i, _)| i);
 
                 match next_alive_index {
@@ -486,7 +1335,7 @@ impl GameState {
                         *current_player_index = index;
                         *current_player_start_time = Utc::now();
                         let player_id = players[index].id.clone();
-                        Ok(GameEvent::NextPlayer(player_id))
+                        Ok(GameEvent::NextPlayer(player_id, *current_player_start_time))
                     }
                     None => {
                         *self = GameState::VotePhase {
@@ -494,11 +1343,12 @@ impl GameState {
                             votes: HashMap::new(),
                             descriptions: descriptions.clone(),
                             start_time: Utc::now(),
-                            duration: crate::config::Config::get().vote_time_limit(),
-                            remaining_time: crate::config::Config::get().vote_time_limit(),
+                            duration: params.vote_time_limit(),
+                            remaining_time: params.vote_time_limit(),
                             chat_messages: chat_messages.clone(),
                             eliminated_chat_messages: chat_messages.clone(),
                             host: host.clone(),
+                            pk_candidates: pk_candidates.clone(),
                         };
                         Ok(GameEvent::DescribePhaseComplete)
                     }
@@ -513,32 +1363,49 @@ impl GameState {
         &mut self,
         voter_id: PlayerId,
         target_id: PlayerId,
-    ) -> Result<GameEvent, String> {
+        settings: &GameSettings,
+        params: &GameParams,
+    ) -> crate::Result<GameEvent> {
         match self {
-            GameState::VotePhase { votes, players, .. } => {
+            GameState::VotePhase { votes, players, pk_candidates, .. } => {
                 if !players.iter().any(|p| p.id == voter_id && p.is_alive) {
-                    return Err("您已被淘汰，无法投票".to_string());
+                    return Err(crate::Error::Rule(ErrorCode::InvalidAction, "您已被淘汰，无法投票".to_string()));
+                }
+
+                let voters = Self::eligible_voters(players, pk_candidates);
+                if !voters.contains(&voter_id) {
+                    return Err(crate::Error::Rule(ErrorCode::InvalidAction, "PK环节中平票玩家不能投票".to_string()));
                 }
                 
                 if votes.contains_key(&voter_id) {
-                    return Err("您已经投过票了".to_string());
+                    return Err(crate::Error::Rule(ErrorCode::AlreadyVoted, "您已经投过票了".to_string()));
+                }
+
+                let abstain = target_id == ABSTAIN_VOTE;
+
+                if !abstain && !players.iter().any(|p| p.id == target_id && p.is_alive) {
+                    return Err(crate::Error::Rule(ErrorCode::InvalidVote, "目标玩家已被淘汰".to_string()));
                 }
 
-                if !players.iter().any(|p| p.id == target_id && p.is_alive) {
-                    return Err("目标玩家已被淘汰".to_string());
+                if !abstain && !pk_candidates.is_empty() && !pk_candidates.contains(&target_id) {
+                    return Err(crate::Error::Rule(ErrorCode::InvalidVote, "PK环节只能投给平票玩家".to_string()));
+                }
+
+                if !settings.allow_self_vote && voter_id == target_id {
+                    return Err(crate::Error::Rule(ErrorCode::SelfVoteForbidden, "本房间不允许投票给自己".to_string()));
                 }
 
                 votes.insert(voter_id.clone(), target_id.clone());
 
-                if votes.len() == players.iter().filter(|p| p.is_alive).count() {
+                if votes.len() == voters.len() {
                     let votes_clone = votes.clone();
-                    self.process_votes()?;
+                    self.process_votes(params).map_err(crate::Error::Game)?;
                     Ok(GameEvent::VotePhaseComplete(votes_clone))
                 } else {
                     Ok(GameEvent::VoteAdded(voter_id, target_id))
                 }
             }
-            _ => Err("当前不是投票阶段".to_string()),
+            _ => Err(crate::Error::Rule(ErrorCode::InvalidState, "当前不是投票阶段".to_string())),
         }
     }
 
@@ -547,6 +1414,24 @@ impl GameState {
         &mut self,
         player_id: PlayerId,
         content: String,
+    ) -> Result<GameEvent, String> {
+        self.push_chat_message(player_id, content, None)
+    }
+
+    /// 添加表情消息，与文字聊天使用相同的发言阶段限制并记入聊天记录
+    pub fn add_sticker_message(
+        &mut self,
+        player_id: PlayerId,
+        sticker: Sticker,
+    ) -> Result<GameEvent, String> {
+        self.push_chat_message(player_id, String::new(), Some(sticker))
+    }
+
+    fn push_chat_message(
+        &mut self,
+        player_id: PlayerId,
+        content: String,
+        sticker: Option<Sticker>,
     ) -> Result<GameEvent, String> {
         // 检查当前阶段是否允许聊天
         match self {
@@ -571,6 +1456,7 @@ impl GameState {
             player_name,
             content,
             timestamp: Utc::now(),
+            sticker,
         };
 
         // 将消息添加到聊天记录
@@ -601,6 +1487,7 @@ impl GameState {
             GameState::DescribePhase { players, .. } |
             GameState::VotePhase { players, .. } |
             GameState::ResultPhase { players, .. } |
+            GameState::GuessPhase { players, .. } |
             GameState::GameOver { players, .. } => {
                 players.iter().any(|p| p.id == player_id && !p.is_alive)
             }
@@ -616,6 +1503,7 @@ impl GameState {
             GameState::DescribePhase { players, .. } |
             GameState::VotePhase { players, .. } |
             GameState::ResultPhase { players, .. } |
+            GameState::GuessPhase { players, .. } |
             GameState::GameOver { players, .. } => {
                 players.iter().find(|p| p.id == player_id).map(|p| p.name.clone())
             }
@@ -627,6 +1515,7 @@ impl GameState {
             player_name,
             content,
             timestamp: Utc::now(),
+            sticker: None,
         };
 
         // 将消息添加到被淘汰玩家聊天记录
@@ -634,6 +1523,7 @@ impl GameState {
             GameState::DescribePhase { eliminated_chat_messages, .. } |
             GameState::VotePhase { eliminated_chat_messages, .. } |
             GameState::ResultPhase { eliminated_chat_messages, .. } |
+            GameState::GuessPhase { eliminated_chat_messages, .. } |
             GameState::GameOver { eliminated_chat_messages, .. } => {
                 eliminated_chat_messages.push(message.clone());
             }
@@ -644,11 +1534,12 @@ impl GameState {
     }
 
     /// 处理投票结果
-    fn process_votes(&mut self) -> Result<(), String> {
+    fn process_votes(&mut self, params: &GameParams) -> Result<(), String> {
         match self {
-            GameState::VotePhase { votes, players, chat_messages, host, .. } => {
+            GameState::VotePhase { votes, players, chat_messages, host, pk_candidates, .. } => {
+                let pk_round = !pk_candidates.is_empty();
                 let mut vote_count: HashMap<PlayerId, usize> = HashMap::new();
-                for target_id in votes.values() {
+                for target_id in votes.values().filter(|target_id| *target_id != ABSTAIN_VOTE) {
                     *vote_count.entry(target_id.clone()).or_insert(0) += 1;
                 }
 
@@ -665,25 +1556,35 @@ impl GameState {
                         players: players.clone(),
                         eliminated: eliminated_id,
                         votes: votes.clone(),
-                        next_round_delay: crate::config::Config::get().round_delay(),
-                        remaining_time: crate::config::Config::get().round_delay(),
+                        next_round_delay: params.round_delay(),
+                        remaining_time: params.round_delay(),
                         start_time: Utc::now(),
                         chat_messages: chat_messages.clone(),
                         eliminated_chat_messages: chat_messages.clone(),
                         host: host.clone(),
+                        tied: Vec::new(),
+                        pk_round,
                     };
                 } else {
                     let tie_id = "tie".to_string();
+                    // 按座位顺序记录平票玩家，PK环节按此顺序补充描述
+                    let tied = players
+                        .iter()
+                        .filter(|p| eliminated.contains(&p.id))
+                        .map(|p| p.id.clone())
+                        .collect();
                     *self = GameState::ResultPhase {
                         players: players.clone(),
                         eliminated: tie_id,
                         votes: votes.clone(),
-                        next_round_delay: crate::config::Config::get().round_delay(),
-                        remaining_time: crate::config::Config::get().round_delay(),
+                        next_round_delay: params.round_delay(),
+                        remaining_time: params.round_delay(),
                         start_time: Utc::now(),
                         chat_messages: chat_messages.clone(),
                         eliminated_chat_messages: chat_messages.clone(),
                         host: host.clone(),
+                        tied,
+                        pk_round,
                     };
                 }
 
@@ -694,87 +1595,284 @@ impl GameState {
     }
 
     /// 处理结果阶段
-    pub fn process_result_phase(&mut self) -> Result<GameEvent, String> {
+    pub fn process_result_phase(
+        &mut self,
+        settings: &GameSettings,
+        params: &GameParams,
+    ) -> Result<GameEvent, String> {
+        // 平票且开启PK时，平票玩家补充描述后由其他玩家重新投票；PK再次平票则无人淘汰
+        if self.will_enter_pk(settings) {
+            return self.start_pk_round(params);
+        }
+
         match self {
             GameState::ResultPhase {
                 players,
                 eliminated,
                 chat_messages,
+                eliminated_chat_messages,
                 host,
                 ..
             } => {
-                if *eliminated != "tie" {
-                    if let Some(player) = players.iter_mut().find(|p| p.id == *eliminated) {
-                        player.is_alive = false;
-                    }
+                if *eliminated != "tie"
+                    && let Some(player) = players.iter_mut().find(|p| p.id == *eliminated)
+                {
+                    player.is_alive = false;
                 }
 
-                // 使用包含角色的玩家信息进行游戏逻辑判断
-                let alive_players: Vec<&Player> = players.iter().filter(|p| p.is_alive).collect();
-
-                let undercover_count = alive_players
+                // 卧底被淘汰时先给一次猜平民词的机会
+                let eliminated_undercover = players
                     .iter()
-                    .filter(|p| p.role == Some(Role::Undercover))
-                    .count();
-
-                let civilian_count = alive_players.len() - undercover_count;
-
-                if undercover_count == 0 {
-                    // 调试：检查玩家信息是否完整
-                    println!("DEBUG: GameOver - Civilian wins");
-                    for player in players.iter() {
-                        println!("DEBUG: Player {} - Role: {:?}, Word: {:?}", 
-                                player.name, player.role, player.word);
-                    }
-                    
-                    *self = GameState::GameOver {
-                        winner: Role::Civilian,
-                        players: players.clone(),
-                        chat_messages: chat_messages.clone(),
-                        eliminated_chat_messages: chat_messages.clone(),
-                        host: host.clone(),
-                    };
-                    Ok(GameEvent::GameOver(Role::Civilian))
-                } else if undercover_count > civilian_count || (alive_players.len() <= 2 && undercover_count > 0) {
-                    // 调试：检查玩家信息是否完整
-                    println!("DEBUG: GameOver - Undercover wins");
-                    for player in players.iter() {
-                        println!("DEBUG: Player {} - Role: {:?}, Word: {:?}", 
-                                player.name, player.role, player.word);
-                    }
-                    
-                    *self = GameState::GameOver {
-                        winner: Role::Undercover,
-                        players: players.clone(),
-                        chat_messages: chat_messages.clone(),
-                        eliminated_chat_messages: chat_messages.clone(),
-                        host: host.clone(),
-                    };
-                    Ok(GameEvent::GameOver(Role::Undercover))
-                } else {
-                    let first_alive_index = players
-                        .iter()
-                        .position(|p| p.is_alive)
-                        .ok_or_else(|| "没有存活的玩家".to_string())?;
-
-                    *self = GameState::DescribePhase {
+                    .any(|p| p.id == *eliminated && p.role == Some(Role::Undercover));
+                if settings.undercover_guess && eliminated_undercover {
+                    let guesser = eliminated.clone();
+                    *self = GameState::GuessPhase {
                         players: players.clone(),
-                        current_player_index: first_alive_index,
-                        descriptions: HashMap::new(),
-                        current_player_start_time: Utc::now(),
-                        player_duration: crate::config::Config::get().describe_time_limit(),
-                        remaining_time: crate::config::Config::get().describe_time_limit(),
+                        guesser: guesser.clone(),
+                        guess: None,
+                        start_time: Utc::now(),
+                        duration: params.guess_time_limit(),
+                        remaining_time: params.guess_time_limit(),
                         chat_messages: chat_messages.clone(),
-                        eliminated_chat_messages: chat_messages.clone(),
+                        eliminated_chat_messages: eliminated_chat_messages.clone(),
                         host: host.clone(),
                     };
-                    Ok(GameEvent::RoundComplete)
+                    return Ok(GameEvent::GuessPhaseStarted(guesser));
                 }
+
+                self.settle_round(None, params)
+            }
+            _ => Err("当前不是结果阶段".to_string()),
+        }
+    }
+
+    /// 进入PK环节：平票玩家按座位顺序补充描述
+    fn start_pk_round(&mut self, params: &GameParams) -> Result<GameEvent, String> {
+        match self {
+            GameState::ResultPhase {
+                players,
+                tied,
+                chat_messages,
+                eliminated_chat_messages,
+                host,
+                ..
+            } => {
+                let pk_candidates = tied.clone();
+                let first_index = players
+                    .iter()
+                    .position(|p| Self::should_describe(p, &pk_candidates))
+                    .ok_or_else(|| "没有可以参与PK的玩家".to_string())?;
+
+                *self = GameState::DescribePhase {
+                    players: players.clone(),
+                    current_player_index: first_index,
+                    descriptions: HashMap::new(),
+                    current_player_start_time: Utc::now(),
+                    player_duration: params.describe_time_limit(),
+                    remaining_time: params.describe_time_limit(),
+                    chat_messages: chat_messages.clone(),
+                    eliminated_chat_messages: eliminated_chat_messages.clone(),
+                    host: host.clone(),
+                    pk_candidates: pk_candidates.clone(),
+                };
+                Ok(GameEvent::PkRoundStarted(pk_candidates))
             }
             _ => Err("当前不是结果阶段".to_string()),
         }
     }
 
+    /// 被淘汰的卧底猜平民词，只能猜一次
+    pub fn guess_word(&mut self, player_id: PlayerId, word: String) -> Result<GameEvent, String> {
+        match self {
+            GameState::GuessPhase { players, guesser, guess, .. } => {
+                if *guesser != player_id {
+                    return Err("只有被淘汰的卧底可以猜词".to_string());
+                }
+                if guess.is_some() {
+                    return Err("您已经猜过了".to_string());
+                }
+
+                let civilian_word = players
+                    .iter()
+                    .find(|p| p.role == Some(Role::Civilian))
+                    .and_then(|p| p.word.clone())
+                    .ok_or_else(|| "无法获取平民词".to_string())?;
+
+                let normalize = |w: &str| w.split_whitespace().collect::<String>().to_lowercase();
+                let word_guess = WordGuess {
+                    player_id,
+                    correct: normalize(&word) == normalize(&civilian_word),
+                    guess: Some(word),
+                };
+                *guess = Some(word_guess.clone());
+                Ok(GameEvent::WordGuessed(word_guess))
+            }
+            _ => Err("当前不是猜词阶段".to_string()),
+        }
+    }
+
+    /// 检查玩家当前是否可以认输：只有卧底方最后一名存活的卧底可以在描述或投票阶段认输，
+    /// 还有存活的卧底或白板队友时认输会连带判负，不允许
+    pub fn check_concede(&self, player_id: &PlayerId) -> Result<(), String> {
+        let players = match self {
+            GameState::DescribePhase { players, .. } | GameState::VotePhase { players, .. } => players,
+            _ => return Err("只能在描述或投票阶段认输".to_string()),
+        };
+        match players.iter().find(|p| p.id == *player_id) {
+            Some(player) if player.is_alive && player.role == Some(Role::Undercover) => {}
+            Some(_) => return Err("只有存活的卧底可以认输".to_string()),
+            None => return Err("玩家不存在".to_string()),
+        }
+        let has_teammate = players.iter().any(|p| {
+            p.id != *player_id && p.is_alive && matches!(p.role, Some(Role::Undercover | Role::Blank))
+        });
+        if has_teammate {
+            return Err("还有存活的队友，不能认输".to_string());
+        }
+        Ok(())
+    }
+
+    /// 卧底主动认输，本局立即结束并判平民获胜
+    pub fn concede(&mut self, player_id: PlayerId) -> Result<GameEvent, String> {
+        self.check_concede(&player_id)?;
+        match self {
+            GameState::DescribePhase { players, chat_messages, eliminated_chat_messages, host, .. }
+            | GameState::VotePhase { players, chat_messages, eliminated_chat_messages, host, .. } => {
+                *self = GameState::GameOver {
+                    winner: Role::Civilian,
+                    players: players.clone(),
+                    chat_messages: chat_messages.clone(),
+                    eliminated_chat_messages: eliminated_chat_messages.clone(),
+                    host: host.clone(),
+                    word_guess: None,
+                    conceded_by: Some(player_id.clone()),
+                };
+                Ok(GameEvent::UndercoverConceded(player_id))
+            }
+            _ => Err("只能在描述或投票阶段认输".to_string()),
+        }
+    }
+
+    /// 处理猜词超时，视为放弃猜词
+    pub fn handle_guess_timeout(&mut self) -> Result<GameEvent, String> {
+        match self {
+            GameState::GuessPhase { guesser, guess, .. } => {
+                let word_guess = WordGuess {
+                    player_id: guesser.clone(),
+                    guess: None,
+                    correct: false,
+                };
+                *guess = Some(word_guess.clone());
+                Ok(GameEvent::WordGuessed(word_guess))
+            }
+            _ => Err("当前不是猜词阶段".to_string()),
+        }
+    }
+
+    /// 结束猜词阶段，根据猜词结果结算本轮
+    pub fn finish_guess_phase(&mut self, params: &GameParams) -> Result<GameEvent, String> {
+        match self {
+            GameState::GuessPhase { guess, .. } => {
+                let word_guess = guess.clone();
+                self.settle_round(word_guess, params)
+            }
+            _ => Err("当前不是猜词阶段".to_string()),
+        }
+    }
+
+    /// 根据存活玩家判断胜负，未分胜负时进入下一轮描述
+    fn settle_round(&mut self, word_guess: Option<WordGuess>, params: &GameParams) -> Result<GameEvent, String> {
+        let (players, chat_messages, host) = match self {
+            GameState::ResultPhase { players, chat_messages, host, .. }
+            | GameState::GuessPhase { players, chat_messages, host, .. } => {
+                (players.clone(), chat_messages.clone(), host.clone())
+            }
+            _ => return Err("当前阶段无法结算".to_string()),
+        };
+
+        // 卧底猜中平民词，卧底方直接获胜
+        if word_guess.as_ref().is_some_and(|guess| guess.correct) {
+            *self = GameState::GameOver {
+                winner: Role::Undercover,
+                players,
+                chat_messages: chat_messages.clone(),
+                eliminated_chat_messages: chat_messages,
+                host,
+                word_guess,
+                conceded_by: None,
+            };
+            return Ok(GameEvent::GameOver(Role::Undercover));
+        }
+
+        // 使用包含角色的玩家信息进行游戏逻辑判断
+        let alive_players: Vec<&Player> = players.iter().filter(|p| p.is_alive).collect();
+
+        // 白板与卧底同一阵营，一起计入卧底方人数
+        let undercover_count = alive_players
+            .iter()
+            .filter(|p| matches!(p.role, Some(Role::Undercover) | Some(Role::Blank)))
+            .count();
+
+        let civilian_count = alive_players.len() - undercover_count;
+
+        if undercover_count == 0 {
+            // 调试：检查玩家信息是否完整
+            println!("DEBUG: GameOver - Civilian wins");
+            for player in players.iter() {
+                println!("DEBUG: Player {} - Role: {:?}, Word: {:?}", 
+                        player.name, player.role, player.word);
+            }
+            
+            *self = GameState::GameOver {
+                winner: Role::Civilian,
+                players: players.clone(),
+                chat_messages: chat_messages.clone(),
+                eliminated_chat_messages: chat_messages.clone(),
+                host: host.clone(),
+                word_guess,
+                conceded_by: None,
+            };
+            Ok(GameEvent::GameOver(Role::Civilian))
+        } else if undercover_count > civilian_count || (alive_players.len() <= 2 && undercover_count > 0) {
+            // 调试：检查玩家信息是否完整
+            println!("DEBUG: GameOver - Undercover wins");
+            for player in players.iter() {
+                println!("DEBUG: Player {} - Role: {:?}, Word: {:?}", 
+                        player.name, player.role, player.word);
+            }
+            
+            *self = GameState::GameOver {
+                winner: Role::Undercover,
+                players: players.clone(),
+                chat_messages: chat_messages.clone(),
+                eliminated_chat_messages: chat_messages.clone(),
+                host: host.clone(),
+                word_guess,
+                conceded_by: None,
+            };
+            Ok(GameEvent::GameOver(Role::Undercover))
+        } else {
+            let first_alive_index = players
+                .iter()
+                .position(|p| p.is_alive)
+                .ok_or_else(|| "没有存活的玩家".to_string())?;
+
+            *self = GameState::DescribePhase {
+                players: players.clone(),
+                current_player_index: first_alive_index,
+                descriptions: HashMap::new(),
+                current_player_start_time: Utc::now(),
+                player_duration: params.describe_time_limit(),
+                remaining_time: params.describe_time_limit(),
+                chat_messages: chat_messages.clone(),
+                eliminated_chat_messages: chat_messages.clone(),
+                host: host.clone(),
+                pk_candidates: Vec::new(),
+            };
+            Ok(GameEvent::RoundComplete)
+        }
+    }
+
     /// 检查超时
     pub fn check_timeout(&self) -> TimeoutResult {
         match self {
@@ -804,10 +1902,11 @@ impl GameState {
                 votes,
                 players,
                 remaining_time,
+                pk_candidates,
                 ..
             } => {
-                let alive_count = players.iter().filter(|p| p.is_alive).count();
-                if votes.len() == alive_count {
+                let voter_count = Self::eligible_voters(players, pk_candidates).len();
+                if votes.len() == voter_count {
                     return TimeoutResult::None; // 所有玩家都投票了
                 }
 
@@ -833,12 +1932,31 @@ impl GameState {
                 }
                 TimeoutResult::None
             }
+            GameState::GuessPhase {
+                guess,
+                start_time,
+                duration,
+                remaining_time,
+                ..
+            } => {
+                // 已经猜过词时由猜词流程推进
+                if guess.is_some() {
+                    return TimeoutResult::None;
+                }
+
+                if Utc::now() - *start_time > chrono::Duration::from_std(*duration).unwrap()
+                    || remaining_time.as_secs() == 0
+                {
+                    return TimeoutResult::GuessTimeout;
+                }
+                TimeoutResult::None
+            }
             _ => TimeoutResult::None,
         }
     }
 
     /// 处理描述超时
-    pub fn handle_describe_timeout(&mut self) -> Result<GameEvent, String> {
+    pub fn handle_describe_timeout(&mut self, params: &GameParams) -> Result<GameEvent, String> {
         match self {
             GameState::DescribePhase {
                 players,
@@ -847,6 +1965,7 @@ impl GameState {
                 chat_messages,
                 descriptions,
                 host,
+                pk_candidates,
                 ..
             } => {
                 // 找到下一个存活的玩家
@@ -855,7 +1974,7 @@ impl GameState {
                     .iter()
                     .enumerate()
                     .skip(*current_player_index + 1)
-                    .find(|(_, p)| p.is_alive)
+                    .find(|(_, p)| Self::should_describe(p, pk_candidates))
                     .map(|(i, _)| i);
 
                 match next_alive_index {
@@ -863,7 +1982,7 @@ impl GameState {
                         *current_player_index = index;
                         *current_player_start_time = Utc::now();
                         let player_id = players_clone[index].id.clone();
-                        Ok(GameEvent::NextPlayer(player_id))
+                        Ok(GameEvent::NextPlayer(player_id, *current_player_start_time))
                     }
                     None => {
                         // 没有更多存活的玩家，进入投票阶段
@@ -872,11 +1991,12 @@ impl GameState {
                             votes: HashMap::new(),
                             descriptions: descriptions.clone(),
                             start_time: Utc::now(),
-                            duration: crate::config::Config::get().vote_time_limit(),
-                            remaining_time: crate::config::Config::get().vote_time_limit(),
+                            duration: params.vote_time_limit(),
+                            remaining_time: params.vote_time_limit(),
                             chat_messages: chat_messages.clone(),
                             eliminated_chat_messages: chat_messages.clone(),
                             host: host.clone(),
+                            pk_candidates: pk_candidates.clone(),
                         };
                         Ok(GameEvent::DescribePhaseComplete)
                     }
@@ -887,11 +2007,12 @@ impl GameState {
     }
 
     /// 处理投票超时
-    pub fn handle_vote_timeout(&mut self) -> Result<GameEvent, String> {
+    pub fn handle_vote_timeout(&mut self, params: &GameParams) -> Result<GameEvent, String> {
         match self {
-            GameState::VotePhase { votes, players, chat_messages, host, .. } => {
+            GameState::VotePhase { votes, players, chat_messages, host, pk_candidates, .. } => {
                 let players_clone = players.clone();
                 let votes_clone = votes.clone();
+                let pk_round = !pk_candidates.is_empty();
 
                 let alive_players: Vec<PlayerId> = players_clone
                     .iter()
@@ -911,29 +2032,38 @@ impl GameState {
                         players: players_clone,
                         eliminated,
                         votes: votes_clone.clone(),
-                        next_round_delay: crate::config::Config::get().round_delay(),
-                        remaining_time: crate::config::Config::get().round_delay(),
+                        next_round_delay: params.round_delay(),
+                        remaining_time: params.round_delay(),
                         start_time: Utc::now(),
                         chat_messages: chat_messages.clone(),
                         eliminated_chat_messages: chat_messages.clone(),
                         host: host.clone(),
+                        tied: Vec::new(),
+                        pk_round,
                     };
 
                     return Ok(GameEvent::VotePhaseComplete(votes_clone));
                 }
 
+                // PK环节只在平票玩家之间投票
+                let targets = if pk_round {
+                    pk_candidates.clone()
+                } else {
+                    alive_players.clone()
+                };
+
                 // 为未投票的玩家随机分配投票
                 let mut rng = rand::rng();
-                for player_id in alive_players.clone() {
-                    if !votes.contains_key(&player_id) {
-                        let available_targets: Vec<PlayerId> = alive_players
+                for player_id in Self::eligible_voters(&players_clone, pk_candidates) {
+                    if let std::collections::hash_map::Entry::Vacant(entry) = votes.entry(player_id.clone()) {
+                        let available_targets: Vec<PlayerId> = targets
                             .iter()
                             .filter(|id| *id != &player_id)
                             .cloned()
                             .collect();
 
                         if let Some(target) = available_targets.choose(&mut rng) {
-                            votes.insert(player_id, target.clone());
+                            entry.insert(target.clone());
                         } else {
                             return Err("无法选择投票目标".to_string());
                         }
@@ -941,13 +2071,107 @@ impl GameState {
                 }
 
                 let final_votes = votes.clone();
-                self.process_votes()?;
+                self.process_votes(params)?;
                 Ok(GameEvent::VotePhaseComplete(final_votes))
             }
             _ => Err("当前不是投票阶段".to_string()),
         }
     }
 
+    /// 该玩家是否需要在当前描述阶段发言，PK环节只有平票玩家发言
+    fn should_describe(player: &Player, pk_candidates: &[PlayerId]) -> bool {
+        player.is_alive && (pk_candidates.is_empty() || pk_candidates.contains(&player.id))
+    }
+
+    /// 当前投票阶段有投票权的玩家：PK环节由平票玩家以外的存活玩家投票，
+    /// 没有其他存活玩家时由全部存活玩家投票
+    fn eligible_voters(players: &[Player], pk_candidates: &[PlayerId]) -> Vec<PlayerId> {
+        let alive = players.iter().filter(|p| p.is_alive);
+        let voters: Vec<PlayerId> = alive
+            .clone()
+            .filter(|p| !pk_candidates.contains(&p.id))
+            .map(|p| p.id.clone())
+            .collect();
+        if voters.is_empty() {
+            alive.map(|p| p.id.clone()).collect()
+        } else {
+            voters
+        }
+    }
+
+    /// 获取当前PK环节的平票玩家，不在PK环节时返回None
+    pub fn get_pk_candidates(&self) -> Option<Vec<PlayerId>> {
+        match self {
+            GameState::DescribePhase { pk_candidates, .. }
+            | GameState::VotePhase { pk_candidates, .. }
+                if !pk_candidates.is_empty() =>
+            {
+                Some(pk_candidates.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// 当前平票结果是否会进入PK环节
+    pub fn will_enter_pk(&self, settings: &GameSettings) -> bool {
+        match self {
+            GameState::ResultPhase { eliminated, tied, pk_round, .. } => {
+                settings.pk_on_tie && *eliminated == "tie" && !*pk_round && tied.len() >= 2
+            }
+            _ => false,
+        }
+    }
+
+    /// 记录大厅玩家的最近一次操作时间
+    pub fn touch_player(&mut self, player_id: &str) {
+        if let GameState::Lobby { players, .. } = self
+            && let Some(player) = players.get_mut(player_id)
+        {
+            player.last_action = Utc::now();
+        }
+    }
+
+    /// 恢复单独持久化的聊天记录，加载状态快照或检查点后调用
+    pub fn restore_chat(&mut self, chat: Vec<ChatMessage>, eliminated_chat: Vec<ChatMessage>) {
+        match self {
+            GameState::Lobby { chat_messages, eliminated_chat_messages, .. }
+            | GameState::DescribePhase { chat_messages, eliminated_chat_messages, .. }
+            | GameState::VotePhase { chat_messages, eliminated_chat_messages, .. }
+            | GameState::ResultPhase { chat_messages, eliminated_chat_messages, .. }
+            | GameState::GuessPhase { chat_messages, eliminated_chat_messages, .. }
+            | GameState::GameOver { chat_messages, eliminated_chat_messages, .. } => {
+                *chat_messages = chat;
+                *eliminated_chat_messages = eliminated_chat;
+            }
+            GameState::RoleAssignment { .. } => {}
+        }
+    }
+
+    /// 取消大厅中指定玩家的准备状态，返回原本已准备的玩家
+    pub fn unready_players(&mut self, player_ids: &[PlayerId]) -> Vec<PlayerId> {
+        let GameState::Lobby { ready_players, .. } = self else {
+            return Vec::new();
+        };
+        player_ids
+            .iter()
+            .filter(|id| ready_players.remove(*id))
+            .cloned()
+            .collect()
+    }
+
+    /// 大厅中未准备且超过指定时长没有操作的玩家
+    pub fn afk_players(&self, timeout: chrono::Duration) -> Vec<PlayerId> {
+        let GameState::Lobby { players, ready_players, .. } = self else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        players
+            .values()
+            .filter(|player| !ready_players.contains(&player.id) && now - player.last_action > timeout)
+            .map(|player| player.id.clone())
+            .collect()
+    }
+
     /// 获取游戏状态类型
     pub fn get_state_type(&self) -> GameStateType {
         match self {
@@ -956,6 +2180,7 @@ impl GameState {
             GameState::DescribePhase { .. } => GameStateType::DescribePhase,
             GameState::VotePhase { .. } => GameStateType::VotePhase,
             GameState::ResultPhase { .. } => GameStateType::ResultPhase,
+            GameState::GuessPhase { .. } => GameStateType::GuessPhase,
             GameState::GameOver { .. } => GameStateType::GameOver,
         }
     }
@@ -987,7 +2212,7 @@ impl GameState {
                     last_action: p.last_action,
                 }).collect()
             },
-            GameState::ResultPhase { players, .. } => {
+            GameState::ResultPhase { players, .. } | GameState::GuessPhase { players, .. } => {
                 // 游戏进行中不显示角色信息
                 players.iter().map(|p| Player {
                     id: p.id.clone(),
@@ -1013,6 +2238,7 @@ impl GameState {
             GameState::DescribePhase { players, .. } => players.clone(),
             GameState::VotePhase { players, .. } => players.clone(),
             GameState::ResultPhase { players, .. } => players.clone(),
+            GameState::GuessPhase { players, .. } => players.clone(),
             GameState::GameOver { players, .. } => players.clone(),
         }
     }
@@ -1042,6 +2268,59 @@ impl GameState {
         }
     }
 
+    /// 获取当前计时阶段的开始时间，描述阶段同时返回正在描述的玩家
+    pub fn get_phase_timing(&self) -> Option<(Option<PlayerId>, DateTime<Utc>)> {
+        match self {
+            GameState::DescribePhase {
+                players,
+                current_player_index,
+                current_player_start_time,
+                ..
+            } => Some((
+                players.get(*current_player_index).map(|p| p.id.clone()),
+                *current_player_start_time,
+            )),
+            GameState::VotePhase { start_time, .. }
+            | GameState::ResultPhase { start_time, .. }
+            | GameState::GuessPhase { start_time, .. } => Some((None, *start_time)),
+            _ => None,
+        }
+    }
+
+    /// 获取当前计时阶段的截止时间
+    pub fn get_deadline(&self) -> Option<DateTime<Utc>> {
+        let (start, duration) = match self {
+            GameState::DescribePhase {
+                current_player_start_time,
+                player_duration,
+                ..
+            } => (*current_player_start_time, *player_duration),
+            GameState::VotePhase { start_time, duration, .. }
+            | GameState::GuessPhase { start_time, duration, .. } => (*start_time, *duration),
+            GameState::ResultPhase {
+                start_time,
+                next_round_delay,
+                ..
+            } => (*start_time, *next_round_delay),
+            _ => return None,
+        };
+        Some(start + chrono::Duration::from_std(duration).ok()?)
+    }
+
+    /// 把当前计时阶段的起点后移，暂停期间不消耗剩余时间
+    pub fn postpone_deadline(&mut self, by: chrono::Duration) {
+        match self {
+            GameState::DescribePhase {
+                current_player_start_time,
+                ..
+            } => *current_player_start_time += by,
+            GameState::VotePhase { start_time, .. }
+            | GameState::GuessPhase { start_time, .. }
+            | GameState::ResultPhase { start_time, .. } => *start_time += by,
+            _ => {}
+        }
+    }
+
     /// 获取当前玩家索引
     pub fn get_current_player_index(&self) -> Option<usize> {
         match self {
@@ -1061,6 +2340,31 @@ impl GameState {
         }
     }
 
+    /// 获取猜词阶段的卧底
+    pub fn get_guesser(&self) -> Option<PlayerId> {
+        match self {
+            GameState::GuessPhase { guesser, .. } => Some(guesser.clone()),
+            _ => None,
+        }
+    }
+
+    /// 获取本局卧底猜词结果
+    pub fn get_word_guess(&self) -> Option<WordGuess> {
+        match self {
+            GameState::GuessPhase { guess, .. } => guess.clone(),
+            GameState::GameOver { word_guess, .. } => word_guess.clone(),
+            _ => None,
+        }
+    }
+
+    /// 获取本局主动认输的卧底
+    pub fn get_conceded_by(&self) -> Option<PlayerId> {
+        match self {
+            GameState::GameOver { conceded_by, .. } => conceded_by.clone(),
+            _ => None,
+        }
+    }
+
     /// 获取投票信息
     pub fn get_votes(&self) -> Option<HashMap<PlayerId, PlayerId>> {
         match self {
@@ -1077,6 +2381,7 @@ impl GameState {
             GameState::DescribePhase { chat_messages, .. } => Some(chat_messages.clone()),
             GameState::VotePhase { chat_messages, .. } => Some(chat_messages.clone()),
             GameState::ResultPhase { chat_messages, .. } => Some(chat_messages.clone()),
+            GameState::GuessPhase { chat_messages, .. } => Some(chat_messages.clone()),
             GameState::GameOver { chat_messages, .. } => Some(chat_messages.clone()),
             _ => None,
         }
@@ -1089,6 +2394,7 @@ impl GameState {
             GameState::DescribePhase { eliminated_chat_messages, .. } => Some(eliminated_chat_messages.clone()),
             GameState::VotePhase { eliminated_chat_messages, .. } => Some(eliminated_chat_messages.clone()),
             GameState::ResultPhase { eliminated_chat_messages, .. } => Some(eliminated_chat_messages.clone()),
+            GameState::GuessPhase { eliminated_chat_messages, .. } => Some(eliminated_chat_messages.clone()),
             GameState::GameOver { eliminated_chat_messages, .. } => Some(eliminated_chat_messages.clone()),
             _ => None,
         }
@@ -1126,6 +2432,12 @@ impl GameState {
                 duration,
                 remaining_time,
                 ..
+            }
+            | GameState::GuessPhase {
+                start_time,
+                duration,
+                remaining_time,
+                ..
             } => {
                 let elapsed = Utc::now() - *start_time;
                 let elapsed_duration = chrono::Duration::from_std(*duration).unwrap();
@@ -1181,6 +2493,7 @@ impl GameState {
             GameState::DescribePhase { host, .. } => Some(host.clone()),
             GameState::VotePhase { host, .. } => Some(host.clone()),
             GameState::ResultPhase { host, .. } => Some(host.clone()),
+            GameState::GuessPhase { host, .. } => Some(host.clone()),
             GameState::GameOver { host, .. } => Some(host.clone()),
             _ => None,
         }
@@ -0,0 +1,115 @@
+use crate::Result;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// JWT 头部，只签发和接受 HS256
+const HEADER: &str = r#"{"alg":"HS256","typ":"JWT"}"#;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 会话令牌的载荷，令牌只是会话的签名包装，会话被删除后令牌随之失效
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionClaims {
+    /// 用户ID
+    pub sub: String,
+    /// 会话ID
+    pub sid: Uuid,
+    /// 签发时间（Unix 秒）
+    pub iat: i64,
+    /// 过期时间（Unix 秒）
+    pub exp: i64,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    alg: String,
+}
+
+/// 是否配置了签名密钥，未配置时不签发也不接受 JWT
+pub fn enabled() -> bool {
+    secret().is_some()
+}
+
+fn secret() -> Option<&'static [u8]> {
+    crate::config::Config::get()
+        .auth
+        .jwt_secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+        .map(str::as_bytes)
+}
+
+/// 形如 xxx.yyy.zzz 的字符串才按 JWT 处理，管理员令牌等不透明令牌不受影响
+pub fn looks_like_jwt(token: &str) -> bool {
+    token.split('.').count() == 3
+}
+
+/// 为会话签发令牌，返回令牌和过期时间；未配置密钥时返回 None
+pub fn issue(user_id: &str, session_id: &Uuid) -> Option<(String, chrono::DateTime<Utc>)> {
+    let secret = secret()?;
+    let now = Utc::now();
+    let expires_at = now + chrono::Duration::minutes(crate::config::Config::get().auth.jwt_ttl_minutes.max(1) as i64);
+    let claims = SessionClaims {
+        sub: user_id.to_string(),
+        sid: *session_id,
+        iat: now.timestamp(),
+        exp: expires_at.timestamp(),
+    };
+    Some((encode(&claims, secret), expires_at))
+}
+
+/// 校验令牌并取出会话ID，会话本身是否有效仍需调用方验证
+pub fn session_id(token: &str) -> Result<Uuid> {
+    let secret = secret().ok_or_else(|| crate::Error::Auth("服务器未启用令牌登录".to_string()))?;
+    let claims = decode(token, secret)?;
+    if claims.exp <= Utc::now().timestamp() {
+        return Err(crate::Error::Auth("登录令牌已过期".to_string()));
+    }
+    Ok(claims.sid)
+}
+
+fn encode(claims: &SessionClaims, secret: &[u8]) -> String {
+    let payload = serde_json::to_vec(claims).unwrap_or_default();
+    let signing_input = format!("{}.{}", URL_SAFE_NO_PAD.encode(HEADER), URL_SAFE_NO_PAD.encode(payload));
+    let signature = signer(secret).chain_update(signing_input.as_bytes()).finalize().into_bytes();
+    format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature))
+}
+
+fn decode(token: &str, secret: &[u8]) -> Result<SessionClaims> {
+    let invalid = || crate::Error::Auth("无效的登录令牌".to_string());
+
+    let (signing_input, signature) = token.rsplit_once('.').ok_or_else(invalid)?;
+    let (header, payload) = signing_input.split_once('.').ok_or_else(invalid)?;
+
+    let header: Header = URL_SAFE_NO_PAD
+        .decode(header)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(invalid)?;
+    if header.alg != "HS256" {
+        return Err(invalid());
+    }
+
+    let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| invalid())?;
+    // verify_slice 以常量时间比较签名
+    signer(secret)
+        .chain_update(signing_input.as_bytes())
+        .verify_slice(&signature)
+        .map_err(|_| invalid())?;
+
+    URL_SAFE_NO_PAD
+        .decode(payload)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .ok_or_else(invalid)
+}
+
+/// 用签名密钥初始化 HMAC-SHA256
+fn signer(secret: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(secret).expect("HMAC 接受任意长度的密钥")
+}
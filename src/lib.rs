@@ -1,16 +1,24 @@
+pub mod admin;
+pub mod auth;
+pub mod avatar;
+pub mod bot;
+pub mod chatroom;
+pub mod client_ip;
 pub mod config;
 pub mod game;
+pub mod jwt;
 pub mod message;
 pub mod network;
 pub mod room;
 pub mod security;
 pub mod storage;
+pub mod tutorial;
 pub mod user;
 pub mod word_bank;
 
 pub use config::Config;
-pub use game::GameState;
-pub use message::GameMessage;
+pub use game::{GameBuilder, GameState};
+pub use message::{GameMessage, ServerMessage};
 pub use network::WebSocketServer;
 pub use room::Room;
 pub use storage::*;
@@ -31,6 +39,20 @@ pub enum Error {
     Config(String),
     #[error("认证错误: {0}")]
     Auth(String),
+    #[error("游戏错误: {1}")]
+    Rule(message::ErrorCode, String),
+}
+
+impl Error {
+    /// 返回给客户端的错误代码
+    pub fn code(&self) -> String {
+        match self {
+            Error::Rule(code, _) => format!("{:?}", code),
+            Error::Auth(_) => "AuthError".to_string(),
+            Error::Config(_) => "ConfigError".to_string(),
+            _ => "InternalError".to_string(),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -1,6 +1,9 @@
 use fishpi_undercover::*;
 use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
+/// 发出关闭信号后等待监听停止的最长时间
+const SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // 初始化全局配置
@@ -9,7 +12,7 @@ async fn main() -> Result<()> {
     
     // 初始化日志
     tracing_subscriber::registry()
-        .with(EnvFilter::new(&config.log_filter()))
+        .with(EnvFilter::new(config.log_filter()))
         .with(tracing_subscriber::fmt::layer())
         .init();
         
@@ -22,12 +25,68 @@ async fn main() -> Result<()> {
     let http_addr = config.http_addr().to_string();
     let ws_addr = config.ws_addr().to_string();
     
-    tracing::info!("HTTP服务器启动在 {}", http_addr);
-    tracing::info!("WebSocket服务器启动在 {}", ws_addr);
-    
-    server.start_http_server(&http_addr).await?;
-    
-    server.start_ws_server(&ws_addr).await?;
+    // 两个端口相同时合并为单端口模式，否则分别监听
+    let single_port = http_addr == ws_addr;
+    if single_port {
+        tracing::info!("单端口模式：HTTP与WebSocket服务器启动在 {}", http_addr);
+    } else {
+        tracing::info!("HTTP服务器启动在 {}", http_addr);
+        tracing::info!("WebSocket服务器启动在 {}", ws_addr);
+        server.start_http_server(&http_addr).await?;
+    }
+
+    let serve = async {
+        if single_port {
+            server.start_single_port(&http_addr).await
+        } else {
+            server.start_ws_server(&ws_addr).await
+        }
+    };
+
+    // 收到退出信号时先通知玩家并保存房间状态，再等待监听停止后退出进程
+    tokio::pin!(serve);
+    tokio::select! {
+        result = &mut serve => return result,
+        _ = shutdown_signal() => {}
+    }
+    tracing::info!("收到退出信号，开始优雅关闭");
+    server.shutdown().await;
+
+    // 仍未断开的 WebSocket 长连接可能让监听迟迟不结束，超时后直接退出
+    match tokio::time::timeout(SHUTDOWN_TIMEOUT, serve).await {
+        Ok(result) => result?,
+        Err(_) => tracing::warn!("等待连接关闭超时，直接退出"),
+    }
 
     Ok(())
 }
+
+/// 等待 Ctrl-C 或 SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("监听Ctrl-C失败: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("监听SIGTERM失败: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
@@ -1,7 +1,13 @@
-use crate::game::PlayerId;
+use crate::config::WebSocketConfig;
+use crate::game::{GameSettings, LeaveReason, PlayerId, RolePlan, Sticker};
+use crate::room::{MatchSeries, SeriesScore};
+use crate::storage::{DailyPlaytime, PlayLimit, RatingChange, ReplayEntry};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
+use std::time::{Duration, Instant};
 
+/// 服务器发往客户端消息的通用结构，由 [`ServerMessage`] 转换得到，序号分配、补发缓存和批量发送都使用它
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GameMessage {
     #[serde(rename = "type")]
@@ -9,33 +15,499 @@ pub struct GameMessage {
     pub data: serde_json::Value,
 }
 
+/// 服务器发往客户端的消息，按 `type` 区分，序列化后的 `type` 和 `data` 与 [`GameMessage`] 一致
+///
+/// 房间和连接处理都用它构造外发消息，再转换成 [`GameMessage`] 进入序号、缓存和批量发送流程。
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// 请求处理失败，code 取值见 [`ErrorCode`] 以及 AuthError、ParseError 等连接层错误
+    Error { code: String, message: String },
+    /// 连接成功后发送的登录用户信息
+    UserInfo {
+        user_id: String,
+        username: String,
+        nickname: Option<String>,
+        avatar: Option<String>,
+    },
+    /// 大厅订阅时的完整房间列表
+    RoomList { rooms: Vec<serde_json::Value> },
+    RoomCreated { room: serde_json::Value },
+    RoomUpdated { room: serde_json::Value },
+    RoomDeleted { room_id: String },
+    MatchQueued { waiting: usize, min_players: usize },
+    MatchFound { room_id: String, players: usize },
+    ServerShutdown { message: String },
+    /// 管理员公告
+    ServerNotice {
+        message: String,
+        level: NoticeLevel,
+        from: String,
+        sent_at: DateTime<Utc>,
+    },
+    ReplayStart {
+        game_id: String,
+        room_id: String,
+        total_events: usize,
+        speed: f64,
+    },
+    ReplayEvent(ReplayEntry),
+    ReplayEnd { game_id: String },
+    /// 合并发送的多条消息
+    Batch(MessageBatch),
+    RoomFrozen {
+        message: String,
+        reason: String,
+        frozen_at: i64,
+    },
+    RoomUnfrozen { message: String, paused_seconds: i64 },
+    /// 完整的房间状态
+    StateUpdate(serde_json::Value),
+    /// 与上一次状态相比变化的字段，开启 delta_updates 能力的客户端才会收到
+    StateDelta(serde_json::Value),
+    IdleWarning { message: String, seconds_left: u64 },
+    RoomClosing { message: String },
+    HostChanged {
+        message: String,
+        previous_host: PlayerId,
+        host: PlayerId,
+        host_name: String,
+    },
+    KickedFromOtherRoom { message: String },
+    Kicked { message: String, ui_event: UiEvent },
+    Capabilities {
+        accepted: ClientCapabilities,
+        ui_event_version: u32,
+    },
+    QueryResult {
+        what: String,
+        state: GameStateType,
+        result: serde_json::Value,
+    },
+    Categories {
+        categories: Vec<serde_json::Value>,
+        selected: Option<String>,
+    },
+    ConcedeConfirm { message: String },
+    SeriesUpdate {
+        message: String,
+        series: MatchSeries,
+        winners: Vec<SeriesScore>,
+    },
+    WordQuizStart {
+        message: String,
+        target: String,
+        seconds: u64,
+    },
+    WordQuizResult {
+        message: String,
+        results: Vec<serde_json::Value>,
+    },
+    PreferencesUpdated { preferences: NotificationPreferences },
+    /// 断线重连用的恢复令牌
+    ResumeToken { token: String },
+    PlayLimitWarning {
+        message: String,
+        usage: DailyPlaytime,
+        limit: PlayLimit,
+        reached: bool,
+    },
+    RatingUpdate { changes: Vec<RatingChange> },
+    DescriptionsUpdate {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        descriptions: Vec<serde_json::Value>,
+    },
+    Chat(ChatPayload),
+    /// 表情消息，字段与 chat 相同，方便客户端单独渲染
+    Sticker(ChatPayload),
+    /// 仅发送给被淘汰玩家的聊天消息
+    EliminatedChat {
+        player_id: PlayerId,
+        player_name: String,
+        content: String,
+        timestamp: i64,
+    },
+    GuessWordPrompt { message: String, time_limit: u64 },
+    Countdown { seconds: u64 },
+    /// 发给被淘汰玩家的本轮投票明细
+    VoteRecap {
+        message: String,
+        votes: Vec<serde_json::Value>,
+        votes_against_you: usize,
+    },
+    EliminationReveal(serde_json::Value),
+    /// 本房间连续对局的记分板
+    SessionStats(serde_json::Value),
+    TutorialStep {
+        step: String,
+        title: String,
+        message: String,
+    },
+    Notification(Notification),
+}
+
+impl From<ServerMessage> for GameMessage {
+    fn from(message: ServerMessage) -> Self {
+        serde_json::to_value(&message)
+            .and_then(serde_json::from_value)
+            .expect("外发消息只包含字符串键，总能转换为 GameMessage")
+    }
+}
+
+/// chat 和 sticker 消息的内容
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatPayload {
+    pub player_id: PlayerId,
+    pub player_name: String,
+    pub content: String,
+    pub sticker: Option<Sticker>,
+    pub timestamp: i64,
+}
+
+/// notification 消息：提示文本、可选的 ui_event 以及随通知不同的附加字段
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ui_event: Option<UiEvent>,
+    #[serde(flatten)]
+    pub detail: NotificationDetail,
+}
+
+impl Notification {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            ui_event: None,
+            detail: NotificationDetail::None,
+        }
+    }
+
+    pub fn ui_event(mut self, ui_event: UiEvent) -> Self {
+        self.ui_event = Some(ui_event);
+        self
+    }
+
+    pub fn detail(mut self, detail: NotificationDetail) -> Self {
+        self.detail = detail;
+        self
+    }
+}
+
+impl From<Notification> for ServerMessage {
+    fn from(notification: Notification) -> Self {
+        ServerMessage::Notification(notification)
+    }
+}
+
+/// 通知的附加字段，序列化时与 message 平铺在同一层
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum NotificationDetail {
+    None,
+    Player { player_id: PlayerId },
+    Players { player_ids: Vec<PlayerId> },
+    PlayerJoined { total_players: usize },
+    PlayerLeft { player_id: PlayerId, reason: LeaveReason },
+    ReadyCount { ready_count: usize, min_players: usize },
+    WaitingForHost { waiting_for_host: bool },
+    GameStarted { roles: RolePlan },
+    GameAborted { ended_by_admin: bool },
+    Strikes { strikes: u32 },
+    AutoMuted {
+        player_id: PlayerId,
+        strikes: u32,
+        muted_until: i64,
+    },
+    Stickers { stickers: Vec<Sticker> },
+    Settings { settings: GameSettings },
+    Voted {
+        voter_id: PlayerId,
+        voter_name: String,
+        target_id: PlayerId,
+        target_name: String,
+    },
+    Abstained {
+        voter_id: PlayerId,
+        voter_name: String,
+        target_id: PlayerId,
+        abstain: bool,
+    },
+    VoteResult {
+        vote_count: Vec<serde_json::Value>,
+        abstain_count: usize,
+        abstentions: Vec<serde_json::Value>,
+    },
+    PkStarted { pk_candidates: Vec<PlayerId> },
+    WordGuessed {
+        player_id: PlayerId,
+        guess: Option<String>,
+        correct: bool,
+    },
+}
+
+/// 房间发往玩家的消息，附带该玩家在房间内从1开始逐条递增的序号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequencedMessage {
+    #[serde(flatten)]
+    pub message: GameMessage,
+    pub seq: u64,
+}
+
+/// 房间范围的广播消息，每个玩家连接各自订阅后过滤并分配序号
+#[derive(Debug, Clone)]
+pub struct RoomBroadcast {
+    /// 通知类别，屏蔽了该类别的玩家不会收到
+    pub category: Option<NotificationCategory>,
+    pub message: GameMessage,
+}
+
+/// 发往单个玩家的消息序号、客户端确认进度和尚未确认的最近消息，同一房间内重连后序号继续递增
+#[derive(Debug, Default)]
+pub struct MessageOutbox {
+    last_seq: u64,
+    acked: u64,
+    recent: VecDeque<SequencedMessage>,
+}
+
+impl MessageOutbox {
+    /// 为下一条消息分配序号，并在缓存中保留最近的 capacity 条
+    pub fn assign(&mut self, message: GameMessage, capacity: usize) -> SequencedMessage {
+        self.last_seq += 1;
+        let sequenced = SequencedMessage {
+            message,
+            seq: self.last_seq,
+        };
+        if capacity > 0 {
+            self.recent.push_back(sequenced.clone());
+            while self.recent.len() > capacity {
+                self.recent.pop_front();
+            }
+        }
+        sequenced
+    }
+
+    /// 最近发出的消息序号，尚未发送时为0
+    pub fn last_sent(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// 客户端确认收到的最大序号
+    pub fn last_acked(&self) -> u64 {
+        self.acked
+    }
+
+    /// 记录客户端的确认并丢弃已确认的缓存，返回仍未确认的消息数；确认了尚未发出的序号时返回 None
+    pub fn ack(&mut self, seq: u64) -> Option<u64> {
+        if seq > self.last_seq {
+            return None;
+        }
+        self.acked = self.acked.max(seq);
+        while self.recent.front().is_some_and(|message| message.seq <= self.acked) {
+            self.recent.pop_front();
+        }
+        Some(self.last_seq - self.acked)
+    }
+
+    /// 序号大于 last_seq 的全部消息；其中有消息已不在缓存中时返回 None
+    pub fn since(&self, last_seq: u64) -> Option<Vec<SequencedMessage>> {
+        if last_seq >= self.last_seq {
+            return Some(Vec::new());
+        }
+        let oldest = self.recent.front().map(|message| message.seq)?;
+        if oldest > last_seq + 1 {
+            return None;
+        }
+        Some(
+            self.recent
+                .iter()
+                .filter(|message| message.seq > last_seq)
+                .cloned()
+                .collect(),
+        )
+    }
+}
+
+/// 客户端发往服务器的消息，按 `type` 区分，`data` 缺少必需字段或字段类型不符时在解析阶段直接报错
+///
+/// 消息不携带发送者，房间按连接的登录身份处理，`data` 中多余的字段（包括旧客户端填写的 `player_id`）会被忽略。
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// 加入房间或断线重连，昵称由服务器根据登录信息覆盖
+    Join {
+        #[serde(default)]
+        player_name: String,
+        /// 未声明时按旧协议处理，也不会收到能力确认消息
+        #[serde(default)]
+        capabilities: Option<ClientCapabilities>,
+        #[serde(default)]
+        invite_code: Option<String>,
+    },
+    Ready {},
+    Describe {
+        content: String,
+    },
+    Vote {
+        target_id: PlayerId,
+    },
+    Chat {
+        content: String,
+    },
+    EliminatedChat {
+        content: String,
+    },
+    /// 被淘汰卧底猜平民词
+    GuessWord {
+        word: String,
+    },
+    /// 游戏结束后的猜词小游戏
+    WordGuess {
+        word: String,
+    },
+    Concede {
+        #[serde(default)]
+        confirm: bool,
+    },
+    Leave {},
+    Kick {
+        target_id: PlayerId,
+    },
+    /// 只包含需要修改的设置项
+    UpdateSettings {
+        settings: serde_json::Map<String, serde_json::Value>,
+    },
+    /// 只包含需要修改的偏好项
+    SetPreferences {
+        preferences: serde_json::Map<String, serde_json::Value>,
+    },
+    StartGame {},
+    TransferHost {
+        target_id: PlayerId,
+    },
+    StartSeries {
+        games: u64,
+    },
+    KeepAlive {},
+    Query {
+        what: String,
+    },
+    ListCategories {},
+    SetStickers {
+        stickers: Vec<Sticker>,
+    },
+    Sticker {
+        sticker_id: String,
+    },
+    /// 房主添加电脑玩家，由连接层处理
+    AddBots {
+        #[serde(default = "default_bot_count")]
+        count: usize,
+        #[serde(default)]
+        difficulty: Option<String>,
+    },
+    /// 确认已收到指定序号及之前的全部消息
+    Ack {
+        seq: u64,
+    },
+    /// 重连后补发序号大于 last_seq 的消息，由连接层处理
+    Resume {
+        last_seq: u64,
+    },
+}
+
+fn default_bot_count() -> usize {
+    1
+}
+
+impl ClientMessage {
+    /// 消息类型，与 JSON 中的 type 字段相同
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ClientMessage::Join { .. } => "join",
+            ClientMessage::Ready { .. } => "ready",
+            ClientMessage::Describe { .. } => "describe",
+            ClientMessage::Vote { .. } => "vote",
+            ClientMessage::Chat { .. } => "chat",
+            ClientMessage::EliminatedChat { .. } => "eliminated_chat",
+            ClientMessage::GuessWord { .. } => "guess_word",
+            ClientMessage::WordGuess { .. } => "word_guess",
+            ClientMessage::Concede { .. } => "concede",
+            ClientMessage::Leave { .. } => "leave",
+            ClientMessage::Kick { .. } => "kick",
+            ClientMessage::UpdateSettings { .. } => "update_settings",
+            ClientMessage::SetPreferences { .. } => "set_preferences",
+            ClientMessage::StartGame { .. } => "start_game",
+            ClientMessage::TransferHost { .. } => "transfer_host",
+            ClientMessage::StartSeries { .. } => "start_series",
+            ClientMessage::KeepAlive { .. } => "keep_alive",
+            ClientMessage::Query { .. } => "query",
+            ClientMessage::ListCategories { .. } => "list_categories",
+            ClientMessage::SetStickers { .. } => "set_stickers",
+            ClientMessage::Sticker { .. } => "sticker",
+            ClientMessage::AddBots { .. } => "add_bots",
+            ClientMessage::Ack { .. } => "ack",
+            ClientMessage::Resume { .. } => "resume",
+        }
+    }
+
+    /// 校验客户端消息的负载大小，聊天、描述等文本按字符数限制，其余消息只限制 data 总大小
+    pub fn check_payload_size(&self, config: &WebSocketConfig) -> crate::Result<()> {
+        let data_size = serde_json::to_value(self)
+            .map(|value| value["data"].to_string().len())
+            .unwrap_or_default();
+        if data_size > config.max_data_size {
+            return Err(crate::Error::Rule(
+                ErrorCode::PayloadTooLarge,
+                format!("消息数据过大（{} 字节），最多 {} 字节", data_size, config.max_data_size),
+            ));
+        }
+
+        let (text, limit, label) = match self {
+            ClientMessage::Chat { content, .. } | ClientMessage::EliminatedChat { content, .. } => {
+                (content, config.max_chat_length, "聊天内容")
+            }
+            ClientMessage::Describe { content, .. } => (content, config.max_description_length, "描述内容"),
+            ClientMessage::GuessWord { word, .. } | ClientMessage::WordGuess { word, .. } => {
+                (word, config.max_description_length, "猜测的词语")
+            }
+            _ => return Ok(()),
+        };
+        if text.chars().count() > limit {
+            return Err(crate::Error::Rule(
+                ErrorCode::PayloadTooLarge,
+                format!("{}不能超过 {} 个字符", label, limit),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum GameStateType {
     Lobby,
     RoleAssignment,
     DescribePhase,
     VotePhase,
     ResultPhase,
+    GuessPhase,
     GameOver,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// state_delta 中的结构化变化，字段与完整状态中对应条目的字段相同
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum StateChange {
-    PlayerAdded(PlayerSnapshot),
-    PlayerRemoved(PlayerId),
-    DescriptionAdded(PlayerId, String),
-    VoteCast(PlayerId, PlayerId),
-    StateTransition(GameStateType),
+    /// 玩家列表新增的条目
+    PlayerAdded { player: serde_json::Value },
+    PlayerRemoved { player_id: PlayerId },
+    DescriptionAdded { player_id: PlayerId, content: String },
+    VoteCast { player_id: PlayerId, target_id: PlayerId },
+    StateTransition { state: GameStateType },
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PlayerSnapshot {
-    pub id: PlayerId,
-    pub name: String,
-    pub is_alive: bool,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ErrorCode {
     RoomFull,
     GameStarted,
@@ -45,14 +517,133 @@ pub enum ErrorCode {
     NotYourTurn,
     AlreadyVoted,
     InvalidVote,
+    SelfVoteForbidden,
     Timeout,
+    /// 服务器并发对局已满
+    ServerBusy,
+    /// 消息内容超出长度限制
+    PayloadTooLarge,
+    /// 玩家被禁言
+    Muted,
+    /// 发言包含敏感词被拒绝
+    SensitiveWord,
+    /// 描述中直接说出了自己的词语
+    WordRevealed,
+    /// 有玩家已达到每日游戏上限
+    PlayLimitReached,
+    /// 私密房间的邀请码缺失、无效或已过期
+    InvalidInvite,
+    /// 房间已被管理员冻结
+    RoomFrozen,
+    /// 用户被管理员全服禁言，仍可描述和投票
+    GloballyMuted,
+    /// 重连补发所需的消息已不在缓存中
+    ResumeUnavailable,
     InternalError,
 }
 
+/// 玩家可屏蔽的通知类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationCategory {
+    /// 玩家加入、离开、重连通知
+    Presence,
+    /// 逐条投票通知
+    Votes,
+    /// 倒计时推送
+    Countdown,
+}
+
+/// 玩家通知偏好，默认接收全部通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationPreferences {
+    pub presence: bool,
+    pub votes: bool,
+    pub countdown: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            presence: true,
+            votes: true,
+            countdown: true,
+        }
+    }
+}
+
+impl NotificationPreferences {
+    /// 检查是否接收指定类别的通知
+    pub fn allows(&self, category: NotificationCategory) -> bool {
+        match category {
+            NotificationCategory::Presence => self.presence,
+            NotificationCategory::Votes => self.votes,
+            NotificationCategory::Countdown => self.countdown,
+        }
+    }
+}
+
+/// 客户端在 join 消息中声明支持的协议能力，未声明的能力按旧协议处理
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientCapabilities {
+    /// 首次之后只接收状态的变化部分（state_delta）
+    pub delta_updates: bool,
+    /// 使用 MessagePack 二进制帧代替 JSON 文本帧
+    pub msgpack: bool,
+    /// 同时待发送的多条消息合并为一条 batch 消息
+    pub batched_messages: bool,
+    /// 使用状态中的截止时间自行倒计时，不再接收每秒的 countdown 推送
+    pub deadline_countdown: bool,
+}
+
+/// 管理员公告的级别，客户端据此选择展示样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NoticeLevel {
+    #[default]
+    Info,
+    /// 维护、重启等需要玩家留意的提醒
+    Warning,
+}
+
+/// ui_event 取值的协议版本，新增或修改取值时递增
+pub const UI_EVENT_VERSION: u32 = 1;
+
+/// 附加在广播消息 ui_event 字段中的机器可读事件，客户端据此播放音效或震动，无需匹配中文提示文本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UiEvent {
+    PlayerJoined,
+    PlayerLeft,
+    GameStart,
+    /// 仅发送给轮到描述的玩家
+    YourTurn,
+    VoteStart,
+    RoundStart,
+    PkStart,
+    Elimination,
+    VoteTied,
+    GuessStart,
+    WordGuessed,
+    GameOver,
+    /// 仅发送给被踢出的玩家
+    Kicked,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MessageBatch {
-    pub messages: Vec<GameMessage>,
+    pub messages: Vec<SequencedMessage>,
     pub timestamp: i64,
+    /// 批次开始收集消息的时间，不随消息发送
+    #[serde(skip, default = "Instant::now")]
+    opened_at: Instant,
+}
+
+impl Default for MessageBatch {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MessageBatch {
@@ -60,10 +651,11 @@ impl MessageBatch {
         Self {
             messages: Vec::new(),
             timestamp: chrono::Utc::now().timestamp(),
+            opened_at: Instant::now(),
         }
     }
 
-    pub fn add_message(&mut self, message: GameMessage) {
+    pub fn add_message(&mut self, message: SequencedMessage) {
         self.messages.push(message);
     }
 
@@ -71,47 +663,88 @@ impl MessageBatch {
         self.messages.len() >= 50
     }
 
-    pub fn is_expired(&self) -> bool {
-        (chrono::Utc::now().timestamp() - self.timestamp) >= 50
+    /// 批次的合并窗口是否已结束
+    pub fn is_expired(&self, window: Duration) -> bool {
+        self.opened_at.elapsed() >= window
     }
 }
 
+/// 按合并窗口把连续的消息分成批次，窗口内或未满的批次继续收集后续消息
 #[derive(Debug)]
 pub struct MessageQueue {
     batches: VecDeque<MessageBatch>,
     current_batch: Option<MessageBatch>,
+    window: Duration,
 }
 
 impl MessageQueue {
-    pub fn new() -> Self {
+    pub fn new(window: Duration) -> Self {
         Self {
             batches: VecDeque::new(),
             current_batch: None,
+            window,
         }
     }
 
-    pub fn enqueue(&mut self, message: GameMessage) {
-        if let Some(batch) = &mut self.current_batch {
-            if !batch.is_full() && !batch.is_expired() {
-                batch.add_message(message);
-                return;
-            }
+    pub fn enqueue(&mut self, message: SequencedMessage) {
+        if let Some(batch) = &mut self.current_batch
+            && !batch.is_full() && !batch.is_expired(self.window)
+        {
+            batch.add_message(message);
+            return;
         }
 
+        if let Some(batch) = self.current_batch.take() {
+            self.batches.push_back(batch);
+        }
         let mut new_batch = MessageBatch::new();
         new_batch.add_message(message);
         self.current_batch = Some(new_batch);
     }
 
-    pub fn dequeue_batch(&mut self) -> Option<MessageBatch> {
-        if let Some(batch) = self.current_batch.take() {
-            if batch.is_full() || batch.is_expired() {
-                self.batches.push_back(batch);
-            } else {
-                self.current_batch = Some(batch);
-            }
+    /// 取出全部批次，包括仍在收集中的批次
+    pub fn drain(&mut self) -> Vec<MessageBatch> {
+        let mut batches: Vec<MessageBatch> = self.batches.drain(..).collect();
+        batches.extend(self.current_batch.take());
+        batches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty() && self.current_batch.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn server_messages_keep_type_and_data_layout() {
+        let message: GameMessage = ServerMessage::from(Notification::new("开始游戏")).into();
+        assert_eq!(message.type_, "notification");
+        assert_eq!(message.data, json!({ "message": "开始游戏" }));
+
+        let message: GameMessage = ServerMessage::from(
+            Notification::new("玩家 a 被淘汰了！")
+                .ui_event(UiEvent::Elimination)
+                .detail(NotificationDetail::Player { player_id: "a".to_string() }),
+        )
+        .into();
+        assert_eq!(
+            message.data,
+            json!({ "message": "玩家 a 被淘汰了！", "ui_event": "elimination", "player_id": "a" })
+        );
+
+        let message: GameMessage = ServerMessage::DescriptionsUpdate {
+            message: None,
+            descriptions: Vec::new(),
         }
+        .into();
+        assert_eq!(message.type_, "descriptions_update");
+        assert_eq!(message.data, json!({ "descriptions": [] }));
 
-        self.batches.pop_front()
+        let message: GameMessage = ServerMessage::StateUpdate(json!({ "state": "Lobby" })).into();
+        assert_eq!((message.type_.as_str(), message.data), ("state_update", json!({ "state": "Lobby" })));
     }
 }
@@ -1,13 +1,15 @@
 use crate::{
-    Result, message::GameMessage, room::Room, storage::Storage, user::UserManager,
+    Result, admin::{AdminAction, AdminAuth, AdminScope, AdminToken}, auth::FISHPI_PROVIDER_ID, message::{ClientCapabilities, ClientMessage, ErrorCode, MessageBatch, NoticeLevel, SequencedMessage, ServerMessage}, room::{GameSlots, Room, RoomCheckpoint, RoomKind, RoomVisibility}, storage::{GlobalMute, LeaderboardMetric, LeaderboardPeriod, PlayLimit, Replay, StorageBackend}, user::{User, UserManager},
     word_bank::WordBank,
 };
 use axum::{
     Router,
-    extract::Query,
+    extract::{FromRequestParts, Query},
+    http::{HeaderMap, StatusCode, header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, SEC_WEBSOCKET_PROTOCOL}},
+    middleware::Next,
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
     response::Html,
-    response::Json,
+    response::{IntoResponse, Json},
     routing::{get, post},
 };
 use dashmap::DashMap;
@@ -16,45 +18,92 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 use tower_http::cors::{CorsLayer, Any};
-use urlencoding;
-use crate::game::PlayerId;
-use tokio::sync::mpsc;
+use crate::client_ip::{ClientIp, IpConnections};
+use axum_server::tls_rustls::RustlsConfig;
+use crate::game::{GameParams, GameSettings, PlayerId};
+use tokio::sync::{broadcast, mpsc, watch};
+
+/// 等待处理的聊天室指令上限
+const CHATROOM_COMMAND_QUEUE: usize = 64;
+
+/// 单条 batch 消息最多合并的消息数量
+const MAX_BATCH_MESSAGES: usize = 50;
+
+/// 客户端确认进度落后超过该数量时记录警告，用于发现丢消息或卡死的客户端
+const UNACKED_WARNING_THRESHOLD: u64 = 200;
+
+/// 回放播放倍速上限
+const REPLAY_MAX_SPEED: f64 = 16.0;
+/// 回放中两条事件之间的最长等待时间（秒），跳过长时间的空档
+const REPLAY_MAX_GAP_SECS: f64 = 10.0;
+
+/// 房间列表订阅比对房间变化的间隔（毫秒）
+const ROOM_FEED_INTERVAL_MS: u64 = 1000;
+/// 房间列表订阅事件的缓冲数量，订阅者落后超过该数量时重新下发完整列表
+const ROOM_FEED_CAPACITY: usize = 256;
+
+/// 优雅关闭时广播关闭通知后等待连接发出消息的时间（毫秒）
+const SHUTDOWN_FLUSH_DELAY_MS: u64 = 500;
+
+/// 快速匹配任务清理已断开排队连接的间隔（秒）
+const MATCH_SWEEP_INTERVAL_SECS: u64 = 5;
+
+#[derive(Debug, Deserialize)]
+struct MatchQuery {
+    session_id: String,
+}
+
+/// 每日统计默认返回的天数
+const STATS_DEFAULT_DAYS: u32 = 7;
+
+#[derive(Debug, Deserialize)]
+struct RoomSearchQuery {
+    /// 房间阶段：lobby（大厅）、playing（对局中）、game_over（已结束）
+    state: Option<String>,
+    /// 为true时只返回还有空位的房间
+    has_space: Option<bool>,
+    /// 房间设置的抽词分类
+    category: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    /// 返回最近多少天（不含当天）的每日统计
+    days: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsSummaryQuery {
+    period: Option<LeaderboardPeriod>, // 汇总周期，默认按日
+    count: Option<u32>, // 返回最近多少个周期（含当前周期）
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayQuery {
+    game_id: String,
+    /// 播放倍速，默认按原始节奏播放
+    speed: Option<f64>,
+}
 
 #[derive(Debug, Deserialize)]
 struct RoomQuery {
     room_id: Option<String>,
     session_id: Option<String>, // 会话ID参数
+    resume_token: Option<String>, // 主备切换后重新接入房间的恢复令牌，提供时不需要会话ID
+    format: Option<String>, // 消息编码格式，msgpack 表示收发都使用 MessagePack 二进制帧
 }
 
-#[derive(Debug, Deserialize)]
-struct AuthCallbackQuery {
-    #[serde(rename = "openid.ns")]
-    openid_ns: Option<String>,
-    #[serde(rename = "openid.mode")]
-    openid_mode: Option<String>,
-    #[serde(rename = "openid.op_endpoint")]
-    openid_op_endpoint: Option<String>,
-    #[serde(rename = "openid.claimed_id")]
-    openid_claimed_id: Option<String>,
-    #[serde(rename = "openid.identity")]
-    openid_identity: Option<String>,
-    #[serde(rename = "openid.return_to")]
-    openid_return_to: Option<String>,
-    #[serde(rename = "openid.response_nonce")]
-    openid_response_nonce: Option<String>,
-    #[serde(rename = "openid.assoc_handle")]
-    openid_assoc_handle: Option<String>,
-    #[serde(rename = "openid.signed")]
-    openid_signed: Option<String>,
-    #[serde(rename = "openid.sig")]
-    openid_sig: Option<String>,
-    // 自定义重定向参数
-    callback_url: Option<String>,
+/// WebSocket连接携带的身份凭证
+struct ConnectCredentials {
+    session_id: Option<Uuid>,
+    bot_key: Option<String>,
+    resume_token: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -62,22 +111,144 @@ struct ValidateQuery {
     session_id: String,
 }
 
+/// 换取令牌的请求体，会话ID不放在地址中，避免出现在访问日志里
+#[derive(Debug, Deserialize)]
+struct TokenRequest {
+    session_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GuestQuery {
+    nickname: String, // 游客昵称
+}
+
 #[derive(Debug, Deserialize)]
 struct LoginQuery {
     callback_url: Option<String>,
+    provider: Option<String>, // 登录方式，默认摸鱼派
 }
 
 #[derive(Debug, Deserialize)]
 struct CreateRoomQuery {
     session_id: String,
     room_id: Option<String>, // 可选的房间ID，如果不提供则自动生成
+    blank_role: Option<bool>, // 是否加入白板角色
+    // 以下游戏参数未提供时使用全局配置
+    min_players: Option<usize>,
+    max_players: Option<usize>,
+    describe_time_limit: Option<u64>, // 描述时间（秒）
+    vote_time_limit: Option<u64>, // 投票时间（秒）
+    round_delay: Option<u64>, // 轮次间隔（秒）
+    visibility: Option<RoomVisibility>, // 房间可见性，私密房间不出现在房间列表中
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    metric: Option<LeaderboardMetric>, // 排序依据，默认按胜场
+    period: Option<LeaderboardPeriod>, // 统计周期，默认总榜
+    page: Option<usize>, // 页码，从1开始
+    page_size: Option<usize>, // 每页条数
 }
 
+/// 排行榜每页默认条数与上限
+const LEADERBOARD_DEFAULT_PAGE_SIZE: usize = 20;
+const LEADERBOARD_MAX_PAGE_SIZE: usize = 100;
+
 #[derive(Debug, Deserialize)]
 struct AdminQuery {
     session_id: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct AdminTokenQuery {
+    session_id: String,
+    scopes: Option<String>, // 逗号分隔的权限范围，默认只读
+    ttl_minutes: Option<u64>, // 令牌有效期（分钟）
+}
+
+#[derive(Debug, Deserialize)]
+struct AnnounceQuery {
+    message: String, // 公告内容
+    level: Option<NoticeLevel>, // 公告级别，默认 info
+}
+
+/// 管理员公告的最大字符数
+const ANNOUNCEMENT_MAX_LENGTH: usize = 500;
+
+#[derive(Debug, Deserialize)]
+struct AdminRoomListQuery {
+    page: Option<usize>, // 页码，从1开始
+    page_size: Option<usize>, // 每页房间数
+}
+
+/// 管理接口房间列表每页默认条数与上限
+const ADMIN_ROOMS_DEFAULT_PAGE_SIZE: usize = 20;
+const ADMIN_ROOMS_MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct AuditLogQuery {
+    before: Option<String>, // 上一页最后一条记录的ID，不提供时从最新一条开始
+    limit: Option<usize>, // 每页条数
+}
+
+/// 审计日志每页默认条数与上限
+const AUDIT_LOG_DEFAULT_PAGE_SIZE: usize = 50;
+const AUDIT_LOG_MAX_PAGE_SIZE: usize = 200;
+
+#[derive(Debug, Deserialize)]
+struct InviteQuery {
+    session_id: String,
+    single_use: Option<bool>, // 为true时邀请码使用一次后失效
+    ttl_minutes: Option<u64>, // 邀请码有效期（分钟）
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayLimitQuery {
+    session_id: Option<String>, // 玩家设置自己的上限时需要会话ID
+    max_games: Option<u32>, // 每日最多对局数，不提供表示不限制
+    max_minutes: Option<u32>, // 每日最多游戏分钟数，不提供表示不限制
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileSettingsQuery {
+    session_id: String,
+    private: bool, // 是否隐藏个人资料中的统计、成就和对局记录
+}
+
+/// 个人资料中展示的最近对局数
+const PROFILE_RECENT_GAMES: usize = 5;
+
+#[derive(Debug, Deserialize)]
+struct PlayerGamesQuery {
+    limit: Option<usize>, // 每页条数
+    offset: Option<usize>, // 跳过最近的条数，用于翻页
+}
+
+/// 对局历史每页默认条数和上限
+const PLAYER_GAMES_DEFAULT_PAGE_SIZE: usize = 20;
+const PLAYER_GAMES_MAX_PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Deserialize)]
+struct GlobalMuteQuery {
+    minutes: u64, // 禁言时长（分钟）
+    reason: Option<String>, // 禁言原因，会展示给被禁言的用户
+}
+
+/// 全服禁言的最长时长（分钟）
+const GLOBAL_MUTE_MAX_MINUTES: u64 = 30 * 24 * 60;
+
+#[derive(Debug, Deserialize)]
+struct RoomFreezeQuery {
+    reason: Option<String>, // 冻结原因，会展示给房间内的玩家
+}
+
+#[derive(Debug, Deserialize)]
+struct RoomCleanupQuery {
+    empty_minutes: Option<u64>, // 没有真人玩家且空闲超过指定分钟数
+    game_over: Option<bool>,    // 游戏已结束
+    no_connections: Option<bool>, // 没有任何在线连接
+}
+
 #[derive(Debug, Serialize)]
 struct ValidateResponse {
     success: bool,
@@ -96,15 +267,183 @@ struct CreateRoomResponse {
 pub struct WebSocketServer {
     rooms: Arc<DashMap<String, Arc<Room>>>,
     word_bank: Arc<WordBank>,
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     user_manager: Arc<tokio::sync::RwLock<UserManager>>, // 添加用户管理器
     connection_manager: Arc<ConnectionManager>, // 添加连接管理器
+    admin_auth: Arc<AdminAuth>, // 管理员令牌校验
+    game_slots: Arc<GameSlots>, // 全服并发对局计数
+    cluster: Arc<ClusterState>, // 主备部署中本实例的角色
+    match_queue: Arc<MatchQueue>, // 快速匹配队列
+    room_feed: Arc<RoomFeed>, // 大厅房间列表订阅
+    shutdown: watch::Sender<bool>, // 优雅关闭信号，发出后停止接受新连接
+    ip_connections: Arc<IpConnections>, // 按客户端IP统计的WebSocket连接数
+}
+
+/// 大厅房间列表订阅：定期比对房间概要，向订阅者推送房间创建、更新和删除事件
+pub struct RoomFeed {
+    events: broadcast::Sender<ServerMessage>,
+    /// 最近一次比对时的房间概要
+    snapshot: tokio::sync::RwLock<HashMap<String, serde_json::Value>>,
+}
+
+impl RoomFeed {
+    fn new() -> Self {
+        let (events, _) = broadcast::channel(ROOM_FEED_CAPACITY);
+        RoomFeed {
+            events,
+            snapshot: tokio::sync::RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 向所有大厅订阅者推送一条消息
+    fn announce(&self, message: ServerMessage) {
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.events.send(message);
+    }
+
+    /// 当前完整房间列表消息
+    async fn room_list(&self) -> ServerMessage {
+        let rooms: Vec<serde_json::Value> = self.snapshot.read().await.values().cloned().collect();
+        ServerMessage::RoomList { rooms }
+    }
+
+    /// 大厅中还有空位的房间，人多的房间排在前面
+    async fn open_rooms(&self, limit: usize) -> Vec<serde_json::Value> {
+        let mut rooms: Vec<serde_json::Value> = self
+            .snapshot
+            .read()
+            .await
+            .values()
+            .filter(|room| {
+                room["phase"] == "Lobby" && room["player_count"].as_u64() < room["max_players"].as_u64()
+            })
+            .cloned()
+            .collect();
+        rooms.sort_by(|a, b| {
+            b["player_count"]
+                .as_u64()
+                .cmp(&a["player_count"].as_u64())
+                .then_with(|| a["room_id"].as_str().cmp(&b["room_id"].as_str()))
+        });
+        rooms.truncate(limit);
+        rooms
+    }
+
+    /// 用最新的房间概要更新快照并推送差异，空闲时间的变化不视为房间更新
+    async fn publish(&self, current: HashMap<String, serde_json::Value>) {
+        let listing = |summary: &serde_json::Value| {
+            let mut summary = summary.clone();
+            if let Some(fields) = summary.as_object_mut() {
+                fields.remove("idle_seconds");
+            }
+            summary
+        };
+
+        let mut snapshot = self.snapshot.write().await;
+        let mut events = Vec::new();
+        for (room_id, summary) in &current {
+            match snapshot.get(room_id) {
+                None => events.push(ServerMessage::RoomCreated { room: summary.clone() }),
+                Some(previous) if listing(previous) != listing(summary) => {
+                    events.push(ServerMessage::RoomUpdated { room: summary.clone() })
+                }
+                Some(_) => {}
+            }
+        }
+        for room_id in snapshot.keys() {
+            if !current.contains_key(room_id) {
+                events.push(ServerMessage::RoomDeleted { room_id: room_id.clone() });
+            }
+        }
+        *snapshot = current;
+        drop(snapshot);
+
+        for event in events {
+            self.announce(event);
+        }
+    }
+}
+
+/// 快速匹配队列中等待的玩家
+struct MatchTicket {
+    user: User,
+    notify: mpsc::Sender<ServerMessage>,
+}
+
+/// 快速匹配队列，凑够最少玩家数后自动创建房间
+pub struct MatchQueue {
+    waiting: tokio::sync::Mutex<std::collections::VecDeque<MatchTicket>>,
+    changed: tokio::sync::Notify,
+}
+
+impl MatchQueue {
+    fn new() -> Self {
+        MatchQueue {
+            waiting: tokio::sync::Mutex::new(std::collections::VecDeque::new()),
+            changed: tokio::sync::Notify::new(),
+        }
+    }
+
+    /// 加入队列，同一玩家重复排队时替换原来的连接，返回当前排队人数
+    async fn enqueue(&self, user: User, notify: mpsc::Sender<ServerMessage>) -> usize {
+        let mut waiting = self.waiting.lock().await;
+        waiting.retain(|ticket| ticket.user.id != user.id);
+        waiting.push_back(MatchTicket { user, notify });
+        let len = waiting.len();
+        drop(waiting);
+        self.changed.notify_one();
+        len
+    }
+
+    /// 玩家断开时退出队列，只移除本连接的排队记录
+    async fn leave(&self, user_id: &str, notify: &mpsc::Sender<ServerMessage>) {
+        self.waiting
+            .lock()
+            .await
+            .retain(|ticket| !(ticket.user.id == user_id && ticket.notify.same_channel(notify)));
+    }
+}
+
+/// 主备部署中本实例的角色，未启用主备模式时始终为主实例
+pub struct ClusterState {
+    instance_id: String,
+    active: std::sync::atomic::AtomicBool,
+}
+
+impl ClusterState {
+    fn from_config() -> Self {
+        let config = &crate::config::Config::get().cluster;
+        let instance_id = if config.instance_id.is_empty() {
+            Uuid::new_v4().simple().to_string()
+        } else {
+            config.instance_id.clone()
+        };
+        ClusterState {
+            instance_id,
+            active: std::sync::atomic::AtomicBool::new(!config.enabled),
+        }
+    }
+
+    /// 本实例是否为主实例
+    pub fn is_active(&self) -> bool {
+        self.active.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    fn set_active(&self, active: bool) {
+        self.active.store(active, std::sync::atomic::Ordering::Release);
+    }
 }
 
 /// 全局连接管理器，用于跟踪每个玩家的WebSocket连接
 pub struct ConnectionManager {
     /// 玩家ID -> (房间ID, 连接发送器) 的映射
-    player_connections: Arc<DashMap<PlayerId, (String, mpsc::Sender<GameMessage>)>>,
+    player_connections: Arc<DashMap<PlayerId, (String, mpsc::Sender<SequencedMessage>)>>,
+}
+
+impl Default for ConnectionManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ConnectionManager {
@@ -119,11 +458,10 @@ impl ConnectionManager {
         &self,
         player_id: PlayerId,
         room_id: String,
-        tx: mpsc::Sender<GameMessage>,
-    ) -> Option<(String, mpsc::Sender<GameMessage>)> {
+        tx: mpsc::Sender<SequencedMessage>,
+    ) -> Option<(String, mpsc::Sender<SequencedMessage>)> {
         // 如果玩家已有连接，返回旧连接信息
-        let old_connection = self.player_connections.insert(player_id.clone(), (room_id, tx));
-        old_connection
+        self.player_connections.insert(player_id.clone(), (room_id, tx))
     }
 
     /// 移除玩家的连接
@@ -132,34 +470,295 @@ impl ConnectionManager {
     }
 
     /// 获取玩家的当前连接信息
-    pub async fn get_connection(&self, player_id: &PlayerId) -> Option<(String, mpsc::Sender<GameMessage>)> {
+    pub async fn get_connection(&self, player_id: &PlayerId) -> Option<(String, mpsc::Sender<SequencedMessage>)> {
         self.player_connections.get(player_id).map(|entry| entry.value().clone())
     }
+
+    /// 玩家在指定房间内是否有存活的连接
+    pub fn is_connected(&self, player_id: &PlayerId, room_id: &str) -> bool {
+        self.player_connections
+            .get(player_id)
+            .is_some_and(|entry| entry.0 == room_id && !entry.1.is_closed())
+    }
 }
 
 impl WebSocketServer {
     pub async fn new() -> Self {
         let config = crate::config::Config::get();
-        let storage = Arc::new(
-            Storage::new(&config.redis.url)
-                .await
-                .expect("Failed to create storage"),
-        );
+        let storage = crate::storage::open_storage(config)
+            .await
+            .expect("Failed to create storage");
 
-        // 创建UserManager实例，传入Storage
-        let user_manager = UserManager::new(Storage::new(&config.redis.url).await.expect("Failed to create storage"));
+        // 创建UserManager实例，与服务器共用同一个存储后端
+        let user_manager = UserManager::new(storage.clone());
 
-        WebSocketServer {
+        let admin_auth = Arc::new(AdminAuth::new(storage.clone()));
+
+        let server = WebSocketServer {
             rooms: Arc::new(DashMap::new()),
-            word_bank: Arc::new(WordBank::new()),
+            word_bank: Arc::new(WordBank::load(storage.as_ref()).await),
             storage,
+            admin_auth,
             user_manager: Arc::new(tokio::sync::RwLock::new(user_manager)),
             connection_manager: Arc::new(ConnectionManager::new()),
+            game_slots: Arc::new(GameSlots::from_config()),
+            cluster: Arc::new(ClusterState::from_config()),
+            match_queue: Arc::new(MatchQueue::new()),
+            room_feed: Arc::new(RoomFeed::new()),
+            shutdown: watch::channel(false).0,
+            ip_connections: Arc::new(IpConnections::from_config()),
+        };
+        // 主备模式下由主实例从检查点恢复房间
+        if !config.cluster.enabled {
+            restore_saved_rooms(&server.rooms, &server.word_bank, &server.storage, &server.game_slots).await;
+        }
+        server.spawn_cluster_loop();
+        server.spawn_matchmaker();
+        server.spawn_room_feed();
+        server.spawn_chatroom_commands();
+        server
+    }
+
+    /// 启用聊天室指令时，监听摸鱼派聊天室并处理开房、找房指令，回复发到聊天室
+    fn spawn_chatroom_commands(&self) {
+        let config = &crate::config::Config::get().chatroom;
+        if !config.commands_enabled || config.api_key.is_empty() {
+            return;
+        }
+
+        let (sender, mut receiver) = mpsc::channel(CHATROOM_COMMAND_QUEUE);
+        tokio::spawn(crate::chatroom::listen(sender));
+
+        let rooms = self.rooms.clone();
+        let word_bank = self.word_bank.clone();
+        let storage = self.storage.clone();
+        let game_slots = self.game_slots.clone();
+        let room_feed = self.room_feed.clone();
+        let cluster = self.cluster.clone();
+        let cooldown = std::time::Duration::from_secs(config.command_cooldown);
+        tokio::spawn(async move {
+            // 每个用户最近一次开房的时间
+            let mut last_created: HashMap<String, std::time::Instant> = HashMap::new();
+            while let Some(message) = receiver.recv().await {
+                let Some(command) = crate::chatroom::parse_command(&message.content) else {
+                    continue;
+                };
+                // 主备部署中只由持有租约的主实例处理指令，避免重复开房和重复回复
+                if !cluster.is_active() {
+                    continue;
+                }
+                let prefix = &crate::config::Config::get().chatroom.command_prefix;
+                let reply = match command {
+                    crate::chatroom::ChatCommand::CreateRoom => {
+                        last_created.retain(|_, at| at.elapsed() < cooldown);
+                        if last_created.contains_key(&message.user_id) {
+                            format!("@{} 开房太频繁了，请稍后再试", message.user_name)
+                        } else if let Some(room_id) = hosted_room(&rooms, &message.user_id).await {
+                            format!(
+                                "@{} 你已经是房间 **{}** 的房主：[点击加入]({})",
+                                message.user_name,
+                                room_id,
+                                room_join_url(&room_id)
+                            )
+                        } else {
+                            let mut room_id = generate_random_room_id();
+                            while rooms.contains_key(&room_id) {
+                                room_id = generate_random_room_id();
+                            }
+                            let room = Room::new(
+                                room_id.clone(),
+                                GameParams::from_config(),
+                                word_bank.clone(),
+                                storage.clone(),
+                                message.user_id.clone(),
+                            );
+                            register_room(room, &rooms, &game_slots);
+                            last_created.insert(message.user_id.clone(), std::time::Instant::now());
+                            info!("摸鱼派聊天室用户 {} 创建了房间 {}", message.user_name, room_id);
+                            format!(
+                                "@{} 谁是卧底房间 **{}** 已创建，你是房主：[点击加入]({})",
+                                message.user_name,
+                                room_id,
+                                room_join_url(&room_id)
+                            )
+                        }
+                    }
+                    crate::chatroom::ChatCommand::FindRoom => match room_feed.open_rooms(1).await.first() {
+                        Some(room) => {
+                            let room_id = room["room_id"].as_str().unwrap_or_default();
+                            format!(
+                                "@{} 房间 **{}** 正在等人（{}/{}人）：[点击加入]({})",
+                                message.user_name,
+                                room_id,
+                                room["player_count"].as_u64().unwrap_or(0),
+                                room["max_players"].as_u64().unwrap_or(0),
+                                room_join_url(room_id)
+                            )
+                        }
+                        None => format!(
+                            "@{} 暂时没有等人的房间，发送「{} 开房」自己开一个吧",
+                            message.user_name, prefix
+                        ),
+                    },
+                    crate::chatroom::ChatCommand::Help => format!(
+                        "@{0} 谁是卧底指令：「{1} 开房」创建房间，「{1} 加入」查找等人的房间",
+                        message.user_name, prefix
+                    ),
+                };
+                if let Err(e) = crate::chatroom::send_message(&reply).await {
+                    warn!("回复摸鱼派聊天室指令失败: {}", e);
+                }
+            }
+        });
+    }
+
+    /// 启动房间列表订阅任务，定期汇总对外展示的房间并推送变化
+    fn spawn_room_feed(&self) {
+        let rooms = self.rooms.clone();
+        let room_feed = self.room_feed.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(ROOM_FEED_INTERVAL_MS));
+            loop {
+                interval.tick().await;
+
+                let candidates: Vec<(String, Arc<Room>)> = rooms
+                    .iter()
+                    .filter(|entry| entry.value().is_listed())
+                    .map(|entry| (entry.key().clone(), entry.value().clone()))
+                    .collect();
+                let mut current = HashMap::new();
+                for (room_id, room) in candidates {
+                    if !room.should_be_deleted().await {
+                        let summary = room_summary(&room_id, &room).await;
+                        current.insert(room_id, summary);
+                    }
+                }
+                room_feed.publish(current).await;
+            }
+        });
+    }
+
+    /// 启动快速匹配任务：排队人数达到最少玩家数时，按排队顺序取出玩家创建房间并通知他们加入
+    fn spawn_matchmaker(&self) {
+        let queue = self.match_queue.clone();
+        let rooms = self.rooms.clone();
+        let word_bank = self.word_bank.clone();
+        let storage = self.storage.clone();
+        let game_slots = self.game_slots.clone();
+
+        tokio::spawn(async move {
+            let params = GameParams::from_config();
+            loop {
+                // 排队变化时立即匹配，同时定期清理已断开的排队连接
+                tokio::select! {
+                    _ = queue.changed.notified() => {}
+                    _ = tokio::time::sleep(std::time::Duration::from_secs(MATCH_SWEEP_INTERVAL_SECS)) => {}
+                }
+
+                // 只在取出成组的玩家时持有队列锁，创建房间和通知玩家在锁外进行
+                let groups: Vec<Vec<MatchTicket>> = {
+                    let mut waiting = queue.waiting.lock().await;
+                    waiting.retain(|ticket| !ticket.notify.is_closed());
+                    let mut groups = Vec::new();
+                    while waiting.len() >= params.min_players {
+                        let count = waiting.len().min(params.max_players);
+                        groups.push(waiting.drain(..count).collect());
+                    }
+                    groups
+                };
+
+                for group in groups {
+                    let mut room_id = generate_random_room_id();
+                    while rooms.contains_key(&room_id) {
+                        room_id = generate_random_room_id();
+                    }
+                    // 排队最久的玩家成为房主
+                    let host = group[0].user.id.clone();
+                    let room = Room::new(room_id.clone(), params.clone(), word_bank.clone(), storage.clone(), host);
+                    register_room(room, &rooms, &game_slots);
+                    info!("快速匹配创建房间 {}，共 {} 名玩家", room_id, group.len());
+
+                    let count = group.len();
+                    for ticket in group {
+                        let message = ServerMessage::MatchFound {
+                            room_id: room_id.clone(),
+                            players: count,
+                        };
+                        // 不等待消息发送，读取缓慢的连接不会拖住其他玩家的匹配
+                        if let Err(e) = ticket.notify.try_send(message) {
+                            debug!("通知玩家 {} 匹配成功失败: {}", ticket.user.id, e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// 主备模式：定期续约主实例租约，主实例写入房间检查点，备用实例获得租约后从检查点恢复房间
+    fn spawn_cluster_loop(&self) {
+        let config = crate::config::Config::get().cluster.clone();
+        if !config.enabled {
+            return;
         }
+        let cluster = self.cluster.clone();
+        let rooms = self.rooms.clone();
+        let word_bank = self.word_bank.clone();
+        let storage = self.storage.clone();
+        let game_slots = self.game_slots.clone();
+        info!("主备模式已启用，实例标识: {}", cluster.instance_id);
+
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(config.checkpoint_interval.max(1)));
+            loop {
+                interval.tick().await;
+                match storage.try_hold_active_lease(&cluster.instance_id, config.lease_ttl).await {
+                    Ok(true) => {
+                        if !cluster.is_active() {
+                            info!("实例 {} 成为主实例，开始从检查点恢复房间", cluster.instance_id);
+                            restore_rooms(&rooms, &word_bank, &storage, &game_slots, config.checkpoint_max_age).await;
+                            cluster.set_active(true);
+                        }
+                        checkpoint_rooms(&rooms, &storage, &cluster.instance_id).await;
+                    }
+                    Ok(false) => {
+                        if cluster.is_active() {
+                            warn!("实例 {} 失去主实例租约，转为备用实例", cluster.instance_id);
+                            cluster.set_active(false);
+                        }
+                    }
+                    // Redis 不可用时备用实例同样无法接管，保持当前角色
+                    Err(e) => warn!("续约主实例租约失败: {}", e),
+                }
+            }
+        });
     }
 
     /// 启动HTTP服务器（用于认证回调等）
     pub async fn start_http_server(&self, http_addr: &str) -> Result<()> {
+        let http_app = self.http_router();
+        let tls = load_tls_config().await?;
+        let http_listener = tokio::net::TcpListener::bind(http_addr)
+            .await
+            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+        
+        // 启动HTTP服务器
+        let shutdown_signal = self.shutdown_signal();
+        tokio::spawn(async move {
+            run_listener(http_listener, http_app, tls, shutdown_signal)
+                .await
+                .map_err(|e| {
+                    error!("HTTP服务器错误: {}", e);
+                    crate::Error::Network(anyhow::anyhow!(e))
+                })
+        });
+
+        Ok(())
+    }
+
+    /// HTTP 接口路由（认证、房间查询、管理接口等）
+    fn http_router(&self) -> Router {
         let user_manager = self.user_manager.clone();
         let config = crate::config::Config::get();
 
@@ -170,6 +769,7 @@ impl WebSocketServer {
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
+                .allow_headers([AUTHORIZATION, CONTENT_TYPE])
                 .allow_credentials(false)
         } else if let Some(allowed_origins) = &config.cors.allowed_origins {
             if allowed_origins.is_empty() {
@@ -178,6 +778,7 @@ impl WebSocketServer {
                 CorsLayer::new()
                     .allow_origin(Any)
                     .allow_methods(Any)
+                    .allow_headers([AUTHORIZATION, CONTENT_TYPE])
                     .allow_credentials(false)
             } else {
                 // 生产环境：限制特定来源，允许凭证
@@ -197,6 +798,7 @@ impl WebSocketServer {
                         axum::http::Method::DELETE,
                         axum::http::Method::OPTIONS,
                     ])
+                    .allow_headers([AUTHORIZATION, CONTENT_TYPE])
                     .allow_credentials(true)
             }
         } else {
@@ -205,10 +807,11 @@ impl WebSocketServer {
             CorsLayer::new()
                 .allow_origin(Any)
                 .allow_methods(Any)
+                .allow_headers([AUTHORIZATION, CONTENT_TYPE])
                 .allow_credentials(false)
         };
 
-        let http_app = Router::new()
+        Router::new()
             .route("/", get(serve_index))
             .route("/index.html", get(serve_index))
             .route("/admin.html", get(serve_admin))
@@ -216,397 +819,2182 @@ impl WebSocketServer {
                 "/auth/callback",
                 get({
                     let user_manager = user_manager.clone();
-                    move |Query(query): Query<AuthCallbackQuery>| async move {
-                        handle_auth_callback(query, user_manager.clone()).await
+                    move |Query(params): Query<HashMap<String, String>>| async move {
+                        handle_auth_callback(FISHPI_PROVIDER_ID, params, user_manager.clone()).await
                     }
                 }),
             )
             .route(
-                "/auth/validate",
+                "/auth/callback/:provider",
                 get({
                     let user_manager = user_manager.clone();
-                    move |Query(query): Query<ValidateQuery>| async move {
-                        handle_validate_session(query, user_manager.clone()).await
+                    move |axum::extract::Path(provider): axum::extract::Path<String>, Query(params): Query<HashMap<String, String>>| async move {
+                        handle_auth_callback(&provider, params, user_manager.clone()).await
                     }
                 }),
             )
             .route(
-                "/auth/login",
+                "/auth/providers",
                 get({
                     let user_manager = user_manager.clone();
-                    move |Query(query): Query<LoginQuery>| async move { 
-                        handle_generate_login_url(query, user_manager.clone()).await 
-                    }
+                    move || async move { handle_list_providers(user_manager.clone()).await }
                 }),
             )
             .route(
-                "/rooms/create",
+                "/auth/validate",
                 get({
-                    let rooms = self.rooms.clone();
-                    let word_bank = self.word_bank.clone();
-                    let storage = self.storage.clone();
-                    let user_manager = self.user_manager.clone();
-                    move |Query(query): Query<CreateRoomQuery>| async move {
-                        handle_create_room(query, rooms.clone(), word_bank.clone(), storage.clone(), user_manager.clone()).await
+                    let user_manager = user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<ValidateQuery>| async move {
+                        handle_validate_session(query, user_manager.clone()).await
                     }
                 }),
             )
             .route(
-                "/rooms/status",
-                get({
-                    let rooms = self.rooms.clone();
-                    move || async move { handle_rooms_status(rooms.clone()).await }
+                "/auth/token",
+                post({
+                    let user_manager = user_manager.clone();
+                    move |bearer: Option<axum::Extension<BearerSession>>, body: Option<Json<TokenRequest>>| async move {
+                        // 携带有效令牌时用令牌中的会话续期，否则使用请求体中的会话ID
+                        let session_id = match (bearer, body) {
+                            (Some(axum::Extension(BearerSession(session_id))), _) => Some(session_id.to_string()),
+                            (None, body) => body.map(|Json(body)| body.session_id),
+                        };
+                        handle_issue_token(session_id, user_manager.clone()).await
+                    }
                 }),
             )
             .route(
-                "/admin/rooms",
-                get({
-                    let rooms = self.rooms.clone();
-                    let user_manager = self.user_manager.clone();
-                    move |Query(query): Query<AdminQuery>| async move {
-                        handle_admin_rooms(query, rooms.clone(), user_manager.clone()).await
+                "/auth/guest",
+                post({
+                    let user_manager = user_manager.clone();
+                    move |client_ip: ClientIp, Query(query): Query<GuestQuery>| async move {
+                        handle_guest_login(query, client_ip, user_manager.clone()).await
                     }
                 }),
             )
             .route(
-                "/admin/rooms/:room_id/delete",
+                "/auth/logout",
                 post({
-                    let rooms = self.rooms.clone();
-                    let user_manager = self.user_manager.clone();
-                    move |axum::extract::Path(room_id): axum::extract::Path<String>, Query(query): Query<AdminQuery>| async move {
-                        handle_admin_delete_room(room_id, query, rooms.clone(), user_manager.clone()).await
+                    let user_manager = user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<ValidateQuery>| async move {
+                        handle_logout(query, user_manager.clone()).await
                     }
                 }),
             )
-            .layer(cors);
-
-        let http_listener = tokio::net::TcpListener::bind(http_addr)
-            .await
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-        
-        // 启动HTTP服务器
-        tokio::spawn(async move {
-            axum::serve(http_listener, http_app)
+            .route(
+                "/auth/logout_all",
+                post({
+                    let user_manager = user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<ValidateQuery>| async move {
+                        handle_logout_all(query, user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/auth/logout_others",
+                post({
+                    let user_manager = user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<ValidateQuery>| async move {
+                        handle_logout_others(query, user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/auth/sessions",
+                get({
+                    let user_manager = user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<ValidateQuery>| async move {
+                        handle_list_sessions(query, user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/auth/login",
+                get({
+                    let user_manager = user_manager.clone();
+                    move |client_ip: ClientIp, Query(query): Query<LoginQuery>| async move {
+                        handle_generate_login_url(query, client_ip, user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/create",
+                get({
+                    let rooms = self.rooms.clone();
+                    let word_bank = self.word_bank.clone();
+                    let storage = self.storage.clone();
+                    let user_manager = self.user_manager.clone();
+                    let game_slots = self.game_slots.clone();
+                    move |SessionQuery(query): SessionQuery<CreateRoomQuery>| async move {
+                        handle_create_room(query, rooms.clone(), word_bank.clone(), storage.clone(), user_manager.clone(), game_slots.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/clone",
+                get({
+                    let rooms = self.rooms.clone();
+                    let word_bank = self.word_bank.clone();
+                    let storage = self.storage.clone();
+                    let user_manager = self.user_manager.clone();
+                    let game_slots = self.game_slots.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, SessionQuery(query): SessionQuery<AdminQuery>| async move {
+                        handle_clone_room(room_id, query, rooms.clone(), word_bank.clone(), storage.clone(), user_manager.clone(), game_slots.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/invite",
+                post({
+                    let rooms = self.rooms.clone();
+                    let user_manager = self.user_manager.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, SessionQuery(query): SessionQuery<InviteQuery>| async move {
+                        handle_create_invite(room_id, query, rooms.clone(), user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/tutorial",
+                get({
+                    let rooms = self.rooms.clone();
+                    let word_bank = self.word_bank.clone();
+                    let storage = self.storage.clone();
+                    let user_manager = self.user_manager.clone();
+                    let game_slots = self.game_slots.clone();
+                    move |SessionQuery(query): SessionQuery<AdminQuery>| async move {
+                        handle_create_tutorial_room(query, rooms.clone(), word_bank.clone(), storage.clone(), user_manager.clone(), game_slots.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/status",
+                get({
+                    let rooms = self.rooms.clone();
+                    let game_slots = self.game_slots.clone();
+                    move || async move { handle_rooms_status(rooms.clone(), game_slots.clone()).await }
+                }),
+            )
+            .route(
+                "/rooms/search",
+                get({
+                    let rooms = self.rooms.clone();
+                    move |Query(query): Query<RoomSearchQuery>| async move {
+                        handle_rooms_search(query, rooms.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/readyz",
+                get({
+                    let storage = self.storage.clone();
+                    let cluster = self.cluster.clone();
+                    move || async move { handle_readyz(storage.clone(), cluster.clone()).await }
+                }),
+            )
+            .route(
+                "/leaderboard",
+                get({
+                    let storage = self.storage.clone();
+                    move |Query(query): Query<LeaderboardQuery>| async move {
+                        handle_leaderboard(query, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/feed.json",
+                get({
+                    let room_feed = self.room_feed.clone();
+                    move || async move { handle_rooms_feed_json(room_feed.clone()).await }
+                }),
+            )
+            .route(
+                "/rooms/feed.xml",
+                get({
+                    let room_feed = self.room_feed.clone();
+                    move || async move { handle_rooms_feed_rss(room_feed.clone()).await }
+                }),
+            )
+            .route(
+                "/stats",
+                get({
+                    let storage = self.storage.clone();
+                    move |Query(query): Query<StatsQuery>| async move {
+                        handle_stats(query, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/stats/summary",
+                get({
+                    let storage = self.storage.clone();
+                    move |Query(query): Query<StatsSummaryQuery>| async move {
+                        handle_stats_summary(query, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/replays/:game_id",
+                get({
+                    let storage = self.storage.clone();
+                    move |axum::extract::Path(game_id): axum::extract::Path<String>| async move {
+                        handle_get_replay(game_id, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/avatars/:user_id",
+                get({
+                    let storage = self.storage.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>| async move {
+                        handle_get_avatar(user_id, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/users/:user_id/stats",
+                get({
+                    let storage = self.storage.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>| async move {
+                        handle_get_player_stats(user_id, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/users/:user_id/profile",
+                get({
+                    let storage = self.storage.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>| async move {
+                        handle_get_profile(user_id, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/users/:user_id/games",
+                get({
+                    let storage = self.storage.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>, Query(query): Query<PlayerGamesQuery>| async move {
+                        handle_get_player_games(user_id, query, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/ratings/:player_id",
+                get({
+                    let storage = self.storage.clone();
+                    move |axum::extract::Path(player_id): axum::extract::Path<String>| async move {
+                        handle_get_rating(player_id, storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/me/playtime",
+                get({
+                    let storage = self.storage.clone();
+                    let user_manager = self.user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<AdminQuery>| async move {
+                        handle_get_playtime(query, storage.clone(), user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/me/profile",
+                post({
+                    let storage = self.storage.clone();
+                    let user_manager = self.user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<ProfileSettingsQuery>| async move {
+                        handle_set_profile_settings(query, storage.clone(), user_manager.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/me/play_limit",
+                post({
+                    let storage = self.storage.clone();
+                    let user_manager = self.user_manager.clone();
+                    move |SessionQuery(query): SessionQuery<PlayLimitQuery>| async move {
+                        handle_set_self_play_limit(query, storage.clone(), user_manager.clone()).await
+                    }
+                }),
+            )
+            .nest("/api/admin", self.admin_router())
+            .layer(axum::middleware::from_fn(resolve_bearer_session))
+            .layer(axum::middleware::from_fn({
+                let admin_auth = self.admin_auth.clone();
+                move |client_ip: ClientIp, request: axum::extract::Request, next: Next| {
+                    let admin_auth = admin_auth.clone();
+                    async move { limit_admin_requests(client_ip, request, next, admin_auth).await }
+                }
+            }))
+            .layer(cors)
+    }
+
+    /// 管理接口路由，挂载在 /api/admin 下，除签发令牌外都需要管理员令牌
+    fn admin_router(&self) -> Router {
+        Router::new()
+            .route(
+                "/tokens",
+                post({
+                    let user_manager = self.user_manager.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |SessionQuery(query): SessionQuery<AdminTokenQuery>| async move {
+                        handle_issue_admin_token(query, user_manager.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/tokens/revoke",
+                post({
+                    let admin_auth = self.admin_auth.clone();
+                    move |headers: HeaderMap| async move {
+                        handle_revoke_admin_token(headers, admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/announce",
+                post({
+                    let rooms = self.rooms.clone();
+                    let room_feed = self.room_feed.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |headers: HeaderMap, Query(query): Query<AnnounceQuery>| async move {
+                        handle_admin_announce(query, headers, rooms.clone(), room_feed.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms",
+                get({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    let storage = self.storage.clone();
+                    move |headers: HeaderMap, Query(query): Query<AdminRoomListQuery>| async move {
+                        handle_admin_rooms(query, headers, rooms.clone(), admin_auth.clone(), storage.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/audit",
+                get({
+                    let storage = self.storage.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |headers: HeaderMap, Query(query): Query<AuditLogQuery>| async move {
+                        handle_admin_audit_log(query, headers, storage.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/cleanup",
+                post({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |headers: HeaderMap, Query(query): Query<RoomCleanupQuery>| async move {
+                        handle_admin_cleanup_rooms(query, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id",
+                get({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_room_detail(room_id, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                })
+                .delete({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_delete_room(room_id, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/state",
+                get({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_room_state(room_id, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/players/:player_id/kick",
+                post({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path((room_id, player_id)): axum::extract::Path<(String, PlayerId)>, headers: HeaderMap| async move {
+                        handle_admin_kick_player(room_id, player_id, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/end",
+                post({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_end_game(room_id, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/freeze",
+                post({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, headers: HeaderMap, Query(query): Query<RoomFreezeQuery>| async move {
+                        handle_admin_freeze_room(room_id, query, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/rooms/:room_id/unfreeze",
+                post({
+                    let rooms = self.rooms.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(room_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_unfreeze_room(room_id, headers, rooms.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/:user_id/sessions",
+                get({
+                    let user_manager = self.user_manager.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_user_sessions(user_id, headers, user_manager.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/:user_id/mute",
+                post({
+                    let storage = self.storage.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>, headers: HeaderMap, Query(query): Query<GlobalMuteQuery>| async move {
+                        handle_admin_mute_user(user_id, query, headers, storage.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/:user_id/unmute",
+                post({
+                    let storage = self.storage.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>, headers: HeaderMap| async move {
+                        handle_admin_unmute_user(user_id, headers, storage.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+            .route(
+                "/players/:user_id/play_limit",
+                post({
+                    let storage = self.storage.clone();
+                    let admin_auth = self.admin_auth.clone();
+                    move |axum::extract::Path(user_id): axum::extract::Path<String>, headers: HeaderMap, SessionQuery(query): SessionQuery<PlayLimitQuery>| async move {
+                        handle_admin_set_play_limit(user_id, query, headers, storage.clone(), admin_auth.clone()).await
+                    }
+                }),
+            )
+    }
+
+    /// 启动WebSocket服务器
+    pub async fn start_ws_server(&self, ws_addr: &str) -> Result<()> {
+        self.serve(ws_addr, self.ws_router(), "WebSocket").await
+    }
+
+    /// 单端口模式：HTTP 接口与 WebSocket 合并到同一个监听端口
+    pub async fn start_single_port(&self, addr: &str) -> Result<()> {
+        let app = self.http_router().merge(self.ws_router());
+        self.serve(addr, app, "HTTP/WebSocket").await
+    }
+
+    /// 绑定地址并运行服务，直到优雅关闭
+    async fn serve(&self, addr: &str, app: Router, name: &str) -> Result<()> {
+        let tls = load_tls_config().await?;
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(|e| {
+                error!("绑定{}地址失败: {} - {}", name, addr, e);
+                crate::Error::Network(anyhow::anyhow!(e))
+            })?;
+        
+        run_listener(listener, app, tls, self.shutdown_signal())
+            .await
+            .map_err(|e| {
+                error!("{}服务器运行错误: {}", name, e);
+                crate::Error::Network(anyhow::anyhow!(e))
+            })?;
+        Ok(())
+    }
+
+    /// WebSocket 及匹配、回放相关路由
+    fn ws_router(&self) -> Router {
+        let rooms = self.rooms.clone();
+        // let word_bank = self.word_bank.clone();
+        let storage = self.storage.clone();
+        let user_manager = self.user_manager.clone();
+        let connection_manager = self.connection_manager.clone();
+        let cluster = self.cluster.clone();
+        let ip_connections = self.ip_connections.clone();
+
+        Router::new()
+            .route(
+                "/ws",
+                get({
+                    let user_manager = user_manager.clone();
+                    let connection_manager = connection_manager.clone();
+                    let cluster = cluster.clone();
+                    let room_feed = self.room_feed.clone();
+                    let ip_connections = ip_connections.clone();
+                    move |client_ip: ClientIp, ws: WebSocketUpgrade, headers: HeaderMap, SessionQuery(query): SessionQuery<RoomQuery>| async move {
+                        let ws = ws.protocols([BEARER_PROTOCOL, BOT_PROTOCOL]);
+                        // 外部机器人的API密钥只从请求头或子协议读取，不出现在URL中
+                        let bot_key = bot_api_key(&headers);
+                        // 备用实例不接受玩家连接，客户端应重试连接主实例
+                        if !cluster.is_active() {
+                            return ws.on_upgrade(send_standby_error);
+                        }
+
+                        let Some(ip_guard) = ip_connections.try_acquire(client_ip.0) else {
+                            return too_many_connections(client_ip);
+                        };
+
+                        // 不提供room_id时订阅大厅房间列表
+                        let room_id = match query.room_id {
+                            Some(id) => id,
+                            None => {
+                                return ws.on_upgrade(move |socket| async move {
+                                    let _ip_guard = ip_guard;
+                                    handle_lobby_connection(socket, room_feed).await
+                                });
+                            }
+                        };
+                        
+                        let session_id = query.session_id.and_then(|id| {
+                            match Uuid::parse_str(&id) {
+                                Ok(uuid) => {
+                                    debug!("解析会话ID成功: {}", uuid);
+                                    Some(uuid)
+                                }
+                                Err(e) => {
+                                    error!("解析会话ID失败: {}, 原始值: {}", e, id);
+                                    None
+                                }
+                            }
+                        });
+                        
+                        debug!(
+                            "WebSocket连接请求详情 - 房间ID: {}, 会话ID: {:?}, 客户端IP: {}",
+                            room_id, session_id, client_ip
+                        );
+
+                        // format=msgpack 时整个连接改用 MessagePack 二进制帧收发
+                        let msgpack = query.format.as_deref() == Some("msgpack");
+
+                        // 限制入站消息大小，超出时连接会被断开，避免超大帧占用内存
+                        let max_message_size = crate::config::Config::get().websocket.max_message_size;
+                        let ws = ws
+                            .max_message_size(max_message_size)
+                            .max_frame_size(max_message_size);
+
+                        // 添加CORS和WebSocket升级头
+                        ws.on_upgrade(move |socket| async move {
+                            let _ip_guard = ip_guard;
+                            debug!("WebSocket连接已升级，开始处理连接");
+                            handle_room_connection(
+                                socket,
+                                room_id,
+                                ConnectCredentials {
+                                    session_id,
+                                    bot_key,
+                                    resume_token: query.resume_token,
+                                },
+                                msgpack,
+                                rooms.clone(),
+                                user_manager.clone(),
+                                connection_manager.clone(),
+                            )
+                            .await;
+                            debug!("WebSocket连接处理完成");
+                        })
+                    }
+                }),
+            )
+            .route(
+                "/match/queue",
+                get({
+                    let user_manager = user_manager.clone();
+                    let match_queue = self.match_queue.clone();
+                    let cluster = cluster.clone();
+                    let ip_connections = ip_connections.clone();
+                    move |client_ip: ClientIp, ws: WebSocketUpgrade, SessionQuery(query): SessionQuery<MatchQuery>| async move {
+                        let ws = ws.protocols([BEARER_PROTOCOL]);
+                        let Some(ip_guard) = ip_connections.try_acquire(client_ip.0) else {
+                            return too_many_connections(client_ip);
+                        };
+                        ws.on_upgrade(move |socket| async move {
+                            let _ip_guard = ip_guard;
+                            if !cluster.is_active() {
+                                send_standby_error(socket).await;
+                                return;
+                            }
+                            handle_match_connection(socket, query, user_manager.clone(), match_queue.clone()).await;
+                        })
+                    }
+                }),
+            )
+            .route(
+                "/ws/replay",
+                get({
+                    let storage = storage.clone();
+                    move |client_ip: ClientIp, ws: WebSocketUpgrade, Query(query): Query<ReplayQuery>| async move {
+                        let Some(ip_guard) = ip_connections.try_acquire(client_ip.0) else {
+                            return too_many_connections(client_ip);
+                        };
+                        ws.on_upgrade(move |socket| async move {
+                            let _ip_guard = ip_guard;
+                            handle_replay_connection(socket, query, storage.clone()).await;
+                        })
+                    }
+                }),
+            )
+            .layer(axum::middleware::from_fn(resolve_bearer_session))
+            // 添加OPTIONS路由处理预检请求
+            .route(
+                "/ws",
+                axum::routing::options(|| async {
+                    axum::response::Response::builder()
+                        .header("Access-Control-Allow-Origin", "*")
+                        .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
+                        .header("Access-Control-Allow-Headers", "Content-Type, Authorization, Upgrade, Connection, Sec-WebSocket-Key, Sec-WebSocket-Version, Sec-WebSocket-Protocol")
+                        .header("Access-Control-Allow-Credentials", "true")
+                        .status(200)
+                        .body(axum::body::Body::empty())
+                        .unwrap()
+                }),
+            )
+    }
+
+    /// 优雅关闭发出后结束的 future，用于停止监听
+    fn shutdown_signal(&self) -> impl std::future::Future<Output = ()> + Send + 'static {
+        let mut shutdown = self.shutdown.subscribe();
+        async move {
+            let _ = shutdown.wait_for(|stopping| *stopping).await;
+        }
+    }
+
+    /// 优雅关闭：停止接受新连接，通知所有房间的玩家，保存房间状态并补写降级期间缓存的写操作
+    pub async fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+
+        let rooms: Vec<Arc<Room>> = self.rooms.iter().map(|entry| entry.value().clone()).collect();
+        info!("开始优雅关闭，共 {} 个房间", rooms.len());
+        for room in &rooms {
+            if room.is_deleted().await {
+                continue;
+            }
+            room.broadcast(ServerMessage::ServerShutdown {
+                message: "服务器正在重启，请稍后重新连接".to_string(),
+            })
+            .await;
+            if let Err(e) = room.save_state_snapshot().await {
+                error!("关闭前保存房间 {} 状态失败: {}", room.id(), e);
+            }
+        }
+
+        // 给连接任务留出发送关闭通知的时间
+        tokio::time::sleep(std::time::Duration::from_millis(SHUTDOWN_FLUSH_DELAY_MS)).await;
+
+        // 降级期间缓存在内存中的写操作（包括上面保存的房间状态）在退出前补写
+        if !self.storage.reconcile().await {
+            let health = self.storage.health();
+            error!(
+                "存储仍不可用，{} 条缓存的写操作未能写入，退出后将丢失",
+                health.pending_writes
+            );
+        }
+        info!("优雅关闭完成");
+    }
+
+    /// 启动服务器（兼容旧接口）
+    pub async fn start(&self, addr: &str) -> Result<()> {
+        // 默认情况下，HTTP和WebSocket使用相同地址
+        self.start_single_port(addr).await
+    }
+
+    /// 获取房间列表
+    pub fn get_room_list(&self) -> Vec<(String, usize)> {
+        self.rooms
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().player_count()))
+            .collect()
+    }
+
+    /// 生成登录URL
+    pub async fn generate_login_url(
+        &self,
+        provider_id: &str,
+        callback_url: Option<String>,
+        client_ip: std::net::IpAddr,
+    ) -> Result<String> {
+        let user_manager = self.user_manager.read().await;
+        user_manager.begin_login(provider_id, callback_url, client_ip)
+    }
+
+    /// 处理登录回调
+    pub async fn handle_login_callback(
+        &self,
+        provider_id: &str,
+        params: HashMap<String, String>,
+    ) -> Result<(Uuid, crate::user::User)> {
+        let user_manager = self.user_manager.read().await;
+        let (session_id, user, _) = user_manager.complete_login(provider_id, &params).await?;
+        Ok((session_id, user))
+    }
+}
+
+/// 读取配置中的 TLS 证书和私钥，未配置时返回 None
+async fn load_tls_config() -> Result<Option<RustlsConfig>> {
+    let server = &crate::config::Config::get().server;
+    match (&server.tls_cert_path, &server.tls_key_path) {
+        (None, None) => Ok(None),
+        (Some(cert), Some(key)) => {
+            let tls = RustlsConfig::from_pem_file(cert, key).await.map_err(|e| {
+                error!("加载TLS证书失败: {} / {} - {}", cert, key, e);
+                crate::Error::Network(anyhow::anyhow!(e))
+            })?;
+            info!("已启用内置TLS，证书: {}", cert);
+            Ok(Some(tls))
+        }
+        _ => Err(crate::Error::Network(anyhow::anyhow!(
+            "tls_cert_path 和 tls_key_path 必须同时配置"
+        ))),
+    }
+}
+
+/// 在已绑定的监听器上运行服务直到优雅关闭，配置了 TLS 时以 https/wss 提供
+async fn run_listener(
+    listener: tokio::net::TcpListener,
+    app: Router,
+    tls: Option<RustlsConfig>,
+    shutdown: impl std::future::Future<Output = ()> + Send + 'static,
+) -> std::io::Result<()> {
+    let Some(tls) = tls else {
+        return axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(shutdown)
+            .await;
+    };
+
+    let handle = axum_server::Handle::new();
+    let stopper = handle.clone();
+    tokio::spawn(async move {
+        shutdown.await;
+        stopper.graceful_shutdown(None);
+    });
+    axum_server::from_tcp_rustls(listener.into_std()?, tls)
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+        .await
+}
+
+/// 同一客户端IP的连接数超过上限时拒绝升级
+fn too_many_connections(client_ip: ClientIp) -> axum::response::Response {
+    warn!("客户端 {} 的连接数已达上限，拒绝新连接", client_ip);
+    (StatusCode::TOO_MANY_REQUESTS, "同一IP的连接数过多，请稍后再试").into_response()
+}
+
+/// 按客户端IP限制管理接口的请求频率，防止逐个尝试管理员令牌
+async fn limit_admin_requests(
+    client_ip: ClientIp,
+    request: axum::extract::Request,
+    next: Next,
+    admin_auth: Arc<AdminAuth>,
+) -> axum::response::Response {
+    if request.uri().path().starts_with("/api/admin/")
+        && let Err(e) = admin_auth.check_rate_limit(&format!("ip:{}", client_ip))
+    {
+        warn!("客户端 {} 的管理接口请求过于频繁", client_ip);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e.to_string()
+            })),
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// 浏览器无法为 WebSocket 设置请求头，改为在子协议中携带令牌：["bearer", "<JWT>"]
+const BEARER_PROTOCOL: &str = "bearer";
+
+/// 外部机器人在子协议中携带API密钥：["bot", "<API密钥>"]
+const BOT_PROTOCOL: &str = "bot";
+
+/// 从 Authorization: Bot <API密钥> 请求头或 Sec-WebSocket-Protocol 中取出外部机器人的API密钥
+fn bot_api_key(headers: &HeaderMap) -> Option<String> {
+    let from_header = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bot "))
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    let key = from_header.or_else(|| {
+        let protocols = headers.get(SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+        let mut protocols = protocols.split(',').map(str::trim);
+        protocols.find(|protocol| *protocol == BOT_PROTOCOL)?;
+        protocols.next().filter(|key| !key.is_empty())
+    });
+    key.map(str::to_string)
+}
+
+/// 从 Authorization 请求头或 Sec-WebSocket-Protocol 中取出 JWT
+fn bearer_jwt(headers: &HeaderMap) -> Option<&str> {
+    bearer_token(headers).filter(|token| crate::jwt::looks_like_jwt(token)).or_else(|| {
+        headers
+            .get(SEC_WEBSOCKET_PROTOCOL)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').map(str::trim).find(|token| crate::jwt::looks_like_jwt(token)))
+    })
+}
+
+/// 请求携带的有效 JWT 中的会话ID，由 resolve_bearer_session 写入请求扩展
+#[derive(Debug, Clone, Copy)]
+struct BearerSession(Uuid);
+
+/// 校验请求携带的 JWT，把其中的会话ID写入请求扩展，请求地址保持不变
+///
+/// 令牌无效或已过期时返回 401。
+async fn resolve_bearer_session(mut request: axum::extract::Request, next: Next) -> axum::response::Response {
+    if !crate::jwt::enabled() {
+        return next.run(request).await;
+    }
+    let Some(token) = bearer_jwt(request.headers()) else {
+        return next.run(request).await;
+    };
+
+    match crate::jwt::session_id(token) {
+        Ok(session_id) => {
+            request.extensions_mut().insert(BearerSession(session_id));
+            next.run(request).await
+        }
+        Err(e) => (
+            StatusCode::UNAUTHORIZED,
+            Json(serde_json::json!({
+                "success": false,
+                "message": e.to_string()
+            })),
+        )
+            .into_response(),
+    }
+}
+
+/// 带会话ID的查询参数：请求携带有效 JWT 时，用令牌中的会话代替查询参数中的 session_id
+struct SessionQuery<T>(T);
+
+#[axum::async_trait]
+impl<T, S> FromRequestParts<S> for SessionQuery<T>
+where
+    T: serde::de::DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut axum::http::request::Parts, state: &S) -> std::result::Result<Self, Self::Rejection> {
+        let Some(BearerSession(session_id)) = parts.extensions.get::<BearerSession>().copied() else {
+            return Query::<T>::from_request_parts(parts, state)
                 .await
-                .map_err(|e| {
-                    error!("HTTP服务器错误: {}", e);
-                    crate::Error::Network(anyhow::anyhow!(e))
-                })
-        });
+                .map(|Query(query)| SessionQuery(query))
+                .map_err(|e| (e.status(), e.body_text()));
+        };
+
+        let query = parts.uri.query().unwrap_or_default();
+        let mut params = url::form_urlencoded::Serializer::new(String::new());
+        params.extend_pairs(url::form_urlencoded::parse(query.as_bytes()).filter(|(key, _)| key != "session_id"));
+        params.append_pair("session_id", &session_id.to_string());
+        serde_urlencoded::from_str(&params.finish())
+            .map(SessionQuery)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("查询参数无效: {}", e)))
+    }
+}
+
+/// 处理认证回调
+async fn handle_auth_callback(
+    provider_id: &str,
+    params: HashMap<String, String>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Html<String> {
+    let user_manager_guard = user_manager.read().await;
+    match user_manager_guard.complete_login(provider_id, &params).await {
+        Ok((session_id, user, callback_url)) => {
+            // 获取重定向地址，优先使用发起登录时指定的callback_url，默认重定向到首页
+            let redirect_url = callback_url.unwrap_or_else(|| "/index.html".to_string());
+
+            // 构建重定向页面，包含session_id；启用JWT时同时保存令牌
+            let display_name: String = user.nickname.as_ref().unwrap_or(&user.username).clone();
+            let token = crate::jwt::issue(&user.id, &session_id)
+                .map(|(token, _)| token)
+                .unwrap_or_default();
+            let html = format!(
+                r#"
+<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>登录成功</title>
+</head>
+<body>
+    <h1>登录成功！</h1>
+    <p>欢迎，{}！</p>
+    <p>正在跳转...</p>
+    <script>
+        // 保存session_id到localStorage
+        localStorage.setItem('fishpi_session_id', '{}');
+        const token = '{}';
+        if (token) {{
+            localStorage.setItem('fishpi_token', token);
+        }}
+        // 跳转到指定页面；会话ID放在地址的 # 之后，不会发送到服务器，也不会出现在访问日志和 Referer 中
+        const redirectUrl = '{}'.split('#')[0];
+        window.location.href = redirectUrl + '#session_id={}';
+    </script>
+</body>
+</html>
+"#,
+                display_name, session_id, token, redirect_url, session_id
+            );
+            Html(html)
+        }
+        Err(e) => {
+            // 登录失败时，也使用callback_url参数决定重定向地址
+            let redirect_url = params
+                .get("callback_url")
+                .cloned()
+                .unwrap_or_else(|| "/index.html".to_string());
+
+            let html = format!(
+                r#"
+<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>登录失败</title>
+</head>
+<body>
+    <h1>登录失败</h1>
+    <p>错误信息: {}</p>
+    <p><a href="{}">返回</a></p>
+</body>
+</html>
+"#,
+                e, redirect_url
+            );
+            Html(html)
+        }
+    }
+}
+
+/// 处理会话验证
+async fn handle_validate_session(
+    query: ValidateQuery,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<ValidateResponse> {
+    let session_id = match Uuid::parse_str(&query.session_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return Json(ValidateResponse {
+                success: false,
+                user: None,
+                message: Some("无效的会话ID格式".to_string()),
+            });
+        }
+    };
+
+    let user_manager_guard = user_manager.read().await;
+    match user_manager_guard.get_user_by_session(&session_id).await {
+        Ok(user) => Json(ValidateResponse {
+            success: true,
+            user: Some(user.clone()),
+            message: None,
+        }),
+        Err(e) => Json(ValidateResponse {
+            success: false,
+            user: None,
+            message: Some(format!("会话验证失败: {}", e)),
+        }),
+    }
+}
+
+/// 用有效的会话或令牌换取新的 JWT，令牌到期前也可用来续期
+async fn handle_issue_token(
+    session_id: Option<String>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    if !crate::jwt::enabled() {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "服务器未启用令牌登录"
+        }));
+    }
+    let Some(session_id) = session_id else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "缺少会话ID"
+        }));
+    };
+
+    let user = match user_from_session(&session_id, &user_manager).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+    let Ok(session_id) = Uuid::parse_str(&session_id) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "无效的会话ID格式"
+        }));
+    };
+
+    match crate::jwt::issue(&user.id, &session_id) {
+        Some((token, expires_at)) => Json(serde_json::json!({
+            "success": true,
+            "token": token,
+            "expires_at": expires_at
+        })),
+        None => Json(serde_json::json!({
+            "success": false,
+            "message": "服务器未启用令牌登录"
+        })),
+    }
+}
+
+/// 处理游客进入，未开启游客模式时拒绝
+async fn handle_guest_login(
+    query: GuestQuery,
+    client_ip: ClientIp,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let auth = &crate::config::Config::get().auth;
+    if !auth.guest_enabled {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "未开启游客模式，请登录摸鱼派账号"
+        }));
+    }
+
+    let nickname = query.nickname.trim();
+    if nickname.is_empty() || nickname.chars().any(char::is_control) {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "请输入有效的昵称"
+        }));
+    }
+    if nickname.chars().count() > auth.guest_nickname_max_length {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": format!("昵称不能超过 {} 个字符", auth.guest_nickname_max_length)
+        }));
+    }
+
+    match user_manager.read().await.create_guest(nickname, client_ip.0).await {
+        Ok((session_id, user)) => {
+            let token = crate::jwt::issue(&user.id, &session_id);
+            Json(serde_json::json!({
+                "success": true,
+                "session_id": session_id,
+                "token": token.as_ref().map(|(token, _)| token),
+                "token_expires_at": token.as_ref().map(|(_, expires_at)| expires_at),
+                "user": user
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("创建游客身份失败: {}", e)
+        })),
+    }
+}
 
-        Ok(())
+/// 处理退出登录，删除当前会话；会话已失效时同样视为成功
+async fn handle_logout(
+    query: ValidateQuery,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let Ok(session_id) = Uuid::parse_str(&query.session_id) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "无效的会话ID格式"
+        }));
+    };
+
+    match user_manager.read().await.remove_session(&session_id).await {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "message": "已退出登录"
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("退出登录失败: {}", e)
+        })),
+    }
+}
+
+/// 处理在所有设备上退出登录，删除会话所属用户的全部会话
+async fn handle_logout_all(
+    query: ValidateQuery,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let Ok(session_id) = Uuid::parse_str(&query.session_id) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "无效的会话ID格式"
+        }));
+    };
+
+    let user_manager_guard = user_manager.read().await;
+    let user = match user_manager_guard.get_user_by_session(&session_id).await {
+        Ok(user) => user,
+        Err(e) => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": format!("会话验证失败: {}", e)
+            }));
+        }
+    };
+
+    match user_manager_guard.remove_all_sessions(&user.id).await {
+        Ok(revoked) => Json(serde_json::json!({
+            "success": true,
+            "message": format!("已在所有设备上退出登录，共 {} 个会话", revoked),
+            "revoked": revoked
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("退出登录失败: {}", e)
+        })),
+    }
+}
+
+/// 处理在其他设备上退出登录，保留当前会话
+async fn handle_logout_others(
+    query: ValidateQuery,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let Ok(session_id) = Uuid::parse_str(&query.session_id) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "无效的会话ID格式"
+        }));
+    };
+
+    let user_manager_guard = user_manager.read().await;
+    let user = match user_manager_guard.get_user_by_session(&session_id).await {
+        Ok(user) => user,
+        Err(e) => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": format!("会话验证失败: {}", e)
+            }));
+        }
+    };
+
+    match user_manager_guard.remove_sessions_except(&user.id, Some(&session_id)).await {
+        Ok(revoked) => Json(serde_json::json!({
+            "success": true,
+            "message": format!("已在其他设备上退出登录，共 {} 个会话", revoked),
+            "revoked": revoked
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("退出登录失败: {}", e)
+        })),
+    }
+}
+
+/// 会话的对外摘要，只展示会话ID前8位，完整ID等同于登录凭证
+fn session_summary(session: &crate::user::UserSession, current: Option<&Uuid>) -> serde_json::Value {
+    serde_json::json!({
+        "id": session.session_id.simple().to_string()[..8],
+        "created_at": session.created_at,
+        "last_used_at": session.last_used_at,
+        "expires_at": session.expires_at,
+        "current": Some(&session.session_id) == current
+    })
+}
+
+/// 处理查看当前用户的所有有效会话
+async fn handle_list_sessions(
+    query: ValidateQuery,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let Ok(session_id) = Uuid::parse_str(&query.session_id) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "无效的会话ID格式"
+        }));
+    };
+
+    let user_manager_guard = user_manager.read().await;
+    let user = match user_manager_guard.get_user_by_session(&session_id).await {
+        Ok(user) => user,
+        Err(e) => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": format!("会话验证失败: {}", e)
+            }));
+        }
+    };
+
+    match user_manager_guard.list_sessions(&user.id).await {
+        Ok(sessions) => Json(serde_json::json!({
+            "success": true,
+            "sessions": sessions
+                .iter()
+                .map(|session| session_summary(session, Some(&session_id)))
+                .collect::<Vec<_>>()
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("读取会话列表失败: {}", e)
+        })),
+    }
+}
+
+/// 列出可用的登录方式
+async fn handle_list_providers(user_manager: Arc<tokio::sync::RwLock<UserManager>>) -> Json<serde_json::Value> {
+    let user_manager_guard = user_manager.read().await;
+    let providers: Vec<serde_json::Value> = user_manager_guard
+        .providers()
+        .iter()
+        .map(|provider| {
+            serde_json::json!({
+                "id": provider.id(),
+                "name": provider.display_name()
+            })
+        })
+        .collect();
+    Json(serde_json::json!({
+        "success": true,
+        "providers": providers,
+        "guest_enabled": crate::config::Config::get().auth.guest_enabled
+    }))
+}
+
+/// 处理生成登录URL
+async fn handle_generate_login_url(
+    query: LoginQuery,
+    client_ip: ClientIp,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let user_manager_guard = user_manager.read().await;
+    let provider_id = query.provider.as_deref().unwrap_or(FISHPI_PROVIDER_ID);
+
+    // callback_url 随登录状态保存在服务器，登录完成后跳转
+    match user_manager_guard.begin_login(provider_id, query.callback_url, client_ip.0) {
+        Ok(login_url) => Json(serde_json::json!({
+            "success": true,
+            "login_url": login_url
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "error": e.to_string()
+        })),
+    }
+}
+
+/// 就绪检查，Redis 不可用时服务仍可用，但会标记为降级
+async fn handle_readyz(storage: Arc<dyn StorageBackend>, cluster: Arc<ClusterState>) -> Json<serde_json::Value> {
+    let health = storage.health();
+    Json(serde_json::json!({
+        // 备用实例不接受玩家连接，负载均衡应只把流量转发给主实例
+        "ready": cluster.is_active(),
+        "role": if cluster.is_active() { "active" } else { "standby" },
+        "instance_id": cluster.instance_id,
+        "degraded": health.degraded,
+        "degraded_since": health.degraded_since,
+        "pending_writes": health.pending_writes,
+        "dropped_writes": health.dropped_writes
+    }))
+}
+
+/// 处理房间状态查询
+async fn handle_rooms_status(
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    game_slots: Arc<GameSlots>,
+) -> Json<serde_json::Value> {
+    let mut room_statuses = Vec::new();
+    
+    for entry in rooms.iter() {
+        let room_id = entry.key();
+        let room = entry.value();
+
+        // 教程房间和私密房间不对外展示
+        if !room.is_listed() {
+            continue;
+        }
+        
+        // 检查房间是否应该被删除
+        let should_be_deleted = room.should_be_deleted().await;
+        
+        // 只返回不应该被删除的房间
+        if !should_be_deleted {
+            room_statuses.push(room_summary(room_id, room).await);
+        }
+    }
+    
+    Json(serde_json::json!({
+        "success": true,
+        "rooms": room_statuses,
+        "total_rooms": room_statuses.len(),
+        "running_games": game_slots.running(),
+        "max_concurrent_games": game_slots.limit()
+    }))
+}
+
+/// 房间列表中单个房间的概要信息
+async fn room_summary(room_id: &str, room: &Room) -> serde_json::Value {
+    let (player_count, idle_seconds, is_game_over, is_empty) = room.get_status().await;
+
+    // 房间暂不支持密码和观战，对应字段固定返回，便于大厅界面统一渲染
+    serde_json::json!({
+        "room_id": room_id,
+        "player_count": player_count,
+        "max_players": room.max_players(),
+        "host_name": room.host_name().await,
+        "phase": room.phase().await,
+        "has_password": false,
+        "spectator_count": 0,
+        "idle_seconds": idle_seconds,
+        "is_game_over": is_game_over,
+        "is_empty": is_empty,
+        "should_be_deleted": false
+    })
+}
+
+/// 公开房间目录的标题
+const ROOM_FEED_TITLE: &str = "谁是卧底 - 公开房间";
+
+/// 房间的加入链接，打开游戏页面并预填房间ID
+fn room_join_url(room_id: &str) -> String {
+    crate::config::Config::get().feed.join_url(room_id)
+}
+
+/// 转义XML文本中的特殊字符
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// 订阅源的缓存头，订阅源内容来自房间列表订阅的快照，允许客户端和代理短时间缓存
+fn feed_cache_control() -> String {
+    format!("public, max-age={}", crate::config::Config::get().feed.cache_ttl)
+}
+
+/// 处理公开房间目录的JSON订阅源，不需要登录
+async fn handle_rooms_feed_json(room_feed: Arc<RoomFeed>) -> impl IntoResponse {
+    let config = &crate::config::Config::get().feed;
+    let rooms: Vec<serde_json::Value> = room_feed
+        .open_rooms(config.max_items)
+        .await
+        .into_iter()
+        .map(|room| {
+            let room_id = room["room_id"].as_str().unwrap_or_default();
+            serde_json::json!({
+                "room_id": room_id,
+                "host_name": room["host_name"],
+                "player_count": room["player_count"],
+                "max_players": room["max_players"],
+                "join_url": room_join_url(room_id)
+            })
+        })
+        .collect();
+
+    (
+        [(CACHE_CONTROL, feed_cache_control())],
+        Json(serde_json::json!({
+            "title": ROOM_FEED_TITLE,
+            "home_page_url": format!("{}/", config.public_url.trim_end_matches('/')),
+            "generated_at": chrono::Utc::now(),
+            "total_rooms": rooms.len(),
+            "rooms": rooms
+        })),
+    )
+}
+
+/// 处理公开房间目录的RSS订阅源，不需要登录
+async fn handle_rooms_feed_rss(room_feed: Arc<RoomFeed>) -> impl IntoResponse {
+    let config = &crate::config::Config::get().feed;
+    let items: String = room_feed
+        .open_rooms(config.max_items)
+        .await
+        .iter()
+        .map(|room| {
+            let room_id = room["room_id"].as_str().unwrap_or_default();
+            let host_name = room["host_name"].as_str().unwrap_or("未知");
+            let join_url = escape_xml(&room_join_url(room_id));
+            format!(
+                "<item><title>{}</title><link>{}</link><guid isPermaLink=\"false\">{}</guid><description>{}</description></item>",
+                escape_xml(&format!(
+                    "房间 {}（{}/{}人）",
+                    room_id,
+                    room["player_count"].as_u64().unwrap_or(0),
+                    room["max_players"].as_u64().unwrap_or(0)
+                )),
+                join_url,
+                escape_xml(room_id),
+                escape_xml(&format!("房主：{}，等待玩家加入", host_name)),
+            )
+        })
+        .collect();
+
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<rss version=\"2.0\"><channel><title>{}</title><link>{}</link><description>正在等待玩家加入的谁是卧底房间</description><lastBuildDate>{}</lastBuildDate><ttl>{}</ttl>{}</channel></rss>",
+        ROOM_FEED_TITLE,
+        escape_xml(&format!("{}/", config.public_url.trim_end_matches('/'))),
+        chrono::Utc::now().to_rfc2822(),
+        config.cache_ttl.div_ceil(60).max(1),
+        items
+    );
+
+    (
+        [
+            (CONTENT_TYPE, "application/rss+xml; charset=utf-8".to_string()),
+            (CACHE_CONTROL, feed_cache_control()),
+        ],
+        body,
+    )
+}
+
+/// 处理房间搜索请求：在服务端按阶段、空位和抽词分类筛选房间
+async fn handle_rooms_search(
+    query: RoomSearchQuery,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+) -> Json<serde_json::Value> {
+    use crate::message::GameStateType;
+
+    let state_filter = query.state.as_deref().map(str::to_lowercase);
+    if let Some(state) = &state_filter
+        && !matches!(state.as_str(), "lobby" | "playing" | "game_over")
+    {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": format!("无效的房间阶段: {}，可选 lobby、playing、game_over", state)
+        }));
+    }
+
+    // 先收集房间引用，避免在遍历DashMap时跨越await持有分片锁
+    let candidates: Vec<(String, Arc<Room>)> = rooms
+        .iter()
+        .filter(|entry| entry.value().is_listed())
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect();
+
+    let mut matched = Vec::new();
+    for (room_id, room) in candidates {
+        if room.should_be_deleted().await {
+            continue;
+        }
+        if let Some(state) = &state_filter {
+            let phase = room.phase().await;
+            let phase_matches = match state.as_str() {
+                "lobby" => phase == GameStateType::Lobby,
+                "game_over" => phase == GameStateType::GameOver,
+                _ => !matches!(phase, GameStateType::Lobby | GameStateType::GameOver),
+            };
+            if !phase_matches {
+                continue;
+            }
+        }
+        if query.has_space == Some(true) && room.player_count() >= room.max_players() {
+            continue;
+        }
+        if let Some(category) = &query.category
+            && room.settings().await.word_category.as_deref() != Some(category.as_str())
+        {
+            continue;
+        }
+        matched.push(room_summary(&room_id, &room).await);
+    }
+
+    Json(serde_json::json!({
+        "success": true,
+        "rooms": matched,
+        "total_rooms": matched.len()
+    }))
+}
+
+/// 处理查询玩家生涯统计请求
+async fn handle_get_player_stats(user_id: String, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    match storage.get_profile_settings(&user_id).await {
+        Ok(settings) if settings.private => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "该玩家未公开个人资料"
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("查询用户 {} 的个人资料设置失败: {}", user_id, e),
+    }
+
+    match storage.get_player_stats(&user_id).await {
+        Ok(stats) => Json(serde_json::json!({
+            "success": true,
+            "user_id": user_id,
+            "stats": stats
+        })),
+        Err(e) => {
+            error!("查询玩家 {} 统计失败: {}", user_id, e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "查询玩家统计失败"
+            }))
+        }
+    }
+}
+
+/// 代理并缓存用户的外部头像，下载失败时重定向到原始地址；不允许代理的地址返回 404
+async fn handle_get_avatar(user_id: String, storage: Arc<dyn StorageBackend>) -> axum::response::Response {
+    let config = &crate::config::Config::get().avatar;
+    let cache_control = format!("public, max-age={}", config.cache_ttl);
+
+    match storage.get_avatar(&user_id).await {
+        Ok(Some((content_type, data))) => {
+            return ([(CONTENT_TYPE, content_type), (CACHE_CONTROL, cache_control)], data).into_response();
+        }
+        Ok(None) => {}
+        Err(e) => warn!("读取用户 {} 的头像缓存失败: {}", user_id, e),
+    }
+
+    // 只代理已登记用户的头像，不会为任意ID请求外部接口
+    let origin = match storage.get_user(&user_id).await {
+        Ok(Some(user)) => user.avatar_origin().map(str::to_string),
+        Ok(None) => None,
+        Err(e) => {
+            error!("查询用户 {} 失败: {}", user_id, e);
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+    };
+    let Some(origin) = origin.filter(|origin| crate::avatar::is_allowed(origin)) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    match crate::avatar::fetch(&origin).await {
+        Ok((content_type, data)) => {
+            if let Err(e) = storage.save_avatar(&user_id, &content_type, &data, config.cache_ttl).await {
+                warn!("缓存用户 {} 的头像失败: {}", user_id, e);
+            }
+            ([(CONTENT_TYPE, content_type), (CACHE_CONTROL, cache_control)], data).into_response()
+        }
+        Err(e) => {
+            warn!("下载用户 {} 的头像失败: {}", user_id, e);
+            axum::response::Redirect::temporary(&origin).into_response()
+        }
+    }
+}
+
+/// 处理个人资料查询：昵称、头像，以及未设为隐藏时的生涯统计、成就和最近对局
+async fn handle_get_profile(user_id: String, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    let user = match storage.get_user(&user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "用户不存在"
+            }));
+        }
+        Err(e) => {
+            error!("查询用户 {} 失败: {}", user_id, e);
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "查询个人资料失败"
+            }));
+        }
+    };
+
+    let settings = match storage.get_profile_settings(&user_id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            error!("查询用户 {} 的个人资料设置失败: {}", user_id, e);
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "查询个人资料失败"
+            }));
+        }
+    };
+    let mut profile = serde_json::json!({
+        "success": true,
+        "user_id": user.id,
+        "nickname": user.nickname.as_deref().unwrap_or(&user.username),
+        "avatar": user.avatar,
+        "private": settings.private
+    });
+    if settings.private {
+        return Json(profile);
     }
 
-    /// 启动WebSocket服务器
-    pub async fn start_ws_server(&self, ws_addr: &str) -> Result<()> {
-        let rooms = self.rooms.clone();
-        // let word_bank = self.word_bank.clone();
-        // let storage = self.storage.clone();
-        let user_manager = self.user_manager.clone();
-        let connection_manager = self.connection_manager.clone();
+    let (stats, recent_games) = match (
+        storage.get_player_stats(&user_id).await,
+        storage.get_player_games(&user_id, 0, PROFILE_RECENT_GAMES).await,
+    ) {
+        (Ok(stats), Ok(games)) => (stats, games),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("查询用户 {} 的个人资料失败: {}", user_id, e);
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "查询个人资料失败"
+            }));
+        }
+    };
+    profile["achievements"] = serde_json::json!(stats.achievements());
+    profile["stats"] = serde_json::json!(stats);
+    profile["recent_games"] = serde_json::json!(recent_games);
+    Json(profile)
+}
 
-        let ws_app = Router::new()
-            .route(
-                "/ws",
-                get({
-                    let user_manager = user_manager.clone();
-                    let connection_manager = connection_manager.clone();
-                    move |ws: WebSocketUpgrade, Query(query): Query<RoomQuery>| async move {
-                        // 必须提供room_id，不再自动生成
-                        let room_id = match query.room_id {
-                            Some(id) => id,
-                            None => {
-                                // 如果没有提供room_id，返回错误
-                                return ws.on_upgrade(|_| async {
-                                    // 这里应该发送错误消息，但WebSocket升级后无法直接返回HTTP错误
-                                    // 所以我们在连接处理中处理这个错误
-                                });
-                            }
-                        };
-                        
-                        let session_id = query.session_id.and_then(|id| {
-                            match Uuid::parse_str(&id) {
-                                Ok(uuid) => {
-                                    debug!("解析会话ID成功: {}", uuid);
-                                    Some(uuid)
-                                }
-                                Err(e) => {
-                                    error!("解析会话ID失败: {}, 原始值: {}", e, id);
-                                    None
-                                }
-                            }
-                        });
-                        
-                        debug!(
-                            "WebSocket连接请求详情 - 房间ID: {}, 会话ID: {:?}",
-                            room_id, session_id
-                        );
+/// 处理玩家对局历史查询，按时间倒序分页返回
+async fn handle_get_player_games(
+    user_id: String,
+    query: PlayerGamesQuery,
+    storage: Arc<dyn StorageBackend>,
+) -> Json<serde_json::Value> {
+    match storage.get_profile_settings(&user_id).await {
+        Ok(settings) if settings.private => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "该玩家未公开个人资料"
+            }));
+        }
+        Ok(_) => {}
+        Err(e) => warn!("查询用户 {} 的个人资料设置失败: {}", user_id, e),
+    }
 
-                        // 添加CORS和WebSocket升级头
-                        ws.on_upgrade(move |socket| async move {
-                            debug!("WebSocket连接已升级，开始处理连接");
-                            handle_room_connection(
-                                socket,
-                                room_id,
-                                session_id,
-                                rooms.clone(),
-                                user_manager.clone(),
-                                connection_manager.clone(),
-                            )
-                            .await;
-                            debug!("WebSocket连接处理完成");
-                        })
-                    }
-                }),
-            )
-            // 添加OPTIONS路由处理预检请求
-            .route(
-                "/ws",
-                axum::routing::options(|| async {
-                    axum::response::Response::builder()
-                        .header("Access-Control-Allow-Origin", "*")
-                        .header("Access-Control-Allow-Methods", "GET, POST, OPTIONS")
-                        .header("Access-Control-Allow-Headers", "Content-Type, Authorization, Upgrade, Connection, Sec-WebSocket-Key, Sec-WebSocket-Version, Sec-WebSocket-Protocol")
-                        .header("Access-Control-Allow-Credentials", "true")
-                        .status(200)
-                        .body(axum::body::Body::empty())
-                        .unwrap()
-                }),
-            );
+    let limit = query
+        .limit
+        .unwrap_or(PLAYER_GAMES_DEFAULT_PAGE_SIZE)
+        .clamp(1, PLAYER_GAMES_MAX_PAGE_SIZE);
+    let offset = query.offset.unwrap_or(0);
+    match storage.get_player_games(&user_id, offset, limit).await {
+        Ok(games) => Json(serde_json::json!({
+            "success": true,
+            "user_id": user_id,
+            "offset": offset,
+            "limit": limit,
+            "has_more": games.len() == limit,
+            "games": games
+        })),
+        Err(e) => {
+            error!("查询玩家 {} 对局历史失败: {}", user_id, e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "查询对局历史失败"
+            }))
+        }
+    }
+}
 
-        let ws_listener = tokio::net::TcpListener::bind(ws_addr)
-            .await
-            .map_err(|e| {
-                error!("绑定WebSocket地址失败: {} - {}", ws_addr, e);
-                crate::Error::Network(anyhow::anyhow!(e))
-            })?;
-        
-        axum::serve(ws_listener, ws_app)
-            .await
-            .map_err(|e| {
-                error!("WebSocket服务器运行错误: {}", e);
-                crate::Error::Network(anyhow::anyhow!(e))
-            })?;
-        Ok(())
+/// 处理玩家修改自己的个人资料设置
+async fn handle_set_profile_settings(
+    query: ProfileSettingsQuery,
+    storage: Arc<dyn StorageBackend>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let user = match user_from_session(&query.session_id, &user_manager).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let settings = crate::storage::ProfileSettings { private: query.private };
+    match storage.save_profile_settings(&user.id, &settings).await {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "settings": settings
+        })),
+        Err(e) => {
+            error!("保存用户 {} 的个人资料设置失败: {}", user.id, e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "保存个人资料设置失败"
+            }))
+        }
     }
+}
 
-    /// 启动服务器（兼容旧接口）
-    pub async fn start(&self, addr: &str) -> Result<()> {
-        // 默认情况下，HTTP和WebSocket使用相同地址
-        self.start_http_server(addr).await?;
-        self.start_ws_server(addr).await?;
-        Ok(())
+/// 根据会话ID查找当前用户，失败时返回错误提示
+async fn user_from_session(
+    session_id: &str,
+    user_manager: &tokio::sync::RwLock<UserManager>,
+) -> std::result::Result<User, Json<serde_json::Value>> {
+    let Ok(session_id) = Uuid::parse_str(session_id) else {
+        return Err(Json(serde_json::json!({
+            "success": false,
+            "message": "无效的会话ID格式"
+        })));
+    };
+    user_manager
+        .read()
+        .await
+        .get_user_by_session(&session_id)
+        .await
+        .map_err(|e| {
+            Json(serde_json::json!({
+                "success": false,
+                "message": format!("会话验证失败: {}", e)
+            }))
+        })
+}
+
+/// 处理查询当前用户当天游戏时长和每日上限的请求
+async fn handle_get_playtime(
+    query: AdminQuery,
+    storage: Arc<dyn StorageBackend>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let user = match user_from_session(&query.session_id, &user_manager).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let (usage, limits) = match (
+        storage.get_daily_playtime(&user.id).await,
+        storage.get_play_limits(&user.id).await,
+    ) {
+        (Ok(usage), Ok(limits)) => (usage, limits),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("查询玩家 {} 游戏时长失败: {}", user.id, e);
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "查询游戏时长失败"
+            }));
+        }
+    };
+    let effective = limits.effective();
+
+    Json(serde_json::json!({
+        "success": true,
+        "user_id": user.id,
+        "usage": usage,
+        "self_limit": limits.self_limit,
+        "admin_limit": limits.admin_limit,
+        "effective_limit": effective,
+        "reached": effective.is_reached(&usage)
+    }))
+}
+
+/// 更新玩家的自设或管理员上限并保存
+async fn update_play_limit(
+    user_id: &str,
+    storage: &dyn StorageBackend,
+    update: impl FnOnce(&mut crate::storage::PlayLimits),
+) -> Json<serde_json::Value> {
+    let mut limits = match storage.get_play_limits(user_id).await {
+        Ok(limits) => limits,
+        Err(e) => {
+            error!("读取玩家 {} 每日游戏上限失败: {}", user_id, e);
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "读取每日游戏上限失败"
+            }));
+        }
+    };
+    update(&mut limits);
+
+    if let Err(e) = storage.set_play_limits(user_id, &limits).await {
+        error!("保存玩家 {} 每日游戏上限失败: {}", user_id, e);
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "保存每日游戏上限失败"
+        }));
     }
 
-    /// 获取房间列表
-    pub fn get_room_list(&self) -> Vec<(String, usize)> {
-        self.rooms
-            .iter()
-            .map(|entry| (entry.key().clone(), entry.value().player_count()))
-            .collect()
+    Json(serde_json::json!({
+        "success": true,
+        "user_id": user_id,
+        "self_limit": limits.self_limit,
+        "admin_limit": limits.admin_limit,
+        "effective_limit": limits.effective()
+    }))
+}
+
+/// 处理玩家设置自己每日游戏上限的请求，两项都不提供时取消自设上限
+async fn handle_set_self_play_limit(
+    query: PlayLimitQuery,
+    storage: Arc<dyn StorageBackend>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let user = match user_from_session(query.session_id.as_deref().unwrap_or_default(), &user_manager).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let limit = PlayLimit {
+        max_games: query.max_games,
+        max_minutes: query.max_minutes,
+    };
+    update_play_limit(&user.id, storage.as_ref(), |limits| limits.self_limit = limit).await
+}
+
+/// 处理管理员设置玩家每日游戏上限的请求，两项都不提供时取消管理员上限
+async fn handle_admin_set_play_limit(
+    user_id: String,
+    query: PlayLimitQuery,
+    headers: HeaderMap,
+    storage: Arc<dyn StorageBackend>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::PlayersManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let limit = PlayLimit {
+        max_games: query.max_games,
+        max_minutes: query.max_minutes,
+    };
+    info!("管理员 {} 设置玩家 {} 的每日游戏上限: {:?}", admin.username, user_id, limit);
+    let response = update_play_limit(&user_id, storage.as_ref(), |limits| limits.admin_limit = limit).await;
+    if response.0["success"] == true {
+        admin_auth
+            .audit(&admin.username, AdminAction::SetPlayLimit, &user_id, serde_json::json!({ "limit": limit }))
+            .await;
     }
+    response
+}
 
-    /// 生成登录URL
-    pub async fn generate_login_url(&self, return_url: &str, realm: &str) -> Result<String> {
-        let user_manager = self.user_manager.read().await;
-        user_manager.generate_login_url(return_url, realm)
+/// 处理管理员查看用户有效会话请求，用于排查可疑账号
+async fn handle_admin_user_sessions(
+    user_id: String,
+    headers: HeaderMap,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    if let Err(response) = authorize_admin(&headers, &admin_auth, AdminScope::PlayersManage).await {
+        return response;
     }
 
-    /// 处理登录回调
-    pub async fn handle_login_callback(
-        &self,
-        params: HashMap<String, String>,
-    ) -> Result<(Uuid, crate::user::User)> {
-        let user_manager = self.user_manager.read().await;
-        user_manager.handle_login(&params).await
+    match user_manager.read().await.list_sessions(&user_id).await {
+        Ok(sessions) => Json(serde_json::json!({
+            "success": true,
+            "user_id": user_id,
+            "sessions": sessions
+                .iter()
+                .map(|session| session_summary(session, None))
+                .collect::<Vec<_>>()
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("读取会话列表失败: {}", e)
+        })),
     }
 }
 
-/// 处理认证回调
-async fn handle_auth_callback(
-    query: AuthCallbackQuery,
-    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
-) -> Html<String> {
-    // 使用迭代器来简化参数转换
-    let param_mappings = [
-        ("openid.ns", &query.openid_ns),
-        ("openid.mode", &query.openid_mode),
-        ("openid.op_endpoint", &query.openid_op_endpoint),
-        ("openid.claimed_id", &query.openid_claimed_id),
-        ("openid.identity", &query.openid_identity),
-        ("openid.return_to", &query.openid_return_to),
-        ("openid.response_nonce", &query.openid_response_nonce),
-        ("openid.assoc_handle", &query.openid_assoc_handle),
-        ("openid.signed", &query.openid_signed),
-        ("openid.sig", &query.openid_sig),
-    ];
-
-    let params: HashMap<String, String> = param_mappings
-        .iter()
-        .filter_map(|(key, value)| value.as_ref().map(|v| (key.to_string(), v.clone())))
-        .collect();
+/// 处理管理员全服禁言请求：禁言期间用户仍可描述和投票，但不能聊天
+async fn handle_admin_mute_user(
+    user_id: String,
+    query: GlobalMuteQuery,
+    headers: HeaderMap,
+    storage: Arc<dyn StorageBackend>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::PlayersManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
 
-    let user_manager_guard = user_manager.read().await;
-    match user_manager_guard.handle_login(&params).await {
-        Ok((session_id, user)) => {
-            // 获取重定向地址，优先使用callback_url参数
-            let redirect_url = if let Some(callback_url) = &query.callback_url {
-                callback_url.clone()
-            } else {
-                // 默认重定向到首页
-                "/index.html".to_string()
-            };
+    if query.minutes == 0 || query.minutes > GLOBAL_MUTE_MAX_MINUTES {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": format!("禁言时长需在 1 到 {} 分钟之间", GLOBAL_MUTE_MAX_MINUTES)
+        }));
+    }
 
-            // 构建重定向页面，包含session_id
-            let display_name: String = user.nickname.as_ref().unwrap_or(&user.username).clone();
-            let html = format!(
-                r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <title>登录成功</title>
-</head>
-<body>
-    <h1>登录成功！</h1>
-    <p>欢迎，{}！</p>
-    <p>正在跳转...</p>
-    <script>
-        // 保存session_id到localStorage
-        localStorage.setItem('fishpi_session_id', '{}');
-        // 跳转到指定页面，并传递session_id参数
-        const redirectUrl = '{}';
-        const separator = redirectUrl.includes('?') ? '&' : '?';
-        window.location.href = redirectUrl + separator + 'session_id={}';
-    </script>
-</body>
-</html>
-"#,
-                display_name, session_id, redirect_url, session_id
-            );
-            Html(html)
+    let now = chrono::Utc::now();
+    let mute = GlobalMute {
+        user_id: user_id.clone(),
+        admin: admin.username.clone(),
+        reason: query
+            .reason
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty())
+            .unwrap_or_else(|| "违反社区规范".to_string()),
+        muted_at: now,
+        expires_at: now + chrono::Duration::minutes(query.minutes as i64),
+    };
+
+    match storage.set_global_mute(&mute).await {
+        Ok(()) => {
+            info!("管理员 {} 全服禁言用户 {} {} 分钟: {}", admin.username, user_id, query.minutes, mute.reason);
+            admin_auth
+                .audit(
+                    &admin.username,
+                    AdminAction::MutePlayer,
+                    &user_id,
+                    serde_json::json!({ "minutes": query.minutes, "reason": mute.reason }),
+                )
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("用户 {} 已被禁言 {} 分钟", user_id, query.minutes),
+                "mute": mute
+            }))
         }
-        Err(e) => {
-            // 登录失败时，也使用callback_url参数决定重定向地址
-            let redirect_url = query.callback_url.unwrap_or_else(|| "/index.html".to_string());
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("保存禁言失败: {}", e)
+        })),
+    }
+}
 
-            let html = format!(
-                r#"
-<!DOCTYPE html>
-<html>
-<head>
-    <meta charset="UTF-8">
-    <title>登录失败</title>
-</head>
-<body>
-    <h1>登录失败</h1>
-    <p>错误信息: {}</p>
-    <p><a href="{}">返回</a></p>
-</body>
-</html>
-"#,
-                e, redirect_url
-            );
-            Html(html)
+/// 处理管理员解除全服禁言请求
+async fn handle_admin_unmute_user(
+    user_id: String,
+    headers: HeaderMap,
+    storage: Arc<dyn StorageBackend>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::PlayersManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    match storage.clear_global_mute(&user_id).await {
+        Ok(()) => {
+            info!("管理员 {} 解除了用户 {} 的全服禁言", admin.username, user_id);
+            admin_auth
+                .audit(&admin.username, AdminAction::UnmutePlayer, &user_id, serde_json::Value::Null)
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("用户 {} 的禁言已解除", user_id)
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("解除禁言失败: {}", e)
+        })),
+    }
+}
+
+/// 读取已结束对局的回放，失败时返回错误代码和提示
+async fn load_finished_replay(
+    game_id: &str,
+    storage: &dyn StorageBackend,
+) -> std::result::Result<Replay, (&'static str, String)> {
+    match storage.get_replay(game_id).await {
+        Ok(Some(replay)) if replay.is_finished() => Ok(replay),
+        Ok(Some(_)) => Err(("ReplayUnavailable", "对局尚未结束，暂不能查看回放".to_string())),
+        Ok(None) => Err(("ReplayNotFound", format!("对局 {} 的回放不存在或已过期", game_id))),
+        Err(e) => {
+            error!("读取对局 {} 回放失败: {}", game_id, e);
+            Err(("StorageError", "读取回放失败".to_string()))
         }
     }
 }
 
-/// 处理会话验证
-async fn handle_validate_session(
-    query: ValidateQuery,
-    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
-) -> Json<ValidateResponse> {
-    let session_id = match Uuid::parse_str(&query.session_id) {
-        Ok(id) => id,
-        Err(_) => {
-            return Json(ValidateResponse {
-                success: false,
-                user: None,
-                message: Some("无效的会话ID格式".to_string()),
-            });
+/// 处理查询对局回放请求
+async fn handle_get_replay(game_id: String, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    match load_finished_replay(&game_id, storage.as_ref()).await {
+        Ok(replay) => Json(serde_json::json!({
+            "success": true,
+            "game_id": replay.game_id,
+            "room_id": replay.room_id,
+            "events": replay.events
+        })),
+        Err((code, message)) => Json(serde_json::json!({
+            "success": false,
+            "code": code,
+            "message": message
+        })),
+    }
+}
+
+/// 回放模式的WebSocket连接，按原始节奏（或加速）依次推送对局事件
+async fn handle_replay_connection(socket: WebSocket, query: ReplayQuery, storage: Arc<dyn StorageBackend>) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    let replay = match load_finished_replay(&query.game_id, storage.as_ref()).await {
+        Ok(replay) => replay,
+        Err((code, message)) => {
+            let error_msg = ServerMessage::Error {
+                code: code.to_string(),
+                message,
+            };
+            if let Ok(text) = serde_json::to_string(&error_msg) {
+                let _ = ws_sender.send(Message::Text(text)).await;
+            }
+            return; // 关闭连接
         }
     };
 
-    let user_manager_guard = user_manager.read().await;
-    match user_manager_guard.get_user_by_session(&session_id).await {
-        Ok(user) => Json(ValidateResponse {
-            success: true,
-            user: Some(user.clone()),
-            message: None,
-        }),
-        Err(e) => Json(ValidateResponse {
-            success: false,
-            user: None,
-            message: Some(format!("会话验证失败: {}", e)),
-        }),
+    let speed = query
+        .speed
+        .filter(|speed| speed.is_finite() && *speed > 0.0)
+        .unwrap_or(1.0)
+        .min(REPLAY_MAX_SPEED);
+
+    let start_msg = ServerMessage::ReplayStart {
+        game_id: replay.game_id.clone(),
+        room_id: replay.room_id.clone(),
+        total_events: replay.events.len(),
+        speed,
+    };
+    if let Ok(text) = serde_json::to_string(&start_msg)
+        && ws_sender.send(Message::Text(text)).await.is_err()
+    {
+        return;
     }
-}
 
-/// 处理生成登录URL
-async fn handle_generate_login_url(
-    query: LoginQuery,
-    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
-) -> Json<serde_json::Value> {
-    let user_manager_guard = user_manager.read().await;
-    let config = crate::config::Config::get();
+    let mut previous: Option<chrono::DateTime<chrono::Utc>> = None;
+    for entry in &replay.events {
+        let gap = previous
+            .map(|at| (entry.timestamp - at).num_milliseconds().max(0) as f64 / 1000.0)
+            .unwrap_or(0.0);
+        previous = Some(entry.timestamp);
+        let delay = std::time::Duration::from_secs_f64((gap / speed).min(REPLAY_MAX_GAP_SECS));
 
-    // 构建return_to URL，如果提供了callback_url则编码到参数中
-    let base_return_to = format!("{}/auth/callback", config.auth.domain);
-    let return_to = if let Some(callback_url) = query.callback_url {
-        let separator = if base_return_to.contains('?') { "&" } else { "?" };
-        format!("{}{}callback_url={}", base_return_to, separator, urlencoding::encode(&callback_url))
-    } else {
-        base_return_to
+        // 等待期间客户端断开则停止播放
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            msg = ws_receiver.next() => {
+                if matches!(msg, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    debug!("回放观看者已断开: {}", replay.game_id);
+                    return;
+                }
+            }
+        }
+
+        let event_msg = ServerMessage::ReplayEvent(entry.clone());
+        if let Ok(text) = serde_json::to_string(&event_msg)
+            && ws_sender.send(Message::Text(text)).await.is_err()
+        {
+            return;
+        }
+    }
+
+    let end_msg = ServerMessage::ReplayEnd {
+        game_id: replay.game_id.clone(),
     };
-    
-    let realm = config.auth.domain.clone();
+    if let Ok(text) = serde_json::to_string(&end_msg) {
+        let _ = ws_sender.send(Message::Text(text)).await;
+    }
+}
 
-    match user_manager_guard.generate_login_url(&return_to, &realm) {
-        Ok(login_url) => Json(serde_json::json!({
+/// 处理查询玩家等级分请求
+async fn handle_get_rating(player_id: String, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    match storage.get_rating(&player_id).await {
+        Ok(rating) => Json(serde_json::json!({
             "success": true,
-            "login_url": login_url
+            "rating": rating
         })),
-        Err(e) => Json(serde_json::json!({
+        Err(e) => {
+            error!("查询玩家 {} 等级分失败: {}", player_id, e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "查询等级分失败"
+            }))
+        }
+    }
+}
+
+/// 处理排行榜查询请求
+async fn handle_leaderboard(query: LeaderboardQuery, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    let metric = query.metric.unwrap_or(LeaderboardMetric::Wins);
+    let period = query.period.unwrap_or(LeaderboardPeriod::All);
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(LEADERBOARD_DEFAULT_PAGE_SIZE)
+        .clamp(1, LEADERBOARD_MAX_PAGE_SIZE);
+
+    // 等级分是累计值，没有按周期划分的榜单
+    if metric == LeaderboardMetric::Rating && period != LeaderboardPeriod::All {
+        return Json(serde_json::json!({
             "success": false,
-            "error": e.to_string()
+            "message": "等级分排行榜只支持总榜（period=all）"
+        }));
+    }
+
+    match storage
+        .get_leaderboard(metric, period, (page - 1) * page_size, page_size)
+        .await
+    {
+        Ok((total, entries)) => Json(serde_json::json!({
+            "success": true,
+            "page": page,
+            "page_size": page_size,
+            "total": total,
+            "entries": entries
         })),
+        Err(e) => {
+            error!("查询排行榜失败: {}", e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "查询排行榜失败"
+            }))
+        }
     }
 }
 
-/// 处理房间状态查询
-async fn handle_rooms_status(
-    rooms: Arc<DashMap<String, Arc<Room>>>,
-) -> Json<serde_json::Value> {
-    let mut room_statuses = Vec::new();
-    
-    for entry in rooms.iter() {
-        let room_id = entry.key();
-        let room = entry.value();
-        
-        // 检查房间是否应该被删除
-        let should_be_deleted = room.should_be_deleted().await;
-        
-        // 只返回不应该被删除的房间
-        if !should_be_deleted {
-            let (player_count, idle_seconds, is_game_over, is_empty) = room.get_status().await;
-            
-            room_statuses.push(serde_json::json!({
-                "room_id": room_id,
-                "player_count": player_count,
-                "idle_seconds": idle_seconds,
-                "is_game_over": is_game_over,
-                "is_empty": is_empty,
-                "should_be_deleted": false
+/// 处理每日对局统计查询：当天实时计算，之前的日期读取汇总结果
+async fn handle_stats(query: StatsQuery, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    let max_days = crate::config::Config::get().stats.retention_days.max(1);
+    let days = query.days.unwrap_or(STATS_DEFAULT_DAYS).clamp(1, max_days);
+
+    let today = match storage.get_today_stats().await {
+        Ok(today) => today,
+        Err(e) => {
+            error!("查询当天对局统计失败: {}", e);
+            return Json(serde_json::json!({
+                "success": false,
+                "message": "查询对局统计失败"
             }));
         }
+    };
+    match storage.get_daily_stats(days).await {
+        Ok(daily) => Json(serde_json::json!({
+            "success": true,
+            "today": today,
+            "days": daily
+        })),
+        Err(e) => {
+            error!("查询每日对局统计失败: {}", e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "查询对局统计失败"
+            }))
+        }
+    }
+}
+
+/// 处理按日或按周的对局汇总查询，供公开统计面板使用
+async fn handle_stats_summary(query: StatsSummaryQuery, storage: Arc<dyn StorageBackend>) -> Json<serde_json::Value> {
+    let period = query.period.unwrap_or(LeaderboardPeriod::Daily);
+    let retention_days = crate::config::Config::get().stats.retention_days.max(1);
+    let max_count = match period {
+        LeaderboardPeriod::Weekly => retention_days.div_ceil(7),
+        _ => retention_days,
+    };
+    let count = query.count.unwrap_or(STATS_DEFAULT_DAYS).clamp(1, max_count);
+
+    match storage.get_stats_summary(period, count).await {
+        Ok(summary) => Json(serde_json::json!({
+            "success": true,
+            "period": period,
+            "summary": summary
+        })),
+        Err(e) => {
+            error!("查询对局汇总失败: {}", e);
+            Json(serde_json::json!({
+                "success": false,
+                "message": "查询对局汇总失败"
+            }))
+        }
     }
-    
-    Json(serde_json::json!({
-        "success": true,
-        "rooms": room_statuses,
-        "total_rooms": room_statuses.len()
-    }))
 }
 
 /// 提供index.html文件
@@ -616,7 +3004,7 @@ async fn serve_index() -> Html<String> {
         Ok(content) => Html(content),
         Err(e) => {
             error!("读取index.html失败: {}", e);
-            Html(format!("<h1>404 Not Found</h1><p>找不到index.html文件</p>"))
+            Html("<h1>404 Not Found</h1><p>找不到index.html文件</p>".to_string())
         }
     }
 }
@@ -628,7 +3016,7 @@ async fn serve_admin() -> Html<String> {
         Ok(content) => Html(content),
         Err(e) => {
             error!("读取admin.html失败: {}", e);
-            Html(format!("<h1>404 Not Found</h1><p>rooms.html文件</p>"))
+            Html("<h1>404 Not Found</h1><p>rooms.html文件</p>".to_string())
         }
     }
 }
@@ -637,53 +3025,93 @@ async fn serve_admin() -> Html<String> {
 async fn handle_room_connection(
     socket: WebSocket,
     room_id: String,
-    session_id: Option<Uuid>,
+    credentials: ConnectCredentials,
+    msgpack: bool,
     rooms: Arc<DashMap<String, Arc<Room>>>,
     user_manager: Arc<tokio::sync::RwLock<UserManager>>,
     connection_manager: Arc<ConnectionManager>,
 ) {
+    let ConnectCredentials { session_id, bot_key, resume_token } = credentials;
+    // 客户端在 join 消息中声明的协议能力，加入前按旧协议发送；连接地址指定 MessagePack 时始终使用二进制帧
+    let mut capabilities = ClientCapabilities {
+        msgpack,
+        ..ClientCapabilities::default()
+    };
     debug!(
         "开始处理WebSocket连接，房间ID: {}, 会话ID: {:?}",
         room_id, session_id
     );
     let (mut ws_sender, mut ws_receiver) = socket.split();
 
-    // 验证用户会话（必需）
-    let user = if let Some(session_id) = session_id {
+    // 外部机器人使用API密钥接入，玩家ID按机器人名称和房间生成，重连时保持不变
+    let is_external_bot = bot_key.is_some();
+    let user = if let Some(key) = bot_key {
+        match crate::config::Config::get().bots.find(&key) {
+            Some(bot) => {
+                debug!("外部机器人接入: {}", bot.name);
+                external_bot_user(&bot.name, &room_id)
+            }
+            None => {
+                error!("无效的机器人API密钥");
+                let error_msg = ServerMessage::Error {
+                    code: "AuthError".to_string(),
+                    message: "无效的机器人API密钥".to_string(),
+                };
+                if let Some(frame) = encode_frame(&error_msg, capabilities) {
+                    let _ = ws_sender.send(frame).await;
+                }
+                return; // 关闭连接
+            }
+        }
+    } else if let Some(token) = resume_token.filter(|_| session_id.is_none()) {
+        // 主备切换后使用恢复令牌重新接入原房间
+        match rooms.get(&room_id).and_then(|room| room.resume_user(&token)) {
+            Some(user) => {
+                debug!("玩家 {} 使用恢复令牌重新接入房间 {}", user.id, room_id);
+                user
+            }
+            None => {
+                error!("无效的恢复令牌");
+                let error_msg = ServerMessage::Error {
+                    code: "AuthError".to_string(),
+                    message: "恢复令牌无效，请重新登录".to_string(),
+                };
+                if let Some(frame) = encode_frame(&error_msg, capabilities) {
+                    let _ = ws_sender.send(frame).await;
+                }
+                return; // 关闭连接
+            }
+        }
+    } else if let Some(session_id) = session_id {
+        // 验证用户会话（必需）
         let user_manager_guard = user_manager.read().await;
         match user_manager_guard.get_user_by_session(&session_id).await {
             Ok(user) => {
                 let display_name: String = user.nickname.as_ref().unwrap_or(&user.username).clone();
                 debug!("用户已登录: {} ({})", display_name, user.username);
-                Some(user.clone())
+                user.clone()
             }
             Err(e) => {
                 error!("会话验证失败: {}", e);
                 // 发送错误消息并关闭连接
-                let error_msg = GameMessage {
-                    type_: "error".to_string(),
-                    data: serde_json::json!({
-                        "code": "AuthError",
-                        "message": "请先登录"
-                    }),
+                let error_msg = ServerMessage::Error {
+                    code: "AuthError".to_string(),
+                    message: "请先登录".to_string(),
                 };
-                if let Ok(text) = serde_json::to_string(&error_msg) {
-                    let _ = ws_sender.send(Message::Text(text)).await;
+                if let Some(frame) = encode_frame(&error_msg, capabilities) {
+                    let _ = ws_sender.send(frame).await;
                 }
                 return; // 关闭连接
             }
         }
     } else {
         // 没有会话ID，发送错误消息并关闭连接
-        let error_msg = GameMessage {
-            type_: "error".to_string(),
-            data: serde_json::json!({
-                "code": "AuthRequired",
-                "message": "需要登录才能进入游戏"
-            }),
+        let error_msg = ServerMessage::Error {
+            code: "AuthRequired".to_string(),
+            message: "需要登录才能进入游戏".to_string(),
         };
-        if let Ok(text) = serde_json::to_string(&error_msg) {
-            let _ = ws_sender.send(Message::Text(text)).await;
+        if let Some(frame) = encode_frame(&error_msg, capabilities) {
+            let _ = ws_sender.send(frame).await;
         }
         return; // 关闭连接
     };
@@ -694,29 +3122,38 @@ async fn handle_room_connection(
         room_entry.value().clone()
     } else {
         // 房间不存在，发送错误消息并关闭连接
-        let error_msg = GameMessage {
-            type_: "error".to_string(),
-            data: serde_json::json!({
-                "code": "RoomNotFound",
-                "message": format!("房间 {} 不存在，请先创建房间", room_id)
-            }),
+        let error_msg = ServerMessage::Error {
+            code: "RoomNotFound".to_string(),
+            message: format!("房间 {} 不存在，请先创建房间", room_id),
         };
-        if let Ok(text) = serde_json::to_string(&error_msg) {
-            let _ = ws_sender.send(Message::Text(text)).await;
+        if let Some(frame) = encode_frame(&error_msg, capabilities) {
+            let _ = ws_sender.send(frame).await;
         }
         return; // 关闭连接
     };
 
     // 检查房间是否已被删除
     if room.is_deleted().await {
-        let error_msg = GameMessage {
-            type_: "error".to_string(),
-            data: serde_json::json!({
-                "message": format!("房间 {} 不存在", room_id)
-            }),
+        let error_msg = ServerMessage::Error {
+            code: "RoomNotFound".to_string(),
+            message: format!("房间 {} 不存在", room_id),
         };
-        if let Ok(text) = serde_json::to_string(&error_msg) {
-            let _ = ws_sender.send(Message::Text(text)).await;
+        if let Some(frame) = encode_frame(&error_msg, capabilities) {
+            let _ = ws_sender.send(frame).await;
+        }
+        return; // 关闭连接
+    }
+
+    // 外部机器人登记后才能加入，状态更新中会标记为机器人且不计入排行榜
+    if is_external_bot
+        && let Err(e) = room.register_external_bot(user.id.clone())
+    {
+        let error_msg = ServerMessage::Error {
+            code: e.code(),
+            message: e.to_string(),
+        };
+        if let Some(frame) = encode_frame(&error_msg, capabilities) {
+            let _ = ws_sender.send(frame).await;
         }
         return; // 关闭连接
     }
@@ -725,7 +3162,7 @@ async fn handle_room_connection(
     room.update_activity().await;
 
     // 创建消息通道
-    let (_tx, mut rx) = tokio::sync::mpsc::channel::<GameMessage>(100);
+    let (_tx, mut rx) = tokio::sync::mpsc::channel::<ServerMessage>(100);
     let ws_sender = Arc::new(tokio::sync::Mutex::new(ws_sender));
 
     // 启动消息接收循环
@@ -733,192 +3170,304 @@ async fn handle_room_connection(
     tokio::spawn(async move {
         while let Some(message) = rx.recv().await {
             debug!("准备发送消息: {:?}", message);
-            if let Ok(text) = serde_json::to_string(&message) {
-                debug!("消息序列化成功: {}", text);
+            if let Some(frame) = encode_frame(&message, capabilities) {
                 let mut sender = ws_sender_clone.lock().await;
-                match sender.send(Message::Text(text)).await {
+                match sender.send(frame).await {
                     Ok(_) => debug!("消息发送成功"),
                     Err(e) => {
                         error!("发送消息失败: {}", e);
                         break;
                     }
                 }
-            } else {
-                error!("消息序列化失败");
             }
         }
     });
 
     // 发送用户信息
-    if let Some(user) = &user {
-        debug!("准备发送用户信息: {:?}", user);
-        let user_info_msg = GameMessage {
-            type_: "user_info".to_string(),
-            data: serde_json::json!({
-                "user_id": user.id,
-                "username": user.username,
-                "nickname": user.nickname,
-                "avatar": user.avatar
-            }),
-        };
-        debug!("用户信息消息: {:?}", user_info_msg);
-        if let Ok(text) = serde_json::to_string(&user_info_msg) {
-            debug!("用户信息序列化成功: {}", text);
-            match ws_sender.lock().await.send(Message::Text(text)).await {
-                Ok(_) => debug!("用户信息发送成功"),
-                Err(e) => error!("发送用户信息失败: {}", e),
-            }
-        } else {
-            error!("用户信息序列化失败");
+    debug!("准备发送用户信息: {:?}", user);
+    let user_info_msg = ServerMessage::UserInfo {
+        user_id: user.id.clone(),
+        username: user.username.clone(),
+        nickname: user.nickname.clone(),
+        avatar: user.avatar.clone(),
+    };
+    debug!("用户信息消息: {:?}", user_info_msg);
+    if let Some(frame) = encode_frame(&user_info_msg, capabilities) {
+        match ws_sender.lock().await.send(frame).await {
+            Ok(_) => debug!("用户信息发送成功"),
+            Err(e) => error!("发送用户信息失败: {}", e),
         }
-    } else {
-        error!("用户信息为空，无法发送user_info消息");
     }
 
+    // 当前连接加入房间时使用的消息通道，用于断线时判断是否仍为玩家的最新连接
+    let mut joined_tx: Option<mpsc::Sender<SequencedMessage>> = None;
+
+    // 定时发送携带时间戳的ping，根据pong计算往返延迟
+    let ping_task = {
+        let ws_sender = ws_sender.clone();
+        let ping_interval = crate::config::Config::get().ping_interval();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(ping_interval);
+            loop {
+                interval.tick().await;
+                let sent_at = chrono::Utc::now().timestamp_millis().to_be_bytes().to_vec();
+                if ws_sender.lock().await.send(Message::Ping(sent_at)).await.is_err() {
+                    break;
+                }
+            }
+        })
+    };
+
     // 处理WebSocket消息
     while let Some(msg) = ws_receiver.next().await {
         // 更新房间活动时间
         room.update_activity().await;
         
-        match msg {
+        let decoded = match msg {
             Ok(Message::Text(text)) => {
                 debug!("收到消息: {}", text);
-                match serde_json::from_str::<GameMessage>(&text) {
-                    Ok(message) => {
-                        debug!("解析消息成功: {:?}", message);
-
-                        // 如果是join消息，需要创建新的消息通道
-                        if message.type_ == "join" {
-                            debug!("处理join消息");
-                            let (player_tx, mut player_rx) =
-                                tokio::sync::mpsc::channel::<GameMessage>(100);
-                            let ws_sender_clone = ws_sender.clone();
-
-                            // 启动一个任务来处理从房间接收到的消息
-                            tokio::spawn(async move {
-                                while let Some(message) = player_rx.recv().await {
-                                    debug!("从房间收到消息: {:?}", message);
-                                    if let Ok(text) = serde_json::to_string(&message) {
-                                        let mut sender = ws_sender_clone.lock().await;
-                                        if let Err(e) = sender.send(Message::Text(text)).await {
-                                            error!("发送消息到WebSocket失败: {}", e);
-                                            break;
-                                        }
+                serde_json::from_str::<ClientMessage>(&text).map_err(|e| e.to_string())
+            }
+            // 使用 MessagePack 格式的连接，客户端消息同样以二进制帧发送
+            Ok(Message::Binary(bytes)) if msgpack => {
+                rmp_serde::from_slice::<ClientMessage>(&bytes).map_err(|e| e.to_string())
+            }
+            Ok(Message::Binary(_)) => {
+                debug!("收到二进制消息，忽略");
+                continue;
+            }
+            Ok(Message::Close(_)) => {
+                debug!("收到关闭消息");
+                break;
+            }
+            Ok(Message::Ping(data)) => {
+                debug!("收到ping消息");
+                if let Err(e) = ws_sender.lock().await.send(Message::Pong(data)).await {
+                    error!("发送pong消息失败: {}", e);
+                }
+                continue;
+            }
+            Ok(Message::Pong(data)) => {
+                debug!("收到pong消息");
+                if let Ok(bytes) = <[u8; 8]>::try_from(data.as_slice()) {
+                    let sent_at = i64::from_be_bytes(bytes);
+                    let rtt_ms = chrono::Utc::now().timestamp_millis() - sent_at;
+                    if rtt_ms >= 0 {
+                        room.record_latency(&user.id, std::time::Duration::from_millis(rtt_ms as u64));
+                    }
+                }
+                continue;
+            }
+            Err(e) => {
+                error!("WebSocket错误: {}", e);
+                break;
+            }
+        };
+
+        match decoded {
+            Ok(mut message) => {
+                debug!("解析消息成功: {:?}", message);
+
+                // 如果是join消息，需要创建新的消息通道
+                if let ClientMessage::Join { capabilities: declared, .. } = &message {
+                    debug!("处理join消息");
+                    let (player_tx, player_rx) =
+                        tokio::sync::mpsc::channel::<SequencedMessage>(100);
+                    // 定向消息与房间广播合并为同一个消息流
+                    let mut player_rx = room.player_feed(user.id.clone(), &player_tx, player_rx);
+                    let ws_sender_clone = ws_sender.clone();
+                    capabilities = ClientCapabilities {
+                        msgpack: msgpack || declared.is_some_and(|declared| declared.msgpack),
+                        ..declared.unwrap_or_default()
+                    };
+
+                    // 启动一个任务来处理从房间接收到的消息
+                    tokio::spawn(async move {
+                        while let Some(message) = player_rx.recv().await {
+                            debug!("从房间收到消息: {:?}", message);
+                            let mut messages = vec![message];
+                            // 支持批量消息的客户端一次取出所有已就绪的消息
+                            if capabilities.batched_messages {
+                                while messages.len() < MAX_BATCH_MESSAGES {
+                                    match player_rx.try_recv() {
+                                        Ok(message) => messages.push(message),
+                                        Err(_) => break,
                                     }
                                 }
-                            });
-
-                            // 修改join消息，使用摸鱼派用户的昵称和ID
-                            let mut modified_message = message.clone();
-                            if let Some(user) = &user {
-                                // 使用摸鱼派用户的昵称作为玩家名称，如果没有昵称则使用用户名
-                                let player_name = user.nickname.as_ref().unwrap_or(&user.username);
-                                modified_message.data["player_name"] =
-                                    serde_json::Value::String(player_name.clone());
-                                // 使用摸鱼派用户ID作为玩家ID
-                                modified_message.data["player_id"] =
-                                    serde_json::Value::String(user.id.clone());
                             }
-
-                            // 注册玩家连接
-                            if let Some(user) = &user {
-                                let player_id = user.id.clone();
-                                connection_manager.register_connection(
-                                    player_id.clone(),
-                                    room_id.clone(),
-                                    player_tx.clone(),
-                                ).await;
+                            if let Some(frame) = encode_outgoing(messages, capabilities) {
+                                let mut sender = ws_sender_clone.lock().await;
+                                if let Err(e) = sender.send(frame).await {
+                                    error!("发送消息到WebSocket失败: {}", e);
+                                    break;
+                                }
                             }
+                        }
+                    });
 
-                            // 将player_tx传递给房间
-                            if let Err(e) =
-                                room.handle_message(modified_message, Some(player_tx)).await
-                            {
-                                error!("处理消息失败: {}", e);
-                                let error = GameMessage {
-                                    type_: "error".to_string(),
-                                    data: serde_json::json!({
-                                        "code": "InternalError",
-                                        "message": e.to_string()
-                                    }),
-                                };
-                                if let Ok(text) = serde_json::to_string(&error) {
-                                    debug!("发送错误消息: {}", text);
-                                    match ws_sender.lock().await.send(Message::Text(text)).await {
-                                        Ok(_) => debug!("错误消息发送成功"),
-                                        Err(e) => error!("发送错误消息失败: {}", e),
-                                    }
+                    // 修改join消息，使用摸鱼派用户的昵称作为玩家名称，如果没有昵称则使用用户名
+                    if let ClientMessage::Join { player_name, .. } = &mut message {
+                        *player_name = user.nickname.clone().unwrap_or_else(|| user.username.clone());
+                    }
+
+                    // 注册玩家连接
+                    connection_manager.register_connection(
+                        user.id.clone(),
+                        room_id.clone(),
+                        player_tx.clone(),
+                    ).await;
+                    joined_tx = Some(player_tx.clone());
+
+                    // 将player_tx传递给房间，所有消息都以本连接的登录身份操作
+                    if let Err(e) =
+                        room.handle_message(user.id.clone(), message, Some(player_tx)).await
+                    {
+                        error!("处理消息失败: {}", e);
+                        let error = ServerMessage::Error {
+                            code: e.code(),
+                            message: e.to_string(),
+                        };
+                        if let Some(frame) = encode_frame(&error, capabilities) {
+                            match ws_sender.lock().await.send(frame).await {
+                                Ok(_) => debug!("错误消息发送成功"),
+                                Err(e) => error!("发送错误消息失败: {}", e),
+                            }
+                        }
+                    }
+                } else {
+                    // 处理其他消息，添加电脑玩家需要房间的共享引用，开始游戏前先确认已准备的玩家仍然在线
+                    let result = match &message {
+                        ClientMessage::Ack { seq } => {
+                            match room.acknowledge(&user.id, *seq) {
+                                Some(unacked) if unacked > UNACKED_WARNING_THRESHOLD => {
+                                    warn!("房间 {} 玩家 {} 仍有 {} 条消息未确认", room_id, user.id, unacked);
                                 }
+                                Some(_) => {}
+                                None => debug!("玩家 {} 确认了尚未发送的序号 {}", user.id, seq),
                             }
-                        } else {
-                            // 处理其他消息
-                            if let Err(e) = room.handle_message(message, None).await {
-                                error!("处理消息失败: {}", e);
-                                let error = GameMessage {
-                                    type_: "error".to_string(),
-                                    data: serde_json::json!({
-                                        "code": "InternalError",
-                                        "message": e.to_string()
-                                    }),
-                                };
-                                if let Ok(text) = serde_json::to_string(&error) {
-                                    debug!("发送错误消息: {}", text);
-                                    match ws_sender.lock().await.send(Message::Text(text)).await {
-                                        Ok(_) => debug!("错误消息发送成功"),
-                                        Err(e) => error!("发送错误消息失败: {}", e),
+                            Ok(())
+                        }
+                        // 重连后补发断线期间错过的消息，直接写入当前连接
+                        ClientMessage::Resume { last_seq } => {
+                            match room.missed_messages(&user.id, *last_seq) {
+                                Some(missed) => {
+                                    debug!("补发 {} 条消息", missed.len());
+                                    let chunk_size = if capabilities.batched_messages { MAX_BATCH_MESSAGES } else { 1 };
+                                    for chunk in missed.chunks(chunk_size) {
+                                        if let Some(frame) = encode_outgoing(chunk.to_vec(), capabilities)
+                                            && let Err(e) = ws_sender.lock().await.send(frame).await
+                                        {
+                                            error!("补发消息失败: {}", e);
+                                            break;
+                                        }
                                     }
+                                    Ok(())
                                 }
+                                None => Err(crate::Error::Rule(
+                                    ErrorCode::ResumeUnavailable,
+                                    "部分消息已不在缓存中，请以最新的房间状态为准".to_string(),
+                                )),
                             }
                         }
-                    }
-                    Err(e) => {
-                        error!("解析消息失败: {}", e);
-                        let error = GameMessage {
-                            type_: "error".to_string(),
-                            data: serde_json::json!({
-                                "code": "ParseError",
-                                "message": "消息格式错误"
-                            }),
+                        ClientMessage::AddBots { count, difficulty } => {
+                            crate::bot::add_bots(&room, &user.id, *count, difficulty.as_deref())
+                                .await
+                                .map(|_| ())
+                        }
+                        ClientMessage::StartGame {} => match room
+                            .clear_stale_ready(&user.id, |id| connection_manager.is_connected(id, &room_id))
+                            .await
+                        {
+                            Ok(()) => room.handle_message(user.id.clone(), message, None).await,
+                            Err(e) => Err(e),
+                        },
+                        _ => room.handle_message(user.id.clone(), message, None).await,
+                    };
+                    if let Err(e) = result {
+                        error!("处理消息失败: {}", e);
+                        let error = ServerMessage::Error {
+                            code: e.code(),
+                            message: e.to_string(),
                         };
-                        if let Ok(text) = serde_json::to_string(&error) {
-                            if let Err(e) = ws_sender.lock().await.send(Message::Text(text)).await {
-                                error!("发送错误消息失败: {}", e);
+                        if let Some(frame) = encode_frame(&error, capabilities) {
+                            match ws_sender.lock().await.send(frame).await {
+                                Ok(_) => debug!("错误消息发送成功"),
+                                Err(e) => error!("发送错误消息失败: {}", e),
                             }
                         }
                     }
                 }
             }
-            Ok(Message::Close(_)) => {
-                debug!("收到关闭消息");
-                break;
-            }
-            Ok(Message::Ping(data)) => {
-                debug!("收到ping消息");
-                if let Err(e) = ws_sender.lock().await.send(Message::Pong(data)).await {
-                    error!("发送pong消息失败: {}", e);
-                }
-            }
-            Ok(Message::Pong(_)) => {
-                debug!("收到pong消息");
-            }
-            Ok(Message::Binary(_)) => {
-                debug!("收到二进制消息，忽略");
-            }
             Err(e) => {
-                error!("WebSocket错误: {}", e);
-                break;
+                error!("解析消息失败: {}", e);
+                let error = ServerMessage::Error {
+                    code: "ParseError".to_string(),
+                    message: format!("消息格式错误: {}", e),
+                };
+                if let Some(frame) = encode_frame(&error, capabilities)
+                    && let Err(e) = ws_sender.lock().await.send(frame).await
+                {
+                    error!("发送错误消息失败: {}", e);
+                }
             }
         }
     }
 
-    // 连接关闭时，移除玩家连接记录
-    if let Some(user) = &user {
+    ping_task.abort();
+
+    // 连接关闭时，移除玩家连接记录（玩家已通过新连接重连时保留新连接）
+    let is_latest_connection = match (&joined_tx, connection_manager.get_connection(&user.id).await) {
+        (Some(tx), Some((_, current_tx))) => tx.same_channel(&current_tx),
+        _ => false,
+    };
+
+    if is_latest_connection {
         connection_manager.remove_connection(&user.id).await;
+        room.mark_disconnected(&user.id).await;
+    }
+
+    if is_external_bot {
+        room.release_external_bot(&user.id);
     }
 
     debug!("WebSocket连接关闭");
 }
 
+/// 编码已分配序号的房间消息，多条消息合并为一条 batch 消息
+fn encode_outgoing(messages: Vec<SequencedMessage>, capabilities: ClientCapabilities) -> Option<Message> {
+    if messages.len() == 1 {
+        return encode_frame(&messages[0], capabilities);
+    }
+
+    // batch 外层不占用序号，序号在其中每条消息上
+    let mut batch = MessageBatch::new();
+    for message in messages {
+        batch.add_message(message);
+    }
+    encode_frame(&ServerMessage::Batch(batch), capabilities)
+}
+
+/// 按客户端声明的协议能力编码单条消息
+fn encode_frame<T: Serialize>(message: &T, capabilities: ClientCapabilities) -> Option<Message> {
+    if capabilities.msgpack {
+        match rmp_serde::to_vec_named(message) {
+            Ok(bytes) => Some(Message::Binary(bytes)),
+            Err(e) => {
+                error!("消息MessagePack编码失败: {}", e);
+                None
+            }
+        }
+    } else {
+        match serde_json::to_string(message) {
+            Ok(text) => Some(Message::Text(text)),
+            Err(e) => {
+                error!("消息序列化失败: {}", e);
+                None
+            }
+        }
+    }
+}
+
 /// 生成随机6个字母的房间ID
 fn generate_random_room_id() -> String {
     const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
@@ -937,8 +3486,9 @@ async fn handle_create_room(
     query: CreateRoomQuery,
     rooms: Arc<DashMap<String, Arc<Room>>>,
     word_bank: Arc<WordBank>,
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    game_slots: Arc<GameSlots>,
 ) -> Json<CreateRoomResponse> {
     // 验证用户会话
     let session_id = match Uuid::parse_str(&query.session_id) {
@@ -964,6 +3514,14 @@ async fn handle_create_room(
         }
     };
 
+    if user.is_guest() {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some("游客不能创建房间，请登录摸鱼派账号".to_string()),
+        });
+    }
+
     // 生成房间ID
     let room_id = if let Some(custom_id) = query.room_id {
         // 验证自定义房间ID
@@ -1007,20 +3565,291 @@ async fn handle_create_room(
         });
     }
 
+    // 合并房间游戏参数，未指定的项沿用全局配置
+    let defaults = GameParams::from_config();
+    let params = GameParams {
+        min_players: query.min_players.unwrap_or(defaults.min_players),
+        max_players: query.max_players.unwrap_or(defaults.max_players),
+        describe_time_limit: query.describe_time_limit.unwrap_or(defaults.describe_time_limit),
+        vote_time_limit: query.vote_time_limit.unwrap_or(defaults.vote_time_limit),
+        round_delay: query.round_delay.unwrap_or(defaults.round_delay),
+        ..defaults
+    };
+    if let Err(e) = params.validate() {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some(e),
+        });
+    }
+
     // 创建新房间
-    let config = crate::config::Config::get();
     let mut new_room = Room::new(
         room_id.clone(),
-        config.game.min_players,
-        config.game.max_players,
+        params,
         word_bank.clone(),
         storage.clone(),
         user.id.clone(), // 使用创建者的用户ID作为房主
     );
+    new_room.set_settings(GameSettings {
+        blank_role: query.blank_role.unwrap_or(false),
+        ..Default::default()
+    });
+    new_room.set_visibility(query.visibility.unwrap_or_default());
+
+    register_room(new_room, &rooms, &game_slots);
+
+    debug!("用户 {} 创建了房间: {}", user.username, room_id);
+
+    Json(CreateRoomResponse {
+        success: true,
+        room_id: Some(room_id),
+        message: None,
+    })
+}
+
+/// 为外部机器人构造用户信息
+fn external_bot_user(bot_name: &str, room_id: &str) -> User {
+    let now = chrono::Utc::now();
+    User {
+        id: format!("bot-{}-{}", bot_name, room_id),
+        username: bot_name.to_string(),
+        nickname: Some(format!("{}（机器人）", bot_name)),
+        avatar: None,
+        avatar_source: None,
+        created_at: now,
+        last_login: now,
+    }
+}
+
+/// 处理大厅房间列表订阅：先下发完整列表，再持续推送房间变化，断开连接即取消订阅
+async fn handle_lobby_connection(socket: WebSocket, room_feed: Arc<RoomFeed>) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+    let mut events = room_feed.events.subscribe();
+
+    let mut pending = Some(room_feed.room_list().await);
+    loop {
+        if let Some(message) = pending.take() {
+            let Ok(text) = serde_json::to_string(&message) else {
+                continue;
+            };
+            if ws_sender.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(message) => pending = Some(message),
+                // 落后太多时重新下发完整列表
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    debug!("房间列表订阅落后 {} 条事件，重新下发完整列表", skipped);
+                    pending = Some(room_feed.room_list().await);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            msg = ws_receiver.next() => {
+                if matches!(msg, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    break;
+                }
+            }
+        }
+    }
+    debug!("房间列表订阅连接已关闭");
+}
+
+/// 告知连接到备用实例的客户端稍后重连
+async fn send_standby_error(mut socket: WebSocket) {
+    let error_msg = ServerMessage::Error {
+        code: "Standby".to_string(),
+        message: "服务器正在切换，请稍后重新连接".to_string(),
+    };
+    if let Ok(text) = serde_json::to_string(&error_msg) {
+        let _ = socket.send(Message::Text(text)).await;
+    }
+}
+
+/// 处理快速匹配连接：验证会话后加入匹配队列，匹配成功时推送房间ID，断开连接即退出队列
+async fn handle_match_connection(
+    socket: WebSocket,
+    query: MatchQuery,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    match_queue: Arc<MatchQueue>,
+) {
+    let (mut ws_sender, mut ws_receiver) = socket.split();
+
+    let user = match Uuid::parse_str(&query.session_id) {
+        Ok(session_id) => user_manager.read().await.get_user_by_session(&session_id).await.ok(),
+        Err(_) => None,
+    };
+    let Some(user) = user else {
+        let error_msg = ServerMessage::Error {
+            code: "AuthError".to_string(),
+            message: "请先登录".to_string(),
+        };
+        if let Ok(text) = serde_json::to_string(&error_msg) {
+            let _ = ws_sender.send(Message::Text(text)).await;
+        }
+        return; // 关闭连接
+    };
+    if user.is_guest() {
+        let error_msg = ServerMessage::Error {
+            code: "InvalidAction".to_string(),
+            message: "游客不能参加快速匹配，请登录摸鱼派账号".to_string(),
+        };
+        if let Ok(text) = serde_json::to_string(&error_msg) {
+            let _ = ws_sender.send(Message::Text(text)).await;
+        }
+        return;
+    }
+
+    let user_id = user.id.clone();
+    let (tx, mut rx) = mpsc::channel::<ServerMessage>(4);
+    let waiting = match_queue.enqueue(user, tx.clone()).await;
+    debug!("玩家 {} 加入快速匹配队列，当前排队 {} 人", user_id, waiting);
+
+    let queued_msg = ServerMessage::MatchQueued {
+        waiting,
+        min_players: GameParams::from_config().min_players,
+    };
+    if let Ok(text) = serde_json::to_string(&queued_msg)
+        && ws_sender.send(Message::Text(text)).await.is_err()
+    {
+        match_queue.leave(&user_id, &tx).await;
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            message = rx.recv() => {
+                let Some(message) = message else {
+                    break;
+                };
+                let matched = matches!(message, ServerMessage::MatchFound { .. });
+                if let Ok(text) = serde_json::to_string(&message) {
+                    let _ = ws_sender.send(Message::Text(text)).await;
+                }
+                if matched {
+                    break;
+                }
+            }
+            msg = ws_receiver.next() => {
+                if matches!(msg, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    debug!("玩家 {} 退出快速匹配", user_id);
+                    break;
+                }
+            }
+        }
+    }
+    match_queue.leave(&user_id, &tx).await;
+}
+
+/// 从Redis中的检查点恢复本实例尚未持有的房间，过旧的检查点直接丢弃
+async fn restore_rooms(
+    rooms: &Arc<DashMap<String, Arc<Room>>>,
+    word_bank: &Arc<WordBank>,
+    storage: &Arc<dyn StorageBackend>,
+    game_slots: &Arc<GameSlots>,
+    max_age_secs: u64,
+) {
+    let checkpoints: Vec<RoomCheckpoint> = match storage.load_room_checkpoints().await {
+        Ok(checkpoints) => checkpoints,
+        Err(e) => {
+            error!("加载房间检查点失败: {}", e);
+            return;
+        }
+    };
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::seconds(max_age_secs as i64);
+    let mut restored = 0;
+    for checkpoint in checkpoints {
+        if rooms.contains_key(&checkpoint.room_id) {
+            continue;
+        }
+        if checkpoint.saved_at < cutoff {
+            debug!("房间 {} 的检查点已过期，跳过恢复", checkpoint.room_id);
+            if let Err(e) = storage.delete_room_checkpoint(&checkpoint.room_id).await {
+                error!("删除过期房间检查点失败: {}", e);
+            }
+            continue;
+        }
+        let room = Room::from_checkpoint(checkpoint, word_bank.clone(), storage.clone()).await;
+        let room = register_room(room, rooms, game_slots);
+        room.resume_game_slot().await;
+        restored += 1;
+    }
+    info!("已从检查点恢复 {} 个房间", restored);
+}
+
+/// 服务启动时从Redis中保存的游戏状态恢复房间，对局在玩家重新连接后继续
+async fn restore_saved_rooms(
+    rooms: &Arc<DashMap<String, Arc<Room>>>,
+    word_bank: &Arc<WordBank>,
+    storage: &Arc<dyn StorageBackend>,
+    game_slots: &Arc<GameSlots>,
+) {
+    let room_ids = match storage.list_saved_room_ids().await {
+        Ok(room_ids) => room_ids,
+        Err(e) => {
+            error!("扫描已保存的房间状态失败: {}", e);
+            return;
+        }
+    };
+
+    let mut restored = 0;
+    for room_id in room_ids {
+        if rooms.contains_key(&room_id) {
+            continue;
+        }
+        match Room::from_saved_state(room_id.clone(), word_bank.clone(), storage.clone()).await {
+            Ok(Some(room)) => {
+                let room = register_room(room, rooms, game_slots);
+                room.resume_game_slot().await;
+                restored += 1;
+            }
+            Ok(None) => debug!("房间 {} 的状态不完整，跳过恢复", room_id),
+            Err(e) => warn!("恢复房间 {} 失败: {}", room_id, e),
+        }
+    }
+    info!("已从保存的状态恢复 {} 个房间", restored);
+}
+
+/// 主实例写入所有房间的检查点
+async fn checkpoint_rooms(rooms: &Arc<DashMap<String, Arc<Room>>>, storage: &Arc<dyn StorageBackend>, instance_id: &str) {
+    let snapshot: Vec<Arc<Room>> = rooms.iter().map(|entry| entry.value().clone()).collect();
+    for room in snapshot {
+        if room.is_deleted().await {
+            continue;
+        }
+        let checkpoint = room.checkpoint(instance_id).await;
+        if let Err(e) = storage.save_room_checkpoint(room.id(), &checkpoint).await {
+            error!("写入房间 {} 检查点失败: {}", room.id(), e);
+        }
+    }
+}
+
+/// 为新房间设置回调、启动生命周期管理并加入全局房间映射
+/// 用户作为房主的未删除房间，用于避免同一用户重复开房
+async fn hosted_room(rooms: &DashMap<String, Arc<Room>>, user_id: &str) -> Option<String> {
+    let candidates: Vec<Arc<Room>> = rooms.iter().map(|entry| entry.value().clone()).collect();
+    for room in candidates {
+        if room.get_host().await == user_id && !room.is_deleted().await {
+            return Some(room.id().to_string());
+        }
+    }
+    None
+}
+
+fn register_room(
+    mut room: Room,
+    rooms: &Arc<DashMap<String, Arc<Room>>>,
+    game_slots: &Arc<GameSlots>,
+) -> Arc<Room> {
+    room.set_game_slots(game_slots.clone());
 
     // 设置房间删除回调
     let rooms_clone = rooms.clone();
-    new_room.set_delete_callback(Box::new(move |id: String| {
+    room.set_delete_callback(Box::new(move |id: String| {
         let rooms = rooms_clone.clone();
         tokio::spawn(async move {
             debug!("执行房间删除回调，删除房间: {}", id);
@@ -1035,7 +3864,7 @@ async fn handle_create_room(
 
     // 设置跨房间玩家踢出回调
     let rooms_clone_for_kick = rooms.clone();
-    new_room.set_player_kick_callback(Box::new(move |player_id: String, other_room_id: String| {
+    room.set_player_kick_callback(Box::new(move |player_id: String, other_room_id: String| {
         let rooms = rooms_clone_for_kick.clone();
         tokio::spawn(async move {
             debug!("执行跨房间玩家踢出回调，玩家: {}, 从房间: {}", player_id, other_room_id);
@@ -1051,15 +3880,221 @@ async fn handle_create_room(
     }));
 
     // 将房间包装在Arc中
-    let room_arc = Arc::new(new_room);
+    let room_arc = Arc::new(room);
     
     // 启动房间的生命周期管理
     Arc::clone(&room_arc).start_lifecycle_management();
 
-    // 将房间插入到全局房间映射中
-    rooms.insert(room_id.clone(), Arc::clone(&room_arc));
+    // 将房间插入到全局房间映射中
+    rooms.insert(room_arc.id().to_string(), Arc::clone(&room_arc));
+
+    room_arc
+}
+
+/// 处理复制房间请求：以调用者为房主创建一个沿用原房间设置的新房间
+async fn handle_clone_room(
+    source_room_id: String,
+    query: AdminQuery,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    word_bank: Arc<WordBank>,
+    storage: Arc<dyn StorageBackend>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    game_slots: Arc<GameSlots>,
+) -> Json<CreateRoomResponse> {
+    // 验证用户会话
+    let session_id = match Uuid::parse_str(&query.session_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return Json(CreateRoomResponse {
+                success: false,
+                room_id: None,
+                message: Some("无效的会话ID格式".to_string()),
+            });
+        }
+    };
+
+    let user_manager_guard = user_manager.read().await;
+    let user = match user_manager_guard.get_user_by_session(&session_id).await {
+        Ok(user) => user,
+        Err(e) => {
+            return Json(CreateRoomResponse {
+                success: false,
+                room_id: None,
+                message: Some(format!("会话验证失败: {}", e)),
+            });
+        }
+    };
+
+    if user.is_guest() {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some("游客不能创建房间，请登录摸鱼派账号".to_string()),
+        });
+    }
+    drop(user_manager_guard);
+
+    // 获取原房间
+    let source_room = match rooms.get(&source_room_id) {
+        Some(entry) => entry.value().clone(),
+        None => {
+            return Json(CreateRoomResponse {
+                success: false,
+                room_id: None,
+                message: Some(format!("房间 {} 不存在", source_room_id)),
+            });
+        }
+    };
+
+    if source_room.kind() == RoomKind::Tutorial {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some("教程房间不能复制".to_string()),
+        });
+    }
+
+    let room_id = generate_random_room_id();
+    if rooms.contains_key(&room_id) {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some("房间ID冲突，请重试".to_string()),
+        });
+    }
+
+    let mut new_room = Room::new(
+        room_id.clone(),
+        source_room.params(),
+        word_bank.clone(),
+        storage.clone(),
+        user.id.clone(),
+    );
+    new_room.set_settings(source_room.settings().await);
+    new_room.set_visibility(source_room.visibility());
+
+    register_room(new_room, &rooms, &game_slots);
+
+    debug!("用户 {} 复制房间 {} 创建了房间: {}", user.username, source_room_id, room_id);
+
+    Json(CreateRoomResponse {
+        success: true,
+        room_id: Some(room_id),
+        message: None,
+    })
+}
+
+/// 处理生成房间邀请码请求，只有房主可以生成
+async fn handle_create_invite(
+    room_id: String,
+    query: InviteQuery,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+) -> Json<serde_json::Value> {
+    let user = match user_from_session(&query.session_id, &user_manager).await {
+        Ok(user) => user,
+        Err(response) => return response,
+    };
+
+    let room = match rooms.get(&room_id) {
+        Some(entry) => entry.value().clone(),
+        None => {
+            return Json(serde_json::json!({
+                "success": false,
+                "message": format!("房间 {} 不存在", room_id)
+            }));
+        }
+    };
+
+    let ttl = query
+        .ttl_minutes
+        .map(|minutes| std::time::Duration::from_secs(minutes.saturating_mul(60)));
+    match room.create_invite(&user.id, query.single_use.unwrap_or(false), ttl).await {
+        Ok(invite) => Json(serde_json::json!({
+            "success": true,
+            "room_id": room_id,
+            "code": invite.code,
+            "single_use": invite.single_use,
+            "expires_at": invite.expires_at,
+            "join_url": format!("{}&invite={}", room_join_url(&room_id), invite.code)
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "code": e.code(),
+            "message": e.to_string()
+        })),
+    }
+}
+
+/// 处理创建新手教程房间请求
+async fn handle_create_tutorial_room(
+    query: AdminQuery,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    word_bank: Arc<WordBank>,
+    storage: Arc<dyn StorageBackend>,
+    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    game_slots: Arc<GameSlots>,
+) -> Json<CreateRoomResponse> {
+    // 验证用户会话
+    let session_id = match Uuid::parse_str(&query.session_id) {
+        Ok(id) => id,
+        Err(_) => {
+            return Json(CreateRoomResponse {
+                success: false,
+                room_id: None,
+                message: Some("无效的会话ID格式".to_string()),
+            });
+        }
+    };
+
+    let user_manager_guard = user_manager.read().await;
+    let user = match user_manager_guard.get_user_by_session(&session_id).await {
+        Ok(user) => user,
+        Err(e) => {
+            return Json(CreateRoomResponse {
+                success: false,
+                room_id: None,
+                message: Some(format!("会话验证失败: {}", e)),
+            });
+        }
+    };
+    drop(user_manager_guard);
+
+    if user.is_guest() {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some("游客不能创建教程房间，请登录摸鱼派账号".to_string()),
+        });
+    }
+
+    let room_id = generate_random_room_id();
+    if rooms.contains_key(&room_id) {
+        return Json(CreateRoomResponse {
+            success: false,
+            room_id: None,
+            message: Some("房间ID冲突，请重试".to_string()),
+        });
+    }
+
+    // 教程房间固定为3个机器人加1名新手
+    let mut new_room = Room::new(
+        room_id.clone(),
+        GameParams {
+            min_players: crate::tutorial::TUTORIAL_PLAYER_COUNT,
+            max_players: crate::tutorial::TUTORIAL_PLAYER_COUNT,
+            ..GameParams::from_config()
+        },
+        word_bank.clone(),
+        storage.clone(),
+        user.id.clone(),
+    );
+    new_room.set_kind(RoomKind::Tutorial);
+
+    let room_arc = register_room(new_room, &rooms, &game_slots);
+    crate::tutorial::spawn_tutorial_bots(&room_arc).await;
 
-    debug!("用户 {} 创建了房间: {}", user.username, room_id);
+    debug!("用户 {} 创建了教程房间: {}", user.username, room_id);
 
     Json(CreateRoomResponse {
         success: true,
@@ -1068,11 +4103,42 @@ async fn handle_create_room(
     })
 }
 
-/// 处理管理员查看房间列表请求
-async fn handle_admin_rooms(
-    query: AdminQuery,
-    rooms: Arc<DashMap<String, Arc<Room>>>,
+/// 从 Authorization 请求头中提取 Bearer 令牌
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+/// 校验管理接口请求携带的管理员令牌，玩家会话ID不能用于管理操作
+async fn authorize_admin(
+    headers: &HeaderMap,
+    admin_auth: &AdminAuth,
+    scope: AdminScope,
+) -> std::result::Result<AdminToken, Json<serde_json::Value>> {
+    let Some(token) = bearer_token(headers) else {
+        return Err(Json(serde_json::json!({
+            "success": false,
+            "message": "缺少管理员令牌，请在 Authorization 请求头中提供 Bearer 令牌"
+        })));
+    };
+
+    admin_auth.authorize(token, scope).await.map_err(|e| {
+        Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        }))
+    })
+}
+
+/// 处理管理员令牌签发请求，需要管理员用户的登录会话
+async fn handle_issue_admin_token(
+    query: AdminTokenQuery,
     user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    admin_auth: Arc<AdminAuth>,
 ) -> Json<serde_json::Value> {
     // 验证用户会话
     let session_id = match Uuid::parse_str(&query.session_id) {
@@ -1095,6 +4161,7 @@ async fn handle_admin_rooms(
             }));
         }
     };
+    drop(user_manager_guard);
 
     // 检查是否为管理员
     let config = crate::config::Config::get();
@@ -1105,75 +4172,493 @@ async fn handle_admin_rooms(
         }));
     }
 
-    // 获取所有房间的详细信息
-    let mut room_details = Vec::new();
-    
-    for entry in rooms.iter() {
-        let room_id = entry.key();
-        let room = entry.value();
-        
-        let (player_count, idle_seconds, is_game_over, is_empty) = room.get_status().await;
-        let host = room.get_host().await;
-        let is_deleted = room.is_deleted().await;
-        
-        room_details.push(serde_json::json!({
-            "room_id": room_id,
-            "player_count": player_count,
-            "idle_seconds": idle_seconds,
-            "is_game_over": is_game_over,
-            "is_empty": is_empty,
-            "is_deleted": is_deleted,
-            "host": host,
-            "should_be_deleted": room.should_be_deleted().await
+    if let Err(e) = admin_auth.check_rate_limit(&format!("issue:{}", user.username)) {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        }));
+    }
+
+    let scopes = match query.scopes.as_deref() {
+        Some(scopes) => match scopes
+            .split(',')
+            .filter(|scope| !scope.trim().is_empty())
+            .map(str::parse::<AdminScope>)
+            .collect::<std::result::Result<Vec<_>, _>>()
+        {
+            Ok(scopes) => scopes,
+            Err(e) => {
+                return Json(serde_json::json!({
+                    "success": false,
+                    "message": e
+                }));
+            }
+        },
+        None => vec![AdminScope::RoomsRead],
+    };
+
+    match admin_auth.issue_token(&user.username, scopes, query.ttl_minutes).await {
+        Ok(token) => {
+            debug!("管理员 {} 签发了新令牌，权限: {:?}", user.username, token.scopes);
+            admin_auth
+                .audit(
+                    &user.username,
+                    AdminAction::IssueToken,
+                    "",
+                    serde_json::json!({ "scopes": token.scopes, "expires_at": token.expires_at }),
+                )
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "token": token.token,
+                "scopes": token.scopes,
+                "expires_at": token.expires_at
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        })),
+    }
+}
+
+/// 处理管理员令牌吊销请求，吊销请求头中携带的令牌
+async fn handle_revoke_admin_token(
+    headers: HeaderMap,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let Some(token) = bearer_token(&headers) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "缺少管理员令牌，请在 Authorization 请求头中提供 Bearer 令牌"
         }));
+    };
+
+    match admin_auth.revoke(token).await {
+        Ok(()) => Json(serde_json::json!({
+            "success": true,
+            "message": "管理员令牌已吊销"
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        })),
+    }
+}
+
+/// 处理管理员查看审计日志请求，从新到旧分页返回
+async fn handle_admin_audit_log(
+    query: AuditLogQuery,
+    headers: HeaderMap,
+    storage: Arc<dyn StorageBackend>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    if let Err(response) = authorize_admin(&headers, &admin_auth, AdminScope::AuditRead).await {
+        return response;
+    }
+
+    let limit = query
+        .limit
+        .unwrap_or(AUDIT_LOG_DEFAULT_PAGE_SIZE)
+        .clamp(1, AUDIT_LOG_MAX_PAGE_SIZE);
+    let before = query.before.as_deref().filter(|id| !id.is_empty());
+
+    match storage.get_audit_records(before, limit).await {
+        Ok(records) => {
+            let next_before = (records.len() == limit)
+                .then(|| records.last().map(|(id, _)| id.clone()))
+                .flatten();
+            let entries: Vec<serde_json::Value> = records
+                .into_iter()
+                .map(|(id, record)| {
+                    let mut entry = serde_json::json!(record);
+                    entry["id"] = serde_json::json!(id);
+                    entry
+                })
+                .collect();
+            Json(serde_json::json!({
+                "success": true,
+                "records": entries,
+                "next_before": next_before
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": format!("读取审计日志失败: {}", e)
+        })),
+    }
+}
+
+/// 处理管理员查看房间列表请求
+async fn handle_admin_rooms(
+    query: AdminRoomListQuery,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+    storage: Arc<dyn StorageBackend>,
+) -> Json<serde_json::Value> {
+    if let Err(response) = authorize_admin(&headers, &admin_auth, AdminScope::RoomsRead).await {
+        return response;
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let page_size = query
+        .page_size
+        .unwrap_or(ADMIN_ROOMS_DEFAULT_PAGE_SIZE)
+        .clamp(1, ADMIN_ROOMS_MAX_PAGE_SIZE);
+
+    // 按房间ID排序，保证翻页结果稳定
+    let mut all_rooms: Vec<(String, Arc<Room>)> = rooms
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect();
+    all_rooms.sort_by(|a, b| a.0.cmp(&b.0));
+    let total_rooms = all_rooms.len();
+
+    let mut room_details = Vec::new();
+    for (room_id, room) in all_rooms.into_iter().skip((page - 1) * page_size).take(page_size) {
+        room_details.push(admin_room_overview(&room_id, &room).await);
     }
 
     Json(serde_json::json!({
         "success": true,
         "rooms": room_details,
-        "total_rooms": room_details.len()
+        "page": page,
+        "page_size": page_size,
+        "total_rooms": total_rooms,
+        "total_pages": total_rooms.div_ceil(page_size),
+        "storage": storage.health()
+    }))
+}
+
+/// 管理接口中房间的概要信息
+async fn admin_room_overview(room_id: &str, room: &Room) -> serde_json::Value {
+    let (player_count, idle_seconds, is_game_over, is_empty) = room.get_status().await;
+    serde_json::json!({
+        "room_id": room_id,
+        "player_count": player_count,
+        "connected_count": room.connected_count(),
+        "idle_seconds": idle_seconds,
+        "is_game_over": is_game_over,
+        "is_empty": is_empty,
+        "is_deleted": room.is_deleted().await,
+        "host": room.get_host().await,
+        "kind": room.kind(),
+        "phase": room.phase().await,
+        "latencies": room.latency_snapshot(),
+        "frozen": room.freeze_info().await,
+        "should_be_deleted": room.should_be_deleted().await
+    })
+}
+
+/// 处理管理员查看房间详情请求，包含带身份和词语的完整玩家名单
+async fn handle_admin_room_detail(
+    room_id: String,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    if let Err(response) = authorize_admin(&headers, &admin_auth, AdminScope::RoomsRead).await {
+        return response;
+    }
+
+    let Some(room) = rooms.get(&room_id).map(|entry| entry.value().clone()) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "房间不存在"
+        }));
+    };
+
+    let mut detail = admin_room_overview(&room_id, &room).await;
+    detail["visibility"] = serde_json::json!(room.visibility());
+    detail["params"] = serde_json::json!(room.params());
+    detail["settings"] = serde_json::json!(room.settings().await);
+    detail["players"] = serde_json::json!(room.roster().await);
+
+    Json(serde_json::json!({
+        "success": true,
+        "room": detail
     }))
 }
 
 /// 处理管理员删除房间请求
 async fn handle_admin_delete_room(
     room_id: String,
-    query: AdminQuery,
+    headers: HeaderMap,
     rooms: Arc<DashMap<String, Arc<Room>>>,
-    user_manager: Arc<tokio::sync::RwLock<UserManager>>,
+    admin_auth: Arc<AdminAuth>,
 ) -> Json<serde_json::Value> {
-    // 验证用户会话
-    let session_id = match Uuid::parse_str(&query.session_id) {
-        Ok(id) => id,
-        Err(_) => {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    // 检查房间是否存在
+    let room = match rooms.get(&room_id) {
+        Some(room_entry) => room_entry.value().clone(),
+        None => {
             return Json(serde_json::json!({
                 "success": false,
-                "message": "无效的会话ID格式"
+                "message": "房间不存在"
             }));
         }
     };
 
-    let user_manager_guard = user_manager.read().await;
-    let user = match user_manager_guard.get_user_by_session(&session_id).await {
-        Ok(user) => user,
-        Err(e) => {
+    // 强制删除房间
+    room.delete().await;
+    admin_auth
+        .audit(&admin.username, AdminAction::DeleteRoom, &room_id, serde_json::Value::Null)
+        .await;
+
+    Json(serde_json::json!({
+        "success": true,
+        "message": format!("房间 {} 已被管理员 {} 强制删除", room_id, admin.username)
+    }))
+}
+
+/// 处理管理员查看房间原始游戏状态请求，用于排查卡住的对局
+async fn handle_admin_room_state(
+    room_id: String,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsRead).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let Some(room) = rooms.get(&room_id).map(|entry| entry.value().clone()) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "房间不存在"
+        }));
+    };
+
+    let state = room.raw_state().await;
+    debug!("管理员 {} 查看了房间 {} 的原始游戏状态", admin.username, room_id);
+    admin_auth
+        .audit(
+            &admin.username,
+            AdminAction::ViewRoomState,
+            &room_id,
+            serde_json::json!({ "phase": state.get_state_type() }),
+        )
+        .await;
+    Json(serde_json::json!({
+        "success": true,
+        "room_id": room_id,
+        "phase": state.get_state_type(),
+        "deadline": state.get_deadline(),
+        "pending_reveal_at": room.pending_reveal_at().await,
+        "frozen": room.freeze_info().await,
+        "is_deleted": room.is_deleted().await,
+        "state": state
+    }))
+}
+
+/// 处理管理员公告请求：推送 server_notice 到所有房间的广播和大厅订阅
+async fn handle_admin_announce(
+    query: AnnounceQuery,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    room_feed: Arc<RoomFeed>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::ServerAnnounce).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let message = query.message.trim();
+    if message.is_empty() {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "公告内容不能为空"
+        }));
+    }
+    if message.chars().count() > ANNOUNCEMENT_MAX_LENGTH {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": format!("公告内容不能超过 {} 个字符", ANNOUNCEMENT_MAX_LENGTH)
+        }));
+    }
+
+    let notice = ServerMessage::ServerNotice {
+        message: message.to_string(),
+        level: query.level.unwrap_or_default(),
+        from: admin.username.clone(),
+        sent_at: chrono::Utc::now(),
+    };
+
+    let targets: Vec<Arc<Room>> = rooms.iter().map(|entry| entry.value().clone()).collect();
+    let mut delivered = 0;
+    for room in targets {
+        if room.is_deleted().await {
+            continue;
+        }
+        room.broadcast(notice.clone()).await;
+        delivered += 1;
+    }
+    room_feed.announce(notice);
+    admin_auth
+        .audit(
+            &admin.username,
+            AdminAction::Announce,
+            "",
+            serde_json::json!({ "message": message, "level": query.level.unwrap_or_default(), "rooms": delivered }),
+        )
+        .await;
+
+    info!("管理员 {} 向 {} 个房间发布公告: {}", admin.username, delivered, message);
+    Json(serde_json::json!({
+        "success": true,
+        "message": format!("公告已发送到 {} 个房间和大厅", delivered),
+        "rooms": delivered
+    }))
+}
+
+/// 处理管理员踢出玩家请求，对局中同样生效
+async fn handle_admin_kick_player(
+    room_id: String,
+    player_id: PlayerId,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let Some(room) = rooms.get(&room_id).map(|entry| entry.value().clone()) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "房间不存在"
+        }));
+    };
+
+    match room.kick_by_admin(player_id.clone(), &admin.username).await {
+        Ok(()) => {
+            admin_auth
+                .audit(
+                    &admin.username,
+                    AdminAction::KickPlayer,
+                    &player_id,
+                    serde_json::json!({ "room_id": room_id }),
+                )
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("玩家 {} 已被管理员 {} 移出房间 {}", player_id, admin.username, room_id)
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        })),
+    }
+}
+
+/// 处理管理员强制结束对局请求：任意阶段的对局都直接结束并回到大厅，不计胜负
+async fn handle_admin_end_game(
+    room_id: String,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let Some(room) = rooms.get(&room_id).map(|entry| entry.value().clone()) else {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "房间不存在"
+        }));
+    };
+
+    match room.force_end_game(&admin.username).await {
+        Ok(()) => {
+            admin_auth
+                .audit(&admin.username, AdminAction::EndGame, &room_id, serde_json::Value::Null)
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("房间 {} 的对局已被管理员 {} 结束", room_id, admin.username)
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        })),
+    }
+}
+
+/// 处理管理员冻结房间请求：暂停对局并拒绝玩家操作，便于调查举报
+async fn handle_admin_freeze_room(
+    room_id: String,
+    query: RoomFreezeQuery,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    let room = match rooms.get(&room_id) {
+        Some(room_entry) => room_entry.value().clone(),
+        None => {
             return Json(serde_json::json!({
                 "success": false,
-                "message": format!("会话验证失败: {}", e)
+                "message": "房间不存在"
             }));
         }
     };
 
-    // 检查是否为管理员
-    let config = crate::config::Config::get();
-    if !config.is_admin(&user.username) {
-        return Json(serde_json::json!({
+    let reason = query
+        .reason
+        .map(|reason| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty())
+        .unwrap_or_else(|| "管理员正在处理举报".to_string());
+    match room.freeze(&admin.username, &reason).await {
+        Ok(freeze) => {
+            admin_auth
+                .audit(
+                    &admin.username,
+                    AdminAction::FreezeRoom,
+                    &room_id,
+                    serde_json::json!({ "reason": reason }),
+                )
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("房间 {} 已被管理员 {} 冻结", room_id, admin.username),
+                "frozen": freeze
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
             "success": false,
-            "message": "权限不足，需要管理员权限"
-        }));
+            "message": e.to_string()
+        })),
     }
+}
+
+/// 处理管理员解除房间冻结请求
+async fn handle_admin_unfreeze_room(
+    room_id: String,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
 
-    // 检查房间是否存在
     let room = match rooms.get(&room_id) {
         Some(room_entry) => room_entry.value().clone(),
         None => {
@@ -1184,13 +4669,103 @@ async fn handle_admin_delete_room(
         }
     };
 
-    // 强制删除房间
-    room.delete().await;
+    match room.unfreeze(&admin.username).await {
+        Ok(paused) => {
+            admin_auth
+                .audit(
+                    &admin.username,
+                    AdminAction::UnfreezeRoom,
+                    &room_id,
+                    serde_json::json!({ "paused_seconds": paused.num_seconds() }),
+                )
+                .await;
+            Json(serde_json::json!({
+                "success": true,
+                "message": format!("房间 {} 已被管理员 {} 解除冻结", room_id, admin.username),
+                "paused_seconds": paused.num_seconds()
+            }))
+        }
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "message": e.to_string()
+        })),
+    }
+}
+
+/// 处理管理员批量清理房间请求，删除同时满足所有给定条件的房间
+async fn handle_admin_cleanup_rooms(
+    query: RoomCleanupQuery,
+    headers: HeaderMap,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    admin_auth: Arc<AdminAuth>,
+) -> Json<serde_json::Value> {
+    let admin = match authorize_admin(&headers, &admin_auth, AdminScope::RoomsManage).await {
+        Ok(admin) => admin,
+        Err(response) => return response,
+    };
+
+    // 至少需要一个过滤条件，避免误删全部房间
+    if query.empty_minutes.is_none() && query.game_over != Some(true) && query.no_connections != Some(true) {
+        return Json(serde_json::json!({
+            "success": false,
+            "message": "请至少指定一个清理条件"
+        }));
+    }
+
+    let candidates: Vec<(String, Arc<Room>)> = rooms
+        .iter()
+        .map(|entry| (entry.key().clone(), entry.value().clone()))
+        .collect();
+
+    let mut removed = Vec::new();
+    for (room_id, room) in candidates {
+        if room.is_deleted().await {
+            continue;
+        }
+
+        let (player_count, idle_seconds, is_game_over, _) = room.get_status().await;
+        let connected_count = room.connected_count();
+
+        let matches_empty = query
+            .empty_minutes
+            .is_none_or(|minutes| room.human_count() == 0 && idle_seconds >= minutes * 60);
+        let matches_game_over = query.game_over != Some(true) || is_game_over;
+        let matches_no_connections = query.no_connections != Some(true) || connected_count == 0;
+
+        if matches_empty && matches_game_over && matches_no_connections {
+            room.delete().await;
+            removed.push(serde_json::json!({
+                "room_id": room_id,
+                "player_count": player_count,
+                "connected_count": connected_count,
+                "idle_seconds": idle_seconds,
+                "is_game_over": is_game_over
+            }));
+        }
+    }
+
+    debug!("管理员 {} 批量清理了 {} 个房间", admin.username, removed.len());
+    if !removed.is_empty() {
+        let room_ids: Vec<&serde_json::Value> = removed.iter().map(|room| &room["room_id"]).collect();
+        admin_auth
+            .audit(
+                &admin.username,
+                AdminAction::CleanupRooms,
+                "",
+                serde_json::json!({
+                    "empty_minutes": query.empty_minutes,
+                    "game_over": query.game_over,
+                    "no_connections": query.no_connections,
+                    "room_ids": room_ids
+                }),
+            )
+            .await;
+    }
 
     Json(serde_json::json!({
         "success": true,
-        "message": format!("房间 {} 已被管理员 {} 强制删除", room_id, user.username)
+        "message": format!("已清理 {} 个房间", removed.len()),
+        "removed": removed,
+        "total_removed": removed.len()
     }))
 }
-
-
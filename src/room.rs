@@ -1,15 +1,27 @@
 use crate::Result;
 use crate::config;
-use crate::game::{GameEvent, GameState, Player, PlayerId, TimeoutResult};
-use crate::message::GameMessage;
-use crate::storage::Storage;
+use crate::game::{
+    ABSTAIN_VOTE, ActionKind, ChatMessage, GameAction, GameClock, GameEvent, GameParams, GameSettings, GameState, LeaveReason,
+    MAX_GAME_ACTIONS, Player, PlayerId, StateRecord, Sticker, TimeoutResult,
+};
+use crate::message::{
+    ChatPayload, ClientCapabilities, ClientMessage, ErrorCode, GameMessage, MessageOutbox, MessageQueue, Notification,
+    NotificationCategory, NotificationDetail, NotificationPreferences, RoomBroadcast, SequencedMessage, ServerMessage,
+    StateChange, UI_EVENT_VERSION, UiEvent,
+};
+use crate::storage::{ChatChannel, GameResult, ReplayEntry, StorageBackend};
+use crate::security::{FilterAction, WordFilter};
+use crate::user::is_guest_id;
 use crate::word_bank::WordBank;
 use chrono::Utc;
-use dashmap::DashMap;
-use std::collections::HashMap;
+use dashmap::{DashMap, DashSet};
+use rand::seq::IndexedRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock, mpsc};
-use tracing::{debug, error, info};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use tokio::sync::{Mutex, RwLock, broadcast, mpsc};
+use tracing::{debug, error, info, warn};
 use std::time::Duration;
 
 /// 房间删除回调函数类型
@@ -18,15 +30,384 @@ pub type RoomDeleteCallback = Box<dyn Fn(String) + Send + Sync>;
 /// 跨房间玩家踢出回调函数类型
 pub type PlayerKickCallback = Box<dyn Fn(String, String) + Send + Sync>;
 
+/// 全服同时进行的对局计数，用于限制并发对局数量
+#[derive(Debug)]
+pub struct GameSlots {
+    /// 上限，None 表示不限制
+    limit: Option<usize>,
+    running: AtomicUsize,
+}
+
+/// 对局占用的名额，释放时自动归还
+#[derive(Debug)]
+pub struct GameSlot {
+    slots: Arc<GameSlots>,
+}
+
+impl GameSlots {
+    pub fn new(limit: Option<usize>) -> Self {
+        GameSlots {
+            limit,
+            running: AtomicUsize::new(0),
+        }
+    }
+
+    /// 按配置创建，max_concurrent_games 为 0 时不限制
+    pub fn from_config() -> Self {
+        let max = crate::config::Config::get().game.max_concurrent_games;
+        Self::new((max > 0).then_some(max))
+    }
+
+    /// 尝试占用一个对局名额，已达上限时返回 None
+    pub fn try_acquire(self: &Arc<Self>) -> Option<GameSlot> {
+        self.running
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |running| {
+                match self.limit {
+                    Some(limit) if running >= limit => None,
+                    _ => Some(running + 1),
+                }
+            })
+            .ok()
+            .map(|_| GameSlot { slots: self.clone() })
+    }
+
+    /// 当前进行中的对局数量
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::Acquire)
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+}
+
+impl Drop for GameSlot {
+    fn drop(&mut self) {
+        self.slots.running.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// 当前对局的回放记录进度
+struct ReplayRecorder {
+    game_id: String,
+    next_seq: u64,
+    /// 对局结束后停止记录，但保留对局ID供保存结果使用
+    finished: bool,
+}
+
+/// 房间类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoomKind {
+    /// 普通房间
+    Normal,
+    /// 新手教程房间，由脚本机器人陪同新玩家练习
+    Tutorial,
+}
+
+/// 房间可见性
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoomVisibility {
+    /// 出现在房间列表中
+    #[default]
+    Public,
+    /// 不出现在房间列表中，新玩家需要房主生成的邀请码才能加入
+    Private,
+}
+
+/// 邀请码使用的字符，去掉了容易混淆的 0/O、1/I
+const INVITE_CODE_CHARSET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// 邀请码长度
+const INVITE_CODE_LEN: usize = 8;
+
+/// 管理员冻结房间的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomFreeze {
+    /// 执行冻结的管理员用户名
+    pub admin: String,
+    pub reason: String,
+    pub frozen_at: chrono::DateTime<Utc>,
+}
+
+/// 房主生成的房间邀请码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomInvite {
+    pub code: String,
+    pub created_by: PlayerId,
+    pub expires_at: chrono::DateTime<Utc>,
+    /// 为true时使用一次后失效
+    pub single_use: bool,
+}
+
+impl RoomFreeze {
+    /// 冻结时广播、以及冻结期间加入的玩家收到的提示
+    fn notice(&self) -> ServerMessage {
+        ServerMessage::RoomFrozen {
+            message: format!("房间已被管理员冻结，原因：{}。对局暂停，请耐心等待", self.reason),
+            reason: self.reason.clone(),
+            frozen_at: self.frozen_at.timestamp(),
+        }
+    }
+}
+
+impl RoomInvite {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= Utc::now()
+    }
+}
+
+/// 玩家网络延迟统计（基于WebSocket ping/pong往返时间）
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PlayerLatency {
+    /// 最近一次往返时间（毫秒）
+    pub last_ms: u64,
+    /// 平滑后的平均往返时间（毫秒）
+    pub average_ms: u64,
+    /// 采样次数
+    pub samples: u32,
+}
+
+/// 管理接口展示的玩家完整信息，对局中也包含身份和词语
+#[derive(Debug, Clone, Serialize)]
+pub struct RosterEntry {
+    #[serde(flatten)]
+    pub player: Player,
+    pub is_host: bool,
+    pub is_bot: bool,
+    pub connected: bool,
+    /// 断线时间，在线时为空
+    pub disconnected_at: Option<chrono::DateTime<Utc>>,
+    /// 自动禁言的解禁时间，未被禁言时为空
+    pub muted_until: Option<chrono::DateTime<Utc>>,
+    pub latency: Option<PlayerLatency>,
+}
+
+/// 系列赛中单名玩家的累计得分
+#[derive(Debug, Clone, Serialize)]
+pub struct SeriesScore {
+    pub player_id: PlayerId,
+    pub player_name: String,
+    pub points: u32,
+}
+
+/// 房间内的多局系列赛（N局制），每局获胜阵营的玩家各得1分，猜词小游戏猜中的玩家额外得1分
+#[derive(Debug, Clone, Serialize)]
+pub struct MatchSeries {
+    pub total_games: u32,
+    pub games_played: u32,
+    /// 按得分从高到低排列
+    pub scores: Vec<SeriesScore>,
+    pub finished: bool,
+}
+
+impl MatchSeries {
+    /// 系列赛最多局数
+    pub const MAX_GAMES: u32 = 9;
+
+    pub fn new(total_games: u32) -> Self {
+        MatchSeries {
+            total_games,
+            games_played: 0,
+            scores: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// 记录一局结果，领先优势已无法被追上或打满局数时结束系列赛
+    pub fn record(&mut self, players: &[Player], winner: crate::game::Role, bonus: &HashSet<PlayerId>) {
+        if self.finished {
+            return;
+        }
+
+        for player in players {
+            let index = match self.scores.iter().position(|s| s.player_id == player.id) {
+                Some(index) => index,
+                None => {
+                    self.scores.push(SeriesScore {
+                        player_id: player.id.clone(),
+                        player_name: player.name.clone(),
+                        points: 0,
+                    });
+                    self.scores.len() - 1
+                }
+            };
+            if player.role.is_some_and(|role| role.wins_with(winner)) {
+                self.scores[index].points += 1;
+            }
+            if bonus.contains(&player.id) {
+                self.scores[index].points += 1;
+            }
+        }
+        self.games_played += 1;
+        self.scores.sort_by_key(|s| std::cmp::Reverse(s.points));
+
+        let remaining = self.total_games - self.games_played;
+        let lead = match self.scores.as_slice() {
+            [first, second, ..] => first.points - second.points,
+            [first] => first.points,
+            [] => 0,
+        };
+        self.finished = remaining == 0 || lead > remaining;
+    }
+
+    /// 当前得分最高的玩家（可能并列）
+    pub fn leaders(&self) -> Vec<&SeriesScore> {
+        let top = self.scores.first().map_or(0, |s| s.points);
+        self.scores.iter().filter(|s| s.points == top).collect()
+    }
+}
+
+/// 游戏结束后进行中的猜词小游戏
+struct WordQuiz {
+    winner: crate::game::Role,
+    /// 参与的真人玩家及其需要猜的词语
+    targets: HashMap<PlayerId, String>,
+    /// 已提交的答案：(猜测, 是否猜中)
+    answers: HashMap<PlayerId, (String, bool)>,
+    deadline: chrono::DateTime<Utc>,
+}
+
+/// 当天使用量达到每日游戏上限的该比例时提醒玩家
+const PLAY_LIMIT_WARNING_RATIO: f64 = 0.8;
+
+/// 判定为持续高延迟所需的最少采样次数
+const SUSTAINED_LATENCY_SAMPLES: u32 = 3;
+
+/// 玩家房间归属租约的有效期（毫秒），持有者异常退出时租约自动过期
+const PLAYER_LEASE_TTL_MS: u64 = 5000;
+
+/// 等待玩家房间归属租约的最长时间
+const PLAYER_LEASE_WAIT: Duration = Duration::from_secs(3);
+
+/// 租约被占用时的重试间隔
+const PLAYER_LEASE_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// 房间广播通道的容量，订阅的连接落后超过该数量时重新发送完整状态
+const ROOM_BROADCAST_CAPACITY: usize = 256;
+
+/// 合并后的玩家消息流的缓冲大小
+const PLAYER_FEED_CAPACITY: usize = 100;
+
+/// 两次状态快照之间最多追加的增量事件数，超过后写入新的快照，限制恢复时需要重放的事件数
+const STATE_SNAPSHOT_INTERVAL: usize = 50;
+
+/// 状态事件日志的写入进度
+#[derive(Default)]
+struct StateLogCursor {
+    /// 重放本实例已写入的日志得到的状态（不含聊天记录），尚未写入快照时为空
+    replayed: Option<GameState>,
+    /// 最近一次快照之后追加的事件数
+    events_since_snapshot: usize,
+}
+
+impl StateLogCursor {
+    /// 计算本次需要写入的记录：待写事件都能在日志状态上依次重放且未到快照间隔时只追加事件，
+    /// 否则写入当前状态的完整快照
+    fn next_records(&mut self, state: &GameState, events: Vec<GameEvent>, force_snapshot: bool) -> Vec<StateRecord> {
+        if !force_snapshot
+            && let Some(replayed) = &self.replayed
+            && self.events_since_snapshot + events.len() <= STATE_SNAPSHOT_INTERVAL
+        {
+            let mut next = replayed.clone();
+            if events.iter().all(|event| next.apply_event(event)) {
+                self.events_since_snapshot += events.len();
+                self.replayed = Some(next);
+                return events.into_iter().map(StateRecord::Event).collect();
+            }
+        }
+
+        let mut snapshot = state.clone();
+        snapshot.restore_chat(Vec::new(), Vec::new());
+        self.replayed = Some(snapshot.clone());
+        self.events_since_snapshot = 0;
+        vec![StateRecord::Snapshot(snapshot)]
+    }
+}
+
+/// 最近一次下发给增量更新客户端的状态
+struct SentState {
+    data: serde_json::Value,
+    /// 最近一次发送完整状态的时间
+    full_sync_at: std::time::Instant,
+}
+
+/// 房间发往玩家的待发消息，合并窗口内的多条消息一并写入玩家通道
+struct Outgoing {
+    queues: DashMap<PlayerId, MessageQueue>,
+    window: Duration,
+    flush_scheduled: AtomicBool,
+    /// 同一时间只有一次刷新在写入通道，避免两次刷新交错打乱消息顺序
+    flushing: Mutex<()>,
+}
+
+impl Outgoing {
+    fn new(window: Duration) -> Self {
+        Self {
+            queues: DashMap::new(),
+            window,
+            flush_scheduled: AtomicBool::new(false),
+            flushing: Mutex::new(()),
+        }
+    }
+
+    fn enqueue(&self, player_id: &PlayerId, message: SequencedMessage) {
+        self.queues
+            .entry(player_id.clone())
+            .or_insert_with(|| MessageQueue::new(self.window))
+            .enqueue(message);
+    }
+
+    /// 把指定玩家（未指定时为全部玩家）的待发消息写入玩家通道，发送失败的通道会被移除
+    async fn flush(
+        &self,
+        player_channels: &DashMap<PlayerId, mpsc::Sender<SequencedMessage>>,
+        only: Option<&PlayerId>,
+    ) {
+        let _flushing = self.flushing.lock().await;
+        let player_ids: Vec<PlayerId> = match only {
+            Some(player_id) => vec![player_id.clone()],
+            None => self.queues.iter().map(|entry| entry.key().clone()).collect(),
+        };
+
+        for player_id in player_ids {
+            let Some(batches) = self.queues.get_mut(&player_id).map(|mut queue| queue.drain()) else {
+                continue;
+            };
+            // 已断线或离开的玩家直接丢弃，补发缓存中仍保留这些消息
+            let Some(channel) = player_channels.get(&player_id).map(|entry| entry.value().clone()) else {
+                self.queues.remove_if(&player_id, |_, queue| queue.is_empty());
+                continue;
+            };
+
+            'batches: for batch in batches {
+                for message in batch.messages {
+                    if let Err(e) = channel.send(message).await {
+                        error!("向玩家 {} 发送消息失败: {}", player_id, e);
+                        debug!("移除失效的玩家通道: {}", player_id);
+                        player_channels.remove_if(&player_id, |_, current| current.same_channel(&channel));
+                        break 'batches;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 表情发送限流的固定时间窗口
+struct StickerWindow {
+    started_at: std::time::Instant,
+    count: u32,
+}
+
 /// 游戏房间，负责管理房间内的玩家和游戏状态
 pub struct Room {
     id: String,
     state: Arc<RwLock<GameState>>,
     players: Arc<DashMap<PlayerId, Player>>,
     word_bank: Arc<WordBank>,
-    player_channels: Arc<DashMap<PlayerId, mpsc::Sender<GameMessage>>>,
+    player_channels: Arc<DashMap<PlayerId, mpsc::Sender<SequencedMessage>>>,
     player_order: Arc<Mutex<Vec<PlayerId>>>,
-    storage: Arc<Storage>,
+    storage: Arc<dyn StorageBackend>,
     last_activity: Arc<Mutex<chrono::DateTime<Utc>>>,
     delete_callback: Option<Arc<RoomDeleteCallback>>,
     player_kick_callback: Option<Arc<PlayerKickCallback>>, // 跨房间玩家踢出回调
@@ -35,20 +416,93 @@ pub struct Room {
     is_new_room: Arc<Mutex<bool>>, // 标记是否为新创建的房间
     is_deleted: Arc<Mutex<bool>>, // 标记房间是否已被删除
     host: Arc<Mutex<PlayerId>>, // 房主ID
+    disconnected_players: Arc<DashMap<PlayerId, chrono::DateTime<Utc>>>, // 断线玩家及断线时间
+    reconnect_grace_period: Duration, // 断线重连宽限时间
+    in_game_grace_period: Duration, // 对局中断线玩家的宽限时间
+    skipped_players: Arc<DashSet<PlayerId>>, // 对局中断线超过宽限时间、被自动跳过的玩家
+    lobby_idle_timeout: Duration, // 大厅无人操作的关闭时间
+    afk_timeout: Duration, // 大厅中未准备玩家的挂机移出时间
+    presence_debounce: Duration, // 同一玩家加入/离开通知的去重窗口
+    presence_notices: Arc<DashMap<PlayerId, chrono::DateTime<Utc>>>, // 玩家最近一次加入/离开通知的时间
+    last_action_at: Arc<Mutex<chrono::DateTime<Utc>>>, // 最近一次玩家操作时间，用于大厅空闲关闭
+    idle_warnings_sent: Arc<Mutex<Vec<u64>>>, // 本次空闲期间已发送的提醒时间点
+    kind: RoomKind, // 房间类型
+    visibility: RoomVisibility, // 房间可见性
+    bots: Arc<DashSet<PlayerId>>, // 机器人玩家ID
+    settings: Arc<RwLock<GameSettings>>, // 房主可调整的房间设置
+    params: GameParams, // 创建房间时确定的游戏参数
+    pending_reveal: Arc<Mutex<Option<chrono::DateTime<Utc>>>>, // 待揭晓淘汰结果的时间点
+    preferences: Arc<DashMap<PlayerId, NotificationPreferences>>, // 玩家通知偏好
+    created_at: chrono::DateTime<Utc>, // 房间创建时间
+    latencies: Arc<DashMap<PlayerId, PlayerLatency>>, // 玩家网络延迟
+    high_latency_threshold: Duration, // 高延迟阈值
+    action_log: Arc<Mutex<VecDeque<GameAction>>>, // 本局玩家操作记录
+    clock: Arc<Mutex<GameClock>>, // 本局各阶段计时
+    series: Arc<Mutex<Option<MatchSeries>>>, // 进行中或刚结束的系列赛
+    stickers: Arc<RwLock<Vec<Sticker>>>, // 房主登记的自定义表情
+    sticker_windows: Arc<DashMap<PlayerId, StickerWindow>>, // 玩家发送表情的限流窗口
+    game_slots: Arc<GameSlots>, // 全服共享的并发对局计数
+    game_slot: Arc<Mutex<Option<GameSlot>>>, // 本局占用的对局名额
+    ratings: Arc<DashMap<PlayerId, i32>>, // 房内真人玩家的等级分缓存
+    replay: Arc<Mutex<Option<ReplayRecorder>>>, // 当前对局的回放记录
+    capabilities: Arc<DashMap<PlayerId, ClientCapabilities>>, // 玩家当前连接声明的协议能力
+    word_filter: Arc<WordFilter>, // 敏感词过滤
+    strikes: Arc<DashMap<PlayerId, u32>>, // 本局玩家敏感词违规次数
+    muted_until: Arc<DashMap<PlayerId, chrono::DateTime<Utc>>>, // 被自动禁言的玩家及解禁时间
+    last_states: Arc<DashMap<PlayerId, SentState>>, // 最近一次下发的状态，用于计算增量更新
+    outboxes: Arc<DashMap<PlayerId, MessageOutbox>>, // 发往玩家的消息序号和未确认的最近消息，用于重连后补发
+    outgoing: Arc<Outgoing>, // 按合并窗口分批的待发消息
+    room_events: broadcast::Sender<RoomBroadcast>, // 房间范围的广播，玩家连接各自订阅
+    resume_tokens: Arc<DashMap<PlayerId, String>>, // 主备切换后玩家重新接入使用的恢复令牌
+    first_speakers: Arc<Mutex<HashMap<PlayerId, u32>>>, // 本房间各玩家在往局中首个描述的次数
+    word_quiz: Arc<Mutex<Option<WordQuiz>>>, // 游戏结束后进行中的猜词小游戏
+    invites: Arc<DashMap<String, RoomInvite>>, // 尚未失效的邀请码
+    frozen: Arc<Mutex<Option<RoomFreeze>>>, // 管理员冻结记录，冻结期间暂停计时并拒绝玩家操作
+    pending_events: Arc<Mutex<Vec<GameEvent>>>, // 尚未写入状态事件日志的游戏事件，按发生先后排列
+    state_log: Arc<Mutex<StateLogCursor>>, // 状态事件日志的写入进度
+}
+
+/// 主实例定期写入Redis的房间检查点，备用实例接管时据此恢复房间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomCheckpoint {
+    pub room_id: String,
+    pub kind: RoomKind,
+    #[serde(default)]
+    pub visibility: RoomVisibility,
+    pub params: GameParams,
+    pub settings: GameSettings,
+    pub host: PlayerId,
+    pub state: GameState,
+    pub players: Vec<Player>,
+    pub player_order: Vec<PlayerId>,
+    pub bots: Vec<PlayerId>,
+    pub stickers: Vec<Sticker>,
+    pub resume_tokens: HashMap<PlayerId, String>,
+    #[serde(default)]
+    pub invites: Vec<RoomInvite>,
+    #[serde(default)]
+    pub frozen: Option<RoomFreeze>,
+    pub created_at: chrono::DateTime<Utc>,
+    /// 写入检查点的实例
+    pub instance_id: String,
+    pub saved_at: chrono::DateTime<Utc>,
 }
 
 impl Room {
     /// 创建新房间
     pub fn new(
         id: String,
-        min_players: usize,
-        max_players: usize,
+        params: GameParams,
         word_bank: Arc<WordBank>,
-        storage: Arc<Storage>,
+        storage: Arc<dyn StorageBackend>,
         host: PlayerId,
     ) -> Self {
         let config = crate::config::Config::get();
-        let state = Arc::new(RwLock::new(GameState::new(min_players, max_players, host.clone())));
+        let state = Arc::new(RwLock::new(GameState::new(
+            params.min_players,
+            params.max_players,
+            host.clone(),
+        )));
 
         Room {
             id,
@@ -66,9 +520,440 @@ impl Room {
             is_new_room: Arc::new(Mutex::new(true)),
             is_deleted: Arc::new(Mutex::new(false)),
             host: Arc::new(Mutex::new(host)),
+            disconnected_players: Arc::new(DashMap::new()),
+            reconnect_grace_period: config.reconnect_grace_period(),
+            in_game_grace_period: config.in_game_grace_period(),
+            skipped_players: Arc::new(DashSet::new()),
+            lobby_idle_timeout: config.lobby_idle_timeout(),
+            afk_timeout: config.afk_timeout(),
+            presence_debounce: config.presence_debounce(),
+            presence_notices: Arc::new(DashMap::new()),
+            last_action_at: Arc::new(Mutex::new(Utc::now())),
+            idle_warnings_sent: Arc::new(Mutex::new(Vec::new())),
+            kind: RoomKind::Normal,
+            visibility: RoomVisibility::Public,
+            bots: Arc::new(DashSet::new()),
+            settings: Arc::new(RwLock::new(GameSettings::default())),
+            params,
+            pending_reveal: Arc::new(Mutex::new(None)),
+            preferences: Arc::new(DashMap::new()),
+            created_at: Utc::now(),
+            latencies: Arc::new(DashMap::new()),
+            high_latency_threshold: config.high_latency_threshold(),
+            action_log: Arc::new(Mutex::new(VecDeque::new())),
+            clock: Arc::new(Mutex::new(GameClock::default())),
+            series: Arc::new(Mutex::new(None)),
+            stickers: Arc::new(RwLock::new(Vec::new())),
+            sticker_windows: Arc::new(DashMap::new()),
+            game_slots: Arc::new(GameSlots::new(None)),
+            game_slot: Arc::new(Mutex::new(None)),
+            ratings: Arc::new(DashMap::new()),
+            replay: Arc::new(Mutex::new(None)),
+            capabilities: Arc::new(DashMap::new()),
+            word_filter: Arc::new(WordFilter::from_config()),
+            strikes: Arc::new(DashMap::new()),
+            muted_until: Arc::new(DashMap::new()),
+            last_states: Arc::new(DashMap::new()),
+            outboxes: Arc::new(DashMap::new()),
+            outgoing: Arc::new(Outgoing::new(config.batch_window())),
+            room_events: broadcast::channel(ROOM_BROADCAST_CAPACITY).0,
+            resume_tokens: Arc::new(DashMap::new()),
+            first_speakers: Arc::new(Mutex::new(HashMap::new())),
+            word_quiz: Arc::new(Mutex::new(None)),
+            invites: Arc::new(DashMap::new()),
+            frozen: Arc::new(Mutex::new(None)),
+            pending_events: Arc::new(Mutex::new(Vec::new())),
+            state_log: Arc::new(Mutex::new(StateLogCursor::default())),
+        }
+    }
+
+    /// 从检查点恢复房间，所有真人玩家先标记为断线，等待其使用恢复令牌重新接入
+    pub async fn from_checkpoint(
+        checkpoint: RoomCheckpoint,
+        word_bank: Arc<WordBank>,
+        storage: Arc<dyn StorageBackend>,
+    ) -> Self {
+        let mut room = Room::new(
+            checkpoint.room_id,
+            checkpoint.params,
+            word_bank,
+            storage,
+            checkpoint.host,
+        );
+        room.set_kind(checkpoint.kind);
+        room.set_visibility(checkpoint.visibility);
+        room.set_settings(checkpoint.settings);
+        room.created_at = checkpoint.created_at;
+        let mut state = checkpoint.state;
+        room.restore_chat(&mut state).await;
+        *room.state.write().await = state;
+        *room.player_order.lock().await = checkpoint.player_order;
+        *room.stickers.write().await = checkpoint.stickers;
+        *room.is_new_room.lock().await = false;
+        for bot in checkpoint.bots {
+            room.bots.insert(bot);
+        }
+        let now = Utc::now();
+        for player in checkpoint.players {
+            if !room.bots.contains(&player.id) {
+                room.disconnected_players.insert(player.id.clone(), now);
+            }
+            room.players.insert(player.id.clone(), player);
+        }
+        for (player_id, token) in checkpoint.resume_tokens {
+            room.resume_tokens.insert(player_id, token);
+        }
+        for invite in checkpoint.invites {
+            room.invites.insert(invite.code.clone(), invite);
+        }
+        *room.frozen.lock().await = checkpoint.frozen;
+        room
+    }
+
+    /// 服务重启后根据Redis中保存的游戏状态重建房间，没有状态或状态中没有房主时返回 `None`
+    ///
+    /// 状态只包含玩家和对局进度：房间人数限制取自大厅状态，其余游戏参数和房间设置使用全局配置。
+    /// 所有玩家先标记为断线，重新连接后继续游戏。
+    pub async fn from_saved_state(
+        room_id: String,
+        word_bank: Arc<WordBank>,
+        storage: Arc<dyn StorageBackend>,
+    ) -> Result<Option<Self>> {
+        let mut room = Room::new(room_id, GameParams::from_config(), word_bank, storage, PlayerId::new());
+        room.load_state().await?;
+
+        let state = room.state.read().await.clone();
+        let Some(host) = state.get_host() else {
+            return Ok(None);
+        };
+        if let GameState::Lobby { min_players, max_players, .. } = &state {
+            room.params.min_players = *min_players;
+            room.params.max_players = *max_players;
+        }
+        *room.host.lock().await = host;
+        *room.is_new_room.lock().await = false;
+
+        let now = Utc::now();
+        let players = state.get_players_with_roles();
+        *room.player_order.lock().await = players.iter().map(|player| player.id.clone()).collect();
+        for player in players {
+            room.disconnected_players.insert(player.id.clone(), now);
+            room.players.insert(player.id.clone(), player);
+        }
+        Ok(Some(room))
+    }
+
+    /// 设置房间初始设置
+    pub fn set_settings(&mut self, settings: GameSettings) {
+        self.settings = Arc::new(RwLock::new(settings));
+    }
+
+    /// 设置房间类型
+    pub fn set_kind(&mut self, kind: RoomKind) {
+        self.kind = kind;
+    }
+
+    /// 获取房间类型
+    pub fn kind(&self) -> RoomKind {
+        self.kind
+    }
+
+    /// 设置房间可见性
+    pub fn set_visibility(&mut self, visibility: RoomVisibility) {
+        self.visibility = visibility;
+    }
+
+    /// 获取房间可见性
+    pub fn visibility(&self) -> RoomVisibility {
+        self.visibility
+    }
+
+    /// 是否出现在公开房间列表中：教程房间和私密房间都不对外展示
+    pub fn is_listed(&self) -> bool {
+        self.kind != RoomKind::Tutorial && self.visibility == RoomVisibility::Public
+    }
+
+    /// 房间是否已被管理员冻结
+    pub async fn is_frozen(&self) -> bool {
+        self.frozen.lock().await.is_some()
+    }
+
+    /// 获取当前的冻结记录
+    pub async fn freeze_info(&self) -> Option<RoomFreeze> {
+        self.frozen.lock().await.clone()
+    }
+
+    /// 冻结期间只允许加入（含重连）和只读查询，其余操作一律拒绝
+    async fn check_frozen(&self, message_type: &str) -> Result<()> {
+        if matches!(message_type, "join" | "query" | "list_categories") {
+            return Ok(());
+        }
+        match &*self.frozen.lock().await {
+            Some(freeze) => Err(crate::Error::Rule(
+                ErrorCode::RoomFrozen,
+                format!("房间已被管理员冻结（{}），暂时不能操作", freeze.reason),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// 管理员冻结房间：暂停对局计时、拒绝玩家操作，连接保持不变
+    pub async fn freeze(&self, admin: &str, reason: &str) -> Result<RoomFreeze> {
+        let mut frozen = self.frozen.lock().await;
+        if frozen.is_some() {
+            return Err(crate::Error::Rule(ErrorCode::InvalidState, "房间已处于冻结状态".to_string()));
+        }
+        let freeze = RoomFreeze {
+            admin: admin.to_string(),
+            reason: reason.to_string(),
+            frozen_at: Utc::now(),
+        };
+        *frozen = Some(freeze.clone());
+        drop(frozen);
+
+        info!("房间 {} 被管理员 {} 冻结: {}", self.id, admin, reason);
+        self.broadcast(freeze.notice()).await;
+        Ok(freeze)
+    }
+
+    /// 解除冻结：各项计时顺延冻结时长，对局从暂停处继续
+    pub async fn unfreeze(&self, admin: &str) -> Result<chrono::Duration> {
+        let Some(freeze) = self.frozen.lock().await.take() else {
+            return Err(crate::Error::Rule(ErrorCode::InvalidState, "房间未被冻结".to_string()));
+        };
+        let paused = Utc::now() - freeze.frozen_at;
+
+        self.state.write().await.postpone_deadline(paused);
+        if let Some(reveal_at) = self.pending_reveal.lock().await.as_mut() {
+            *reveal_at += paused;
+        }
+        if let Some(quiz) = self.word_quiz.lock().await.as_mut() {
+            quiz.deadline += paused;
+        }
+        for mut entry in self.disconnected_players.iter_mut() {
+            *entry.value_mut() += paused;
+        }
+        self.touch_lobby().await;
+        // 顺延截止时间不经过游戏事件，写入快照保证恢复后的倒计时一致
+        self.save_state_snapshot().await?;
+
+        info!("房间 {} 被管理员 {} 解除冻结，共暂停 {} 秒", self.id, admin, paused.num_seconds());
+        self.broadcast(ServerMessage::RoomUnfrozen {
+            message: "房间已解除冻结，对局继续".to_string(),
+            paused_seconds: paused.num_seconds(),
+        })
+        .await;
+        self.broadcast_state_update().await;
+        Ok(paused)
+    }
+
+    /// 管理员强制结束卡住的对局：先按胜方为 aborted 的游戏结束公布身份和词语，
+    /// 再把房间重置到大厅，不记录战绩和等级分
+    pub async fn force_end_game(&self, admin: &str) -> Result<()> {
+        let players = {
+            let mut state = self.state.write().await;
+            let players = state.get_players_with_roles();
+            state
+                .abort_game(&self.params)
+                .map_err(|e| crate::Error::Rule(ErrorCode::InvalidState, e))?;
+            players
+        };
+
+        // 归还对局名额，丢弃尚未揭晓的结果和猜词小游戏，回放到此为止
+        self.game_slot.lock().await.take();
+        *self.pending_reveal.lock().await = None;
+        *self.word_quiz.lock().await = None;
+        if let Some(recorder) = self.replay.lock().await.as_mut() {
+            recorder.finished = true;
+        }
+        self.skipped_players.clear();
+        self.touch_lobby().await;
+
+        info!("房间 {} 的对局被管理员 {} 强制结束", self.id, admin);
+        self.broadcast(ServerMessage::StateUpdate(serde_json::json!({
+            "state": crate::message::GameStateType::GameOver,
+            "winner": "aborted",
+            "aborted_by": admin,
+            "players": players.iter().map(|player| {
+                serde_json::json!({
+                    "id": player.id.to_string(),
+                    "name": player.name,
+                    "is_alive": player.is_alive,
+                    "role": player.role.map(|r| r.to_string()),
+                    "word": player.word.clone()
+                })
+            }).collect::<Vec<_>>(),
+            "total_players": players.len(),
+            "game_id": self.current_game_id().await
+        })))
+        .await;
+        self.broadcast(
+            Notification::new(format!("本局已被管理员 {} 强制结束，不计胜负，所有玩家回到大厅", admin))
+                .ui_event(UiEvent::GameOver)
+                .detail(NotificationDetail::GameAborted { ended_by_admin: true })
+                .into(),
+        )
+        .await;
+
+        // 终局消息之后重新下发完整状态，避免增量更新基于结束前的状态
+        self.last_states.clear();
+        self.broadcast_state_update().await;
+        self.save_state_snapshot().await
+    }
+
+    /// 管理接口使用的完整玩家名单，按入座顺序排列
+    pub async fn roster(&self) -> Vec<RosterEntry> {
+        let players = self.state.read().await.get_players_with_roles();
+        let order = self.player_order.lock().await.clone();
+        let host = self.get_host().await;
+        let latencies = self.latency_snapshot();
+
+        let mut roster: Vec<RosterEntry> = players
+            .into_iter()
+            .map(|player| RosterEntry {
+                is_host: player.id == host,
+                is_bot: self.is_bot(&player.id),
+                connected: self
+                    .player_channels
+                    .get(&player.id)
+                    .is_some_and(|channel| !channel.is_closed()),
+                disconnected_at: self.disconnected_players.get(&player.id).map(|at| *at),
+                muted_until: self
+                    .muted_until
+                    .get(&player.id)
+                    .map(|until| *until)
+                    .filter(|until| *until > Utc::now()),
+                latency: latencies.get(&player.id).copied(),
+                player,
+            })
+            .collect();
+        roster.sort_by_key(|entry| {
+            order
+                .iter()
+                .position(|id| *id == entry.player.id)
+                .unwrap_or(usize::MAX)
+        });
+        roster
+    }
+
+    /// 房主生成邀请码，未指定有效期时使用默认值，超过上限时截断
+    pub async fn create_invite(
+        &self,
+        requester: &PlayerId,
+        single_use: bool,
+        ttl: Option<Duration>,
+    ) -> Result<RoomInvite> {
+        if self.kind == RoomKind::Tutorial {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "教程房间不能生成邀请码".to_string()));
+        }
+        if !self.is_host(requester).await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "只有房主可以生成邀请码".to_string()));
+        }
+
+        // 顺带清理已过期的邀请码
+        self.invites.retain(|_, invite| !invite.is_expired());
+
+        let config = config::Config::get();
+        let ttl = ttl
+            .unwrap_or(config.invite_ttl())
+            .clamp(Duration::from_secs(60), config.max_invite_ttl().max(Duration::from_secs(60)));
+        let mut rng = rand::rng();
+        let code: String = (0..INVITE_CODE_LEN)
+            .map(|_| *INVITE_CODE_CHARSET.choose(&mut rng).unwrap_or(&b'A') as char)
+            .collect();
+        let invite = RoomInvite {
+            code: code.clone(),
+            created_by: requester.clone(),
+            expires_at: Utc::now() + chrono::Duration::from_std(ttl).unwrap_or_default(),
+            single_use,
+        };
+        self.invites.insert(code, invite.clone());
+        Ok(invite)
+    }
+
+    /// 校验新玩家加入私密房间时提供的邀请码，一次性邀请码会被取出，加入失败时需放回
+    async fn redeem_invite(&self, player_id: &PlayerId, code: Option<&str>) -> Result<Option<RoomInvite>> {
+        if self.visibility != RoomVisibility::Private || self.is_bot(player_id) || self.is_host(player_id).await {
+            return Ok(None);
+        }
+
+        let code = code.map(|code| code.trim().to_uppercase()).unwrap_or_default();
+        let invite = self.invites.get(&code).map(|entry| entry.value().clone());
+        match invite {
+            Some(invite) if !invite.is_expired() => {
+                if invite.single_use {
+                    // 同时使用同一个一次性邀请码时只有一个玩家能取出，其余玩家视为邀请码已被使用
+                    return match self.invites.remove(&code) {
+                        Some((_, invite)) => Ok(Some(invite)),
+                        None => Err(crate::Error::Rule(ErrorCode::InvalidInvite, "邀请码无效或已被使用".to_string())),
+                    };
+                }
+                Ok(None)
+            }
+            Some(_) => {
+                self.invites.remove(&code);
+                Err(crate::Error::Rule(ErrorCode::InvalidInvite, "邀请码已过期".to_string()))
+            }
+            None if code.is_empty() => Err(crate::Error::Rule(
+                ErrorCode::InvalidInvite,
+                "私密房间需要邀请码才能加入".to_string(),
+            )),
+            None => Err(crate::Error::Rule(ErrorCode::InvalidInvite, "邀请码无效或已被使用".to_string())),
+        }
+    }
+
+    /// 登记机器人玩家，机器人不计入真人玩家数量
+    pub(crate) fn register_bot(&self, player_id: PlayerId) {
+        self.bots.insert(player_id);
+    }
+
+    /// 登记通过API密钥接入的外部机器人，教程房间不允许接入，且每个房间有数量上限
+    pub(crate) fn register_external_bot(&self, player_id: PlayerId) -> Result<()> {
+        if self.kind == RoomKind::Tutorial {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "教程房间不允许机器人接入".to_string()));
+        }
+        if self.bots.contains(&player_id) {
+            return Ok(());
+        }
+        let max_bots = crate::config::Config::get().bots.max_per_room;
+        if self.bots.len() >= max_bots {
+            return Err(crate::Error::Rule(
+                ErrorCode::RoomFull,
+                format!("房间机器人数量已达上限（{}个）", max_bots),
+            ));
+        }
+        self.bots.insert(player_id);
+        Ok(())
+    }
+
+    /// 外部机器人断开且未加入房间时撤销登记，避免占用机器人名额
+    pub(crate) fn release_external_bot(&self, player_id: &PlayerId) {
+        if !self.players.contains_key(player_id) {
+            self.bots.remove(player_id);
         }
     }
 
+    /// 检查玩家是否为机器人
+    pub fn is_bot(&self, player_id: &PlayerId) -> bool {
+        self.bots.contains(player_id)
+    }
+
+    /// 获取房间内在线的真人玩家连接数量
+    pub fn connected_count(&self) -> usize {
+        self.player_channels
+            .iter()
+            .filter(|entry| !self.bots.contains(entry.key()))
+            .count()
+    }
+
+    /// 获取房间内真人玩家数量
+    pub fn human_count(&self) -> usize {
+        self.players
+            .iter()
+            .filter(|entry| !self.bots.contains(entry.key()))
+            .count()
+    }
+
     /// 设置房间删除回调
     pub fn set_delete_callback(&mut self, callback: RoomDeleteCallback) {
         self.delete_callback = Some(Arc::new(callback));
@@ -79,6 +964,11 @@ impl Room {
         self.player_kick_callback = Some(Arc::new(callback));
     }
 
+    /// 设置全服共享的并发对局计数
+    pub fn set_game_slots(&mut self, game_slots: Arc<GameSlots>) {
+        self.game_slots = game_slots;
+    }
+
     /// 获取房间ID
     pub fn id(&self) -> &str {
         &self.id
@@ -121,13 +1011,19 @@ impl Room {
         let idle_duration = Duration::from_secs(idle_time.num_seconds() as u64);
         
         // 房间为空且超过最大空闲时间，或者房间状态为游戏结束且超过空闲时间
-        let is_empty = self.players.is_empty();
+        // 只剩机器人的房间同样视为空房间
+        let is_empty = self.human_count() == 0;
         let is_new = *self.is_new_room.lock().await;
         let state = self.state.read().await;
         let is_game_over = matches!(*state, crate::game::GameState::GameOver { .. });
         drop(state);
         
-        let should_delete = (is_empty && !is_new) || (idle_duration > self.max_idle_time) && is_empty;
+        // 有玩家但在大厅长时间无人操作的房间同样关闭
+        let lobby_idle = self
+            .lobby_idle_duration()
+            .await
+            .is_some_and(|idle| idle >= self.lobby_idle_timeout);
+        let should_delete = (is_empty && (!is_new || idle_duration > self.max_idle_time)) || lobby_idle;
         
         // 添加详细的调试信息
         debug!(
@@ -144,6 +1040,61 @@ impl Room {
         should_delete
     }
 
+    /// 大厅或游戏结束后无人操作的时长，游戏进行中或房间内没有真人玩家时返回None
+    async fn lobby_idle_duration(&self) -> Option<Duration> {
+        if self.human_count() == 0 {
+            return None;
+        }
+        let state_type = self.state.read().await.get_state_type();
+        if !matches!(
+            state_type,
+            crate::message::GameStateType::Lobby | crate::message::GameStateType::GameOver
+        ) {
+            return None;
+        }
+        let idle = Utc::now() - *self.last_action_at.lock().await;
+        Some(idle.to_std().unwrap_or_default())
+    }
+
+    /// 记录玩家操作，重新开始大厅空闲计时
+    async fn touch_lobby(&self) {
+        *self.last_action_at.lock().await = Utc::now();
+        self.idle_warnings_sent.lock().await.clear();
+    }
+
+    /// 到达提醒时间点时广播房间即将因空闲关闭的提醒，只发送最紧迫的一条
+    async fn warn_lobby_idle(&self) {
+        let Some(idle) = self.lobby_idle_duration().await else {
+            return;
+        };
+        let remaining = self.lobby_idle_timeout.saturating_sub(idle).as_secs();
+
+        let mut sent = self.idle_warnings_sent.lock().await;
+        let due: Vec<u64> = config::Config::get()
+            .game
+            .idle_warning_thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| remaining <= *threshold && !sent.contains(threshold))
+            .collect();
+        if due.is_empty() {
+            return;
+        }
+        sent.extend(&due);
+        drop(sent);
+
+        let remaining_text = if remaining >= 60 {
+            format!("{} 分钟", remaining / 60)
+        } else {
+            format!("{} 秒", remaining)
+        };
+        self.broadcast(ServerMessage::IdleWarning {
+            message: format!("房间长时间无人操作，将在 {}后关闭", remaining_text),
+            seconds_left: remaining,
+        })
+        .await;
+    }
+
     /// 删除房间
     pub async fn delete(&self) {
         // 检查是否已经被删除
@@ -157,18 +1108,19 @@ impl Room {
         }
         
         info!("删除房间: {}", self.id);
+        self.game_slot.lock().await.take();
         
         // 通知所有玩家房间即将关闭
-        self.broadcast(GameMessage {
-            type_: "room_closing".to_string(),
-            data: serde_json::json!({
-                "message": "房间即将关闭"
-            }),
+        self.broadcast(ServerMessage::RoomClosing {
+            message: "房间即将关闭".to_string(),
         }).await;
         
-        // 保存最终状态
-        if let Err(e) = self.save_state().await {
-            error!("保存房间最终状态失败: {}", e);
+        // 删除保存的状态，重启时不再恢复已关闭的房间
+        if let Err(e) = self.storage.delete_room_state(&self.id).await {
+            error!("删除房间状态失败: {}", e);
+        }
+        if let Err(e) = self.storage.delete_room_checkpoint(&self.id).await {
+            error!("删除房间检查点失败: {}", e);
         }
         
         // 调用删除回调
@@ -188,6 +1140,11 @@ impl Room {
             loop {
                 tokio::select! {
                     _ = heartbeat_interval.tick() => {
+                        // 冻结期间保留房间和连接，暂停所有计时
+                        if self.is_frozen().await {
+                            continue;
+                        }
+
                         if self.should_be_deleted().await {
                             self.delete().await;
                             break;
@@ -197,10 +1154,39 @@ impl Room {
                         if let Err(e) = self.check_timeout().await {
                             error!("检查房间 {} 超时失败: {}", self.id, e);
                         }
+
+                        // 清理超过宽限时间仍未重连的玩家
+                        if let Err(e) = self.expire_disconnected_players().await {
+                            error!("清理房间 {} 断线玩家失败: {}", self.id, e);
+                        }
+
+                        // 移出大厅中长时间未准备也未操作的玩家
+                        if let Err(e) = self.expire_afk_players().await {
+                            error!("清理房间 {} 挂机玩家失败: {}", self.id, e);
+                        }
                     }
                     _ = countdown_interval.tick() => {
+                        if self.is_frozen().await {
+                            continue;
+                        }
+
                         // 更新倒计时并广播
-                        if let Some(_) = self.update_countdown().await {
+                        self.update_countdown().await;
+
+                        // 大厅空闲即将关闭时提醒玩家
+                        self.warn_lobby_idle().await;
+
+                        // 到达揭晓时间后公布淘汰结果
+                        if let Err(e) = self.process_pending_reveal().await {
+                            error!("揭晓房间 {} 淘汰结果失败: {}", self.id, e);
+                        }
+
+                        // 猜词小游戏超时后公布答案
+                        self.expire_word_quiz().await;
+
+                        // 对局中跳过断线超过宽限时间的玩家
+                        if let Err(e) = self.skip_absent_players().await {
+                            error!("跳过房间 {} 断线玩家失败: {}", self.id, e);
                         }
                     }
                 }
@@ -209,34 +1195,72 @@ impl Room {
         });
     }
 
-    /// 添加玩家到房间
-    pub async fn add_player(
-        &self,
-        player: &Player,
-        channel: mpsc::Sender<GameMessage>,
+    /// 获取玩家房间归属租约，被其他房间占用时短暂重试
+    ///
+    /// 同一玩家的加入、离开、踢出在所有房间（包括其他实例）之间串行执行。
+    async fn acquire_player_lease(&self, player_id: &str) -> Result<String> {
+        let deadline = tokio::time::Instant::now() + PLAYER_LEASE_WAIT;
+        loop {
+            if let Some(token) = self
+                .storage
+                .try_acquire_player_lease(player_id, PLAYER_LEASE_TTL_MS)
+                .await
+                .map_err(|e| crate::Error::Storage(e.to_string()))?
+            {
+                return Ok(token);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(crate::Error::Room("玩家正在切换房间，请稍后再试".to_string()));
+            }
+            tokio::time::sleep(PLAYER_LEASE_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// 释放玩家房间归属租约，失败时等待租约自然过期
+    async fn release_player_lease(&self, player_id: &str, token: &str) {
+        if let Err(e) = self.storage.release_player_lease(player_id, token).await {
+            error!("释放玩家 {} 房间租约失败: {}", player_id, e);
+        }
+    }
+
+    /// 在租约保护下把玩家的房间信息改为本房间，返回玩家之前所在的其他房间
+    ///
+    /// 租约只覆盖房间信息的读写，不能在持有租约时等待房间状态锁或广播。
+    async fn claim_player_room(&self, player: &Player) -> Result<Option<String>> {
+        let token = self.acquire_player_lease(&player.id).await?;
+        let result: Result<Option<String>> = async {
+            let previous = self.storage.get_player_current_room(&player.id).await?;
+            self.storage.save_player_room_info(&player.id, &player.name, &self.id).await?;
+            Ok(previous.filter(|room_id| *room_id != self.id))
+        }
+        .await;
+        self.release_player_lease(&player.id, &token).await;
+        result
+    }
+
+    /// 在租约保护下清理玩家房间信息，仅当玩家仍归属本房间时才会清理
+    async fn release_player_room(&self, player_id: &str) -> Result<()> {
+        let token = self.acquire_player_lease(player_id).await?;
+        let result = self
+            .storage
+            .clear_player_room_info_if(player_id, &self.id)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()));
+        self.release_player_lease(player_id, &token).await;
+        result.map(|_| ())
+    }
+
+    /// 添加玩家到房间
+    pub async fn add_player(
+        &self,
+        player: &Player,
+        channel: mpsc::Sender<SequencedMessage>,
     ) -> Result<()> {
         // 检查房间是否已被删除
         if *self.is_deleted.lock().await {
             return Err(crate::Error::Game("房间已被删除".to_string()));
         }
-        
-        // 检查玩家是否已在其他房间，如果是则自动离开原房间
-        if let Some(other_room_id) = self.storage.get_player_current_room(&player.id).await? {
-            if other_room_id != self.id {
-                debug!("玩家 {} 已在房间 {} 中，自动离开原房间", player.name, other_room_id);
-                
-                // 调用跨房间玩家踢出回调
-                if let Some(callback) = &self.player_kick_callback {
-                    callback(player.id.clone(), other_room_id.clone());
-                }
-                
-                // 清理玩家在原房间的信息，允许加入新房间
-                if let Err(e) = self.storage.clear_player_room_info(&player.id).await {
-                    error!("清理玩家原房间信息失败: {}", e);
-                }
-            }
-        }
-        
+
         // 检查玩家是否已经存在
         if self.players.contains_key(&player.id) {
             debug!("玩家 {} 已经存在于房间中，更新连接通道", player.name);
@@ -245,64 +1269,374 @@ impl Room {
                 self.player_channels.remove(&player.id);
             }
             self.player_channels.insert(player.id.clone(), channel);
+            self.disconnected_players.remove(&player.id);
+            if !self.is_bot(&player.id) {
+                self.switch_player_room(player).await;
+            }
             return Ok(());
         }
 
-        let mut state = self.state.write().await;
-        let event = state
-            .add_player(player.clone())
-            .map_err(|e| crate::Error::Game(e))?;
+        let event = {
+            let mut state = self.state.write().await;
+            let event = state
+                .add_player(player.clone())
+                .map_err(crate::Error::Game)?;
 
-        self.players.insert(player.id.clone(), player.clone());
+            self.players.insert(player.id.clone(), player.clone());
 
-        if self.player_channels.contains_key(&player.id) {
-            self.player_channels.remove(&player.id);
-        }
+            if self.player_channels.contains_key(&player.id) {
+                self.player_channels.remove(&player.id);
+            }
 
-        self.player_channels.insert(player.id.clone(), channel);
-        self.player_order.lock().await.push(player.id.clone());
+            self.player_channels.insert(player.id.clone(), channel);
+            self.player_order.lock().await.push(player.id.clone());
+            event
+        };
 
-        // 保存玩家房间信息到存储
-        if let Err(e) = self.storage.save_player_room_info(&player.id, &player.name, &self.id).await {
-            error!("保存玩家房间信息失败: {}", e);
+        // 保存玩家房间信息到存储并读取等级分（机器人不会跨房间，不需要）
+        if !self.is_bot(&player.id) {
+            self.switch_player_room(player).await;
+            match self.storage.get_rating(&player.id).await {
+                Ok(rating) => {
+                    self.ratings.insert(player.id.clone(), rating.rating);
+                }
+                Err(e) => error!("读取玩家 {} 等级分失败: {}", player.id, e),
+            }
         }
 
-        // 当第一个玩家加入时，标记房间不再是新房间
-        if self.players.len() == 1 {
+        // 当第一个真人玩家加入时，标记房间不再是新房间
+        if !self.is_bot(&player.id) && self.human_count() == 1 {
             let mut is_new = self.is_new_room.lock().await;
             *is_new = false;
         }
 
         // 处理事件
-        drop(state);
         Box::pin(self.handle_game_event(event)).await?;
 
         Ok(())
     }
 
+    /// 把玩家的房间信息改为本房间，玩家仍在其他房间时通知原房间让其离开
+    async fn switch_player_room(&self, player: &Player) {
+        match self.claim_player_room(player).await {
+            Ok(Some(other_room_id)) => {
+                debug!("玩家 {} 从房间 {} 切换到房间 {}", player.name, other_room_id, self.id);
+                // 原房间清理房间信息时发现已归属本房间，会保留本房间的信息
+                if let Some(callback) = &self.player_kick_callback {
+                    callback(player.id.clone(), other_room_id);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => error!("保存玩家房间信息失败: {}", e),
+        }
+    }
+
     /// 从房间移除玩家
-    pub async fn remove_player(&self, player_id: PlayerId) -> Result<()> {
-        let mut state = self.state.write().await;
-        let event = state
-            .remove_player(player_id.clone())
-            .map_err(|e| crate::Error::Game(e))?;
+    pub async fn remove_player(&self, player_id: PlayerId, reason: LeaveReason) -> Result<()> {
+        // 只在修改游戏状态时持有写锁，清理房间信息需要等待玩家租约
+        let event = self
+            .state
+            .write()
+            .await
+            .remove_player(player_id.clone(), reason)
+            .map_err(crate::Error::Game)?;
 
         self.players.remove(&player_id);
         self.player_channels.remove(&player_id);
+        self.disconnected_players.remove(&player_id);
+        self.skipped_players.remove(&player_id);
+        self.preferences.remove(&player_id);
+        self.latencies.remove(&player_id);
+        self.sticker_windows.remove(&player_id);
+        self.ratings.remove(&player_id);
+        self.capabilities.remove(&player_id);
+        self.last_states.remove(&player_id);
+        self.outboxes.remove(&player_id);
+        self.resume_tokens.remove(&player_id);
+        self.bots.remove(&player_id);
         self.player_order.lock().await.retain(|id| id != &player_id);
 
-        // 清理玩家房间信息
-        if let Err(e) = self.storage.clear_player_room_info(&player_id).await {
+        // 清理玩家房间信息（玩家已切换到其他房间时保留新房间的信息）
+        if let Err(e) = self.release_player_room(&player_id).await {
             error!("清理玩家房间信息失败: {}", e);
         }
 
         // 处理事件
-        drop(state);
         Box::pin(self.handle_game_event(event)).await?;
 
+        // 房主离开时自动转交给入座最久的玩家
+        self.transfer_host_from(&player_id).await?;
+
+        Ok(())
+    }
+
+    /// 标记玩家断线，在宽限时间内重连可保留准备状态，且不广播离开通知
+    pub async fn mark_disconnected(&self, player_id: &PlayerId) {
+        if !self.players.contains_key(player_id) {
+            return;
+        }
+
+        debug!("玩家 {} 断线，等待重连", player_id);
+        self.player_channels.remove(player_id);
+        self.disconnected_players.insert(player_id.clone(), Utc::now());
+
+        // 让其他玩家看到断线标记
+        self.broadcast_state_update().await;
+    }
+
+    /// 对局中自动跳过断线超过宽限时间的玩家：轮到其描述时直接跳过、投票阶段记为弃票、猜词阶段视为放弃
+    async fn skip_absent_players(&self) -> Result<()> {
+        if self.disconnected_players.is_empty() || self.pending_reveal.lock().await.is_some() {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let absent: Vec<PlayerId> = self
+            .disconnected_players
+            .iter()
+            .filter(|entry| now - *entry.value() > self.in_game_grace_for(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        if absent.is_empty() {
+            return Ok(());
+        }
+
+        let state_type = self.state.read().await.get_state_type();
+        if matches!(
+            state_type,
+            crate::message::GameStateType::Lobby | crate::message::GameStateType::GameOver
+        ) {
+            return Ok(());
+        }
+
+        for player_id in &absent {
+            if self.skipped_players.insert(player_id.clone()) {
+                let name = self.players.get(player_id).map(|p| p.name.clone()).unwrap_or_default();
+                self.broadcast(
+                    Notification::new(format!("玩家 {} 断线时间过长，将自动跳过其发言和投票", name))
+                        .detail(NotificationDetail::Player { player_id: player_id.clone() })
+                        .into(),
+                )
+                .await;
+            }
+        }
+
+        // 连续轮到多名断线玩家时逐个跳过
+        let settings = self.settings.read().await.clone();
+        for _ in 0..=absent.len() {
+            let event = {
+                let mut state = self.state.write().await;
+                match &*state {
+                    GameState::DescribePhase { .. } => {
+                        let current = state.get_phase_timing().and_then(|(current, _)| current);
+                        if !current.is_some_and(|id| absent.contains(&id)) {
+                            break;
+                        }
+                        state.handle_describe_timeout(&self.params).map_err(crate::Error::Game)?
+                    }
+                    GameState::VotePhase { .. } => {
+                        let voted = state.get_votes().unwrap_or_default();
+                        let pending: Vec<PlayerId> = state
+                            .get_players()
+                            .into_iter()
+                            .filter(|p| p.is_alive && absent.contains(&p.id) && !voted.contains_key(&p.id))
+                            .map(|p| p.id)
+                            .collect();
+                        let mut event = None;
+                        for voter_id in pending {
+                            // PK环节中平票玩家不能投票，直接忽略
+                            if let Ok(next) = state.add_vote(voter_id, ABSTAIN_VOTE.to_string(), &settings, &self.params) {
+                                event = Some(next);
+                                break;
+                            }
+                        }
+                        match event {
+                            Some(event) => event,
+                            None => break,
+                        }
+                    }
+                    GameState::GuessPhase { .. } => {
+                        if !state.get_guesser().is_some_and(|id| absent.contains(&id)) {
+                            break;
+                        }
+                        state.handle_guess_timeout().map_err(crate::Error::Game)?
+                    }
+                    _ => break,
+                }
+            };
+            Box::pin(self.handle_game_event(event)).await?;
+        }
+
+        Ok(())
+    }
+
+    /// 移除超过宽限时间仍未重连的玩家（仅在大厅和游戏结束状态下）
+    async fn expire_disconnected_players(&self) -> Result<()> {
+        let now = Utc::now();
+        let expired: Vec<PlayerId> = self
+            .disconnected_players
+            .iter()
+            .filter(|entry| now - *entry.value() > self.grace_period_for(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect();
+
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        // 房主断线超过宽限时间时，无论游戏阶段都先转交房主
+        for player_id in &expired {
+            self.transfer_host_from(player_id).await?;
+        }
+
+        let state_type = self.state.read().await.get_state_type();
+        if !matches!(
+            state_type,
+            crate::message::GameStateType::Lobby | crate::message::GameStateType::GameOver
+        ) {
+            return Ok(());
+        }
+
+        for player_id in expired {
+            self.disconnected_players.remove(&player_id);
+            if self.players.contains_key(&player_id) {
+                debug!("玩家 {} 超过重连宽限时间，移出房间", player_id);
+                self.remove_player(player_id, LeaveReason::ConnectionLost).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 移出大厅中未准备且超过挂机时间没有任何操作的真人玩家
+    async fn expire_afk_players(&self) -> Result<()> {
+        if self.afk_timeout.is_zero() {
+            return Ok(());
+        }
+        let timeout = chrono::Duration::from_std(self.afk_timeout).unwrap_or(chrono::Duration::MAX);
+        let afk: Vec<PlayerId> = self
+            .state
+            .read()
+            .await
+            .afk_players(timeout)
+            .into_iter()
+            .filter(|player_id| !self.is_bot(player_id))
+            .collect();
+
+        for player_id in afk {
+            debug!("玩家 {} 在大厅挂机超时，移出房间", player_id);
+            self.send_to_player(&player_id, Notification::new("您长时间未准备，已被移出房间").into())
+            .await;
+            self.remove_player(player_id, LeaveReason::AfkTimeout).await?;
+        }
+
         Ok(())
     }
 
+    /// 如果离开的玩家是房主，将房主转交给入座最久的在线玩家
+    async fn transfer_host_from(&self, departed_id: &PlayerId) -> Result<()> {
+        if !self.is_host(departed_id).await {
+            return Ok(());
+        }
+
+        let candidate = {
+            let order = self.player_order.lock().await;
+            let humans: Vec<&PlayerId> = order
+                .iter()
+                .filter(|id| *id != departed_id && !self.is_bot(id) && self.players.contains_key(*id))
+                .collect();
+            // 优先选择仍在线的玩家
+            humans
+                .iter()
+                .find(|id| self.player_channels.contains_key(**id) && !self.disconnected_players.contains_key(**id))
+                .or_else(|| humans.first())
+                .map(|id| (*id).clone())
+        };
+
+        match candidate {
+            Some(new_host) => self.change_host(new_host).await,
+            None => Ok(()),
+        }
+    }
+
+    /// 变更房主并广播 host_changed 消息
+    async fn change_host(&self, new_host: PlayerId) -> Result<()> {
+        let previous_host = {
+            let mut host = self.host.lock().await;
+            std::mem::replace(&mut *host, new_host.clone())
+        };
+        self.state.write().await.set_host(new_host.clone());
+
+        let host_name = self
+            .players
+            .get(&new_host)
+            .map(|p| p.name.clone())
+            .unwrap_or_else(|| "未知玩家".to_string());
+        info!("房间 {} 房主由 {} 变更为 {}", self.id, previous_host, new_host);
+
+        self.broadcast(ServerMessage::HostChanged {
+            message: format!("房主已转交给 {}", host_name),
+            previous_host,
+            host: new_host,
+            host_name,
+        })
+        .await;
+        self.broadcast_state_update().await;
+        self.save_state_snapshot().await
+    }
+
+    /// 获取玩家的断线重连宽限时间，持续高延迟的玩家获得双倍宽限
+    fn grace_period_for(&self, player_id: &PlayerId) -> chrono::Duration {
+        self.scaled_grace(player_id, self.reconnect_grace_period)
+    }
+
+    /// 获取玩家对局中断线的宽限时间，同样对高延迟玩家加倍
+    fn in_game_grace_for(&self, player_id: &PlayerId) -> chrono::Duration {
+        self.scaled_grace(player_id, self.in_game_grace_period)
+    }
+
+    fn scaled_grace(&self, player_id: &PlayerId, base: Duration) -> chrono::Duration {
+        let grace = if self.has_high_latency(player_id) { base * 2 } else { base };
+        chrono::Duration::from_std(grace).unwrap_or_else(|_| chrono::Duration::zero())
+    }
+
+    /// 记录玩家的ping/pong往返时间
+    pub fn record_latency(&self, player_id: &PlayerId, rtt: Duration) {
+        if !self.players.contains_key(player_id) {
+            return;
+        }
+
+        let rtt_ms = rtt.as_millis() as u64;
+        self.latencies
+            .entry(player_id.clone())
+            .and_modify(|latency| {
+                latency.last_ms = rtt_ms;
+                latency.average_ms = (latency.average_ms * 3 + rtt_ms) / 4;
+                latency.samples = latency.samples.saturating_add(1);
+            })
+            .or_insert(PlayerLatency {
+                last_ms: rtt_ms,
+                average_ms: rtt_ms,
+                samples: 1,
+            });
+    }
+
+    /// 获取房间内所有玩家的延迟统计
+    pub fn latency_snapshot(&self) -> HashMap<PlayerId, PlayerLatency> {
+        self.latencies
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect()
+    }
+
+    /// 检查玩家是否持续高延迟
+    fn has_high_latency(&self, player_id: &PlayerId) -> bool {
+        self.latencies.get(player_id).is_some_and(|latency| {
+            latency.samples >= SUSTAINED_LATENCY_SAMPLES
+                && latency.average_ms >= self.high_latency_threshold.as_millis() as u64
+        })
+    }
+
     /// 从其他房间踢出玩家（跨房间踢出）
     pub async fn kick_player_from_other_room(&self, player_id: PlayerId) -> Result<()> {
         // 检查玩家是否在当前房间
@@ -311,135 +1645,268 @@ impl Room {
             return Ok(());
         }
 
-        // 发送踢出消息给玩家
-        if let Some(channel) = self.player_channels.get(&player_id) {
-            let kick_message = GameMessage {
-                type_: "kicked_from_other_room".to_string(),
-                data: serde_json::json!({
-                    "message": "您已加入其他房间，已从当前房间断开连接"
-                }),
+        // 发送踢出消息给玩家，移除连接前立即发出其待发消息
+        if self.player_channels.contains_key(&player_id) {
+            let kick_message = ServerMessage::KickedFromOtherRoom {
+                message: "您已加入其他房间，已从当前房间断开连接".to_string(),
             };
-            
-            if let Err(e) = channel.send(kick_message).await {
-                error!("向被踢玩家发送踢出消息失败: {}", e);
-            }
+            self.enqueue(&player_id, kick_message.into());
+            self.flush_player(&player_id).await;
         }
 
         // 自动处理状态更新、通知和保存
-        self.remove_player(player_id).await?;
+        self.remove_player(player_id, LeaveReason::SwitchedRoom).await?;
 
         Ok(())
     }
 
+    /// 管理员将玩家移出房间，不受房主权限和游戏阶段限制
+    pub async fn kick_by_admin(&self, player_id: PlayerId, admin: &str) -> Result<()> {
+        if !self.players.contains_key(&player_id) {
+            return Err(crate::Error::Rule(ErrorCode::PlayerNotFound, "玩家不在该房间中".to_string()));
+        }
+
+        // 发送踢出消息给玩家，移除连接前立即发出其待发消息
+        if self.player_channels.contains_key(&player_id) {
+            let kick_message = ServerMessage::Kicked {
+                message: "您已被管理员移出房间".to_string(),
+                ui_event: UiEvent::Kicked,
+            };
+            self.enqueue(&player_id, kick_message.into());
+            self.flush_player(&player_id).await;
+        }
+
+        info!("管理员 {} 将玩家 {} 移出房间 {}", admin, player_id, self.id);
+        self.remove_player(player_id, LeaveReason::Banned).await
+    }
+
     /// 广播消息给房间内所有玩家
-    pub async fn broadcast(&self, message: GameMessage) {
-        let message = message.clone();
-        let mut failed_players = Vec::new();
+    pub async fn broadcast(&self, message: ServerMessage) {
+        self.publish(None, message.into()).await;
+    }
 
-        for entry in self.player_channels.iter() {
-            let player_id = entry.key().clone();
-            match entry.value().send(message.clone()).await {
-                Ok(_) => {
-                    // 消息发送成功
-                }
-                Err(e) => {
-                    // 记录发送失败的玩家ID
-                    error!("向玩家 {} 广播消息失败: {}", player_id, e);
-                    failed_players.push(player_id);
+    /// 按通知类别广播消息，跳过屏蔽了该类别的玩家
+    pub async fn broadcast_category(&self, category: NotificationCategory, message: ServerMessage) {
+        self.publish(Some(category), message.into()).await;
+    }
+
+    /// 发布房间范围的消息，只写入一次广播通道，由订阅的玩家连接各自过滤和分配序号；
+    /// 先发出已排队的定向消息，保证玩家收到的先后顺序与发送顺序一致
+    async fn publish(&self, category: Option<NotificationCategory>, message: GameMessage) {
+        self.buffer_for_disconnected(&message, category);
+        self.outgoing.flush(&self.player_channels, None).await;
+        // 没有任何订阅者时发送失败，直接忽略
+        let _ = self.room_events.send(RoomBroadcast { category, message });
+    }
+
+    /// 合并发给玩家的定向消息和房间广播，返回该玩家连接读取的消息流
+    ///
+    /// 需要在玩家加入房间之前调用，以免错过加入时的广播；连接被新连接取代或玩家离开房间后不再转发广播。
+    pub(crate) fn player_feed(
+        self: &Arc<Self>,
+        player_id: PlayerId,
+        player_tx: &mpsc::Sender<SequencedMessage>,
+        mut player_rx: mpsc::Receiver<SequencedMessage>,
+    ) -> mpsc::Receiver<SequencedMessage> {
+        let (feed_tx, feed_rx) = mpsc::channel(PLAYER_FEED_CAPACITY);
+        let mut events = self.room_events.subscribe();
+        let channel = player_tx.downgrade();
+        let room = Arc::downgrade(self);
+
+        tokio::spawn(async move {
+            loop {
+                // 定向消息在广播发布前已写入通道，优先读取以保持顺序
+                let message = tokio::select! {
+                    biased;
+                    message = player_rx.recv() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    event = events.recv() => {
+                        let (Some(room), Some(channel)) = (room.upgrade(), channel.upgrade()) else {
+                            break;
+                        };
+                        match event {
+                            Ok(event) => match room.accept_broadcast(&player_id, &channel, event) {
+                                Some(message) => message,
+                                None => continue,
+                            },
+                            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                                warn!("玩家 {} 落后房间广播 {} 条，重新发送完整状态", player_id, skipped);
+                                room.resync(&player_id).await;
+                                continue;
+                            }
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                };
+                if feed_tx.send(message).await.is_err() {
+                    break;
                 }
             }
+        });
+
+        feed_rx
+    }
+
+    /// 按玩家的通知偏好和协议能力过滤房间广播，需要发送时分配序号；该通道已不是玩家的当前连接时不发送
+    fn accept_broadcast(
+        &self,
+        player_id: &PlayerId,
+        channel: &mpsc::Sender<SequencedMessage>,
+        event: RoomBroadcast,
+    ) -> Option<SequencedMessage> {
+        let current = self
+            .player_channels
+            .get(player_id)
+            .is_some_and(|entry| entry.value().same_channel(channel));
+        if !current {
+            return None;
         }
 
-        // 移除发送失败的玩家通道
-        for player_id in failed_players {
-            debug!("移除失效的玩家通道: {}", player_id);
-            self.player_channels.remove(&player_id);
+        if let Some(category) = event.category {
+            let muted = self
+                .preferences
+                .get(player_id)
+                .is_some_and(|preferences| !preferences.allows(category));
+            // 支持截止时间的客户端根据状态中的 deadline 自行倒计时
+            let self_timed = category == NotificationCategory::Countdown
+                && self.capabilities_of(player_id).deadline_countdown;
+            if muted || self_timed {
+                return None;
+            }
         }
+
+        Some(self.sequence(player_id, event.message))
     }
 
-    /// 广播消息给被淘汰的玩家
-    pub async fn broadcast_to_eliminated_players(&self, message: GameMessage) {
-        let message = message.clone();
-        let mut failed_players = Vec::new();
+    /// 丢失了部分广播的玩家重新接收完整状态，只发给该玩家
+    async fn resync(&self, player_id: &PlayerId) {
+        self.last_states.remove(player_id);
+        self.send_state_update(Some(player_id)).await;
+    }
 
+    /// 广播消息给被淘汰的玩家
+    pub async fn broadcast_to_eliminated_players(&self, message: ServerMessage) {
+        let message: GameMessage = message.into();
         // 获取当前游戏状态中的玩家信息
         let state = self.state.read().await;
         let players = state.get_players_with_roles();
         drop(state);
 
-        // 找出被淘汰的玩家
-        let eliminated_players: Vec<PlayerId> = players
-            .iter()
-            .filter(|p| !p.is_alive)
-            .map(|p| p.id.clone())
-            .collect();
-
         // 只向被淘汰的玩家发送消息
-        for player_id in eliminated_players {
-            if let Some(channel) = self.player_channels.get(&player_id) {
-                match channel.send(message.clone()).await {
-                    Ok(_) => {
-                        // 消息发送成功
-                    }
-                    Err(e) => {
-                        // 记录发送失败的玩家ID
-                        error!("向被淘汰玩家 {} 广播消息失败: {}", player_id, e);
-                        failed_players.push(player_id);
-                    }
-                }
+        for player in players.iter().filter(|p| !p.is_alive) {
+            if self.player_channels.contains_key(&player.id) {
+                self.enqueue(&player.id, message.clone());
             }
         }
-
-        // 移除发送失败的玩家通道
-        for player_id in failed_players {
-            debug!("移除失效的被淘汰玩家通道: {}", player_id);
-            self.player_channels.remove(&player_id);
-        }
+        self.schedule_flush().await;
     }
 
-    /// 处理房间消息
+    /// 以 player_id 的身份处理房间消息，player_id 由调用方按连接的登录身份确定
     pub(crate) async fn handle_message(
         &self,
-        message: GameMessage,
-        player_tx: Option<tokio::sync::mpsc::Sender<GameMessage>>,
+        player_id: PlayerId,
+        message: ClientMessage,
+        player_tx: Option<tokio::sync::mpsc::Sender<SequencedMessage>>,
     ) -> Result<()> {
         // 检查房间是否已被删除
         if *self.is_deleted.lock().await {
             return Err(crate::Error::Game("房间已被删除".to_string()));
         }
         
-        let message_type = message.type_.clone();
+        message.check_payload_size(&crate::config::Config::get().websocket)?;
+        self.check_frozen(message.type_name()).await?;
+
+        // 只读的查询不算作玩家操作，房主通过 keep_alive 显式续期
+        if !matches!(
+            message,
+            ClientMessage::Query { .. } | ClientMessage::ListCategories { .. } | ClientMessage::KeepAlive { .. }
+        ) {
+            self.touch_lobby().await;
+        }
+        self.state.write().await.touch_player(&player_id);
 
-        match message_type.as_str() {
-            "join" => {
+        match message {
+            ClientMessage::Join { player_name, capabilities, invite_code } => {
                 if let Some(tx) = player_tx {
-                    self.handle_join(message, tx).await?;
+                    let player = self.handle_join(player_id, player_name, capabilities, invite_code, tx).await?;
+                    let freeze = self.frozen.lock().await.clone();
+                    if let Some(freeze) = freeze {
+                        self.send_to_player(&player.id, freeze.notice()).await;
+                    }
                 } else {
                     return Err(crate::Error::Game("join消息需要player_tx".to_string()));
                 }
             }
-            "ready" => {
-                self.handle_ready(message).await?;
+            ClientMessage::Ready {} => {
+                self.handle_ready(player_id).await?;
             }
-            "describe" => {
-                self.handle_describe(message).await?;
+            ClientMessage::Describe { content } => {
+                self.handle_describe(player_id, content).await?;
             }
-            "vote" => {
-                self.handle_vote(message).await?;
+            ClientMessage::Vote { target_id } => {
+                self.handle_vote(player_id, target_id).await?;
             }
-            "chat" => {
-                self.handle_chat(message).await?;
+            ClientMessage::Chat { content } => {
+                self.handle_chat(player_id, content).await?;
+            }
+            ClientMessage::EliminatedChat { content } => {
+                self.handle_eliminated_chat(player_id, content).await?;
+            }
+            ClientMessage::GuessWord { word } => {
+                self.handle_guess_word(player_id, word).await?;
+            }
+            ClientMessage::WordGuess { word } => {
+                self.handle_word_quiz_guess(player_id, word).await?;
+            }
+            ClientMessage::Concede { confirm } => {
+                self.handle_concede(player_id, confirm).await?;
+            }
+            ClientMessage::Leave {} => {
+                self.handle_leave(player_id).await?;
+            }
+            ClientMessage::Kick { target_id } => {
+                self.handle_kick(player_id, target_id).await?;
+            }
+            ClientMessage::UpdateSettings { settings } => {
+                self.handle_update_settings(player_id, settings).await?;
+            }
+            ClientMessage::SetPreferences { preferences } => {
+                self.handle_set_preferences(player_id, preferences).await?;
+            }
+            ClientMessage::StartGame {} => {
+                self.handle_start_game(player_id).await?;
             }
-            "eliminated_chat" => {
-                self.handle_eliminated_chat(message).await?;
+            ClientMessage::TransferHost { target_id } => {
+                self.handle_transfer_host(player_id, target_id).await?;
             }
-            "leave" => {
-                self.handle_leave(message).await?;
+            ClientMessage::StartSeries { games } => {
+                self.handle_start_series(player_id, games).await?;
             }
-            "kick" => {
-                self.handle_kick(message).await?;
+            ClientMessage::KeepAlive {} => {
+                self.handle_keep_alive(player_id).await?;
+            }
+            ClientMessage::Query { what } => {
+                self.handle_query(player_id, what).await?;
+            }
+            ClientMessage::ListCategories {} => {
+                self.handle_list_categories(player_id).await?;
+            }
+            ClientMessage::SetStickers { stickers } => {
+                self.handle_set_stickers(player_id, stickers).await?;
+            }
+            ClientMessage::Sticker { sticker_id } => {
+                self.handle_sticker(player_id, sticker_id).await?;
+            }
+            // 添加电脑玩家需要房间的共享引用，由连接层调用 bot::add_bots 处理
+            ClientMessage::AddBots { .. } => {
+                return Err(crate::Error::Game("add_bots消息不能直接发送给房间".to_string()));
+            }
+            // 消息确认和补发直接作用于玩家连接，由连接层调用 acknowledge 和 missed_messages 处理
+            ClientMessage::Ack { .. } | ClientMessage::Resume { .. } => {
+                return Err(crate::Error::Game(format!("{}消息不能直接发送给房间", message.type_name())));
             }
-            _ => return Err(crate::Error::Game("未知的消息类型".to_string())),
         }
         Ok(())
     }
@@ -447,18 +1914,28 @@ impl Room {
     /// 处理玩家加入消息
     async fn handle_join(
         &self,
-        message: GameMessage,
-        player_tx: tokio::sync::mpsc::Sender<GameMessage>,
+        player_id: PlayerId,
+        player_name: String,
+        capabilities: Option<ClientCapabilities>,
+        invite_code: Option<String>,
+        player_tx: tokio::sync::mpsc::Sender<SequencedMessage>,
     ) -> Result<Player> {
-        let message_data = message.data.clone();
-        let player_name = message_data["player_name"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家名称".to_string()))?
-            .to_string();
-        let player_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家ID".to_string()))?
-            .to_string();
+        if player_name.is_empty() {
+            return Err(crate::Error::Game("无效的玩家名称".to_string()));
+        }
+        if player_id.is_empty() {
+            return Err(crate::Error::Game("无效的玩家ID".to_string()));
+        }
+        let declared_capabilities = capabilities.is_some();
+
+        // 教程房间只允许创建者和教程机器人进入
+        if self.kind == RoomKind::Tutorial && !self.is_bot(&player_id) && !self.is_host(&player_id).await {
+            return Err(crate::Error::Room("教程房间仅限创建者进入".to_string()));
+        }
+
+        // 每次连接重新声明协议能力，新连接需要先收到完整状态才能应用增量更新
+        self.capabilities.insert(player_id.clone(), capabilities.unwrap_or_default());
+        self.last_states.remove(&player_id);
 
         // 检查玩家是否已经存在
         let is_reconnect = self.players.contains_key(&player_id);
@@ -473,16 +1950,28 @@ impl Room {
             }
             self.player_channels.insert(player_id.clone(), player_tx);
 
-            // 发送重新连接通知
-            self.broadcast(GameMessage {
-                type_: "notification".to_string(),
-                data: serde_json::json!({
-                    "message": format!("玩家 {} 重新连接", player_name)
-                }),
-            })
-            .await;
+            self.skipped_players.remove(&player_id);
+
+            // 宽限时间内的断线重连静默恢复，避免刷屏的加入/离开通知
+            let within_grace = self
+                .disconnected_players
+                .remove(&player_id)
+                .is_some_and(|(_, disconnected_at)| {
+                    Utc::now() - disconnected_at <= self.grace_period_for(&player_id)
+                });
+
+            if !within_grace && self.should_announce_presence(&player_id) {
+                // 发送重新连接通知
+                self.broadcast_category(
+                    NotificationCategory::Presence,
+                    Notification::new(format!("玩家 {} 重新连接", player_name)).into(),
+                )
+                .await;
+            }
             // 发送当前状态更新
             self.broadcast_state_update().await;
+            self.acknowledge_capabilities(&player_id, declared_capabilities).await;
+            self.issue_resume_token(&player_id).await;
 
             Ok(existing_player)
         } else {
@@ -496,40 +1985,60 @@ impl Room {
                 last_action: Utc::now(),
             };
 
-            // 检查玩家是否已在其他房间，如果是则自动离开原房间
-            if let Some(other_room_id) = self.storage.get_player_current_room(&player.id).await? {
-                if other_room_id != self.id {
-                    debug!("玩家 {} 从房间 {} 切换到房间 {}", player_name, other_room_id, self.id);
-                    
-                    // 调用跨房间玩家踢出回调
-                    if let Some(callback) = &self.player_kick_callback {
-                        callback(player.id.clone(), other_room_id.clone());
-                    }
-                    
-                    // 清理玩家在原房间的信息
-                    if let Err(e) = self.storage.clear_player_room_info(&player.id).await {
-                        error!("清理玩家原房间信息失败: {}", e);
-                    }
+            let redeemed = match self.redeem_invite(&player_id, invite_code.as_deref()).await {
+                Ok(redeemed) => redeemed,
+                Err(e) => {
+                    self.capabilities.remove(&player_id);
+                    return Err(e);
                 }
-            }
+            };
 
-            self.add_player(&player, player_tx).await?;
+            // 跨房间切换在 add_player 中持有租约处理
+            if let Err(e) = self.add_player(&player, player_tx).await {
+                self.capabilities.remove(&player_id);
+                if let Some(invite) = redeemed {
+                    self.invites.insert(invite.code.clone(), invite);
+                }
+                return Err(e);
+            }
+            self.acknowledge_capabilities(&player_id, declared_capabilities).await;
+            self.issue_resume_token(&player_id).await;
             Ok(player)
         }
     }
 
+    /// 向声明了协议能力的客户端确认服务器已启用的能力，旧客户端不会收到该消息
+    async fn acknowledge_capabilities(&self, player_id: &PlayerId, declared: bool) {
+        if !declared {
+            return;
+        }
+        let capabilities = self.capabilities_of(player_id);
+        self.send_to_player(player_id, ServerMessage::Capabilities {
+            accepted: capabilities,
+            ui_event_version: UI_EVENT_VERSION,
+        })
+        .await;
+    }
+
+    /// 获取玩家当前连接声明的协议能力
+    fn capabilities_of(&self, player_id: &PlayerId) -> ClientCapabilities {
+        self.capabilities
+            .get(player_id)
+            .map(|entry| *entry)
+            .unwrap_or_default()
+    }
+
     /// 处理玩家准备消息
-    async fn handle_ready(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let player_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家ID".to_string()))?
-            .to_string();
+    async fn handle_ready(&self, player_id: PlayerId) -> Result<()> {
+
+        if self.word_quiz.lock().await.is_some() {
+            return Err(crate::Error::Rule(ErrorCode::InvalidState, "猜词小游戏进行中，请稍候".to_string()));
+        }
 
         let mut state = self.state.write().await;
         let event = state
-            .player_ready(player_id)
-            .map_err(|e| crate::Error::Game(e))?;
+            .player_ready(player_id, &self.params)
+            .map_err(crate::Error::Game)?;
 
         // 处理事件
         drop(state);
@@ -539,20 +2048,19 @@ impl Room {
     }
 
     /// 处理玩家描述消息
-    async fn handle_describe(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let player_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家ID".to_string()))?
-            .to_string();
-        let content = message_data["content"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的描述内容".to_string()))?;
+    async fn handle_describe(&self, player_id: PlayerId, content: String) -> Result<()> {
+        // 描述是轮到玩家时必须完成的操作，静默丢弃会让玩家一直等到超时，按拒绝处理
+        let Some(content) = &self.moderate_text(&player_id, &content).await? else {
+            return Err(crate::Error::Rule(
+                ErrorCode::SensitiveWord,
+                "描述包含敏感词，请修改后重新描述".to_string(),
+            ));
+        };
 
         let mut state = self.state.write().await;
         let event = state
-            .add_description(player_id, content.to_string())
-            .map_err(|e| crate::Error::Game(e))?;
+            .add_description(player_id.clone(), content.to_string(), &self.params)?;
+        self.record_action(player_id, ActionKind::Describe, content.to_string()).await;
 
         // 处理事件
         drop(state);
@@ -562,21 +2070,12 @@ impl Room {
     }
 
     /// 处理玩家投票消息
-    async fn handle_vote(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let voter_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的投票者ID".to_string()))?
-            .to_string();
-        let target_id = message_data["target_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的目标ID".to_string()))?
-            .to_string();
+    async fn handle_vote(&self, voter_id: PlayerId, target_id: PlayerId) -> Result<()> {
 
+        let settings = self.settings.read().await.clone();
         let mut state = self.state.write().await;
-        let event = state
-            .add_vote(voter_id, target_id)
-            .map_err(|e| crate::Error::Game(e))?;
+        let event = state.add_vote(voter_id.clone(), target_id.clone(), &settings, &self.params)?;
+        self.record_action(voter_id, ActionKind::Vote, target_id).await;
 
         // 处理事件
         drop(state);
@@ -586,20 +2085,18 @@ impl Room {
     }
 
     /// 处理玩家聊天消息
-    async fn handle_chat(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let player_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家ID".to_string()))?
-            .to_string();
-        let content = message_data["content"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的聊天内容".to_string()))?;
+    async fn handle_chat(&self, player_id: PlayerId, content: String) -> Result<()> {
+        self.check_muted(&player_id)?;
+        self.check_global_mute(&player_id).await?;
+        let Some(content) = &self.moderate_text(&player_id, &content).await? else {
+            return Ok(());
+        };
 
         let mut state = self.state.write().await;
         let event = state
-            .add_chat_message(player_id, content.to_string())
-            .map_err(|e| crate::Error::Game(e))?;
+            .add_chat_message(player_id.clone(), content.to_string())
+            .map_err(crate::Error::Game)?;
+        self.record_action(player_id, ActionKind::Chat, content.to_string()).await;
 
         // 处理事件
         drop(state);
@@ -608,23 +2105,416 @@ impl Room {
         Ok(())
     }
 
-    /// 处理被淘汰玩家聊天消息
-    async fn handle_eliminated_chat(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let player_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家ID".to_string()))?
-            .to_string();
-        let content = message_data["content"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的聊天内容".to_string()))?;
-
-        let mut state = self.state.write().await;
-        let event = state
-            .add_eliminated_chat_message(player_id, content.to_string())
-            .map_err(|e| crate::Error::Game(e))?;
+    /// 被自动禁言的玩家不能发送聊天消息
+    fn check_muted(&self, player_id: &PlayerId) -> Result<()> {
+        let Some(until) = self.muted_until.get(player_id).map(|entry| *entry) else {
+            return Ok(());
+        };
+        let remaining = until - Utc::now();
+        if remaining <= chrono::Duration::zero() {
+            self.muted_until.remove(player_id);
+            return Ok(());
+        }
+        Err(crate::Error::Rule(
+            ErrorCode::Muted,
+            format!("您已被禁言，{} 秒后解除", remaining.num_seconds().max(1)),
+        ))
+    }
 
-        // 处理事件
+    /// 被管理员全服禁言的用户不能发送聊天消息，读取禁言记录失败时放行
+    async fn check_global_mute(&self, player_id: &PlayerId) -> Result<()> {
+        if self.is_bot(player_id) {
+            return Ok(());
+        }
+        match self.storage.get_global_mute(player_id).await {
+            Ok(Some(mute)) => {
+                let remaining = (mute.expires_at - Utc::now()).num_minutes().max(1);
+                Err(crate::Error::Rule(
+                    ErrorCode::GloballyMuted,
+                    format!("您已被管理员禁言（{}），约 {} 分钟后解除", mute.reason, remaining),
+                ))
+            }
+            Ok(None) => Ok(()),
+            Err(e) => {
+                warn!("读取玩家 {} 的全服禁言失败: {}", player_id, e);
+                Ok(())
+            }
+        }
+    }
+
+    /// 检查发言中的敏感词，命中时记一次违规，一局内达到上限后自动禁言并通知房主
+    ///
+    /// 按房间设置（未设置时按全局配置）的处理方式返回替换后的内容、拒绝错误，
+    /// 或在静默丢弃时返回 `None`。
+    async fn moderate_text(&self, player_id: &PlayerId, text: &str) -> Result<Option<String>> {
+        if !self.word_filter.contains_sensitive_words(text) {
+            return Ok(Some(text.to_string()));
+        }
+
+        let action = self
+            .settings
+            .read()
+            .await
+            .sensitive_word_action
+            .unwrap_or(self.word_filter.action());
+
+        let moderation = &config::Config::get().security.moderation;
+        let strikes = {
+            let mut entry = self.strikes.entry(player_id.clone()).or_insert(0);
+            *entry += 1;
+            *entry
+        };
+        self.record_action(player_id.clone(), ActionKind::Strike, strikes.to_string()).await;
+
+        let reached_limit = moderation.strike_limit > 0 && strikes >= moderation.strike_limit;
+        let warning = if reached_limit {
+            format!("您的发言多次包含敏感词，已被禁言 {} 分钟", moderation.mute_minutes)
+        } else if moderation.strike_limit > 0 {
+            format!("您的发言包含敏感词，已被过滤（{}/{}，达到上限将被禁言）", strikes, moderation.strike_limit)
+        } else {
+            "您的发言包含敏感词，已被过滤".to_string()
+        };
+        // 拒绝时发送者会收到错误，静默丢弃时不提示，这两种情况只在被禁言时另行通知
+        if action == FilterAction::Replace || reached_limit {
+            self.send_to_player(
+                player_id,
+                Notification::new(warning)
+                    .detail(NotificationDetail::Strikes { strikes })
+                    .into(),
+            )
+            .await;
+        }
+
+        if reached_limit {
+            let until = Utc::now() + chrono::Duration::minutes(moderation.mute_minutes as i64);
+            self.muted_until.insert(player_id.clone(), until);
+            // 禁言后重新计数
+            self.strikes.remove(player_id);
+            self.record_action(player_id.clone(), ActionKind::Mute, moderation.mute_minutes.to_string()).await;
+
+            let player_name = self.players.get(player_id).map(|p| p.name.clone()).unwrap_or_default();
+            info!("房间 {} 玩家 {} 违规 {} 次，自动禁言 {} 分钟", self.id, player_id, strikes, moderation.mute_minutes);
+            let host = self.host.lock().await.clone();
+            self.send_to_player(
+                &host,
+                Notification::new(format!("玩家 {} 多次发送敏感词，已被自动禁言 {} 分钟", player_name, moderation.mute_minutes))
+                    .detail(NotificationDetail::AutoMuted {
+                        player_id: player_id.clone(),
+                        strikes,
+                        muted_until: until.timestamp(),
+                    })
+                    .into(),
+            )
+            .await;
+        }
+
+        match action {
+            FilterAction::Replace => Ok(Some(self.word_filter.filter(text))),
+            FilterAction::Reject => Err(crate::Error::Rule(
+                ErrorCode::SensitiveWord,
+                "发言包含敏感词，请修改后重新发送".to_string(),
+            )),
+            FilterAction::Drop => Ok(None),
+        }
+    }
+
+    /// 处理房主登记表情包消息，整体替换房间当前的表情
+    async fn handle_set_stickers(&self, player_id: PlayerId, stickers: Vec<Sticker>) -> Result<()> {
+
+        if !self.is_host(&player_id).await {
+            return Err(crate::Error::Game("只有房主可以设置表情包".to_string()));
+        }
+
+        let config = &config::Config::get().stickers;
+        if stickers.len() > config.max_per_room {
+            return Err(crate::Error::Game(format!("每个房间最多登记 {} 个表情", config.max_per_room)));
+        }
+        let mut ids = std::collections::HashSet::new();
+        for sticker in &stickers {
+            if sticker.id.trim().is_empty() || sticker.id.chars().count() > 32 {
+                return Err(crate::Error::Game("表情ID长度需在1-32个字符之间".to_string()));
+            }
+            if !ids.insert(sticker.id.as_str()) {
+                return Err(crate::Error::Game(format!("表情ID重复: {}", sticker.id)));
+            }
+            if sticker.url.len() > config.max_url_length {
+                return Err(crate::Error::Game(format!("表情URL长度不能超过 {}", config.max_url_length)));
+            }
+            let url = url::Url::parse(&sticker.url)
+                .map_err(|_| crate::Error::Game(format!("无效的表情URL: {}", sticker.url)))?;
+            let allowed = url.scheme() == "https"
+                && url
+                    .host_str()
+                    .is_some_and(|host| config.allowed_hosts.iter().any(|allowed| allowed == host));
+            if !allowed {
+                return Err(crate::Error::Game(format!("表情URL的域名不在允许列表中: {}", sticker.url)));
+            }
+        }
+
+        *self.stickers.write().await = stickers.clone();
+
+        self.broadcast(Notification::new("房主更新了表情包").detail(NotificationDetail::Stickers { stickers }).into())
+        .await;
+
+        Ok(())
+    }
+
+    /// 处理发送表情消息
+    async fn handle_sticker(&self, player_id: PlayerId, sticker_id: String) -> Result<()> {
+
+        let sticker = self
+            .stickers
+            .read()
+            .await
+            .iter()
+            .find(|sticker| sticker.id == sticker_id)
+            .cloned()
+            .ok_or_else(|| crate::Error::Game("房间中没有该表情".to_string()))?;
+
+        self.check_sticker_rate_limit(&player_id)?;
+
+        let mut state = self.state.write().await;
+        let event = state
+            .add_sticker_message(player_id.clone(), sticker.clone())
+            .map_err(crate::Error::Game)?;
+        self.record_action(player_id, ActionKind::Sticker, sticker.id).await;
+
+        // 处理事件
+        drop(state);
+        Box::pin(self.handle_game_event(event)).await?;
+
+        Ok(())
+    }
+
+    /// 按玩家限制发送表情的频率
+    fn check_sticker_rate_limit(&self, player_id: &PlayerId) -> Result<()> {
+        let config = &config::Config::get().stickers;
+        let window = Duration::from_secs(config.rate_limit_window);
+        let now = std::time::Instant::now();
+
+        let mut entry = self
+            .sticker_windows
+            .entry(player_id.clone())
+            .or_insert(StickerWindow {
+                started_at: now,
+                count: 0,
+            });
+        if now.duration_since(entry.started_at) > window {
+            entry.started_at = now;
+            entry.count = 0;
+        }
+        if entry.count >= config.rate_limit_max {
+            return Err(crate::Error::Game("发送表情过于频繁，请稍后再试".to_string()));
+        }
+        entry.count += 1;
+        Ok(())
+    }
+
+    /// 处理被淘汰玩家聊天消息
+    async fn handle_eliminated_chat(&self, player_id: PlayerId, content: String) -> Result<()> {
+        self.check_muted(&player_id)?;
+        self.check_global_mute(&player_id).await?;
+        let Some(content) = &self.moderate_text(&player_id, &content).await? else {
+            return Ok(());
+        };
+
+        let mut state = self.state.write().await;
+        let event = state
+            .add_eliminated_chat_message(player_id.clone(), content.to_string())
+            .map_err(crate::Error::Game)?;
+        self.record_action(player_id, ActionKind::EliminatedChat, content.to_string()).await;
+
+        // 处理事件
+        drop(state);
+        Box::pin(self.handle_game_event(event)).await?;
+
+        Ok(())
+    }
+
+    /// 处理局部状态查询，只回复请求的那部分状态，不触发全量广播
+    async fn handle_query(&self, player_id: PlayerId, what: String) -> Result<()> {
+
+        if !self.players.contains_key(&player_id) {
+            return Err(crate::Error::Game("玩家不存在".to_string()));
+        }
+
+        let settings = self.settings.read().await.clone();
+        let result = match what.as_str() {
+            "players" => {
+                let state = self.state.read().await;
+                serde_json::json!(self.players_view(&state, &settings, &player_id))
+            }
+            "descriptions" => {
+                let state = self.state.read().await;
+                let descriptions = state.get_descriptions().unwrap_or_default();
+                serde_json::Value::Array(
+                    descriptions
+                        .iter()
+                        .map(|(id, desc)| serde_json::json!({ "player_id": id, "content": desc }))
+                        .collect(),
+                )
+            }
+            "votes" => {
+                let state = self.state.read().await;
+                let votes = state.get_votes().unwrap_or_default();
+                serde_json::Value::Array(
+                    votes
+                        .iter()
+                        .map(|(voter_id, target_id)| {
+                            serde_json::json!({ "player_id": voter_id, "target_id": target_id })
+                        })
+                        .collect(),
+                )
+            }
+            "chat" => {
+                let state = self.state.read().await;
+                // 与 state_update 中的聊天记录格式保持一致
+                let to_json = |messages: Vec<crate::game::ChatMessage>| {
+                    messages
+                        .iter()
+                        .map(|msg| {
+                            serde_json::json!({
+                                "player_id": msg.player_id,
+                                "player_name": msg.player_name,
+                                "content": msg.content,
+                                "sticker": msg.sticker,
+                                "timestamp": msg.timestamp.timestamp()
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                };
+                serde_json::json!({
+                    "chat_messages": to_json(state.get_chat_messages().unwrap_or_default()),
+                    "eliminated_chat_messages": to_json(state.get_eliminated_chat_messages().unwrap_or_default()),
+                })
+            }
+            "settings" => serde_json::to_value(&settings).unwrap_or(serde_json::Value::Null),
+            "scoreboard" => self.session_scoreboard().await.unwrap_or_else(|| {
+                serde_json::json!({ "games_played": 0, "players": [] })
+            }),
+            _ => return Err(crate::Error::Game(format!("未知的查询类型: {}", what))),
+        };
+
+        self.send_to_player(
+            &player_id,
+            ServerMessage::QueryResult {
+                what,
+                state: self.state.read().await.get_state_type(),
+                result,
+            },
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// 处理被淘汰卧底的猜词消息
+    async fn handle_guess_word(&self, player_id: PlayerId, word: String) -> Result<()> {
+        let word = Some(word.trim())
+            .filter(|word| !word.is_empty())
+            .ok_or_else(|| crate::Error::Game("无效的猜词内容".to_string()))?;
+
+        let mut state = self.state.write().await;
+        let event = state
+            .guess_word(player_id.clone(), word.to_string())
+            .map_err(crate::Error::Game)?;
+        self.record_action(player_id, ActionKind::GuessWord, word.to_string()).await;
+
+        // 处理事件
+        drop(state);
+        Box::pin(self.handle_game_event(event)).await?;
+
+        Ok(())
+    }
+
+    /// 处理猜词小游戏的答案，每名玩家只能提交一次，所有参与者都提交后立即公布结果
+    async fn handle_word_quiz_guess(&self, player_id: PlayerId, word: String) -> Result<()> {
+        let word = Some(word.trim())
+            .filter(|word| !word.is_empty())
+            .ok_or_else(|| crate::Error::Game("无效的猜词内容".to_string()))?;
+
+        let mut quiz_guard = self.word_quiz.lock().await;
+        let quiz = quiz_guard
+            .as_mut()
+            .ok_or_else(|| crate::Error::Rule(ErrorCode::InvalidState, "当前没有进行中的猜词小游戏".to_string()))?;
+        let target = quiz
+            .targets
+            .get(&player_id)
+            .ok_or_else(|| crate::Error::Rule(ErrorCode::InvalidAction, "你没有参与本次猜词小游戏".to_string()))?;
+        if quiz.answers.contains_key(&player_id) {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "你已经提交过答案了".to_string()));
+        }
+
+        let normalize = |w: &str| w.split_whitespace().collect::<String>().to_lowercase();
+        let correct = normalize(word) == normalize(target);
+        quiz.answers.insert(player_id.clone(), (word.to_string(), correct));
+        let all_answered = quiz.answers.len() == quiz.targets.len();
+        drop(quiz_guard);
+
+        self.send_to_player(&player_id, Notification::new("答案已提交，等待公布结果").into())
+        .await;
+
+        if all_answered {
+            self.finish_word_quiz().await;
+        }
+        Ok(())
+    }
+
+    /// 处理获取词库分类消息，只回复请求者
+    async fn handle_list_categories(&self, player_id: PlayerId) -> Result<()> {
+
+        let weights = self.word_bank.get_category_weights();
+        let mut categories = self.word_bank.get_categories();
+        categories.sort();
+        let categories: Vec<_> = categories
+            .into_iter()
+            .map(|name| {
+                serde_json::json!({
+                    "name": name,
+                    "word_count": self.word_bank.get_category_word_count(name),
+                    "weight": weights.get(name).copied().unwrap_or(1.0)
+                })
+            })
+            .collect();
+
+        self.send_to_player(
+            &player_id,
+            ServerMessage::Categories {
+                categories,
+                selected: self.settings.read().await.word_category.clone(),
+            },
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// 处理卧底认输消息，未确认时只向该玩家发送确认提示
+    async fn handle_concede(&self, player_id: PlayerId, confirmed: bool) -> Result<()> {
+
+        if !confirmed {
+            self.state
+                .read()
+                .await
+                .check_concede(&player_id)
+                .map_err(crate::Error::Game)?;
+            self.send_to_player(
+                &player_id,
+                ServerMessage::ConcedeConfirm {
+                    message: "确定要认输吗？认输后本局立即结束，判平民获胜".to_string(),
+                },
+            )
+            .await;
+            return Ok(());
+        }
+
+        let mut state = self.state.write().await;
+        let event = state
+            .concede(player_id.clone())
+            .map_err(crate::Error::Game)?;
+        self.record_action(player_id, ActionKind::Concede, String::new()).await;
+
+        // 处理事件
         drop(state);
         Box::pin(self.handle_game_event(event)).await?;
 
@@ -632,12 +2522,7 @@ impl Room {
     }
 
     /// 处理玩家离开消息
-    async fn handle_leave(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let player_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的玩家ID".to_string()))?
-            .to_string();
+    async fn handle_leave(&self, player_id: PlayerId) -> Result<()> {
 
         // 检查玩家是否还存在（可能已经被踢出或其他原因移除）
         if !self.players.contains_key(&player_id) {
@@ -655,84 +2540,610 @@ impl Room {
         match state_type {
             crate::message::GameStateType::Lobby => {
                 // 在大厅状态，直接移除玩家
-                self.remove_player(player_id).await?;
+                self.remove_player(player_id, LeaveReason::Voluntary).await?;
             },
             crate::message::GameStateType::GameOver => {
                 // 游戏结束状态，直接移除玩家
-                self.remove_player(player_id).await?;
+                self.remove_player(player_id, LeaveReason::Voluntary).await?;
             },
             _ => {
-                // 游戏进行中，标记玩家为非活跃
-                let state = self.state.write().await;
-                
-                // 获取玩家列表
-                let mut players = state.get_players();
-                
-                // 找到要离开的玩家并获取其名字
-                let mut player_name = "未知玩家".to_string();
-                for player in &mut players {
-                    if player.id == player_id {
-                        player_name = player.name.clone();
-                        player.is_alive = false;
-                        break;
-                    }
-                }
-                
+                // 游戏进行中，找到要离开的玩家并获取其名字
+                let player_name = self
+                    .state
+                    .read()
+                    .await
+                    .get_players()
+                    .into_iter()
+                    .find(|player| player.id == player_id)
+                    .map(|player| player.name)
+                    .unwrap_or_else(|| "未知玩家".to_string());
+
                 // 从通信通道中移除玩家
                 self.player_channels.remove(&player_id);
                 
                 // 清理玩家的房间信息（游戏进行中离开时也要清理）
-                if let Err(e) = self.storage.clear_player_room_info(&player_id).await {
+                if let Err(e) = self.release_player_room(&player_id).await {
                     error!("清理离开玩家房间信息失败: {}", e);
                 }
                 
                 // 广播玩家离开的消息
-                drop(state);
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 离开了游戏", player_name)
-                    }),
-                }).await;
+                self.broadcast_category(
+                    NotificationCategory::Presence,
+                    Notification::new(format!("玩家 {} {}", player_name, LeaveReason::Voluntary.describe()))
+                        .detail(NotificationDetail::PlayerLeft {
+                            player_id: player_id.clone(),
+                            reason: LeaveReason::Voluntary,
+                        })
+                        .into(),
+                ).await;
                 self.broadcast_state_update().await;
+
+                // 房主中途离开时转交房主
+                self.transfer_host_from(&player_id).await?;
             }
         }
 
-        Ok(())
+        Ok(())
+    }
+
+    /// 处理房主踢人消息
+    async fn handle_kick(&self, kicker_id: PlayerId, target_id: PlayerId) -> Result<()> {
+
+        let mut state = self.state.write().await;
+        let event = state
+            .kick_player(kicker_id, target_id)
+            .map_err(crate::Error::Game)?;
+
+        // 处理事件
+        drop(state);
+        Box::pin(self.handle_game_event(event)).await?;
+
+        Ok(())
+    }
+
+    /// 处理房主开始游戏消息
+    async fn handle_start_game(&self, player_id: PlayerId) -> Result<()> {
+
+        if !self.is_host(&player_id).await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "只有房主可以开始游戏".to_string()));
+        }
+
+        if self.state.read().await.get_state_type() != crate::message::GameStateType::Lobby {
+            return Err(crate::Error::Rule(ErrorCode::GameStarted, "游戏已经开始".to_string()));
+        }
+
+        if !self.all_players_ready().await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidState, "还有玩家未准备或人数不足".to_string()));
+        }
+
+        self.start_game().await
+    }
+
+    /// 处理房主手动转交房主消息
+    async fn handle_transfer_host(&self, player_id: PlayerId, target_id: PlayerId) -> Result<()> {
+
+        if !self.is_host(&player_id).await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "只有房主可以转交房主".to_string()));
+        }
+        if player_id == target_id {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "您已经是房主了".to_string()));
+        }
+        if !self.players.contains_key(&target_id) || self.is_bot(&target_id) {
+            return Err(crate::Error::Rule(ErrorCode::PlayerNotFound, "目标玩家不存在".to_string()));
+        }
+
+        self.change_host(target_id).await
+    }
+
+    /// 处理房主保持房间活跃消息，重新开始大厅空闲计时
+    async fn handle_keep_alive(&self, player_id: PlayerId) -> Result<()> {
+
+        if !self.is_host(&player_id).await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "只有房主可以保持房间活跃".to_string()));
+        }
+
+        // 已经发出过关闭提醒时告知其他玩家房间会继续保留
+        let warned = !self.idle_warnings_sent.lock().await.is_empty();
+        self.touch_lobby().await;
+        if warned {
+            self.broadcast(Notification::new("房主保持了房间活跃，房间不会关闭").into())
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// 处理房主开启系列赛消息，会替换尚未结束的系列赛
+    async fn handle_start_series(&self, player_id: PlayerId, games: u64) -> Result<()> {
+
+        if !self.is_host(&player_id).await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "只有房主可以开启系列赛".to_string()));
+        }
+
+        let state_type = self.state.read().await.get_state_type();
+        if !matches!(
+            state_type,
+            crate::message::GameStateType::Lobby | crate::message::GameStateType::GameOver
+        ) {
+            return Err(crate::Error::Rule(ErrorCode::GameStarted, "游戏进行中不能开启系列赛".to_string()));
+        }
+
+        if !(2..=MatchSeries::MAX_GAMES as u64).contains(&games) {
+            return Err(crate::Error::Game(format!(
+                "系列赛局数需在 2-{} 之间",
+                MatchSeries::MAX_GAMES
+            )));
+        }
+
+        let series = MatchSeries::new(games as u32);
+        *self.series.lock().await = Some(series.clone());
+
+        self.broadcast(ServerMessage::SeriesUpdate {
+            message: format!("房主开启了 {} 局系列赛", games),
+            series,
+            winners: Vec::new(),
+        })
+        .await;
+
+        Ok(())
+    }
+
+    /// 为进行中的系列赛记录本局结果并广播比分，bonus 中的玩家额外得1分
+    async fn score_series(&self, winner: crate::game::Role, bonus: &HashSet<PlayerId>) {
+        let mut series_guard = self.series.lock().await;
+        let Some(series) = series_guard.as_mut().filter(|series| !series.finished) else {
+            return;
+        };
+
+        let players = self.state.read().await.get_players();
+        series.record(&players, winner, bonus);
+
+        let winners: Vec<_> = if series.finished {
+            series.leaders().into_iter().cloned().collect()
+        } else {
+            Vec::new()
+        };
+        let message = if series.finished {
+            let names: Vec<_> = winners.iter().map(|s| s.player_name.as_str()).collect();
+            format!("系列赛结束！{} 以 {} 分获胜", names.join("、"), winners.first().map_or(0, |s| s.points))
+        } else {
+            format!("系列赛第 {}/{} 局结束", series.games_played, series.total_games)
+        };
+        let update = ServerMessage::SeriesUpdate {
+            message,
+            series: series.clone(),
+            winners,
+        };
+        drop(series_guard);
+
+        self.broadcast(update)
+        .await;
+    }
+
+    /// 房间开启猜词小游戏时开始小游戏：平民猜卧底词，卧底和白板猜平民词
+    ///
+    /// 卧底已在猜词阶段猜中平民词时平民词已公开，只有平民参与。没有可参与的玩家时返回false。
+    async fn start_word_quiz(&self, winner: crate::game::Role) -> bool {
+        if !self.settings.read().await.word_quiz {
+            return false;
+        }
+
+        let state = self.state.read().await;
+        let players = state.get_players_with_roles();
+        let civilian_word_revealed = state.get_word_guess().is_some_and(|guess| guess.correct);
+        drop(state);
+
+        let word_of = |role: crate::game::Role| {
+            players
+                .iter()
+                .find(|player| player.role == Some(role))
+                .and_then(|player| player.word.clone())
+        };
+        let (Some(civilian_word), Some(undercover_word)) =
+            (word_of(crate::game::Role::Civilian), word_of(crate::game::Role::Undercover))
+        else {
+            return false;
+        };
+
+        let targets: HashMap<PlayerId, String> = players
+            .iter()
+            .filter(|player| !self.is_bot(&player.id))
+            .filter_map(|player| match player.role? {
+                crate::game::Role::Civilian => Some((player.id.clone(), undercover_word.clone())),
+                _ if civilian_word_revealed => None,
+                _ => Some((player.id.clone(), civilian_word.clone())),
+            })
+            .collect();
+        if targets.is_empty() {
+            return false;
+        }
+
+        let time_limit = config::Config::get().word_quiz_time_limit();
+        for player_id in targets.keys() {
+            let guessing_undercover = players
+                .iter()
+                .any(|player| player.id == *player_id && player.role == Some(crate::game::Role::Civilian));
+            self.send_to_player(player_id, ServerMessage::WordQuizStart {
+                message: if guessing_undercover {
+                    "游戏结束！猜猜卧底的词语是什么，猜中可获得加分"
+                } else {
+                    "游戏结束！猜猜平民的词语是什么，猜中可获得加分"
+                }
+                .to_string(),
+                target: if guessing_undercover { "undercover_word" } else { "civilian_word" }.to_string(),
+                seconds: time_limit.as_secs(),
+            })
+            .await;
+        }
+
+        *self.word_quiz.lock().await = Some(WordQuiz {
+            winner,
+            targets,
+            answers: HashMap::new(),
+            deadline: Utc::now() + chrono::Duration::from_std(time_limit).unwrap_or_default(),
+        });
+        true
+    }
+
+    /// 猜词小游戏超时后公布结果
+    async fn expire_word_quiz(&self) {
+        let expired = self
+            .word_quiz
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|quiz| Utc::now() >= quiz.deadline);
+        if expired {
+            self.finish_word_quiz().await;
+        }
+    }
+
+    /// 结束猜词小游戏：公布每名玩家的答案，再公布双方词语并为猜中的玩家计入系列赛加分
+    async fn finish_word_quiz(&self) {
+        let Some(quiz) = self.word_quiz.lock().await.take() else {
+            return;
+        };
+
+        let results: Vec<_> = quiz
+            .targets
+            .keys()
+            .map(|player_id| {
+                let answer = quiz.answers.get(player_id);
+                serde_json::json!({
+                    "player_id": player_id,
+                    "player_name": self.players.get(player_id).map(|p| p.name.clone()),
+                    "guess": answer.map(|(guess, _)| guess),
+                    "correct": answer.is_some_and(|(_, correct)| *correct)
+                })
+            })
+            .collect();
+        let bonus: HashSet<PlayerId> = quiz
+            .answers
+            .iter()
+            .filter(|(_, (_, correct))| *correct)
+            .map(|(player_id, _)| player_id.clone())
+            .collect();
+
+        self.broadcast(ServerMessage::WordQuizResult {
+            message: format!("猜词小游戏结束，{} 人猜中", bonus.len()),
+            results,
+        })
+        .await;
+
+        self.broadcast_game_over(quiz.winner).await;
+        self.broadcast_session_stats().await;
+        self.score_series(quiz.winner, &bonus).await;
+    }
+
+    /// 开始游戏前检查已准备的真人玩家是否仍然在线，取消断线玩家的准备状态并告知大厅
+    ///
+    /// is_connected 判断玩家在本房间是否有存活的连接，有断线玩家时返回错误阻止开始。
+    /// 请求者不是房主时不做处理，交由开始游戏流程拒绝。
+    pub async fn clear_stale_ready<F>(&self, requester: &PlayerId, is_connected: F) -> Result<()>
+    where
+        F: Fn(&PlayerId) -> bool,
+    {
+        self.check_frozen("start_game").await?;
+        if !self.is_host(requester).await {
+            return Ok(());
+        }
+
+        let stale: Vec<PlayerId> = {
+            let state = self.state.read().await;
+            let GameState::Lobby { ready_players, .. } = &*state else {
+                return Ok(());
+            };
+            ready_players
+                .iter()
+                .filter(|id| !self.is_bot(id) && !is_connected(id))
+                .cloned()
+                .collect()
+        };
+        if stale.is_empty() {
+            return Ok(());
+        }
+
+        let cleared = self.state.write().await.unready_players(&stale);
+        if cleared.is_empty() {
+            return Ok(());
+        }
+        // 取消准备不经过游戏事件，需要写入快照，否则下次保存时没有待写事件会跳过这次变化
+        self.save_state_snapshot().await?;
+        let names: Vec<String> = cleared
+            .iter()
+            .map(|id| self.players.get(id).map(|p| p.name.clone()).unwrap_or_else(|| id.clone()))
+            .collect();
+        info!("房间 {} 开始游戏前取消断线玩家的准备状态: {:?}", self.id, cleared);
+
+        self.broadcast(
+            Notification::new(format!("{} 已断线，已取消准备，暂时无法开始游戏", names.join("、")))
+                .detail(NotificationDetail::Players { player_ids: cleared })
+                .into(),
+        )
+        .await;
+        self.broadcast_state_update().await;
+
+        Err(crate::Error::Rule(
+            ErrorCode::InvalidState,
+            format!("{} 已断线，请等待重连或重新准备", names.join("、")),
+        ))
+    }
+
+    /// 检查大厅内是否所有玩家都已准备且人数满足要求
+    async fn all_players_ready(&self) -> bool {
+        let state = self.state.read().await;
+        match &*state {
+            GameState::Lobby { players, ready_players, min_players, .. } => {
+                players.len() == ready_players.len() && ready_players.len() >= *min_players
+            }
+            _ => false,
+        }
+    }
+
+    /// 处理房主修改房间设置消息
+    async fn handle_update_settings(&self, player_id: PlayerId, patch: serde_json::Map<String, serde_json::Value>) -> Result<()> {
+
+        if !self.is_host(&player_id).await {
+            return Err(crate::Error::Game("只有房主可以修改房间设置".to_string()));
+        }
+
+        let state_type = self.state.read().await.get_state_type();
+        if !matches!(
+            state_type,
+            crate::message::GameStateType::Lobby | crate::message::GameStateType::GameOver
+        ) {
+            return Err(crate::Error::Game("游戏进行中不能修改房间设置".to_string()));
+        }
+
+        // 只覆盖消息中提供的字段，其余保持不变
+        let mut settings = self.settings.write().await;
+        let mut merged = serde_json::to_value(&*settings)
+            .map_err(|e| crate::Error::Game(e.to_string()))?;
+        if let Some(fields) = merged.as_object_mut() {
+            for (key, value) in patch {
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+        let mut updated: GameSettings = serde_json::from_value(merged)
+            .map_err(|e| crate::Error::Game(format!("无效的房间设置: {}", e)))?;
+        updated.validate().map_err(crate::Error::Game)?;
+
+        // "random" 与不指定分类等价
+        if updated.word_category.as_deref() == Some("random") {
+            updated.word_category = None;
+        }
+        if let Some(category) = &updated.word_category
+            && self.word_bank.get_category_word_count(category) == 0
+        {
+            return Err(crate::Error::Game(format!("词库中没有分类: {}", category)));
+        }
+        *settings = updated.clone();
+        drop(settings);
+
+        self.broadcast(Notification::new("房主更新了房间设置").detail(NotificationDetail::Settings { settings: updated }).into())
+        .await;
+        self.broadcast_state_update().await;
+
+        Ok(())
+    }
+
+    /// 处理玩家修改通知偏好消息
+    async fn handle_set_preferences(&self, player_id: PlayerId, patch: serde_json::Map<String, serde_json::Value>) -> Result<()> {
+
+        if !self.players.contains_key(&player_id) {
+            return Err(crate::Error::Game("玩家不存在".to_string()));
+        }
+
+        // 只覆盖消息中提供的字段，其余保持不变
+        let current = self
+            .preferences
+            .get(&player_id)
+            .map(|entry| entry.value().clone())
+            .unwrap_or_default();
+        let mut merged = serde_json::to_value(&current)
+            .map_err(|e| crate::Error::Game(e.to_string()))?;
+        if let Some(fields) = merged.as_object_mut() {
+            for (key, value) in patch {
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+        let updated: NotificationPreferences = serde_json::from_value(merged)
+            .map_err(|e| crate::Error::Game(format!("无效的通知偏好: {}", e)))?;
+        self.preferences.insert(player_id.clone(), updated.clone());
+
+        self.send_to_player(
+            &player_id,
+            ServerMessage::PreferencesUpdated { preferences: updated },
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// 获取当前房间设置
+    pub async fn settings(&self) -> GameSettings {
+        self.settings.read().await.clone()
+    }
+
+    /// 获取房间的游戏参数
+    pub fn params(&self) -> GameParams {
+        self.params.clone()
+    }
+
+    /// 获取房间使用的词库
+    pub(crate) fn word_bank(&self) -> &Arc<WordBank> {
+        &self.word_bank
+    }
+
+    /// 检查房主能否添加指定数量的电脑玩家：仅限大厅阶段的普通房间，且不超过机器人上限和房间人数上限
+    pub(crate) async fn check_bot_seats(&self, player_id: &PlayerId, count: usize) -> Result<()> {
+        self.check_frozen("add_bots").await?;
+        if !self.is_host(player_id).await {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "只有房主可以添加电脑玩家".to_string()));
+        }
+        if self.kind == RoomKind::Tutorial {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "教程房间不能添加电脑玩家".to_string()));
+        }
+        if count == 0 {
+            return Err(crate::Error::Rule(ErrorCode::InvalidAction, "添加数量至少为1".to_string()));
+        }
+        if self.state.read().await.get_state_type() != crate::message::GameStateType::Lobby {
+            return Err(crate::Error::Rule(ErrorCode::GameStarted, "游戏已开始，不能添加电脑玩家".to_string()));
+        }
+        let max_bots = crate::config::Config::get().bots.max_per_room;
+        if self.bots.len() + count > max_bots {
+            return Err(crate::Error::Rule(
+                ErrorCode::RoomFull,
+                format!("房间机器人数量已达上限（{}个）", max_bots),
+            ));
+        }
+        if self.players.len() + count > self.params.max_players {
+            return Err(crate::Error::Rule(ErrorCode::RoomFull, "房间剩余座位不足".to_string()));
+        }
+        Ok(())
+    }
+
+    /// 获取房间内玩家数量
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+
+    /// 获取房主ID
+    pub async fn get_host(&self) -> PlayerId {
+        self.host.lock().await.clone()
+    }
+
+    /// 获取房主昵称，房主不在房间内时为空
+    pub async fn host_name(&self) -> Option<String> {
+        let host = self.get_host().await;
+        self.players.get(&host).map(|player| player.name.clone())
+    }
+
+    /// 获取当前游戏阶段
+    pub async fn phase(&self) -> crate::message::GameStateType {
+        self.state.read().await.get_state_type()
+    }
+
+    /// 未脱敏的完整游戏状态（含所有玩家的身份和词语），仅供管理员排查卡住的对局
+    pub async fn raw_state(&self) -> GameState {
+        self.state.read().await.clone()
+    }
+
+    /// 待揭晓淘汰结果的时间点
+    pub async fn pending_reveal_at(&self) -> Option<chrono::DateTime<Utc>> {
+        *self.pending_reveal.lock().await
+    }
+
+    /// 获取房间人数上限
+    pub fn max_players(&self) -> usize {
+        self.params.max_players
+    }
+
+    /// 获取包含角色信息的玩家列表（仅供服务端内部使用，不可直接下发给客户端）
+    pub(crate) async fn players_with_roles(&self) -> Vec<Player> {
+        self.state.read().await.get_players_with_roles()
+    }
+
+    /// 为发往玩家的消息分配序号，真人玩家的消息同时缓存以便重连后补发
+    fn sequence(&self, player_id: &PlayerId, message: GameMessage) -> SequencedMessage {
+        let capacity = if self.is_bot(player_id) {
+            0
+        } else {
+            config::Config::get().websocket.replay_buffer_size
+        };
+        self.outboxes
+            .entry(player_id.clone())
+            .or_default()
+            .assign(message, capacity)
+    }
+
+    /// 分配序号后放入玩家的待发队列，由 schedule_flush 或 flush_player 发出
+    fn enqueue(&self, player_id: &PlayerId, message: GameMessage) {
+        let sequenced = self.sequence(player_id, message);
+        self.outgoing.enqueue(player_id, sequenced);
     }
 
-    /// 处理房主踢人消息
-    async fn handle_kick(&self, message: GameMessage) -> Result<()> {
-        let message_data = message.data.clone();
-        let kicker_id = message_data["player_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的踢人者ID".to_string()))?
-            .to_string();
-        let target_id = message_data["target_id"]
-            .as_str()
-            .ok_or_else(|| crate::Error::Game("无效的目标ID".to_string()))?
-            .to_string();
+    /// 合并窗口结束后发出所有待发消息，窗口内的后续消息随同一次刷新发出；窗口为0时立即发送
+    async fn schedule_flush(&self) {
+        if self.outgoing.window.is_zero() {
+            self.outgoing.flush(&self.player_channels, None).await;
+            return;
+        }
+        if self.outgoing.flush_scheduled.swap(true, Ordering::SeqCst) {
+            return;
+        }
 
-        let mut state = self.state.write().await;
-        let event = state
-            .kick_player(kicker_id, target_id)
-            .map_err(|e| crate::Error::Game(e))?;
+        let outgoing = self.outgoing.clone();
+        let player_channels = self.player_channels.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(outgoing.window).await;
+            outgoing.flush_scheduled.store(false, Ordering::SeqCst);
+            outgoing.flush(&player_channels, None).await;
+        });
+    }
 
-        // 处理事件
-        drop(state);
-        Box::pin(self.handle_game_event(event)).await?;
+    /// 立即发出指定玩家的待发消息，用于移除玩家连接之前
+    async fn flush_player(&self, player_id: &PlayerId) {
+        self.outgoing.flush(&self.player_channels, Some(player_id)).await;
+    }
 
-        Ok(())
+    /// 为断线中的玩家缓存广播消息，倒计时推送和玩家屏蔽的通知不缓存
+    fn buffer_for_disconnected(&self, message: &GameMessage, category: Option<NotificationCategory>) {
+        if category == Some(NotificationCategory::Countdown) {
+            return;
+        }
+        for entry in self.disconnected_players.iter() {
+            let player_id = entry.key();
+            let muted = category.is_some_and(|category| {
+                self.preferences
+                    .get(player_id)
+                    .is_some_and(|preferences| !preferences.allows(category))
+            });
+            if !muted && !self.player_channels.contains_key(player_id) {
+                self.sequence(player_id, message.clone());
+            }
+        }
     }
 
-    /// 获取房间内玩家数量
-    pub fn player_count(&self) -> usize {
-        self.players.len()
+    /// 记录玩家确认收到的消息序号，返回仍未确认的消息数；确认了尚未发出的序号时返回 None
+    pub fn acknowledge(&self, player_id: &PlayerId, seq: u64) -> Option<u64> {
+        self.outboxes.get_mut(player_id)?.ack(seq)
     }
 
-    /// 获取房主ID
-    pub async fn get_host(&self) -> PlayerId {
-        self.host.lock().await.clone()
+    /// 发给玩家且序号大于 last_seq 的消息，其中有消息已不在缓存中时返回 None
+    pub fn missed_messages(&self, player_id: &PlayerId, last_seq: u64) -> Option<Vec<SequencedMessage>> {
+        match self.outboxes.get(player_id) {
+            Some(outbox) => outbox.since(last_seq),
+            None => (last_seq == 0).then(Vec::new),
+        }
+    }
+
+    /// 单独发送消息给指定玩家
+    pub async fn send_to_player(&self, player_id: &PlayerId, message: ServerMessage) {
+        if self.player_channels.contains_key(player_id) {
+            self.enqueue(player_id, message.into());
+            self.schedule_flush().await;
+        }
     }
 
     /// 检查玩家是否为房主
@@ -741,43 +3152,443 @@ impl Room {
         *host == *player_id
     }
 
-    /// 保存房间状态到存储
+    /// 把自上次保存以来的状态变化写入事件日志：能重放的事件只追加事件本身，其余情况写入快照
     pub async fn save_state(&self) -> Result<()> {
+        self.write_state_log(false).await
+    }
+
+    /// 写入当前状态的完整快照，用于不经过游戏事件直接修改状态的操作
+    pub async fn save_state_snapshot(&self) -> Result<()> {
+        self.write_state_log(true).await
+    }
+
+    async fn write_state_log(&self, force_snapshot: bool) -> Result<()> {
         let state = self.state.read().await;
-        self.storage
-            .save_room_state(self.id.clone(), &*state)
-            .await
-            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        let events = std::mem::take(&mut *self.pending_events.lock().await);
+        let mut cursor = self.state_log.lock().await;
+        if events.is_empty() && !force_snapshot && cursor.replayed.is_some() {
+            return Ok(());
+        }
+        let records = cursor.next_records(&state, events, force_snapshot);
+        drop(state);
+
+        for record in &records {
+            if let Err(e) = self.storage.append_state_record(&self.id, record).await {
+                // 日志与内存中的进度不再一致，下次保存时重新写入快照
+                *cursor = StateLogCursor::default();
+                return Err(crate::Error::Storage(e.to_string()));
+            }
+        }
         Ok(())
     }
 
+    /// 生成房间检查点
+    pub async fn checkpoint(&self, instance_id: &str) -> RoomCheckpoint {
+        let settings = self.settings.read().await.clone();
+        let state = self.state.read().await.clone();
+        RoomCheckpoint {
+            room_id: self.id.clone(),
+            kind: self.kind,
+            visibility: self.visibility,
+            params: self.params.clone(),
+            settings,
+            host: self.host.lock().await.clone(),
+            state,
+            players: self.players.iter().map(|entry| entry.value().clone()).collect(),
+            player_order: self.player_order.lock().await.clone(),
+            bots: self.bots.iter().map(|id| id.clone()).collect(),
+            stickers: self.stickers.read().await.clone(),
+            resume_tokens: self
+                .resume_tokens
+                .iter()
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect(),
+            invites: self.invites.iter().map(|entry| entry.value().clone()).collect(),
+            frozen: self.frozen.lock().await.clone(),
+            created_at: self.created_at,
+            instance_id: instance_id.to_string(),
+            saved_at: Utc::now(),
+        }
+    }
+
+    /// 恢复的房间若对局仍在进行，重新占用对局名额
+    pub async fn resume_game_slot(&self) {
+        let state_type = self.state.read().await.get_state_type();
+        if matches!(
+            state_type,
+            crate::message::GameStateType::Lobby | crate::message::GameStateType::GameOver
+        ) {
+            return;
+        }
+        let mut slot = self.game_slot.lock().await;
+        if slot.is_none() {
+            *slot = self.game_slots.try_acquire();
+        }
+    }
+
+    /// 使用恢复令牌查找对应玩家，令牌匹配时返回用于重新接入的用户信息
+    pub fn resume_user(&self, token: &str) -> Option<crate::user::User> {
+        let player_id = self
+            .resume_tokens
+            .iter()
+            .find(|entry| !token.is_empty() && entry.value() == token)
+            .map(|entry| entry.key().clone())?;
+        let player = self.players.get(&player_id)?;
+        let now = Utc::now();
+        Some(crate::user::User {
+            id: player.id.clone(),
+            username: player.name.clone(),
+            nickname: None,
+            avatar: None,
+            avatar_source: None,
+            created_at: now,
+            last_login: now,
+        })
+    }
+
+    /// 主备模式下为真人玩家签发恢复令牌，同一玩家在房间内的令牌保持不变
+    async fn issue_resume_token(&self, player_id: &PlayerId) {
+        if !config::Config::get().cluster.enabled || self.is_bot(player_id) {
+            return;
+        }
+        let token = self
+            .resume_tokens
+            .entry(player_id.clone())
+            .or_insert_with(|| uuid::Uuid::new_v4().simple().to_string())
+            .clone();
+        self.send_to_player(player_id, ServerMessage::ResumeToken { token })
+        .await;
+    }
+
     /// 从存储加载房间状态
     pub async fn load_state(&self) -> Result<()> {
-        if let Some(state) = self
+        if let Some(mut state) = self
             .storage
             .load_room_state(self.id.clone())
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?
         {
+            self.restore_chat(&mut state).await;
             let mut current_state = self.state.write().await;
             *current_state = state;
         }
         Ok(())
     }
 
+    /// 保存一条聊天消息到房间的聊天记录，写入失败不影响聊天
+    async fn save_chat_message(&self, channel: ChatChannel, message: &ChatMessage) {
+        if let Err(e) = self.storage.append_chat_message(&self.id, channel, message).await {
+            error!("保存房间 {} 聊天记录失败: {}", self.id, e);
+        }
+    }
+
+    /// 从单独保存的聊天记录中恢复状态的聊天消息
+    async fn restore_chat(&self, state: &mut GameState) {
+        let load = |channel| async move {
+            self.storage.load_chat_log(&self.id, channel).await.unwrap_or_else(|e| {
+                error!("读取房间 {} 聊天记录失败: {}", self.id, e);
+                Vec::new()
+            })
+        };
+        let chat = load(ChatChannel::Public).await;
+        let eliminated_chat = load(ChatChannel::Eliminated).await;
+        state.restore_chat(chat, eliminated_chat);
+    }
+
+    /// 记录玩家操作，超出上限时丢弃最早的记录
+    async fn record_action(&self, player_id: PlayerId, kind: ActionKind, content: String) {
+        let mut log = self.action_log.lock().await;
+        if log.len() >= MAX_GAME_ACTIONS {
+            log.pop_front();
+        }
+        log.push_back(GameAction {
+            player_id,
+            kind,
+            content,
+            timestamp: Utc::now(),
+        });
+    }
+
     /// 保存游戏结果
     pub async fn save_game_result(&self, winner: crate::game::Role) -> Result<()> {
         let state = self.state.read().await;
         let players = state.get_players();
+        let conceded_by = state.get_conceded_by();
+        drop(state);
+        let result = GameResult {
+            room_id: self.id.clone(),
+            winner,
+            timestamp: Utc::now(),
+            conceded_by,
+            clock: self.clock.lock().await.stats(Utc::now()),
+            actions: self.action_log.lock().await.iter().cloned().collect(),
+            game_id: self.current_game_id().await,
+            players,
+        };
+        let leaderboard_players = self.leaderboard_players(&result.players).await;
+        let history_players = self.history_players(&result.players);
         self.storage
-            .save_game_result(self.id.clone(), winner, &players)
+            .save_game_result(&result, &leaderboard_players, &history_players)
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        self.update_player_stats(&result).await;
+        self.record_playtime(&result).await;
+        self.post_result_to_chatroom(&result);
         Ok(())
     }
 
+    /// 公开房间的对局结束后向摸鱼派聊天室发送战报，不展示隐藏了个人资料的玩家，发送失败只记录日志
+    fn post_result_to_chatroom(&self, result: &GameResult) {
+        let config = &config::Config::get().chatroom;
+        if !config.post_game_results
+            || config.api_key.is_empty()
+            || !self.is_listed()
+            || result.players.len() < config.min_players
+        {
+            return;
+        }
+
+        let result = result.clone();
+        let storage = self.storage.clone();
+        let room_id = self.id.clone();
+        tokio::spawn(async move {
+            // 隐藏了个人资料的玩家不出现在战报中，查询失败时同样不展示
+            let mut hidden = std::collections::HashSet::new();
+            for player in &result.players {
+                if !storage
+                    .get_profile_settings(&player.id)
+                    .await
+                    .is_ok_and(|settings| !settings.private)
+                {
+                    hidden.insert(player.id.clone());
+                }
+            }
+            let content = crate::chatroom::format_game_result(&result, &hidden);
+            if let Err(e) = crate::chatroom::send_message(&content).await {
+                warn!("房间 {} 的战报发送到摸鱼派聊天室失败: {}", room_id, e);
+            }
+        });
+    }
+
+    /// 是否记录玩家的生涯统计、游戏时长和排名，机器人和游客都不记录
+    fn keeps_records(&self, player_id: &PlayerId) -> bool {
+        !self.is_bot(player_id) && !is_guest_id(player_id)
+    }
+
+    /// 累加真人玩家当天的对局数和游戏时长，接近或达到每日上限时私下提醒
+    async fn record_playtime(&self, result: &GameResult) {
+        let seconds = result.clock.total_ms / 1000;
+        for player in result.players.iter().filter(|p| self.keeps_records(&p.id)) {
+            if let Err(e) = self.storage.record_playtime(&player.id, seconds).await {
+                error!("记录玩家 {} 游戏时长失败: {}", player.id, e);
+                continue;
+            }
+            if self.storage.is_degraded() {
+                continue;
+            }
+
+            let (usage, limits) = match (
+                self.storage.get_daily_playtime(&player.id).await,
+                self.storage.get_play_limits(&player.id).await,
+            ) {
+                (Ok(usage), Ok(limits)) => (usage, limits.effective()),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!("读取玩家 {} 每日游戏上限失败: {}", player.id, e);
+                    continue;
+                }
+            };
+            if limits.is_unlimited() || limits.usage_ratio(&usage) < PLAY_LIMIT_WARNING_RATIO {
+                continue;
+            }
+
+            let message = if limits.is_reached(&usage) {
+                "今天的游戏时间已用完，休息一下吧，明天再来".to_string()
+            } else {
+                format!("今天已玩 {} 局、{} 分钟，即将达到每日上限", usage.games, usage.seconds / 60)
+            };
+            self.send_to_player(&player.id, ServerMessage::PlayLimitWarning {
+                message,
+                usage,
+                limit: limits,
+                reached: limits.is_reached(&usage),
+            })
+            .await;
+        }
+    }
+
+    /// 开始游戏前检查真人玩家是否已达到每日游戏上限，只在开局时拦截，不会中断进行中的对局
+    ///
+    /// Redis 不可用或读取失败时不做限制。
+    async fn check_play_limits(&self) -> Result<()> {
+        if self.storage.is_degraded() {
+            return Ok(());
+        }
+
+        let players: Vec<(PlayerId, String)> = self
+            .players
+            .iter()
+            .filter(|entry| !self.is_bot(entry.key()))
+            .map(|entry| (entry.key().clone(), entry.value().name.clone()))
+            .collect();
+        let mut blocked = Vec::new();
+        for (player_id, name) in players {
+            let (usage, limits) = match (
+                self.storage.get_daily_playtime(&player_id).await,
+                self.storage.get_play_limits(&player_id).await,
+            ) {
+                (Ok(usage), Ok(limits)) => (usage, limits.effective()),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!("读取玩家 {} 每日游戏上限失败: {}", player_id, e);
+                    continue;
+                }
+            };
+            if limits.is_reached(&usage) {
+                blocked.push((player_id, name));
+            }
+        }
+        if blocked.is_empty() {
+            return Ok(());
+        }
+
+        let names: Vec<&str> = blocked.iter().map(|(_, name)| name.as_str()).collect();
+        self.broadcast(
+            Notification::new(format!("{} 今天的游戏时间已用完，无法开始新的一局", names.join("、")))
+                .detail(NotificationDetail::Players { player_ids: blocked.iter().map(|(id, _)| id.clone()).collect() })
+                .into(),
+        )
+        .await;
+        Err(crate::Error::Rule(
+            ErrorCode::PlayLimitReached,
+            format!("{} 已达到每日游戏上限", names.join("、")),
+        ))
+    }
+
+    /// 将本局结果累加到真人玩家的生涯统计，游客和教程房间不计入
+    async fn update_player_stats(&self, result: &GameResult) {
+        // Redis 不可用时读到的是默认统计，写回会覆盖真实数据
+        if self.kind == RoomKind::Tutorial || self.storage.is_degraded() {
+            return;
+        }
+
+        for player in result.players.iter().filter(|p| self.keeps_records(&p.id)) {
+            let mut stats = match self.storage.get_player_stats(&player.id).await {
+                Ok(stats) => stats,
+                Err(e) => {
+                    error!("读取玩家 {} 统计失败: {}", player.id, e);
+                    continue;
+                }
+            };
+            stats.record_game(&player.id, result);
+            if let Err(e) = self.storage.update_player_stats(&player.id, &stats).await {
+                error!("更新玩家 {} 统计失败: {}", player.id, e);
+            }
+        }
+    }
+
+    /// 记入个人对局历史的玩家：与生涯统计一致，游客和教程房间不计入
+    fn history_players(&self, players: &[Player]) -> Vec<PlayerId> {
+        if self.kind == RoomKind::Tutorial {
+            return Vec::new();
+        }
+        players
+            .iter()
+            .filter(|player| self.keeps_records(&player.id))
+            .map(|player| player.id.clone())
+            .collect()
+    }
+
+    /// 计入排行榜和等级分的玩家：排位赛中的真人玩家，游客和教程房间不计入
+    async fn leaderboard_players(&self, players: &[Player]) -> Vec<PlayerId> {
+        if self.kind == RoomKind::Tutorial || !self.settings.read().await.ranked {
+            return Vec::new();
+        }
+        players
+            .iter()
+            .filter(|player| self.keeps_records(&player.id))
+            .map(|player| player.id.clone())
+            .collect()
+    }
+
+    /// 排位赛结束后更新真人玩家的等级分并广播变化
+    async fn update_ratings(&self, winner: crate::game::Role) {
+        let players = self.state.read().await.get_players();
+        let ranked = self.leaderboard_players(&players).await;
+        // Redis 不可用时读到的是初始等级分，本局不结算等级分
+        if ranked.is_empty() || self.storage.is_degraded() {
+            return;
+        }
+
+        let players: Vec<Player> = players
+            .into_iter()
+            .filter(|player| ranked.contains(&player.id))
+            .collect();
+        let changes = match self.storage.update_ratings(&players, winner).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                error!("更新房间 {} 玩家等级分失败: {}", self.id, e);
+                return;
+            }
+        };
+
+        for change in &changes {
+            self.ratings.insert(change.player_id.clone(), change.new_rating);
+        }
+
+        self.broadcast(ServerMessage::RatingUpdate { changes })
+        .await;
+    }
+
+    /// 将事件追加到本局回放，从开局记录到游戏结束，写入失败不影响对局
+    async fn record_replay(&self, event: &GameEvent) {
+        let mut replay = self.replay.lock().await;
+        if matches!(event, GameEvent::GameStarted(..)) {
+            *replay = Some(ReplayRecorder {
+                game_id: uuid::Uuid::new_v4().simple().to_string(),
+                next_seq: 0,
+                finished: false,
+            });
+        }
+        let Some(recorder) = replay.as_mut().filter(|recorder| !recorder.finished) else {
+            return;
+        };
+
+        let entry = ReplayEntry {
+            seq: recorder.next_seq,
+            timestamp: Utc::now(),
+            event: event.clone(),
+        };
+        recorder.next_seq += 1;
+        if matches!(event, GameEvent::GameOver(_)) {
+            recorder.finished = true;
+        }
+
+        if let Err(e) = self
+            .storage
+            .append_replay_event(&self.id, &recorder.game_id, &entry)
+            .await
+        {
+            error!("记录房间 {} 对局回放失败: {}", self.id, e);
+        }
+    }
+
+    /// 当前或刚结束的对局ID
+    async fn current_game_id(&self) -> Option<String> {
+        self.replay.lock().await.as_ref().map(|recorder| recorder.game_id.clone())
+    }
+
+    /// 根据当前状态更新本局计时
+    async fn observe_clock(&self) {
+        let state = self.state.read().await;
+        self.clock.lock().await.observe(&state, Utc::now());
+    }
+
     /// 检查游戏状态超时
     pub async fn check_timeout(&self) -> Result<()> {
+        // 等待分段揭晓期间，由揭晓流程推进结果阶段
+        if self.pending_reveal.lock().await.is_some() {
+            return Ok(());
+        }
+
         let state = self.state.read().await;
         let timeout_result = state.check_timeout();
         drop(state);
@@ -787,8 +3598,8 @@ impl Room {
             TimeoutResult::DescribeTimeout(_) => {
                 let mut state = self.state.write().await;
                 let event = state
-                    .handle_describe_timeout()
-                    .map_err(|e| crate::Error::Game(e))?;
+                    .handle_describe_timeout(&self.params)
+                    .map_err(crate::Error::Game)?;
                 drop(state);
                 Box::pin(self.handle_game_event(event)).await?;
                 Ok(())
@@ -796,17 +3607,27 @@ impl Room {
             TimeoutResult::VoteTimeout => {
                 let mut state = self.state.write().await;
                 let event = state
-                    .handle_vote_timeout()
-                    .map_err(|e| crate::Error::Game(e))?;
+                    .handle_vote_timeout(&self.params)
+                    .map_err(crate::Error::Game)?;
                 drop(state);
                 Box::pin(self.handle_game_event(event)).await?;
                 Ok(())
             }
             TimeoutResult::ResultTimeout => {
+                let settings = self.settings.read().await.clone();
+                let mut state = self.state.write().await;
+                let event = state
+                    .process_result_phase(&settings, &self.params)
+                    .map_err(crate::Error::Game)?;
+                drop(state);
+                Box::pin(self.handle_game_event(event)).await?;
+                Ok(())
+            }
+            TimeoutResult::GuessTimeout => {
                 let mut state = self.state.write().await;
                 let event = state
-                    .process_result_phase()
-                    .map_err(|e| crate::Error::Game(e))?;
+                    .handle_guess_timeout()
+                    .map_err(crate::Error::Game)?;
                 drop(state);
                 Box::pin(self.handle_game_event(event)).await?;
                 Ok(())
@@ -816,28 +3637,45 @@ impl Room {
 
     /// 处理游戏事件
     async fn handle_game_event(&self, event: GameEvent) -> Result<()> {
+        // 倒计时不会改变阶段，其余事件都可能伴随阶段切换
+        if !matches!(event, GameEvent::CountdownUpdate(_)) {
+            self.observe_clock().await;
+            self.record_replay(&event).await;
+            // 聊天记录单独持久化，不写入状态事件日志
+            if !matches!(event, GameEvent::ChatMessageAdded(_) | GameEvent::EliminatedChatMessageAdded(_)) {
+                self.pending_events.lock().await.push(event.clone());
+            }
+        }
+
         match event {
             GameEvent::PlayerJoined(player) => {
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 加入了游戏", player.name),
-                        "total_players": self.players.len()
-                    }),
-                })
-                .await;
+                if self.should_announce_presence(&player.id) {
+                    self.broadcast_category(
+                        NotificationCategory::Presence,
+                        Notification::new(format!("玩家 {} 加入了游戏", player.name))
+                            .ui_event(UiEvent::PlayerJoined)
+                            .detail(NotificationDetail::PlayerJoined { total_players: self.players.len() })
+                            .into(),
+                    )
+                    .await;
+                }
                 self.broadcast_state_update().await;
                 // 保存状态
                 self.save_state().await?;
             }
-            GameEvent::PlayerLeft(player) => {
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 离开了游戏", player.name)
-                    }),
-                })
-                .await;
+            GameEvent::PlayerLeft(player, reason) => {
+                // 被踢出、被管理员移出等由他人触发的离开总是通知
+                let involuntary = matches!(reason, LeaveReason::Kicked | LeaveReason::Banned);
+                if involuntary || self.should_announce_presence(&player.id) {
+                    self.broadcast_category(
+                        NotificationCategory::Presence,
+                        Notification::new(format!("玩家 {} {}", player.name, reason.describe()))
+                            .ui_event(UiEvent::PlayerLeft)
+                            .detail(NotificationDetail::PlayerLeft { player_id: player.id, reason })
+                            .into(),
+                    )
+                    .await;
+                }
                 self.broadcast_state_update().await;
                 // 保存状态
                 self.save_state().await?;
@@ -857,47 +3695,89 @@ impl Room {
                 };
                 drop(state);
 
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 已准备", player_name),
-                        "ready_count": ready_count,
-                        "min_players": config::Config::get().game.min_players
-                    }),
-                })
+                self.broadcast(
+                    Notification::new(format!("玩家 {} 已准备", player_name))
+                        .detail(NotificationDetail::ReadyCount { ready_count, min_players: self.params.min_players })
+                        .into(),
+                )
                 .await;
 
                 // 广播状态更新
                 self.broadcast_state_update().await;
 
-                // 只有当所有玩家都准备好时，才自动开始游戏
-                if can_start {
-                    // 检查是否所有玩家都已准备
-                    let state = self.state.read().await;
-                    let all_players_ready = match &*state {
-                        GameState::Lobby { players, ready_players, .. } => {
-                            players.len() == ready_players.len() && ready_players.len() >= config::Config::get().game.min_players
-                        },
-                        _ => false
+                // 所有玩家都准备好时，提示等待房主开始游戏
+                if can_start && self.all_players_ready().await {
+                    let host_name = {
+                        let host = self.host.lock().await;
+                        self.players
+                            .get(&*host)
+                            .map(|p| p.name.clone())
+                            .unwrap_or_else(|| "房主".to_string())
                     };
-                    drop(state);
-                    
-                    if all_players_ready {
-                        Box::pin(self.start_game()).await?;
-                    }
+                    self.broadcast(
+                        Notification::new(format!("所有玩家已准备，等待房主 {} 开始游戏", host_name))
+                            .detail(NotificationDetail::WaitingForHost { waiting_for_host: true })
+                            .into(),
+                    )
+                    .await;
                 }
                 // 保存状态
                 self.save_state().await?;
             }
-            GameEvent::GameStarted(..) => {
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": "游戏开始，进入描述阶段"
-                    }),
-                })
+            GameEvent::PlayerUnready(player_id, _) => {
+                let player_name = self
+                    .players
+                    .get(&player_id)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| "未知玩家".to_string());
+
+                let state = self.state.read().await;
+                let ready_count = match &*state {
+                    GameState::Lobby { ready_players, .. } => ready_players.len(),
+                    _ => 0,
+                };
+                drop(state);
+
+                self.broadcast(
+                    Notification::new(format!("玩家 {} 取消了准备", player_name))
+                        .detail(NotificationDetail::ReadyCount { ready_count, min_players: self.params.min_players })
+                        .into(),
+                )
+                .await;
+
+                self.broadcast_state_update().await;
+                self.save_state().await?;
+            }
+            GameEvent::GameStarted(_, plan) => {
+                // 新的一局重新开始记录操作和计时
+                self.action_log.lock().await.clear();
+                // 违规次数按局统计
+                self.strikes.clear();
+                *self.clock.lock().await = GameClock::start(Utc::now());
+                self.observe_clock().await;
+
+                let mut message = format!(
+                    "游戏开始，本局 {} 名平民、{} 名卧底",
+                    plan.civilians, plan.undercovers
+                );
+                if plan.blanks > 0 {
+                    message.push_str(&format!("、{} 名白板", plan.blanks));
+                }
+                message.push_str("，进入描述阶段");
+
+                self.broadcast(
+                    Notification::new(message)
+                        .ui_event(UiEvent::GameStart)
+                        .detail(NotificationDetail::GameStarted { roles: plan })
+                        .into(),
+                )
                 .await;
                 self.broadcast_state_update().await;
+                self.notify_turn().await;
+                // 新的一局从空的被淘汰聊天开始
+                if let Err(e) = self.storage.clear_chat_log(&self.id, ChatChannel::Eliminated).await {
+                    error!("清空房间 {} 被淘汰聊天记录失败: {}", self.id, e);
+                }
                 // 保存状态
                 self.save_state().await?;
             }
@@ -924,12 +3804,9 @@ impl Room {
                         .collect();
 
                     // 广播完整的描述列表
-                    self.broadcast(GameMessage {
-                        type_: "descriptions_update".to_string(),
-                        data: serde_json::json!({
-                            "message": format!("玩家 {} 完成了描述", player_id),
-                            "descriptions": descriptions_list
-                        }),
+                    self.broadcast(ServerMessage::DescriptionsUpdate {
+                        message: Some(format!("玩家 {} 完成了描述", player_id)),
+                        descriptions: descriptions_list,
                     })
                     .await;
                 }
@@ -938,8 +3815,8 @@ impl Room {
                 // 推进描述阶段到下一个玩家
                 let mut state = self.state.write().await;
                 let advance_event = state
-                    .advance_describe_phase()
-                    .map_err(|e| crate::Error::Game(e))?;
+                    .advance_describe_phase(&self.params)
+                    .map_err(crate::Error::Game)?;
                 drop(state);
                 
                 // 递归处理推进事件
@@ -949,7 +3826,7 @@ impl Room {
                 // 保存状态
                 self.save_state().await?;
             }
-            GameEvent::NextPlayer(player_id) => {
+            GameEvent::NextPlayer(player_id, _) => {
                 let player_name = self
                     .players
                     .get(&player_id)
@@ -977,17 +3854,15 @@ impl Room {
                         .collect();
 
                     // 广播完整的描述列表
-                    self.broadcast(GameMessage {
-                        type_: "descriptions_update".to_string(),
-                        data: serde_json::json!({
-                            "message": format!("轮到玩家 {} 描述", player_name),
-                            "descriptions": descriptions_list
-                        }),
+                    self.broadcast(ServerMessage::DescriptionsUpdate {
+                        message: Some(format!("轮到玩家 {} 描述", player_name)),
+                        descriptions: descriptions_list,
                     })
                     .await;
                 }
                 drop(state);
                 self.broadcast_state_update().await;
+                self.notify_turn().await;
                 // 保存状态
                 self.save_state().await?;
             }
@@ -1014,22 +3889,15 @@ impl Room {
                         .collect();
 
                     // 广播描述列表
-                    self.broadcast(GameMessage {
-                        type_: "descriptions_update".to_string(),
-                        data: serde_json::json!({
-                            "descriptions": descriptions_list
-                        }),
+                    self.broadcast(ServerMessage::DescriptionsUpdate {
+                        message: None,
+                        descriptions: descriptions_list,
                     })
                     .await;
                 }
                 drop(state);
 
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": "描述阶段结束，进入投票阶段"
-                    }),
-                })
+                self.broadcast(Notification::new("描述阶段结束，进入投票阶段").ui_event(UiEvent::VoteStart).into())
                 .await;
                 self.broadcast_state_update().await;
                 // 保存状态
@@ -1042,54 +3910,64 @@ impl Room {
                     .get(&voter_id)
                     .map(|p| p.name.clone())
                     .unwrap_or_else(|| "未知玩家".to_string());
+
+                if target_id == ABSTAIN_VOTE {
+                    self.broadcast_category(
+                        NotificationCategory::Votes,
+                        Notification::new(format!("玩家 {} 选择了弃票", voter_name))
+                            .detail(NotificationDetail::Abstained { voter_id, voter_name, target_id, abstain: true })
+                            .into(),
+                    )
+                    .await;
+                    // 保存状态
+                    self.save_state().await?;
+                    return Ok(());
+                }
+
                 let target_name = self
                     .players
                     .get(&target_id)
                     .map(|p| p.name.clone())
                     .unwrap_or_else(|| "未知玩家".to_string());
 
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 投票给了 {}", voter_name, target_name),
-                        "voter_id": voter_id,
-                        "voter_name": voter_name,
-                        "target_id": target_id,
-                        "target_name": target_name
-                    }),
-                })
+                self.broadcast_category(
+                    NotificationCategory::Votes,
+                    Notification::new(format!("玩家 {} 投票给了 {}", voter_name, target_name))
+                        .detail(NotificationDetail::Voted { voter_id, voter_name, target_id, target_name })
+                        .into(),
+                )
                 .await;
                 // 保存状态
                 self.save_state().await?;
             }
             GameEvent::ChatMessageAdded(chat_message) => {
-                self.broadcast(GameMessage {
-                    type_: "chat".to_string(),
-                    data: serde_json::json!({
-                        "player_id": chat_message.player_id.to_string(),
-                        "player_name": chat_message.player_name,
-                        "content": chat_message.content,
-                        "timestamp": chat_message.timestamp.timestamp()
-                    }),
+                // 表情消息单独使用 sticker 类型，方便客户端渲染
+                let payload = ChatPayload {
+                    player_id: chat_message.player_id.clone(),
+                    player_name: chat_message.player_name.clone(),
+                    content: chat_message.content.clone(),
+                    sticker: chat_message.sticker.clone(),
+                    timestamp: chat_message.timestamp.timestamp(),
+                };
+                self.broadcast(if chat_message.sticker.is_some() {
+                    ServerMessage::Sticker(payload)
+                } else {
+                    ServerMessage::Chat(payload)
                 })
                 .await;
-                // 保存状态
-                self.save_state().await?;
+                // 聊天记录单独保存，不需要重新保存整个状态
+                self.save_chat_message(ChatChannel::Public, &chat_message).await;
             }
             GameEvent::EliminatedChatMessageAdded(chat_message) => {
                 // 只向被淘汰的玩家广播被淘汰聊天消息
-                self.broadcast_to_eliminated_players(GameMessage {
-                    type_: "eliminated_chat".to_string(),
-                    data: serde_json::json!({
-                        "player_id": chat_message.player_id.to_string(),
-                        "player_name": chat_message.player_name,
-                        "content": chat_message.content,
-                        "timestamp": chat_message.timestamp.timestamp()
-                    }),
+                self.broadcast_to_eliminated_players(ServerMessage::EliminatedChat {
+                    player_id: chat_message.player_id.clone(),
+                    player_name: chat_message.player_name.clone(),
+                    content: chat_message.content.clone(),
+                    timestamp: chat_message.timestamp.timestamp(),
                 })
                 .await;
-                // 保存状态
-                self.save_state().await?;
+                self.save_chat_message(ChatChannel::Eliminated, &chat_message).await;
             }
             GameEvent::VotePhaseComplete(votes) => {
                 // 处理投票结果
@@ -1100,20 +3978,45 @@ impl Room {
                 // 统计投票
                 let mut vote_count: HashMap<PlayerId, usize> = HashMap::new();
                 let mut vote_notifications = Vec::new();
+                let mut abstentions = Vec::new();
 
                 for (voter_id, target_id) in &votes {
+                    let voter = players.iter().find(|p| p.id == *voter_id);
+
+                    // 弃票计入完成人数，但不计入任何玩家的票数
+                    if target_id == ABSTAIN_VOTE {
+                        if let Some(v) = voter {
+                            vote_notifications.push(format!("{} 弃票", v.name));
+                        }
+                        abstentions.push(serde_json::json!({
+                            "player_id": voter_id,
+                            "player_name": voter.map(|p| p.name.clone()).unwrap_or_else(|| "未知玩家".to_string()),
+                        }));
+                        continue;
+                    }
+
                     *vote_count.entry(target_id.clone()).or_insert(0) += 1;
 
-                    let voter = players.iter().find(|p| p.id == *voter_id);
                     let target = players.iter().find(|p| p.id == *target_id);
                     if let (Some(v), Some(t)) = (voter, target) {
                         vote_notifications.push(format!("{} 投给了 {}", v.name, t.name));
                     }
                 }
 
+                // 开启分段揭晓时，先只公布票数
+                let settings = self.settings.read().await.clone();
+                let reveal_delay = settings.reveal_delay();
+                let enter_pk = state.will_enter_pk(&settings);
+
                 // 检查是否是平票
-                let result_message = if let Some(eliminated) = eliminated {
-                    if eliminated == "tie" {
+                let result_message = if reveal_delay.is_some() {
+                    "投票结束，即将揭晓结果……".to_string()
+                } else if let Some(eliminated) = eliminated {
+                    if eliminated == "tie" && enter_pk {
+                        "投票平票！".to_string()
+                    } else if eliminated == "tie" && vote_count.is_empty() && !abstentions.is_empty() {
+                        "所有玩家都弃票了，没有人被淘汰！".to_string()
+                    } else if eliminated == "tie" {
                         "投票平票，没有人被淘汰！".to_string()
                     } else {
                         let eliminated_player = players.iter().find(|p| p.id == eliminated);
@@ -1128,40 +4031,50 @@ impl Room {
                 };
 
                 // 广播投票结果
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": result_message,
-                        "vote_count": vote_count.iter().map(|(player_id, count)| {
-                            let player = players.iter().find(|p| p.id == *player_id);
-                            serde_json::json!({
-                                "player_id": player_id,
-                                "player_name": player.map(|p| p.name.clone()).unwrap_or_else(|| "未知玩家".to_string()),
-                                "votes": count
-                            })
-                        }).collect::<Vec<_>>()
-                    }),
-                }).await;
+                let vote_count = vote_count.iter().map(|(player_id, count)| {
+                    let player = players.iter().find(|p| p.id == *player_id);
+                    serde_json::json!({
+                        "player_id": player_id,
+                        "player_name": player.map(|p| p.name.clone()).unwrap_or_else(|| "未知玩家".to_string()),
+                        "votes": count
+                    })
+                }).collect();
+                self.broadcast(
+                    Notification::new(result_message)
+                        .detail(NotificationDetail::VoteResult {
+                            vote_count,
+                            abstain_count: abstentions.len(),
+                            abstentions,
+                        })
+                        .into(),
+                ).await;
 
                 // 发送投票详情
                 for vote_notification in vote_notifications {
-                    self.broadcast(GameMessage {
-                        type_: "notification".to_string(),
-                        data: serde_json::json!({
-                            "message": vote_notification
-                        }),
-                    })
+                    self.broadcast_category(NotificationCategory::Votes, Notification::new(vote_notification).into())
                     .await;
                 }
 
+                drop(state);
+
+                if let Some(delay) = reveal_delay {
+                    // 由生命周期任务在延迟结束后公布淘汰结果
+                    let reveal_at = Utc::now()
+                        + chrono::Duration::from_std(delay).unwrap_or_else(|_| chrono::Duration::zero());
+                    *self.pending_reveal.lock().await = Some(reveal_at);
+                    self.broadcast_state_update().await;
+                    self.save_state().await?;
+                    return Ok(());
+                }
+
                 self.broadcast_state_update().await;
+                self.send_vote_recap(&settings).await;
 
                 // 处理结果阶段
-                drop(state);
                 let mut state = self.state.write().await;
                 let event = state
-                    .process_result_phase()
-                    .map_err(|e| crate::Error::Game(e))?;
+                    .process_result_phase(&settings, &self.params)
+                    .map_err(crate::Error::Game)?;
                 drop(state);
 
                 // 递归处理结果事件
@@ -1174,24 +4087,19 @@ impl Room {
                     .map(|p| p.name.clone())
                     .unwrap_or_else(|| "未知玩家".to_string());
 
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 被淘汰了！", player_name)
-                    }),
-                })
+                self.broadcast(
+                    Notification::new(format!("玩家 {} 被淘汰了！", player_name))
+                        .ui_event(UiEvent::Elimination)
+                        .detail(NotificationDetail::Player { player_id })
+                        .into(),
+                )
                 .await;
                 self.broadcast_state_update().await;
                 // 保存状态
                 self.save_state().await?;
             }
             GameEvent::VoteTied => {
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": "投票平票，没有人被淘汰！"
-                    }),
-                })
+                self.broadcast(Notification::new("投票平票，没有人被淘汰！").ui_event(UiEvent::VoteTied).into())
                 .await;
                 self.broadcast_state_update().await;
                 // 保存状态
@@ -1203,30 +4111,134 @@ impl Room {
                     let players = state.get_players();
                     let current_player = &players[current_player_index];
 
-                    self.broadcast(GameMessage {
-                        type_: "notification".to_string(),
-                        data: serde_json::json!({
-                            "message": format!("开始新一轮，轮到玩家 {} 描述", current_player.name)
-                        }),
-                    })
-                    .await;
-                }
+                    self.broadcast(
+                        Notification::new(format!("开始新一轮，轮到玩家 {} 描述", current_player.name))
+                            .ui_event(UiEvent::RoundStart)
+                            .into(),
+                    )
+                    .await;
+                }
+                drop(state);
+                self.broadcast_state_update().await;
+                self.notify_turn().await;
+                // 保存状态
+                self.save_state().await?;
+            }
+            GameEvent::PkRoundStarted(pk_candidates) => {
+                let names: Vec<String> = pk_candidates
+                    .iter()
+                    .map(|id| {
+                        self.players
+                            .get(id)
+                            .map(|p| p.name.clone())
+                            .unwrap_or_else(|| "未知玩家".to_string())
+                    })
+                    .collect();
+
+                self.broadcast(
+                    Notification::new(format!("进入PK环节：{} 补充描述，其他玩家在他们之间重新投票", names.join("、")))
+                        .ui_event(UiEvent::PkStart)
+                        .detail(NotificationDetail::PkStarted { pk_candidates })
+                        .into(),
+                )
+                .await;
+                self.broadcast_state_update().await;
+                self.notify_turn().await;
+                // 保存状态
+                self.save_state().await?;
+            }
+            GameEvent::GuessPhaseStarted(guesser) => {
+                let guesser_name = self
+                    .players
+                    .get(&guesser)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| "未知玩家".to_string());
+                let time_limit = self.params.guess_time_limit;
+
+                self.broadcast(
+                    Notification::new(format!("卧底 {} 被淘汰了！TA还有一次猜平民词的机会", guesser_name))
+                        .ui_event(UiEvent::GuessStart)
+                        .detail(NotificationDetail::Player { player_id: guesser.clone() })
+                        .into(),
+                )
+                .await;
+
+                self.send_to_player(
+                    &guesser,
+                    ServerMessage::GuessWordPrompt {
+                        message: "你被淘汰了，但还有一次机会：猜中平民词，卧底方直接获胜".to_string(),
+                        time_limit,
+                    },
+                )
+                .await;
+
                 self.broadcast_state_update().await;
                 // 保存状态
                 self.save_state().await?;
             }
+            GameEvent::UndercoverConceded(player_id) => {
+                let player_name = self
+                    .players
+                    .get(&player_id)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| "未知玩家".to_string());
+
+                self.broadcast(
+                    Notification::new(format!("卧底 {} 认输了", player_name))
+                        .detail(NotificationDetail::Player { player_id })
+                        .into(),
+                )
+                .await;
+
+                Box::pin(self.handle_game_event(GameEvent::GameOver(crate::game::Role::Civilian))).await?;
+            }
+            GameEvent::WordGuessed(word_guess) => {
+                let guesser_name = self
+                    .players
+                    .get(&word_guess.player_id)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_else(|| "未知玩家".to_string());
+                let message = match (&word_guess.guess, word_guess.correct) {
+                    (Some(guess), true) => format!("卧底 {} 猜中了平民词「{}」！", guesser_name, guess),
+                    (Some(guess), false) => format!("卧底 {} 猜测平民词为「{}」，猜错了", guesser_name, guess),
+                    (None, _) => format!("卧底 {} 没有在规定时间内猜词", guesser_name),
+                };
+
+                self.broadcast(
+                    Notification::new(message)
+                        .ui_event(UiEvent::WordGuessed)
+                        .detail(NotificationDetail::WordGuessed {
+                            player_id: word_guess.player_id,
+                            guess: word_guess.guess,
+                            correct: word_guess.correct,
+                        })
+                        .into(),
+                )
+                .await;
+
+                let mut state = self.state.write().await;
+                let event = state
+                    .finish_guess_phase(&self.params)
+                    .map_err(crate::Error::Game)?;
+                drop(state);
+
+                Box::pin(self.handle_game_event(event)).await?;
+            }
             GameEvent::GameOver(winner) => {
+                // 归还对局名额
+                self.game_slot.lock().await.take();
                 // 保存游戏结果
                 self.save_game_result(winner).await?;
-                self.broadcast_game_over(winner).await;
+                self.update_ratings(winner).await;
+                // 开启猜词小游戏时，等小游戏结束后再公布双方词语
+                if !self.start_word_quiz(winner).await {
+                    self.broadcast_game_over(winner).await;
+                    self.broadcast_session_stats().await;
+                    self.score_series(winner, &HashSet::new()).await;
+                }
             }
             GameEvent::GameReset => {
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": "开始游戏"
-                    }),
-                })
+                self.broadcast(Notification::new("开始游戏").into())
                 .await;
                 self.broadcast_state_update().await;
                 // 保存状态
@@ -1234,11 +4246,8 @@ impl Room {
             }
             GameEvent::CountdownUpdate(remaining_time) => {
                 // 倒计时更新事件，直接广播给所有玩家
-                self.broadcast(GameMessage {
-                    type_: "countdown".to_string(),
-                    data: serde_json::json!({
-                        "seconds": remaining_time.as_secs()
-                    }),
+                self.broadcast_category(NotificationCategory::Countdown, ServerMessage::Countdown {
+                    seconds: remaining_time.as_secs(),
                 })
                 .await;
             }
@@ -1247,38 +4256,45 @@ impl Room {
                 let kicker_player = self.players.get(&kicker_id).map(|r| r.clone());
                 
                 // 在被踢玩家被移除之前，先发送踢出消息给被踢玩家
-                if let Some(kicked_channel) = self.player_channels.get(&kicked_player.id) {
+                if self.player_channels.contains_key(&kicked_player.id) {
                     let kicker_name = kicker_player.as_ref().map(|p| p.name.clone()).unwrap_or_else(|| "房主".to_string());
-                    let kick_message = GameMessage {
-                        type_: "kicked".to_string(),
-                        data: serde_json::json!({
-                            "message": format!("您被房主 {} 踢出了房间", kicker_name)
-                        }),
+                    let kick_message = ServerMessage::Kicked {
+                        message: format!("您被房主 {} 踢出了房间", kicker_name),
+                        ui_event: UiEvent::Kicked,
                     };
                     
-                    if let Err(e) = kicked_channel.send(kick_message).await {
-                        error!("向被踢玩家发送踢出消息失败: {}", e);
-                    }
+                    self.enqueue(&kicked_player.id, kick_message.into());
+                    self.flush_player(&kicked_player.id).await;
                 }
                 
                 // 从房间中移除被踢玩家
                 self.players.remove(&kicked_player.id);
                 self.player_channels.remove(&kicked_player.id);
+                self.disconnected_players.remove(&kicked_player.id);
+                self.skipped_players.remove(&kicked_player.id);
+                self.preferences.remove(&kicked_player.id);
+                self.latencies.remove(&kicked_player.id);
+                self.sticker_windows.remove(&kicked_player.id);
+                self.ratings.remove(&kicked_player.id);
+                self.capabilities.remove(&kicked_player.id);
+                self.last_states.remove(&kicked_player.id);
+                self.outboxes.remove(&kicked_player.id);
+                self.resume_tokens.remove(&kicked_player.id);
+                self.bots.remove(&kicked_player.id);
                 self.player_order.lock().await.retain(|id| id != &kicked_player.id);
                 
                 // 清理被踢玩家的房间信息
-                if let Err(e) = self.storage.clear_player_room_info(&kicked_player.id).await {
+                if let Err(e) = self.release_player_room(&kicked_player.id).await {
                     error!("清理被踢玩家房间信息失败: {}", e);
                 }
                 
                 // 广播踢人消息给其他玩家
                 let kicker_name = kicker_player.map(|p| p.name).unwrap_or_else(|| "房主".to_string());
-                self.broadcast(GameMessage {
-                    type_: "notification".to_string(),
-                    data: serde_json::json!({
-                        "message": format!("玩家 {} 被房主 {} 踢出了房间", kicked_player.name, kicker_name)
-                    }),
-                })
+                self.broadcast(
+                    Notification::new(format!("玩家 {} 被房主 {} 踢出了房间", kicked_player.name, kicker_name))
+                        .detail(NotificationDetail::PlayerLeft { player_id: kicked_player.id, reason: LeaveReason::Kicked })
+                        .into(),
+                )
                 .await;
                 
                 self.broadcast_state_update().await;
@@ -1289,51 +4305,230 @@ impl Room {
         Ok(())
     }
 
+    /// 私下向本轮被淘汰的玩家发送完整投票明细，需在推进结果阶段前调用
+    async fn send_vote_recap(&self, settings: &GameSettings) {
+        if !settings.vote_recap {
+            return;
+        }
+
+        let state = self.state.read().await;
+        let (Some(eliminated), Some(votes)) = (state.get_eliminated_player(), state.get_votes()) else {
+            return;
+        };
+        if eliminated == "tie" {
+            return;
+        }
+        let players = state.get_players();
+        drop(state);
+
+        let name_of = |id: &PlayerId| {
+            players
+                .iter()
+                .find(|p| p.id == *id)
+                .map(|p| p.name.clone())
+                .unwrap_or_else(|| "未知玩家".to_string())
+        };
+        // 按座位顺序列出每名投票者，弃票排在最后
+        let mut breakdown: Vec<serde_json::Value> = players
+            .iter()
+            .filter_map(|voter| votes.get(&voter.id).map(|target_id| (voter, target_id)))
+            .map(|(voter, target_id)| {
+                let abstain = target_id == ABSTAIN_VOTE;
+                serde_json::json!({
+                    "voter_id": voter.id,
+                    "voter_name": voter.name,
+                    "target_id": (!abstain).then(|| target_id.clone()),
+                    "target_name": (!abstain).then(|| name_of(target_id)),
+                    "abstain": abstain
+                })
+            })
+            .collect();
+        breakdown.sort_by_key(|entry| entry["abstain"].as_bool().unwrap_or(false));
+
+        self.send_to_player(
+            &eliminated,
+            ServerMessage::VoteRecap {
+                message: "你被淘汰了，以下是本轮的投票明细".to_string(),
+                votes: breakdown,
+                votes_against_you: votes.values().filter(|target| **target == eliminated).count(),
+            },
+        )
+        .await;
+    }
+
+    /// 分段揭晓：到达揭晓时间后公布被淘汰玩家，再推进结果阶段
+    async fn process_pending_reveal(&self) -> Result<()> {
+        {
+            let mut pending = self.pending_reveal.lock().await;
+            match *pending {
+                Some(reveal_at) if Utc::now() >= reveal_at => *pending = None,
+                _ => return Ok(()),
+            }
+        }
+
+        let settings = self.settings.read().await.clone();
+        let state = self.state.read().await;
+        let players = state.get_players_with_roles();
+        let eliminated = state.get_eliminated_player();
+        let enter_pk = state.will_enter_pk(&settings);
+        drop(state);
+
+        let eliminated_player = eliminated
+            .filter(|id| id != "tie")
+            .and_then(|id| players.into_iter().find(|p| p.id == id));
+
+        let reveal_data = match &eliminated_player {
+            Some(player) => {
+                let mut data = serde_json::json!({
+                    "message": format!("玩家 {} 被淘汰了！", player.name),
+                    "player_id": player.id,
+                    "player_name": player.name,
+                });
+                if settings.reveal_role {
+                    data["role"] = serde_json::to_value(player.role).unwrap_or(serde_json::Value::Null);
+                }
+                data
+            }
+            None => serde_json::json!({
+                "message": if enter_pk { "投票平票！" } else { "投票平票，没有人被淘汰！" },
+                "player_id": null,
+            }),
+        };
+
+        self.broadcast(ServerMessage::EliminationReveal(reveal_data))
+        .await;
+        self.send_vote_recap(&settings).await;
+
+        let mut state = self.state.write().await;
+        let event = state
+            .process_result_phase(&settings, &self.params)
+            .map_err(crate::Error::Game)?;
+        drop(state);
+
+        Box::pin(self.handle_game_event(event)).await
+    }
+
     /// 开始游戏
     async fn start_game(&self) -> Result<()> {
+        self.check_play_limits().await?;
+
+        let slot = self.game_slots.try_acquire().ok_or_else(|| {
+            crate::Error::Rule(
+                ErrorCode::ServerBusy,
+                format!(
+                    "服务器同时进行的对局已达上限（{}局），请稍后再试",
+                    self.game_slots.limit().unwrap_or_default()
+                ),
+            )
+        })?;
+
         let mut state = self.state.write().await;
-        let player_order = self.player_order.lock().await.clone();
+        let player_order = self.balanced_turn_order().await;
+        let settings = self.settings.read().await.clone();
         let event = state
-            .start_game(self.word_bank.clone(), &player_order)
-            .map_err(|e| crate::Error::Game(e))?;
+            .start_game(self.word_bank.clone(), &player_order, &settings, &self.params)
+            .map_err(crate::Error::Game)?;
         drop(state);
+        *self.game_slot.lock().await = Some(slot);
+        if let Some(first) = player_order.first() {
+            *self.first_speakers.lock().await.entry(first.clone()).or_insert(0) += 1;
+        }
 
         Box::pin(self.handle_game_event(event)).await?;
         Ok(())
     }
 
+    /// 本局的描述顺序：按入座顺序轮转，由往局中首个描述次数最少的玩家先发言，次数相同时随机选择
+    async fn balanced_turn_order(&self) -> Vec<PlayerId> {
+        let mut order = self.player_order.lock().await.clone();
+        let first_speakers = self.first_speakers.lock().await;
+        let fewest = order
+            .iter()
+            .map(|id| first_speakers.get(id).copied().unwrap_or(0))
+            .min()
+            .unwrap_or(0);
+        let candidates: Vec<usize> = order
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| first_speakers.get(*id).copied().unwrap_or(0) == fewest)
+            .map(|(index, _)| index)
+            .collect();
+        if let Some(&start) = candidates.choose(&mut rand::rng()) {
+            order.rotate_left(start);
+        }
+        order
+    }
+
+    /// 检查并记录玩家的加入/离开通知，去重窗口内已经通知过时返回 false
+    fn should_announce_presence(&self, player_id: &PlayerId) -> bool {
+        if self.presence_debounce.is_zero() {
+            return true;
+        }
+        let now = Utc::now();
+        let window = chrono::Duration::from_std(self.presence_debounce).unwrap_or(chrono::Duration::MAX);
+        // 顺带清理窗口之外的记录，避免长期运行的房间积累已离开玩家的记录
+        self.presence_notices.retain(|_, notified_at| now - *notified_at <= window);
+
+        match self.presence_notices.entry(player_id.clone()) {
+            dashmap::mapref::entry::Entry::Occupied(_) => false,
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+
+    /// 提醒轮到描述的玩家
+    async fn notify_turn(&self) {
+        let current = {
+            let state = self.state.read().await;
+            state
+                .get_current_player_index()
+                .and_then(|index| state.get_players().get(index).map(|player| player.id.clone()))
+        };
+        let Some(player_id) = current else {
+            return;
+        };
+        self.send_to_player(&player_id, Notification::new("轮到你描述了").ui_event(UiEvent::YourTurn).into())
+        .await;
+    }
+
     /// 广播游戏结束消息
     async fn broadcast_game_over(&self, winner: crate::game::Role) {
         let state = self.state.read().await;
         let players = state.get_players();
+        let word_guess = state.get_word_guess();
+        let conceded_by = state.get_conceded_by();
+        let clock = self.clock.lock().await.stats(Utc::now());
+        let game_id = self.current_game_id().await;
 
         // 收集词语信息
         let mut civilian_word = None;
         let mut undercover_word = None;
 
         for player in &players {
-            if let Some(role) = player.role {
-                if let Some(word) = &player.word {
-                    match role {
-                        crate::game::Role::Civilian => {
-                            if civilian_word.is_none() {
-                                civilian_word = Some(word.clone());
-                            }
+            if let Some(role) = player.role
+                && let Some(word) = &player.word
+            {
+                match role {
+                    crate::game::Role::Civilian => {
+                        if civilian_word.is_none() {
+                            civilian_word = Some(word.clone());
                         }
-                        crate::game::Role::Undercover => {
-                            if undercover_word.is_none() {
-                                undercover_word = Some(word.clone());
-                            }
+                    }
+                    crate::game::Role::Undercover => {
+                        if undercover_word.is_none() {
+                            undercover_word = Some(word.clone());
                         }
                     }
+                    crate::game::Role::Blank => {}
                 }
             }
         }
 
         // 游戏结束时，为所有玩家发送完整的状态信息（包括所有玩家的角色和词语）
         for entry in self.player_channels.iter() {
-            // let target_player_id = entry.key();
-            let channel = entry.value();
+            let target_player_id = entry.key();
 
             let state_data = serde_json::json!({
                 "state": state.get_state_type(),
@@ -1349,160 +4544,309 @@ impl Room {
                 }).collect::<Vec<_>>(),
                 "total_players": players.len(),
                 "civilian_word": civilian_word.clone(),
-                "undercover_word": undercover_word.clone()
+                "undercover_word": undercover_word.clone(),
+                "word_guess": word_guess.clone(),
+                "conceded_by": conceded_by.clone(),
+                "clock": clock,
+                "game_id": game_id
             });
 
-            let state_update = GameMessage {
-                type_: "state_update".to_string(),
-                data: state_data,
-            };
-
-            if let Err(e) = channel.send(state_update).await {
-                error!("发送游戏结束状态更新失败: {}", e);
-            }
+            self.enqueue(target_player_id, ServerMessage::StateUpdate(state_data).into());
         }
-
-        self.broadcast(GameMessage {
-            type_: "notification".to_string(),
-            data: serde_json::json!({
-                "message": format!("游戏结束，{}胜利！平民词语：{}，卧底词语：{}",
-                    winner,
-                    civilian_word.unwrap_or_else(|| "未知".to_string()),
-                    undercover_word.unwrap_or_else(|| "未知".to_string()))
-            }),
-        })
+        self.schedule_flush().await;
+
+        let message = format!(
+            "游戏结束，{}胜利！{}平民词语：{}，卧底词语：{}",
+            winner,
+            if word_guess.as_ref().is_some_and(|guess| guess.correct) {
+                "卧底猜中平民词，反败为胜！"
+            } else if conceded_by.is_some() {
+                "卧底认输！"
+            } else {
+                ""
+            },
+            civilian_word.unwrap_or_else(|| "未知".to_string()),
+            undercover_word.unwrap_or_else(|| "未知".to_string())
+        );
+        self.broadcast(Notification::new(message).ui_event(UiEvent::GameOver).into())
         .await;
     }
 
-    /// 广播状态更新
-    async fn broadcast_state_update(&self) {
-        let state = self.state.read().await;
+    /// 广播本房间连续对局的统计，鼓励玩家继续下一局
+    async fn broadcast_session_stats(&self) {
+        let Some(scoreboard) = self.session_scoreboard().await else {
+            return;
+        };
 
-        for entry in self.player_channels.iter() {
-            let target_player_id = entry.key();
-            let channel = entry.value();
+        self.broadcast(ServerMessage::SessionStats(scoreboard))
+        .await;
+    }
 
-            let mut state_data = serde_json::json!({
-                "state": state.get_state_type(),
-                "players": state.get_players().iter().map(|player| {
-                    let mut player_data = serde_json::json!({
-                        "id": player.id.to_string(),
-                        "name": player.name,
-                        "is_alive": player.is_alive,
-                    });
+    /// 统计本房间连续对局的记分板，还没有完成的对局时返回None
+    async fn session_scoreboard(&self) -> Option<serde_json::Value> {
+        let results = match self.storage.get_room_results(&self.id).await {
+            Ok(results) => results,
+            Err(e) => {
+                error!("获取房间 {} 历史对局失败: {}", self.id, e);
+                return None;
+            }
+        };
 
-                    // 在Lobby状态下，添加准备状态
-                    if let GameState::Lobby { ready_players, .. } = &*state {
-                        player_data["is_ready"] = serde_json::Value::Bool(ready_players.contains(&player.id));
-                    }
+        // 只统计本房间创建之后的对局，避免复用房间ID时混入旧数据
+        let results: Vec<_> = results
+            .into_iter()
+            .filter(|result| result.timestamp >= self.created_at)
+            .collect();
+        if results.is_empty() {
+            return None;
+        }
 
-                    if player.id == *target_player_id {
-                        if let Some(role) = player.role {
-                            player_data["role"] = serde_json::to_value(role).unwrap_or(serde_json::Value::Null);
-                        }
-                        if let Some(word) = &player.word {
-                            player_data["word"] = serde_json::to_value(word).unwrap_or(serde_json::Value::Null);
-                        }
-                    }
+        let stats = crate::storage::SessionStats::from_results(&results);
+        Some(serde_json::json!({
+            "games_played": results.len(),
+            "players": stats.iter().map(|s| {
+                serde_json::json!({
+                    "player_id": s.player_id,
+                    "player_name": s.player_name,
+                    "games_played": s.games_played,
+                    "wins": s.wins,
+                    "games_as_undercover": s.games_as_undercover,
+                    "undercover_win_rate": s.undercover_win_rate(),
+                    "current_streak": s.current_streak,
+                    "concessions": s.concessions
+                })
+            }).collect::<Vec<_>>()
+        }))
+    }
 
-                    player_data
-                }).collect::<Vec<_>>(),
-                "total_players": state.get_players().len()
+    /// 以指定玩家的视角生成玩家列表，只包含该玩家自己的角色和词语
+    fn players_view(
+        &self,
+        state: &GameState,
+        settings: &GameSettings,
+        target_player_id: &PlayerId,
+    ) -> Vec<serde_json::Value> {
+        let mut view = self.public_players_view(state, settings);
+        reveal_own_identity(&mut view, &state.get_players(), target_player_id);
+        view
+    }
+
+    /// 所有玩家都能看到的玩家列表，不含身份和词语
+    fn public_players_view(&self, state: &GameState, settings: &GameSettings) -> Vec<serde_json::Value> {
+        state.get_players().iter().map(|player| {
+            let mut player_data = serde_json::json!({
+                "id": player.id.to_string(),
+                "name": player.name,
+                "is_alive": player.is_alive,
+                "is_bot": self.is_bot(&player.id),
+                "disconnected": self.disconnected_players.contains_key(&player.id),
             });
 
-            // 添加房主信息
-            if let Some(host_id) = state.get_host() {
-                state_data["host"] = serde_json::Value::String(host_id);
+            if let Some(rating) = self.ratings.get(&player.id) {
+                player_data["rating"] = serde_json::json!(*rating);
             }
 
-            // 添加特定状态的数据
-            if let Some(current_player_index) = state.get_current_player_index() {
-                let players = state.get_players();
-                if current_player_index < players.len() {
-                    state_data["current_player"] =
-                        serde_json::Value::String(players[current_player_index].id.to_string());
+            // 在Lobby状态下，添加准备状态
+            if let GameState::Lobby { ready_players, .. } = state {
+                player_data["is_ready"] = serde_json::Value::Bool(ready_players.contains(&player.id));
+
+                // 房主开启后展示玩家延迟
+                if settings.show_latency
+                    && let Some(latency) = self.latencies.get(&player.id)
+                {
+                    player_data["latency_ms"] = serde_json::json!(latency.average_ms);
                 }
             }
 
-            if let Some(descriptions) = state.get_descriptions() {
-                state_data["descriptions"] = serde_json::Value::Array(
-                    descriptions
-                        .iter()
-                        .map(|(id, desc)| {
-                            serde_json::json!({
-                                "player_id": id.to_string(),
-                                "content": desc
-                            })
-                        })
-                        .collect(),
-                );
-            }
+            player_data
+        }).collect()
+    }
 
-            if let Some(eliminated) = state.get_eliminated_player() {
-                if eliminated == "tie" {
-                    state_data["eliminated"] = serde_json::Value::Null;
-                } else {
-                    state_data["eliminated"] = serde_json::Value::String(eliminated);
-                }
+    /// 广播状态更新，所有玩家共用的部分只构建一次，每名玩家只补上自己的身份和词语
+    async fn broadcast_state_update(&self) {
+        self.send_state_update(None).await;
+    }
+
+    /// 向指定玩家（未指定时为房间内所有玩家）发送状态更新
+    async fn send_state_update(&self, only: Option<&PlayerId>) {
+        let reveal_pending = self.pending_reveal.lock().await.is_some();
+        let settings = self.settings.read().await.clone();
+        let stickers = self.stickers.read().await.clone();
+        let series = self.series.lock().await.clone();
+        let state = self.state.read().await;
+
+        let mut public_state = serde_json::json!({
+            "state": state.get_state_type(),
+            "players": self.public_players_view(&state, &settings),
+            "total_players": state.get_players().len()
+        });
+
+        // 添加房主信息
+        if let Some(host_id) = state.get_host() {
+            public_state["host"] = serde_json::Value::String(host_id);
+        }
+
+        // 添加房间设置
+        public_state["settings"] = serde_json::to_value(&settings).unwrap_or(serde_json::Value::Null);
+        public_state["params"] = serde_json::to_value(&self.params).unwrap_or(serde_json::Value::Null);
+        public_state["stickers"] = serde_json::json!(stickers);
+        public_state["series"] = serde_json::json!(series);
+
+        // 添加特定状态的数据
+        if let Some(current_player_index) = state.get_current_player_index() {
+            let players = state.get_players();
+            if current_player_index < players.len() {
+                public_state["current_player"] =
+                    serde_json::Value::String(players[current_player_index].id.to_string());
             }
+        }
 
-            if let Some(chat_messages) = state.get_chat_messages() {
-                state_data["chat_messages"] = serde_json::Value::Array(
-                    chat_messages
-                        .iter()
-                        .map(|msg| {
-                            serde_json::json!({
-                                "player_id": msg.player_id.to_string(),
-                                "player_name": msg.player_name,
-                                "content": msg.content,
-                                "timestamp": msg.timestamp.timestamp()
-                            })
+        if let Some(descriptions) = state.get_descriptions() {
+            public_state["descriptions"] = serde_json::Value::Array(
+                descriptions
+                    .iter()
+                    .map(|(id, desc)| {
+                        serde_json::json!({
+                            "player_id": id.to_string(),
+                            "content": desc
                         })
-                        .collect(),
-                );
+                    })
+                    .collect(),
+            );
+        }
+
+        // 分段揭晓完成前不下发淘汰结果
+        if let Some(eliminated) = state.get_eliminated_player().filter(|_| !reveal_pending) {
+            if eliminated == "tie" {
+                public_state["eliminated"] = serde_json::Value::Null;
+            } else {
+                public_state["eliminated"] = serde_json::Value::String(eliminated);
             }
+        }
 
-            // 为被淘汰的玩家添加被淘汰聊天消息
-            if let Some(eliminated_chat_messages) = state.get_eliminated_chat_messages() {
-                state_data["eliminated_chat_messages"] = serde_json::Value::Array(
-                    eliminated_chat_messages
-                        .iter()
-                        .map(|msg| {
-                            serde_json::json!({
-                                "player_id": msg.player_id.to_string(),
-                                "player_name": msg.player_name,
-                                "content": msg.content,
-                                "timestamp": msg.timestamp.timestamp()
-                            })
+        if let Some(pk_candidates) = state.get_pk_candidates() {
+            public_state["pk_candidates"] = serde_json::json!(pk_candidates);
+        }
+
+        // 当前计时阶段的截止时间（毫秒时间戳）
+        if let Some(deadline) = state.get_deadline() {
+            public_state["deadline"] = serde_json::json!(deadline.timestamp_millis());
+        }
+
+        if let Some(guesser) = state.get_guesser() {
+            public_state["guesser"] = serde_json::Value::String(guesser);
+        }
+
+        if let Some(chat_messages) = state.get_chat_messages() {
+            public_state["chat_messages"] = serde_json::Value::Array(
+                chat_messages
+                    .iter()
+                    .map(|msg| {
+                        serde_json::json!({
+                            "player_id": msg.player_id.to_string(),
+                            "player_name": msg.player_name,
+                            "content": msg.content,
+                            "sticker": msg.sticker,
+                            "timestamp": msg.timestamp.timestamp()
                         })
-                        .collect(),
-                );
-            }
+                    })
+                    .collect(),
+            );
+        }
 
-            // 添加投票信息
-            if let Some(votes) = state.get_votes() {
-                state_data["votes"] = serde_json::Value::Array(
-                    votes
-                        .iter()
-                        .map(|(voter_id, target_id)| {
-                            serde_json::json!({
-                                "player_id": voter_id.to_string(),
-                                "target_id": target_id.to_string()
-                            })
+        // 为被淘汰的玩家添加被淘汰聊天消息
+        if let Some(eliminated_chat_messages) = state.get_eliminated_chat_messages() {
+            public_state["eliminated_chat_messages"] = serde_json::Value::Array(
+                eliminated_chat_messages
+                    .iter()
+                    .map(|msg| {
+                        serde_json::json!({
+                            "player_id": msg.player_id.to_string(),
+                            "player_name": msg.player_name,
+                            "content": msg.content,
+                            "sticker": msg.sticker,
+                            "timestamp": msg.timestamp.timestamp()
                         })
-                        .collect(),
-                );
+                    })
+                    .collect(),
+            );
+        }
+
+        // 添加投票信息
+        if let Some(votes) = state.get_votes() {
+            public_state["votes"] = serde_json::Value::Array(
+                votes
+                    .iter()
+                    .map(|(voter_id, target_id)| {
+                        serde_json::json!({
+                            "player_id": voter_id.to_string(),
+                            "target_id": target_id.to_string()
+                        })
+                    })
+                    .collect(),
+            );
+        }
+
+        let players = state.get_players();
+        let targets: Vec<PlayerId> = match only {
+            Some(player_id) if self.player_channels.contains_key(player_id) => vec![player_id.clone()],
+            Some(_) => Vec::new(),
+            None => self.player_channels.iter().map(|entry| entry.key().clone()).collect(),
+        };
+        for target_player_id in &targets {
+            let mut state_data = public_state.clone();
+            if let Some(serde_json::Value::Array(view)) = state_data.get_mut("players") {
+                reveal_own_identity(view, &players, target_player_id);
             }
 
-            let state_update = GameMessage {
-                type_: "state_update".to_string(),
-                data: state_data,
+            let Some(state_update) = self.state_message(target_player_id, state_data) else {
+                continue;
             };
 
-            if let Err(e) = channel.send(state_update).await {
-                error!("发送状态更新失败: {}", e);
-            }
+            self.enqueue(target_player_id, state_update.into());
+        }
+        self.schedule_flush().await;
+    }
+
+    /// 生成发给指定玩家的状态消息
+    ///
+    /// 支持增量更新的客户端在收到首个完整状态后只接收 `state_delta`，没有变化时不发送；
+    /// 每隔 `websocket.full_state_sync_interval` 重新发送一次完整状态，纠正客户端累积的偏差。
+    fn state_message(&self, player_id: &PlayerId, state_data: serde_json::Value) -> Option<ServerMessage> {
+        if !self.capabilities_of(player_id).delta_updates {
+            return Some(ServerMessage::StateUpdate(state_data));
+        }
+
+        let sync_interval = config::Config::get().full_state_sync_interval();
+        let previous = self
+            .last_states
+            .remove(player_id)
+            .map(|(_, sent)| sent)
+            .filter(|sent| sync_interval.is_zero() || sent.full_sync_at.elapsed() < sync_interval);
+
+        if let Some(previous) = previous
+            && let (serde_json::Value::Object(old), serde_json::Value::Object(current)) =
+                (&previous.data, &state_data)
+        {
+            let delta = state_delta(old, current);
+            self.last_states.insert(
+                player_id.clone(),
+                SentState {
+                    data: state_data,
+                    full_sync_at: previous.full_sync_at,
+                },
+            );
+            return delta.map(ServerMessage::StateDelta);
         }
+
+        self.last_states.insert(
+            player_id.clone(),
+            SentState {
+                data: state_data.clone(),
+                full_sync_at: std::time::Instant::now(),
+            },
+        );
+        Some(ServerMessage::StateUpdate(state_data))
     }
 
     /// 更新倒计时并广播
@@ -1512,11 +4856,8 @@ impl Room {
             drop(state);
             
             // 广播倒计时更新
-            self.broadcast(GameMessage {
-                type_: "countdown".to_string(),
-                data: serde_json::json!({
-                    "seconds": remaining_time.as_secs()
-                }),
+            self.broadcast_category(NotificationCategory::Countdown, ServerMessage::Countdown {
+                seconds: remaining_time.as_secs(),
             }).await;
             
             Some(remaining_time)
@@ -1533,3 +4874,222 @@ impl Room {
         }
     }
 }
+
+/// 在玩家列表中补上查看者自己的身份和词语
+fn reveal_own_identity(view: &mut [serde_json::Value], players: &[Player], target_player_id: &PlayerId) {
+    let Some(player) = players.iter().find(|player| player.id == *target_player_id) else {
+        return;
+    };
+    let Some(player_data) = view
+        .iter_mut()
+        .find(|data| data["id"].as_str() == Some(target_player_id.as_str()))
+    else {
+        return;
+    };
+
+    if let Some(role) = player.role {
+        player_data["role"] = serde_json::to_value(role).unwrap_or(serde_json::Value::Null);
+    }
+    if let Some(word) = &player.word {
+        player_data["word"] = serde_json::to_value(word).unwrap_or(serde_json::Value::Null);
+    }
+}
+
+/// 计算两次完整状态之间的增量，没有变化时返回 None
+///
+/// 玩家加入离开、新增描述和投票以及阶段切换写入 `changes`，无法用结构化变化表达的顶层字段
+/// 整体放入 `changed`，被移除的顶层字段名放入 `removed`。
+fn state_delta(
+    previous: &serde_json::Map<String, serde_json::Value>,
+    current: &serde_json::Map<String, serde_json::Value>,
+) -> Option<serde_json::Value> {
+    let mut changes = Vec::new();
+    let mut changed = serde_json::Map::new();
+
+    for (key, value) in current {
+        let old = previous.get(key);
+        if old == Some(value) {
+            continue;
+        }
+
+        let structured = match key.as_str() {
+            "state" => serde_json::from_value(value.clone())
+                .ok()
+                .map(|state| vec![StateChange::StateTransition { state }]),
+            "players" => array_diff(old, value, "id").map(|(added, removed)| {
+                added
+                    .into_iter()
+                    .map(|player| StateChange::PlayerAdded { player: player.clone() })
+                    .chain(removed.into_iter().map(|player_id| StateChange::PlayerRemoved {
+                        player_id: player_id.to_string(),
+                    }))
+                    .collect()
+            }),
+            "votes" => array_diff(old, value, "player_id")
+                .filter(|(_, removed)| removed.is_empty())
+                .map(|(added, _)| {
+                    added
+                        .into_iter()
+                        .map(|vote| StateChange::VoteCast {
+                            player_id: vote["player_id"].as_str().unwrap_or_default().to_string(),
+                            target_id: vote["target_id"].as_str().unwrap_or_default().to_string(),
+                        })
+                        .collect()
+                }),
+            "descriptions" => array_diff(old, value, "player_id")
+                .filter(|(_, removed)| removed.is_empty())
+                .map(|(added, _)| {
+                    added
+                        .into_iter()
+                        .map(|description| StateChange::DescriptionAdded {
+                            player_id: description["player_id"].as_str().unwrap_or_default().to_string(),
+                            content: description["content"].as_str().unwrap_or_default().to_string(),
+                        })
+                        .collect()
+                }),
+            _ => None,
+        };
+
+        match structured {
+            Some(structured) => changes.extend(structured),
+            None => {
+                changed.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    let removed: Vec<&String> = previous.keys().filter(|key| !current.contains_key(*key)).collect();
+    if changes.is_empty() && changed.is_empty() && removed.is_empty() {
+        return None;
+    }
+
+    Some(serde_json::json!({
+        "changes": changes,
+        "changed": changed,
+        "removed": removed
+    }))
+}
+
+/// 比较两个以 `id_key` 字段标识条目的数组
+///
+/// 两边共有的条目完全相同时返回新增的条目和被移除条目的标识，否则返回 None，由调用方整体下发。
+fn array_diff<'a>(
+    previous: Option<&'a serde_json::Value>,
+    current: &'a serde_json::Value,
+    id_key: &str,
+) -> Option<(Vec<&'a serde_json::Value>, Vec<&'a str>)> {
+    let (Some(serde_json::Value::Array(previous)), serde_json::Value::Array(current)) = (previous, current) else {
+        return None;
+    };
+
+    let mut old: HashMap<&str, &serde_json::Value> = HashMap::new();
+    for entry in previous {
+        old.insert(entry.get(id_key)?.as_str()?, entry);
+    }
+
+    let mut added = Vec::new();
+    let mut kept = HashSet::new();
+    for entry in current {
+        let id = entry.get(id_key)?.as_str()?;
+        match old.get(id) {
+            Some(existing) if *existing != entry => return None,
+            Some(_) => {
+                kept.insert(id);
+            }
+            None => added.push(entry),
+        }
+    }
+
+    let removed = old.keys().filter(|id| !kept.contains(*id)).copied().collect();
+    Some((added, removed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameBuilder;
+
+    fn player(id: &str) -> Player {
+        Player {
+            id: id.to_string(),
+            name: id.to_string(),
+            role: None,
+            word: None,
+            is_alive: true,
+            last_action: Utc::now(),
+        }
+    }
+
+    /// 把一次操作产生的事件交给日志进度，累积写入的记录
+    fn log(cursor: &mut StateLogCursor, records: &mut Vec<StateRecord>, state: &GameState, event: GameEvent) {
+        records.extend(cursor.next_records(state, vec![event], false));
+    }
+
+    fn ready_players(state: &GameState) -> HashSet<PlayerId> {
+        match state {
+            GameState::Lobby { ready_players, .. } => ready_players.clone(),
+            _ => panic!("不是大厅状态"),
+        }
+    }
+
+    #[test]
+    fn replaying_ready_toggles_matches_live_state() {
+        let params = GameParams::default();
+        let mut state = GameState::new(params.min_players, params.max_players, "a".to_string());
+        let mut cursor = StateLogCursor::default();
+        let mut records = cursor.next_records(&state, Vec::new(), true);
+
+        for id in ["a", "b", "c"] {
+            let event = state.add_player(player(id)).unwrap();
+            log(&mut cursor, &mut records, &state, event);
+        }
+        // 准备、取消准备、再次准备
+        for id in ["a", "b", "a", "b", "a"] {
+            let event = state.player_ready(id.to_string(), &params).unwrap();
+            log(&mut cursor, &mut records, &state, event);
+        }
+        assert!(records[1..].iter().all(|record| matches!(record, StateRecord::Event(_))));
+        let replayed = GameState::replay(records.clone()).unwrap();
+        assert_eq!(ready_players(&replayed), ready_players(&state));
+        assert_eq!(ready_players(&state), HashSet::from(["a".to_string()]));
+
+        // 断线取消准备不产生事件，写入快照后再次准备仍与实际状态一致
+        assert_eq!(state.unready_players(&["a".to_string()]), vec!["a".to_string()]);
+        records.extend(cursor.next_records(&state, Vec::new(), true));
+        let event = state.player_ready("a".to_string(), &params).unwrap();
+        assert!(matches!(event, GameEvent::PlayerReady(_, _)));
+        log(&mut cursor, &mut records, &state, event);
+
+        let replayed = GameState::replay(records).unwrap();
+        assert_eq!(ready_players(&replayed), ready_players(&state));
+    }
+
+    #[test]
+    fn replaying_next_player_keeps_start_time() {
+        let params = GameParams::default();
+        let mut state = GameBuilder::new()
+            .players([("a", "阿狸"), ("b", "布丁"), ("c", "彩虹"), ("d", "多多")])
+            .undercovers(["d"])
+            .word_pair("苹果", "梨")
+            .params(params.clone())
+            .build()
+            .unwrap();
+        let mut cursor = StateLogCursor::default();
+        let mut records = cursor.next_records(&state, Vec::new(), true);
+
+        let event = state.add_description("a".to_string(), "水果".to_string(), &params).unwrap();
+        log(&mut cursor, &mut records, &state, event);
+        let event = state.advance_describe_phase(&params).unwrap();
+        assert!(matches!(event, GameEvent::NextPlayer(_, _)));
+        log(&mut cursor, &mut records, &state, event);
+
+        let timing = |state: &GameState| match state {
+            GameState::DescribePhase { current_player_index, current_player_start_time, .. } => {
+                (*current_player_index, *current_player_start_time)
+            }
+            _ => panic!("不是描述阶段"),
+        };
+        let replayed = GameState::replay(records).unwrap();
+        assert_eq!(timing(&replayed), timing(&state));
+    }
+}
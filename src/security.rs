@@ -2,6 +2,7 @@ use crate::Result;
 use crate::config::{SecurityConfig, WordFilterConfig};
 use dashmap::DashMap;
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
@@ -45,10 +46,24 @@ impl RateLimiter {
     }
 }
 
+/// 发言命中敏感词时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// 把敏感词替换为 replacement 后照常发送
+    #[default]
+    Replace,
+    /// 拒绝发送并提示发送者
+    Reject,
+    /// 不发送也不提示发送者
+    Drop,
+}
+
 pub struct WordFilter {
     sensitive_words: Vec<String>,
     custom_words: Vec<String>,
     replacement: String,
+    action: FilterAction,
 }
 
 impl Security {
@@ -130,15 +145,26 @@ impl Security {
 }
 
 impl WordFilter {
+    /// 使用全局配置创建过滤器
+    pub fn from_config() -> Self {
+        Self::new(&crate::config::Config::get().security.word_filter)
+    }
+
     fn new(config: &WordFilterConfig) -> Self {
         WordFilter {
             sensitive_words: config.sensitive_words.clone(),
             custom_words: config.custom_words.clone(),
             replacement: config.replacement.clone(),
+            action: config.action,
         }
     }
 
-    fn filter(&self, text: &str) -> String {
+    /// 全局配置的处理方式，房间可以另行设置
+    pub fn action(&self) -> FilterAction {
+        self.action
+    }
+
+    pub fn filter(&self, text: &str) -> String {
         let mut result = text.to_string();
 
         // 过滤敏感词
@@ -154,7 +180,7 @@ impl WordFilter {
         result
     }
 
-    fn contains_sensitive_words(&self, text: &str) -> bool {
+    pub fn contains_sensitive_words(&self, text: &str) -> bool {
         self.sensitive_words.iter().any(|word| text.contains(word))
             || self.custom_words.iter().any(|word| text.contains(word))
     }
@@ -173,3 +199,8 @@ impl WordFilter {
         self.custom_words.clone()
     }
 }
+
+/// 比较密钥、签名等机密值，不因提前返回泄露匹配的前缀长度
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -1,241 +1,1815 @@
-use crate::game::{GameState, Player, Role};
+use crate::admin::{AdminToken, AuditRecord};
+use crate::config::{Config, StorageKind};
+use crate::game::{ABSTAIN_VOTE, ActionKind, GameAction, GameClockStats, GameEvent, ChatMessage, GameState, Player, Role, StateRecord};
+use crate::room::RoomCheckpoint;
 use crate::user::{User, UserSession};
+use crate::word_bank::WordBankData;
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
 use hex;
 use redis::aio::ConnectionManager;
 use redis::{AsyncCommands, Client};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use tokio::sync::Mutex;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-#[derive(Clone)]
+mod memory;
+mod sqlite;
+#[cfg(test)]
+mod backend_tests;
+
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+
+/// 存储后端：房间状态、会话与用户、对局结果和统计等持久化数据的读写
+///
+/// 默认使用 Redis；不想部署 Redis 的小规模自建服务可以使用 SQLite，单机演示或测试时可以改用纯内存实现（重启后数据丢失）
+#[axum::async_trait]
+pub trait StorageBackend: Send + Sync {
+    /// 存储当前是否不可用，不可用期间写操作会被缓存
+    fn is_degraded(&self) -> bool;
+
+    /// 获取存储状态快照
+    fn health(&self) -> StorageHealthSnapshot;
+
+    /// 补写降级期间缓存的写操作，全部写入（或没有缓存）时返回 true
+    async fn reconcile(&self) -> bool {
+        true
+    }
+
+    // 房间状态与聊天记录
+
+    /// 追加一条房间状态变化到事件日志，写入快照时丢弃快照之前的记录
+    async fn append_state_record(&self, room_id: &str, record: &StateRecord) -> Result<()>;
+
+    /// 删除房间的状态事件日志，房间关闭后不再在重启时恢复
+    async fn delete_room_state(&self, room_id: &str) -> Result<()>;
+
+    /// 读取房间的状态事件日志，按写入先后排列
+    async fn load_state_log(&self, room_id: &str) -> Result<Vec<StateLogEntry>>;
+
+    /// 所有保存了状态事件日志的房间ID
+    async fn list_saved_room_ids(&self) -> Result<Vec<String>>;
+
+    /// 重放状态事件日志得到房间的最新状态
+    async fn load_room_state(&self, room_id: String) -> Result<Option<GameState>> {
+        let entries = self.load_state_log(&room_id).await?;
+        Ok(GameState::replay(entries.into_iter().map(|entry| entry.record)))
+    }
+
+    /// 追加一条聊天消息到房间的聊天记录
+    async fn append_chat_message(&self, room_id: &str, channel: ChatChannel, message: &ChatMessage) -> Result<()>;
+
+    /// 清空房间指定频道的聊天记录
+    async fn clear_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<()>;
+
+    /// 读取房间指定频道的聊天记录，按发送先后排列
+    async fn load_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<Vec<ChatMessage>>;
+
+    // 对局结果、回放与统计
+
+    /// 保存游戏结果，并为 leaderboard_players 中的玩家更新排行榜，为 history_players 记入对局历史和活跃玩家统计
+    async fn save_game_result(
+        &self,
+        result: &GameResult,
+        leaderboard_players: &[String],
+        history_players: &[String],
+    ) -> Result<()>;
+
+    /// 房间归档的历史对局结果（按时间顺序）
+    async fn get_room_results(&self, room_id: &str) -> Result<Vec<GameResult>>;
+
+    /// 玩家最近的对局历史，按时间倒序，`offset` 为跳过的条数
+    async fn get_player_games(&self, player_id: &str, offset: usize, limit: usize) -> Result<Vec<PlayerGameEntry>>;
+
+    /// 当天截至目前的统计
+    async fn get_today_stats(&self) -> Result<DailyStats>;
+
+    /// 最近若干天（不含当天）的每日统计，按日期从近到远排列
+    async fn get_daily_stats(&self, days: u32) -> Result<Vec<DailyStats>>;
+
+    /// 最近若干个周期（含当前周期）的对局汇总，按时间从近到远排列
+    async fn get_stats_summary(&self, period: LeaderboardPeriod, count: u32) -> Result<Vec<StatsSummary>>;
+
+    /// 追加一条对局回放事件
+    async fn append_replay_event(&self, room_id: &str, game_id: &str, entry: &ReplayEntry) -> Result<()>;
+
+    /// 按对局ID获取完整回放，回放不存在或已过期时返回 None
+    async fn get_replay(&self, game_id: &str) -> Result<Option<Replay>>;
+
+    /// 分页获取排行榜，返回榜单总人数和本页条目
+    async fn get_leaderboard(
+        &self,
+        metric: LeaderboardMetric,
+        period: LeaderboardPeriod,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(usize, Vec<LeaderboardEntry>)>;
+
+    // 玩家统计、资料设置与房间归属
+
+    async fn get_player_stats(&self, player_id: &str) -> Result<PlayerStats>;
+
+    async fn update_player_stats(&self, player_id: &str, stats: &PlayerStats) -> Result<()>;
+
+    /// 玩家的个人资料设置，未设置过时返回默认值
+    async fn get_profile_settings(&self, player_id: &str) -> Result<ProfileSettings>;
+
+    async fn save_profile_settings(&self, player_id: &str, settings: &ProfileSettings) -> Result<()>;
+
+    /// 玩家当前所在的房间ID，存储不可用时视为不在任何房间
+    async fn get_player_current_room(&self, player_id: &str) -> Result<Option<String>>;
+
+    /// 保存玩家房间信息
+    async fn save_player_room_info(&self, player_id: &str, name: &str, room_id: &str) -> Result<()>;
+
+    /// 仅当玩家记录的房间仍是指定房间时才清理
+    async fn clear_player_room_info_if(&self, player_id: &str, room_id: &str) -> Result<bool>;
+
+    /// 尝试获取玩家房间归属的租约，成功时返回租约令牌
+    async fn try_acquire_player_lease(&self, player_id: &str, ttl_ms: u64) -> Result<Option<String>>;
+
+    /// 释放玩家房间归属的租约，只有令牌匹配时才会删除
+    async fn release_player_lease(&self, player_id: &str, token: &str) -> Result<()>;
+
+    // 会话与用户
+
+    /// 保存用户会话，同时登记到用户的会话索引
+    async fn save_session(&self, session: &UserSession) -> Result<()>;
+
+    async fn get_session(&self, session_id: &Uuid) -> Result<Option<UserSession>>;
+
+    async fn delete_session(&self, session_id: &Uuid) -> Result<()>;
+
+    /// 用户当前有效的会话，按创建时间排列
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>>;
+
+    /// 更新会话过期时间和最后使用时间
+    async fn extend_session(&self, session_id: &Uuid, new_expires_at: DateTime<Utc>) -> Result<()>;
+
+    /// 保存用户信息，永久保存
+    async fn save_user(&self, user: &User) -> Result<()>;
+
+    /// 保存游客信息，与游客会话同时过期
+    async fn save_guest_user(&self, user: &User, ttl_secs: u64) -> Result<()>;
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>>;
+
+    // 管理员令牌与审计日志
+
+    /// 保存管理员令牌，到期后自动删除
+    async fn save_admin_token(&self, token: &AdminToken) -> Result<()>;
+
+    async fn get_admin_token(&self, token: &str) -> Result<Option<AdminToken>>;
+
+    async fn delete_admin_token(&self, token: &str) -> Result<()>;
+
+    /// 追加一条管理操作审计记录，只保留最近 max_len 条
+    async fn append_audit_record(&self, record: &AuditRecord, max_len: usize) -> Result<()>;
+
+    /// 从新到旧分页读取审计记录，before 为上一页最后一条记录的ID（不含）
+    async fn get_audit_records(&self, before: Option<&str>, limit: usize) -> Result<Vec<(String, AuditRecord)>>;
+
+    // 等级分
+
+    /// 获取玩家等级分，没有记录时返回初始分
+    async fn get_rating(&self, player_id: &str) -> Result<PlayerRating>;
+
+    /// 保存玩家等级分，并同步等级分排行榜
+    async fn save_rating(&self, rating: &PlayerRating) -> Result<()>;
+
+    /// 根据本局结果更新参与玩家的等级分
+    ///
+    /// 平民阵营与卧底阵营（卧底和白板）各取平均分计算期望胜率，
+    /// 没有角色的玩家不参与计算
+    async fn update_ratings(&self, players: &[Player], winner: Role) -> Result<Vec<RatingChange>> {
+        let mut ratings = Vec::new();
+        for player in players {
+            if let Some(role) = player.role {
+                ratings.push((player, role, self.get_rating(&player.id).await?));
+            }
+        }
+
+        let side_average = |civilian: bool| {
+            let side: Vec<i32> = ratings
+                .iter()
+                .filter(|(_, role, _)| (*role == Role::Civilian) == civilian)
+                .map(|(_, _, rating)| rating.rating)
+                .collect();
+            if side.is_empty() {
+                PlayerRating::INITIAL_RATING as f64
+            } else {
+                side.iter().sum::<i32>() as f64 / side.len() as f64
+            }
+        };
+        let civilian_average = side_average(true);
+        let undercover_average = side_average(false);
+
+        let now = Utc::now();
+        let mut changes = Vec::new();
+        for (player, role, mut rating) in ratings {
+            let (own, opponent) = if role == Role::Civilian {
+                (civilian_average, undercover_average)
+            } else {
+                (undercover_average, civilian_average)
+            };
+            let won = role.wins_with(winner);
+            let delta = PlayerRating::elo_delta(own, opponent, role, won);
+
+            let old_rating = rating.rating;
+            rating.rating = (rating.rating + delta).max(0);
+            rating.games_played += 1;
+            if won {
+                rating.wins += 1;
+            }
+            rating.updated_at = Some(now);
+            self.save_rating(&rating).await?;
+
+            changes.push(RatingChange {
+                player_id: player.id.clone(),
+                player_name: player.name.clone(),
+                old_rating,
+                new_rating: rating.rating,
+                delta: rating.rating - old_rating,
+            });
+        }
+
+        Ok(changes)
+    }
+
+    // 每日游戏上限、禁言与头像
+
+    /// 累加玩家当天的对局数和游戏时长
+    async fn record_playtime(&self, player_id: &str, seconds: u64) -> Result<()>;
+
+    /// 玩家当天的对局数和游戏时长
+    async fn get_daily_playtime(&self, player_id: &str) -> Result<DailyPlaytime>;
+
+    async fn get_play_limits(&self, player_id: &str) -> Result<PlayLimits>;
+
+    async fn set_play_limits(&self, player_id: &str, limits: &PlayLimits) -> Result<()>;
+
+    /// 保存全服禁言，到期后自动失效
+    async fn set_global_mute(&self, mute: &GlobalMute) -> Result<()>;
+
+    /// 用户当前生效的全服禁言
+    async fn get_global_mute(&self, user_id: &str) -> Result<Option<GlobalMute>>;
+
+    async fn clear_global_mute(&self, user_id: &str) -> Result<()>;
+
+    /// 缓存用户头像的内容类型和图片数据
+    async fn save_avatar(&self, user_id: &str, content_type: &str, data: &[u8], ttl_secs: u64) -> Result<()>;
+
+    /// 缓存的用户头像，返回内容类型和图片数据
+    async fn get_avatar(&self, user_id: &str) -> Result<Option<(String, Vec<u8>)>>;
+
+    async fn delete_avatar(&self, user_id: &str) -> Result<()>;
+
+    // 主备部署
+
+    /// 尝试持有或续约主实例租约，成功时表示当前实例为主实例
+    async fn try_hold_active_lease(&self, instance_id: &str, ttl_secs: u64) -> Result<bool>;
+
+    /// 保存房间检查点，存储不可用时可以跳过
+    async fn save_room_checkpoint(&self, room_id: &str, checkpoint: &RoomCheckpoint) -> Result<()>;
+
+    async fn delete_room_checkpoint(&self, room_id: &str) -> Result<()>;
+
+    /// 加载所有房间检查点
+    async fn load_room_checkpoints(&self) -> Result<Vec<RoomCheckpoint>>;
+
+    // 词库
+
+    /// 读取保存的词库，后端不保存词库时返回 None
+    async fn load_word_bank(&self) -> Result<Option<WordBankData>> {
+        Ok(None)
+    }
+
+    /// 保存词库，后端不保存词库时忽略
+    async fn save_word_bank(&self, _data: &WordBankData) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// 按配置创建存储后端，并启动该后端需要的后台任务
+pub async fn open_storage(config: &Config) -> Result<Arc<dyn StorageBackend>> {
+    match config.storage.backend {
+        StorageKind::Redis => {
+            let storage = Arc::new(RedisStorage::new(&config.redis.url).await?);
+            storage.spawn_reconciler(std::time::Duration::from_secs(config.redis.reconcile_interval.max(1)));
+            storage.spawn_stats_aggregator(
+                std::time::Duration::from_secs(config.stats.aggregate_interval.max(60)),
+                config.stats.retention_days,
+            );
+            Ok(storage)
+        }
+        StorageKind::Sqlite => {
+            let storage = Arc::new(SqliteStorage::open(&config.storage.sqlite_path).await?);
+            info!("使用 SQLite 存储: {}", config.storage.sqlite_path);
+            storage.spawn_sweeper(std::time::Duration::from_secs(60));
+            Ok(storage)
+        }
+        StorageKind::Memory => {
+            warn!("使用内存存储，服务重启后所有数据都会丢失，只适合单机演示和测试");
+            let storage = Arc::new(MemoryStorage::new());
+            storage.spawn_sweeper(std::time::Duration::from_secs(60));
+            Ok(storage)
+        }
+    }
+}
+
 pub struct RedisStorage {
     manager: Arc<Mutex<ConnectionManager>>,
+    health: Arc<StorageHealth>,
+}
+
+/// Redis 可用性状态，不可用期间进入降级模式并缓存写操作
+struct StorageHealth {
+    degraded: AtomicBool,
+    degraded_since: std::sync::Mutex<Option<DateTime<Utc>>>,
+    /// 等待 Redis 恢复后按顺序补写的写操作
+    pending: std::sync::Mutex<VecDeque<redis::Pipeline>>,
+    max_pending: usize,
+    /// 缓冲区已满时丢弃的最早写操作数量
+    dropped: AtomicU64,
+}
+
+/// 存储状态快照，用于就绪检查和管理统计
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageHealthSnapshot {
+    pub degraded: bool,
+    pub degraded_since: Option<DateTime<Utc>>,
+    pub pending_writes: usize,
+    pub dropped_writes: u64,
+}
+
+/// 是否为 Redis 连接不可用类错误（而非命令本身的错误）
+fn is_connection_error(e: &redis::RedisError) -> bool {
+    e.is_io_error() || e.is_connection_dropped() || e.is_connection_refusal() || e.is_timeout()
 }
 
-impl RedisStorage {
-    pub async fn new(redis_url: &str) -> Result<Self> {
-        let client = Client::open(redis_url)?;
-        let manager = ConnectionManager::new(client).await?;
-        Ok(Self {
-            manager: Arc::new(Mutex::new(manager)),
-        })
+impl RedisStorage {
+    pub async fn new(redis_url: &str) -> Result<Self> {
+        let client = Client::open(redis_url)?;
+        let manager = ConnectionManager::new(client).await?;
+        Ok(RedisStorage {
+            manager: Arc::new(Mutex::new(manager)),
+            health: Arc::new(StorageHealth {
+                degraded: AtomicBool::new(false),
+                degraded_since: std::sync::Mutex::new(None),
+                pending: std::sync::Mutex::new(VecDeque::new()),
+                max_pending: crate::config::Config::get().redis.max_pending_writes,
+                dropped: AtomicU64::new(0),
+            }),
+        })
+    }
+
+    /// 连接类错误使存储进入降级模式
+    fn observe_error(&self, e: &redis::RedisError) -> bool {
+        if !is_connection_error(e) {
+            return false;
+        }
+        if !self.health.degraded.swap(true, Ordering::AcqRel) {
+            *self.health.degraded_since.lock().unwrap_or_else(|e| e.into_inner()) = Some(Utc::now());
+            warn!("Redis 不可用，进入降级模式，写操作将在恢复后补写: {}", e);
+        }
+        true
+    }
+
+    /// 缓存一条待补写的写操作，缓冲区已满时丢弃最早的一条
+    fn enqueue_write(&self, pipe: redis::Pipeline) {
+        let mut pending = self.health.pending.lock().unwrap_or_else(|e| e.into_inner());
+        if pending.len() >= self.health.max_pending {
+            pending.pop_front();
+            self.health.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        pending.push_back(pipe);
+    }
+
+    /// 执行写操作；Redis 不可用时缓存该操作并返回 None，恢复后按顺序补写
+    async fn write<T: redis::FromRedisValue>(&self, pipe: redis::Pipeline) -> Result<Option<T>> {
+        if self.is_degraded() {
+            self.enqueue_write(pipe);
+            return Ok(None);
+        }
+
+        let mut conn = self.manager.lock().await;
+        match pipe.query_async::<T>(&mut *conn).await {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.observe_error(&e) => {
+                drop(conn);
+                self.enqueue_write(pipe);
+                Ok(None)
+            }
+            Err(e) => Err(crate::Error::Storage(e.to_string()).into()),
+        }
+    }
+
+    /// 启动后台任务，降级期间定期检查 Redis 并补写缓存的写操作
+    pub fn spawn_reconciler(self: &Arc<Self>, interval: std::time::Duration) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if storage.is_degraded() {
+                    storage.reconcile().await;
+                }
+            }
+        });
+    }
+
+    /// 读取某一天的精简对局记录
+    async fn get_game_records(&self, date: NaiveDate) -> Result<Vec<GameRecord>> {
+        let mut conn = self.manager.lock().await;
+        let records: Vec<String> = conn.lrange(GameRecord::key(date), 0, -1).await?;
+        Ok(records
+            .iter()
+            .filter_map(|record| serde_json::from_str(record).ok())
+            .collect())
+    }
+
+    /// 把指定日期的对局记录汇总为每日统计并删除原始记录，已汇总过时直接返回
+    pub async fn aggregate_day(&self, date: NaiveDate) -> Result<DailyStats> {
+        let field = date.to_string();
+        let existing: Option<String> = self.manager.lock().await.hget(DAILY_STATS_KEY, &field).await?;
+        if let Some(stats) = existing.and_then(|data| serde_json::from_str(&data).ok()) {
+            return Ok(stats);
+        }
+
+        let stats = DailyStats::from_records(date, &self.get_game_records(date).await?);
+        let data = serde_json::to_string(&stats)?;
+        let mut conn = self.manager.lock().await;
+        // 多个实例同时汇总时只保留最先写入的结果
+        let (saved,): (bool,) = redis::pipe()
+            .hset_nx(DAILY_STATS_KEY, &field, data)
+            .del(GameRecord::key(date))
+            .ignore()
+            .query_async(&mut *conn)
+            .await?;
+        if saved {
+            return Ok(stats);
+        }
+        let existing: Option<String> = conn.hget(DAILY_STATS_KEY, &field).await?;
+        Ok(existing
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or(stats))
+    }
+
+    /// 删除超过保留天数的每日统计
+    async fn prune_daily_stats(&self, retention_days: u32) -> Result<()> {
+        let cutoff = Utc::now().date_naive() - chrono::Duration::days(retention_days as i64);
+        let mut conn = self.manager.lock().await;
+        let fields: Vec<String> = conn.hkeys(DAILY_STATS_KEY).await?;
+        let expired: Vec<&String> = fields
+            .iter()
+            .filter(|field| field.parse::<NaiveDate>().is_ok_and(|date| date < cutoff))
+            .collect();
+        if !expired.is_empty() {
+            conn.hdel::<_, _, ()>(DAILY_STATS_KEY, expired).await?;
+        }
+        Ok(())
+    }
+
+    /// 启动每日统计汇总任务：定期汇总前两天（防止跨零点时错过）的对局记录，Redis 不可用时跳过
+    pub fn spawn_stats_aggregator(self: &Arc<Self>, interval: std::time::Duration, retention_days: u32) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if storage.is_degraded() {
+                    continue;
+                }
+                let today = Utc::now().date_naive();
+                for offset in [2, 1] {
+                    let Some(date) = today.checked_sub_days(chrono::Days::new(offset)) else {
+                        continue;
+                    };
+                    match storage.aggregate_day(date).await {
+                        Ok(stats) => debug!("已汇总 {} 的对局统计，共 {} 局", date, stats.games),
+                        Err(e) => error!("汇总 {} 的对局统计失败: {}", date, e),
+                    }
+                }
+                if let Err(e) = storage.prune_daily_stats(retention_days).await {
+                    error!("清理过期每日统计失败: {}", e);
+                }
+            }
+        });
+    }
+
+    pub async fn get_game_history(&self, limit: usize) -> Result<Vec<GameResult>> {
+        let mut conn = self.manager.lock().await;
+        let history_key = "game_history";
+        let keys: Vec<String> = conn.lrange(history_key, 0, limit as isize - 1).await?;
+
+        let mut results = Vec::new();
+        for key in keys {
+            if let Some(data) = conn.get::<_, Option<String>>(&key).await?
+                && let Ok(result) = serde_json::from_str::<GameResult>(&data)
+            {
+                results.push(result);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// 检查玩家是否已在其他房间（保持向后兼容）
+    pub async fn is_player_in_other_room(&self, player_id: &str, current_room_id: &str) -> Result<bool> {
+        if let Some(room_id) = self.get_player_current_room(player_id).await? {
+            Ok(room_id != current_room_id)
+        } else {
+            Ok(false) // 玩家没有房间信息，说明不在任何房间
+        }
+    }
+
+    /// 清理玩家的房间信息（当玩家离开房间时调用）
+    pub async fn clear_player_room_info(&self, player_id: &str) -> Result<()> {
+        let mut conn = self.manager.lock().await;
+        let key = format!("player:{}", player_id);
+        conn.del::<_, ()>(&key).await?;
+        Ok(())
+    }
+
+    pub async fn save_checkpoint(&self, room_id: Uuid, state: &GameState) -> Result<()> {
+        let mut conn = self.manager.lock().await;
+        let key = format!("checkpoint:{}", room_id);
+        let data = serde_json::to_string(state)?;
+        conn.set::<_, _, ()>(&key, data).await?;
+        Ok(())
+    }
+
+    pub async fn load_checkpoint(&self, room_id: Uuid) -> Result<Option<GameState>> {
+        let mut conn = self.manager.lock().await;
+        let key = format!("checkpoint:{}", room_id);
+        let data: Option<String> = conn.get(&key).await?;
+
+        match data {
+            Some(data) => {
+                let state = serde_json::from_str(&data)?;
+                Ok(Some(state))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 删除用户信息
+    pub async fn delete_user(&self, user_id: &str) -> Result<()> {
+        let key = format!("user:{}", user_id);
+        let mut conn = self.manager.lock().await;
+        
+        conn.del::<_, ()>(&key)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        
+        Ok(())
+    }
+}
+
+/// 对局回放保留时间（秒）
+const REPLAY_TTL_SECS: i64 = 7 * 86400;
+
+/// 用户会话索引的过期时间，超过会话最长有效期即可，每次登记会话时刷新
+const USER_SESSIONS_INDEX_TTL_SECS: i64 = 31 * 86400;
+
+/// 用户会话索引的键，有序集合，成员为会话ID，分数为过期时间戳
+fn user_sessions_key(user_id: &str) -> String {
+    format!("sessions:{}", user_id)
+}
+
+/// 管理操作审计日志的 Redis Stream 键
+const ADMIN_AUDIT_KEY: &str = "admin:audit";
+
+/// 对局回放中的一条事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    /// 本局内从0开始的事件序号
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub event: GameEvent,
+}
+
+/// 一局完整的对局回放
+#[derive(Debug, Clone, Serialize)]
+pub struct Replay {
+    pub game_id: String,
+    pub room_id: String,
+    /// 按序号排列的事件
+    pub events: Vec<ReplayEntry>,
+}
+
+impl Replay {
+    /// 对局是否已结束，进行中的对局回放会泄露身份和词语，不对外提供
+    pub fn is_finished(&self) -> bool {
+        self.events
+            .last()
+            .is_some_and(|entry| matches!(entry.event, GameEvent::GameOver(_)))
+    }
+}
+
+/// 排行榜玩家ID到昵称的映射
+const LEADERBOARD_NAMES_KEY: &str = "leaderboard:names";
+
+/// 每个玩家保留的对局历史条数
+const PLAYER_GAMES_MAX_LEN: isize = 200;
+
+/// 每个房间归档的最近对局结果条数，会话统计只看最近的对局
+const ROOM_RESULTS_MAX_LEN: isize = 50;
+
+/// 玩家对局历史的键，列表，新记录在前
+fn player_games_key(player_id: &str) -> String {
+    format!("player_games:{}", player_id)
+}
+
+/// 玩家个人资料设置的键
+fn profile_settings_key(player_id: &str) -> String {
+    format!("profile_settings:{}", player_id)
+}
+
+/// 玩家对局历史中的一局
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerGameEntry {
+    /// 对局ID，可用于查看回放
+    pub game_id: Option<String>,
+    pub room_id: String,
+    pub role: Option<Role>,
+    pub word: Option<String>,
+    /// 该玩家所在阵营是否获胜
+    pub won: bool,
+    pub winner: Role,
+    pub player_count: usize,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PlayerGameEntry {
+    /// 从对局结果中提取某个玩家的记录，玩家不在本局中时返回 None
+    pub fn from_result(player_id: &str, result: &GameResult) -> Option<Self> {
+        let player = result.players.iter().find(|p| p.id == player_id)?;
+        Some(PlayerGameEntry {
+            game_id: result.game_id.clone(),
+            room_id: result.room_id.clone(),
+            role: player.role,
+            word: player.word.clone(),
+            won: player.role.is_some_and(|role| role.wins_with(result.winner)),
+            winner: result.winner,
+            player_count: result.players.len(),
+            timestamp: result.timestamp,
+        })
+    }
+}
+
+/// 玩家的个人资料设置
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ProfileSettings {
+    /// 隐藏个人资料中的统计、成就和对局记录，只公开昵称和头像
+    #[serde(default)]
+    pub private: bool,
+}
+
+/// 每日统计汇总，字段为日期
+const DAILY_STATS_KEY: &str = "stats:daily";
+
+/// 精简对局记录的保留时间，汇总任务正常运行时会提前删除
+const GAME_RECORD_TTL_SECS: i64 = 3 * 86400;
+
+/// 每个房间的状态事件日志保留的最近记录数，快照之间的增量事件远少于该数量
+/// （房间每追加 STATE_SNAPSHOT_INTERVAL 条事件就会写入一次快照）
+const GAME_EVENT_LOG_MAX_LEN: isize = 500;
+
+/// 房间状态事件日志的保存时间（秒），每次追加时续期
+const GAME_EVENT_LOG_TTL_SECS: i64 = 3600;
+
+fn game_events_key(room_id: &str) -> String {
+    format!("game_events:{}", room_id)
+}
+
+/// 状态事件日志中的一条记录及其写入时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateLogEntry {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub record: StateRecord,
+}
+
+/// 每个房间每个聊天频道在Redis中保留的最近消息数
+const CHAT_LOG_MAX_LEN: isize = 200;
+
+/// 房间聊天记录的保存时间（秒），与房间状态一致
+const CHAT_LOG_TTL_SECS: i64 = 3600;
+
+/// 房间的聊天频道
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatChannel {
+    /// 所有玩家可见的聊天
+    Public,
+    /// 只有被淘汰玩家可见的聊天
+    Eliminated,
+}
+
+impl ChatChannel {
+    fn key(self, room_id: &str) -> String {
+        match self {
+            ChatChannel::Public => format!("room:{}:chat", room_id),
+            ChatChannel::Eliminated => format!("room:{}:chat:eliminated", room_id),
+        }
+    }
+}
+
+/// 对局结束时写入的精简记录，用于汇总每日统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub timestamp: DateTime<Utc>,
+    pub winner: Role,
+    /// 对局总时长（毫秒）
+    pub duration_ms: u64,
+    /// 参与对局的真人玩家
+    pub players: Vec<String>,
+}
+
+impl GameRecord {
+    fn key(date: NaiveDate) -> String {
+        format!("stats:games:{}", date)
+    }
+}
+
+/// 一天内所有对局的汇总统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyStats {
+    pub date: NaiveDate,
+    pub games: u32,
+    /// 每小时（UTC）结束的对局数
+    pub games_per_hour: Vec<u32>,
+    pub civilian_wins: u32,
+    pub undercover_wins: u32,
+    /// 平均对局时长（毫秒）
+    pub average_duration_ms: u64,
+    /// 当天至少完成一局的真人玩家数
+    pub active_users: u32,
+}
+
+impl DailyStats {
+    pub fn from_records(date: NaiveDate, records: &[GameRecord]) -> Self {
+        let mut games_per_hour = vec![0; 24];
+        let mut civilian_wins = 0;
+        let mut undercover_wins = 0;
+        let mut total_duration = 0;
+        let mut users = std::collections::HashSet::new();
+        for record in records {
+            games_per_hour[record.timestamp.hour() as usize] += 1;
+            match record.winner {
+                Role::Civilian => civilian_wins += 1,
+                _ => undercover_wins += 1,
+            }
+            total_duration += record.duration_ms;
+            users.extend(record.players.iter());
+        }
+        DailyStats {
+            date,
+            games: records.len() as u32,
+            games_per_hour,
+            civilian_wins,
+            undercover_wins,
+            average_duration_ms: total_duration / records.len().max(1) as u64,
+            active_users: users.len() as u32,
+        }
+    }
+}
+
+/// 排行榜排序依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaderboardMetric {
+    /// 胜场数
+    Wins,
+    /// 排位等级分
+    Rating,
+}
+
+/// 指定时间所在统计周期的标识，日为 20240101，周为 2024-W01
+fn stats_period_label(period: LeaderboardPeriod, at: DateTime<Utc>) -> String {
+    match period {
+        LeaderboardPeriod::Daily => at.format("%Y%m%d").to_string(),
+        LeaderboardPeriod::Weekly => at.format("%G-W%V").to_string(),
+        LeaderboardPeriod::All => "all".to_string(),
+    }
+}
+
+/// 对局汇总计数的键，哈希，字段为 games、civilian_wins、undercover_wins
+fn stats_counters_key(period: LeaderboardPeriod, at: DateTime<Utc>) -> String {
+    format!("stats:counters:{}", stats_period_label(period, at))
+}
+
+/// 统计周期内参与过对局的玩家，HyperLogLog，用于估算去重玩家数
+fn stats_players_key(period: LeaderboardPeriod, at: DateTime<Utc>) -> String {
+    format!("stats:players:{}", stats_period_label(period, at))
+}
+
+/// 一个统计周期内的对局汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsSummary {
+    /// 周期标识，日为 20240101，周为 2024-W01，全部时间为 all
+    pub period: String,
+    pub games: u64,
+    /// 参与过对局的真人玩家数（估算值）
+    pub unique_players: u64,
+    pub civilian_wins: u64,
+    /// 卧底阵营（含白板）获胜的局数
+    pub undercover_wins: u64,
+    pub civilian_win_rate: f64,
+    pub undercover_win_rate: f64,
+}
+
+/// 排行榜统计周期（按UTC自然日、ISO周划分）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaderboardPeriod {
+    Daily,
+    Weekly,
+    All,
+}
+
+impl LeaderboardPeriod {
+    /// 周期榜单的过期时间，周期结束后保留一段时间再删除
+    fn ttl_secs(&self) -> Option<i64> {
+        match self {
+            LeaderboardPeriod::Daily => Some(2 * 86400),
+            LeaderboardPeriod::Weekly => Some(8 * 86400),
+            LeaderboardPeriod::All => None,
+        }
+    }
+}
+
+impl LeaderboardMetric {
+    /// 指定时间所在周期的榜单键
+    fn key(&self, period: LeaderboardPeriod, at: DateTime<Utc>) -> String {
+        let metric = match self {
+            LeaderboardMetric::Wins => "wins",
+            LeaderboardMetric::Rating => "rating",
+        };
+        match period {
+            LeaderboardPeriod::Daily => format!("leaderboard:{}:daily:{}", metric, at.format("%Y%m%d")),
+            LeaderboardPeriod::Weekly => format!("leaderboard:{}:weekly:{}", metric, at.format("%G-W%V")),
+            LeaderboardPeriod::All => format!("leaderboard:{}:all", metric),
+        }
+    }
+}
+
+/// 排行榜条目
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub player_id: String,
+    pub player_name: String,
+    pub score: i64,
+}
+
+/// 玩家等级分（ELO）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerRating {
+    pub player_id: String,
+    pub rating: i32,
+    pub games_played: u32,
+    pub wins: u32,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl PlayerRating {
+    /// 新玩家的初始分
+    pub const INITIAL_RATING: i32 = 1000;
+
+    pub fn new(player_id: &str) -> Self {
+        PlayerRating {
+            player_id: player_id.to_string(),
+            rating: Self::INITIAL_RATING,
+            games_played: 0,
+            wins: 0,
+            updated_at: None,
+        }
+    }
+
+    /// 各角色的K值：卧底方人数少，单人对胜负的影响更大
+    fn k_factor(role: Role) -> f64 {
+        match role {
+            Role::Civilian => 24.0,
+            Role::Undercover => 40.0,
+            Role::Blank => 32.0,
+        }
+    }
+
+    /// 按本方与对方阵营平均分计算本局的分数变化
+    pub fn elo_delta(own_average: f64, opponent_average: f64, role: Role, won: bool) -> i32 {
+        let expected = 1.0 / (1.0 + 10f64.powf((opponent_average - own_average) / 400.0));
+        let score = if won { 1.0 } else { 0.0 };
+        (Self::k_factor(role) * (score - expected)).round() as i32
+    }
+}
+
+/// 单名玩家在一局中的等级分变化
+#[derive(Debug, Clone, Serialize)]
+pub struct RatingChange {
+    pub player_id: String,
+    pub player_name: String,
+    pub old_rating: i32,
+    pub new_rating: i32,
+    pub delta: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameResult {
+    pub room_id: String,
+    pub winner: Role,
+    pub players: Vec<Player>,
+    pub timestamp: DateTime<Utc>,
+    /// 主动认输结束本局的卧底
+    #[serde(default)]
+    pub conceded_by: Option<String>,
+    /// 本局计时统计
+    #[serde(default)]
+    pub clock: GameClockStats,
+    /// 本局玩家操作记录（按时间顺序，有条数上限）
+    #[serde(default)]
+    pub actions: Vec<GameAction>,
+    /// 对局ID，用于查找本局回放
+    #[serde(default)]
+    pub game_id: Option<String>,
+}
+
+/// 同一房间内连续多局的玩家统计
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SessionStats {
+    pub player_id: String,
+    pub player_name: String,
+    pub games_played: u32,
+    pub wins: u32,
+    pub games_as_undercover: u32,
+    pub wins_as_undercover: u32,
+    /// 当前连胜局数
+    pub current_streak: u32,
+    /// 作为卧底主动认输的局数
+    pub concessions: u32,
+}
+
+impl SessionStats {
+    /// 根据按时间排序的对局结果计算每名玩家的统计
+    pub fn from_results(results: &[GameResult]) -> Vec<SessionStats> {
+        let mut stats: Vec<SessionStats> = Vec::new();
+
+        for result in results {
+            for player in &result.players {
+                let index = match stats.iter().position(|s| s.player_id == player.id) {
+                    Some(index) => index,
+                    None => {
+                        stats.push(SessionStats {
+                            player_id: player.id.clone(),
+                            ..Default::default()
+                        });
+                        stats.len() - 1
+                    }
+                };
+                let entry = &mut stats[index];
+                let won = player.role.is_some_and(|role| role.wins_with(result.winner));
+
+                entry.player_name = player.name.clone();
+                entry.games_played += 1;
+                if won {
+                    entry.wins += 1;
+                    entry.current_streak += 1;
+                } else {
+                    entry.current_streak = 0;
+                }
+                if player.role == Some(Role::Undercover) {
+                    entry.games_as_undercover += 1;
+                    if won {
+                        entry.wins_as_undercover += 1;
+                    }
+                }
+                if result.conceded_by.as_ref() == Some(&player.id) {
+                    entry.concessions += 1;
+                }
+            }
+        }
+
+        stats
+    }
+
+    /// 卧底胜率，未当过卧底时返回None
+    pub fn undercover_win_rate(&self) -> Option<f64> {
+        (self.games_as_undercover > 0)
+            .then(|| self.wins_as_undercover as f64 / self.games_as_undercover as f64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PlayerStats {
+    pub games_played: u32,
+    pub games_won: u32,
+    pub games_as_undercover: u32,
+    pub games_won_as_undercover: u32,
+    pub games_as_civilian: u32,
+    pub games_won_as_civilian: u32,
+    pub total_votes_received: u32,
+    pub total_votes_cast: u32,
+    pub correct_votes: u32,
+    pub last_played: Option<DateTime<Utc>>,
+}
+
+impl PlayerStats {
+    /// 将一局的结果计入玩家统计，投票数据来自对局操作记录（弃票不计入）
+    pub fn record_game(&mut self, player_id: &str, result: &GameResult) {
+        let Some(player) = result.players.iter().find(|p| p.id == player_id) else {
+            return;
+        };
+        let won = player.role.is_some_and(|role| role.wins_with(result.winner));
+
+        self.games_played += 1;
+        if won {
+            self.games_won += 1;
+        }
+        match player.role {
+            Some(Role::Undercover) => {
+                self.games_as_undercover += 1;
+                if won {
+                    self.games_won_as_undercover += 1;
+                }
+            }
+            Some(Role::Civilian) => {
+                self.games_as_civilian += 1;
+                if won {
+                    self.games_won_as_civilian += 1;
+                }
+            }
+            _ => {}
+        }
+
+        let role_of = |id: &str| result.players.iter().find(|p| p.id == id).and_then(|p| p.role);
+        for action in result.actions.iter().filter(|a| a.kind == ActionKind::Vote) {
+            if action.content == ABSTAIN_VOTE {
+                continue;
+            }
+            if action.player_id == player_id {
+                self.total_votes_cast += 1;
+                // 投给卧底阵营（卧底或白板）视为投对
+                if role_of(&action.content).is_some_and(|role| role != Role::Civilian) {
+                    self.correct_votes += 1;
+                }
+            }
+            if action.content == player_id {
+                self.total_votes_received += 1;
+            }
+        }
+
+        self.last_played = Some(result.timestamp);
+    }
+
+    /// 根据生涯统计已达成的成就
+    pub fn achievements(&self) -> Vec<&'static Achievement> {
+        ACHIEVEMENTS
+            .iter()
+            .filter(|achievement| (achievement.reached)(self))
+            .collect()
+    }
+}
+
+/// 由生涯统计推导出的成就，不单独存储
+#[derive(Debug, Serialize)]
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    /// 达成条件
+    #[serde(skip)]
+    reached: fn(&PlayerStats) -> bool,
+}
+
+/// 所有成就
+static ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "first_game",
+        name: "初来乍到",
+        description: "完成第一局游戏",
+        reached: |stats| stats.games_played >= 1,
+    },
+    Achievement {
+        id: "first_win",
+        name: "旗开得胜",
+        description: "赢得第一局游戏",
+        reached: |stats| stats.games_won >= 1,
+    },
+    Achievement {
+        id: "regular",
+        name: "常客",
+        description: "累计完成50局游戏",
+        reached: |stats| stats.games_played >= 50,
+    },
+    Achievement {
+        id: "veteran",
+        name: "身经百战",
+        description: "累计完成200局游戏",
+        reached: |stats| stats.games_played >= 200,
+    },
+    Achievement {
+        id: "master_of_disguise",
+        name: "伪装大师",
+        description: "作为卧底获胜10局",
+        reached: |stats| stats.games_won_as_undercover >= 10,
+    },
+    Achievement {
+        id: "civilian_hero",
+        name: "群众的力量",
+        description: "作为平民获胜50局",
+        reached: |stats| stats.games_won_as_civilian >= 50,
+    },
+    Achievement {
+        id: "sharp_eye",
+        name: "火眼金睛",
+        description: "累计投中卧底阵营100次",
+        reached: |stats| stats.correct_votes >= 100,
+    },
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub state: GameState,
+    pub version: u64,
+    pub timestamp: DateTime<Utc>,
+    pub is_consistent: bool,
+}
+
+impl RedisStorage {
+    pub async fn create_checkpoint(
+        &self,
+        room_id: Uuid,
+        state: &GameState,
+        version: u64,
+    ) -> Result<()> {
+        let checkpoint = Checkpoint {
+            state: state.clone(),
+            version,
+            timestamp: Utc::now(),
+            is_consistent: true,
+        };
+
+        let mut conn = self.manager.lock().await;
+        let key = format!("checkpoint:{}", room_id);
+        let data = serde_json::to_string(&checkpoint)?;
+
+        // 使用事务确保原子性
+        let mut pipe = redis::pipe();
+        pipe.atomic().set(&key, &data).expire(&key, 24 * 60 * 60); // 24小时过期
+
+        let _: () = pipe.query_async(&mut *conn).await?;
+
+        Ok(())
     }
 
-    // 房间相关操作
-    pub async fn save_room_state(&self, room_id: &str, state: &impl Serialize) -> Result<()> {
-        let state_json = serde_json::to_string(state)?;
+    pub async fn load_latest_checkpoint(&self, room_id: Uuid) -> Result<Option<Checkpoint>> {
         let mut conn = self.manager.lock().await;
-        conn.hset::<_, _, _, ()>(format!("room:{}", room_id), "state", state_json)
-            .await?;
-        Ok(())
+        let key = format!("checkpoint:{}", room_id);
+        let data: Option<String> = conn.get(&key).await?;
+
+        match data {
+            Some(data) => {
+                let checkpoint = serde_json::from_str(&data)?;
+                Ok(Some(checkpoint))
+            }
+            None => Ok(None),
+        }
     }
 
-    pub async fn load_room_state<T: for<'de> Deserialize<'de>>(
-        &self,
-        room_id: &str,
-    ) -> Result<Option<T>> {
+    pub async fn verify_state_consistency(&self, room_id: Uuid, state: &GameState) -> Result<bool> {
+        let mut conn = self.manager.lock().await;
+        let key = format!("state_verification:{}", room_id);
+
+        // 计算状态哈希
+        let state_hash = self.calculate_state_hash(state).await?;
+
+        // 获取之前的状态哈希
+        let prev_hash: Option<String> = conn.get(&key).await?;
+
+        // 更新状态哈希
+        conn.set::<_, _, ()>(&key, &state_hash).await?;
+
+        // 验证一致性
+        Ok(prev_hash.is_none_or(|h| h == state_hash))
+    }
+
+    async fn calculate_state_hash(&self, state: &GameState) -> Result<String> {
+        let state_json = serde_json::to_string(state)?;
+        let hash = Sha256::digest(state_json.as_bytes());
+        Ok(hex::encode(hash))
+    }
+
+    pub async fn recover_from_crash(&self, room_id: Uuid) -> Result<Option<GameState>> {
+        // 1. 尝试加载最新的检查点
+        if let Some(checkpoint) = self.load_latest_checkpoint(room_id).await? {
+            // 2. 验证检查点的一致性
+            if checkpoint.is_consistent {
+                return Ok(Some(checkpoint.state));
+            }
+        }
+
+        // 3. 如果没有有效的检查点，尝试从游戏历史恢复
         let mut conn = self.manager.lock().await;
-        let state_json: Option<String> = conn.hget(format!("room:{}", room_id), "state").await?;
+        let history_key = format!("game_history:{}", room_id);
+        let latest_state: Option<String> = conn.lindex(&history_key, 0).await?;
 
-        match state_json {
-            Some(json) => Ok(Some(serde_json::from_str(&json)?)),
+        match latest_state {
+            Some(state_json) => {
+                let state = serde_json::from_str(&state_json)?;
+                Ok(Some(state))
+            }
             None => Ok(None),
         }
     }
 
-    // 玩家相关操作
-    pub async fn save_player_info(&self, player_id: &str, name: &str, room_id: &str) -> Result<()> {
+    pub async fn cleanup_old_data(&self) -> Result<()> {
         let mut conn = self.manager.lock().await;
-        let key = format!("player:{}", player_id);
-        conn.hset::<_, _, _, ()>(&key, "name", name).await?;
-        conn.hset::<_, _, _, ()>(&key, "room_id", room_id).await?;
-        conn.hset::<_, _, _, ()>(&key, "last_active", chrono::Utc::now().timestamp())
-            .await?;
+
+        // 清理过期的检查点
+        let pattern = "checkpoint:*";
+        let keys: Vec<String> = conn.keys(pattern).await?;
+        for key in keys {
+            let _: i32 = conn.del(&key).await?;
+        }
+
+        // 清理过期的游戏历史
+        let pattern = "game_history:*";
+        let keys: Vec<String> = conn.keys(pattern).await?;
+        for key in keys {
+            let _: bool = conn.ltrim(&key, 0, 999).await?; // 只保留最近1000条记录
+        }
+
         Ok(())
     }
+}
 
-    pub async fn get_player_info(&self, player_id: &str) -> Result<Option<(String, String)>> {
-        let mut conn = self.manager.lock().await;
-        let key = format!("player:{}", player_id);
-        let name: Option<String> = conn.hget(&key, "name").await?;
-        let room_id: Option<String> = conn.hget(&key, "room_id").await?;
+/// 主备部署：主实例租约与房间检查点
+/// 玩家每日游戏时长记录的保存时间（秒）
+const PLAYTIME_TTL_SECS: i64 = 2 * 86400;
+
+/// 玩家当天（UTC）已完成的对局数和游戏时长
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DailyPlaytime {
+    pub games: u32,
+    pub seconds: u64,
+}
+
+/// 每日游戏上限，两项都为空时不限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayLimit {
+    /// 每日最多对局数
+    pub max_games: Option<u32>,
+    /// 每日最多游戏分钟数
+    pub max_minutes: Option<u32>,
+}
+
+impl PlayLimit {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_games.is_none() && self.max_minutes.is_none()
+    }
 
-        match (name, room_id) {
-            (Some(name), Some(room_id)) => Ok(Some((name, room_id))),
-            _ => Ok(None),
+    /// 合并两个上限，每一项取更严格的值
+    pub fn stricter(self, other: PlayLimit) -> PlayLimit {
+        let min = |a: Option<u32>, b: Option<u32>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        PlayLimit {
+            max_games: min(self.max_games, other.max_games),
+            max_minutes: min(self.max_minutes, other.max_minutes),
+        }
+    }
+
+    /// 当天使用量占上限的最大比例，不限制时为0
+    pub fn usage_ratio(&self, usage: &DailyPlaytime) -> f64 {
+        let games = self
+            .max_games
+            .map_or(0.0, |max| usage.games as f64 / max.max(1) as f64);
+        let minutes = self
+            .max_minutes
+            .map_or(0.0, |max| usage.seconds as f64 / 60.0 / max.max(1) as f64);
+        games.max(minutes)
+    }
+
+    /// 当天是否已达到上限
+    pub fn is_reached(&self, usage: &DailyPlaytime) -> bool {
+        self.usage_ratio(usage) >= 1.0
+    }
+}
+
+/// 玩家的每日游戏上限：玩家自己设置的和管理员设置的同时生效
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PlayLimits {
+    #[serde(default)]
+    pub self_limit: PlayLimit,
+    #[serde(default)]
+    pub admin_limit: PlayLimit,
+}
+
+impl PlayLimits {
+    /// 实际生效的上限
+    pub fn effective(&self) -> PlayLimit {
+        self.self_limit.stricter(self.admin_limit)
+    }
+}
+
+impl RedisStorage {
+    fn playtime_key(player_id: &str) -> String {
+        format!("playtime:{}:{}", player_id, Utc::now().date_naive())
+    }
+}
+
+/// 管理员对用户的全服禁言，到期后由 Redis 自动删除
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalMute {
+    pub user_id: String,
+    /// 执行禁言的管理员用户名
+    pub admin: String,
+    pub reason: String,
+    pub muted_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RedisStorage {
+    fn global_mute_key(user_id: &str) -> String {
+        format!("global_mute:{}", user_id)
+    }
+
+    fn avatar_key(user_id: &str) -> String {
+        format!("avatar:{}", user_id)
+    }
+}
+
+#[axum::async_trait]
+impl StorageBackend for RedisStorage {
+    /// Redis 当前是否不可用
+    fn is_degraded(&self) -> bool {
+        self.health.degraded.load(Ordering::Acquire)
+    }
+
+    /// 获取存储状态快照
+    fn health(&self) -> StorageHealthSnapshot {
+        StorageHealthSnapshot {
+            degraded: self.is_degraded(),
+            degraded_since: *self.health.degraded_since.lock().unwrap_or_else(|e| e.into_inner()),
+            pending_writes: self.health.pending.lock().unwrap_or_else(|e| e.into_inner()).len(),
+            dropped_writes: self.health.dropped.load(Ordering::Relaxed),
         }
     }
 
-    // 投票相关操作
-    pub async fn save_vote(&self, room_id: &str, voter_id: &str, target_id: &str) -> Result<()> {
+    /// Redis 恢复后按顺序补写缓存的写操作，全部补写完成时退出降级模式并返回 true
+    async fn reconcile(&self) -> bool {
+        if !self.is_degraded() {
+            return true;
+        }
+
         let mut conn = self.manager.lock().await;
-        conn.hset::<_, _, _, ()>(format!("votes:{}", room_id), voter_id, target_id)
-            .await?;
+        if redis::cmd("PING").query_async::<String>(&mut *conn).await.is_err() {
+            return false;
+        }
+
+        let mut replayed = 0;
+        loop {
+            let next = self.health.pending.lock().unwrap_or_else(|e| e.into_inner()).pop_front();
+            let Some(pipe) = next else {
+                break;
+            };
+            match pipe.query_async::<redis::Value>(&mut *conn).await {
+                Ok(_) => replayed += 1,
+                Err(e) if is_connection_error(&e) => {
+                    // 补写过程中再次断开，放回队首等待下次重试
+                    self.health.pending.lock().unwrap_or_else(|e| e.into_inner()).push_front(pipe);
+                    return false;
+                }
+                Err(e) => error!("补写缓存的 Redis 写操作失败，已丢弃: {}", e),
+            }
+        }
+
+        // 持有连接锁期间不会有新的写操作直接写入，队列清空后即可退出降级模式
+        self.health.degraded.store(false, Ordering::Release);
+        *self.health.degraded_since.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        info!("Redis 已恢复，补写了 {} 条缓存的写操作", replayed);
+        true
+    }
+
+    /// 追加一条房间状态变化到事件日志，只保留最近的 GAME_EVENT_LOG_MAX_LEN 条；
+    /// 写入快照时丢弃之前的记录，重放只需要最近一次快照及之后的事件
+    async fn append_state_record(&self, room_id: &str, record: &StateRecord) -> Result<()> {
+        let key = game_events_key(room_id);
+        let value = serde_json::to_string(&StateLogEntry {
+            timestamp: Utc::now(),
+            record: record.clone(),
+        })?;
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if matches!(record, StateRecord::Snapshot(_)) {
+            pipe.del(&key).ignore();
+        }
+        pipe.rpush(&key, value).ignore();
+        pipe.ltrim(&key, -GAME_EVENT_LOG_MAX_LEN, -1).ignore();
+        pipe.expire(&key, GAME_EVENT_LOG_TTL_SECS).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
+
+    /// 删除房间的状态事件日志，房间关闭后不再在重启时恢复
+    async fn delete_room_state(&self, room_id: &str) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.del(game_events_key(room_id)).ignore();
+        self.write::<()>(pipe).await?;
         Ok(())
     }
 
-    pub async fn get_votes(&self, room_id: &str) -> Result<Vec<(String, String)>> {
+    /// 读取房间的状态事件日志，按写入先后排列，无法解析的记录会被跳过
+    async fn load_state_log(&self, room_id: &str) -> Result<Vec<StateLogEntry>> {
         let mut conn = self.manager.lock().await;
-        let votes: Vec<(String, String)> = conn.hgetall(format!("votes:{}", room_id)).await?;
-        Ok(votes)
+        let values: Vec<String> = conn.lrange(game_events_key(room_id), 0, -1).await?;
+        drop(conn);
+
+        Ok(values
+            .iter()
+            .filter_map(|value| match serde_json::from_str(value) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    warn!("房间 {} 的状态事件无法解析: {}", room_id, e);
+                    None
+                }
+            })
+            .collect())
     }
 
-    // 词库相关操作
-    pub async fn add_word(&self, word: &str) -> Result<()> {
+    /// 扫描所有保存了状态事件日志的房间ID
+    async fn list_saved_room_ids(&self) -> Result<Vec<String>> {
         let mut conn = self.manager.lock().await;
-        conn.sadd::<_, _, ()>("word_bank", word).await?;
+        let mut room_ids = Vec::new();
+        let mut cursor: u64 = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg("game_events:*")
+                .arg("COUNT")
+                .arg(100)
+                .query_async(&mut *conn)
+                .await?;
+            room_ids.extend(
+                keys.iter()
+                    .filter_map(|key| key.strip_prefix("game_events:"))
+                    .map(str::to_string),
+            );
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        Ok(room_ids)
+    }
+
+    /// 追加一条聊天消息到房间的聊天记录，只保留最近的 CHAT_LOG_MAX_LEN 条
+    async fn append_chat_message(&self, room_id: &str, channel: ChatChannel, message: &ChatMessage) -> Result<()> {
+        let key = channel.key(room_id);
+        let value = serde_json::to_string(message)?;
+
+        let mut pipe = redis::pipe();
+        pipe.rpush(&key, value).ignore();
+        pipe.ltrim(&key, -CHAT_LOG_MAX_LEN, -1).ignore();
+        pipe.expire(&key, CHAT_LOG_TTL_SECS).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
+
+    /// 清空房间指定频道的聊天记录
+    async fn clear_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.del(channel.key(room_id)).ignore();
+        self.write::<()>(pipe).await?;
         Ok(())
     }
 
-    pub async fn get_random_word(&self) -> Result<Option<String>> {
+    /// 读取房间指定频道的聊天记录，按发送先后排列，无法解析的记录会被跳过
+    async fn load_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<Vec<ChatMessage>> {
         let mut conn = self.manager.lock().await;
-        let word: Option<String> = conn.srandmember("word_bank").await?;
-        Ok(word)
+        let values: Vec<String> = conn.lrange(channel.key(room_id), 0, -1).await?;
+        drop(conn);
+
+        Ok(values
+            .iter()
+            .filter_map(|value| serde_json::from_str(value).ok())
+            .collect())
     }
 
-    // 游戏历史记录
-    pub async fn save_game_history(
+    /// 保存游戏结果，并为 leaderboard_players 中的玩家更新排行榜
+    ///
+    /// `history_players` 为需要记入个人对局历史和活跃玩家统计的玩家，`leaderboard_players` 为计入排行榜的玩家
+    async fn save_game_result(
         &self,
-        room_id: &str,
-        game_state: &impl Serialize,
+        result: &GameResult,
+        leaderboard_players: &[String],
+        history_players: &[String],
     ) -> Result<()> {
-        let state_json = serde_json::to_string(game_state)?;
-        let mut conn = self.manager.lock().await;
-        conn.rpush::<_, _, ()>(format!("game_history:{}", room_id), state_json)
-            .await?;
+        let key = format!("game:{}:result", result.room_id);
+        let value =
+            serde_json::to_string(result).map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        let mut pipe = redis::pipe();
+        pipe.set_ex(&key, &value, 86400).ignore();
+
+        // 归档到房间的历史对局列表，用于统计同一房间内的连续对局
+        let archive_key = format!("room:{}:results", result.room_id);
+        pipe.rpush(&archive_key, &value).ignore();
+        pipe.ltrim(&archive_key, -ROOM_RESULTS_MAX_LEN, -1).ignore();
+        pipe.expire(&archive_key, 86400).ignore();
+
+        // 更新胜场排行榜，只统计计入排行榜的玩家
+        let now = result.timestamp;
+        for player in result.players.iter().filter(|p| leaderboard_players.contains(&p.id)) {
+            pipe.hset(LEADERBOARD_NAMES_KEY, &player.id, &player.name).ignore();
+
+            if !player.role.is_some_and(|role| role.wins_with(result.winner)) {
+                continue;
+            }
+            for period in [LeaderboardPeriod::Daily, LeaderboardPeriod::Weekly, LeaderboardPeriod::All] {
+                let board_key = LeaderboardMetric::Wins.key(period, now);
+                pipe.zincr(&board_key, &player.id, 1).ignore();
+                if let Some(ttl) = period.ttl_secs() {
+                    pipe.expire(&board_key, ttl).ignore();
+                }
+            }
+        }
+
+        // 按玩家索引的对局历史，新记录在前，只保留最近的若干局
+        for player_id in history_players {
+            let Some(entry) = PlayerGameEntry::from_result(player_id, result) else {
+                continue;
+            };
+            let history_key = player_games_key(player_id);
+            let entry = serde_json::to_string(&entry).map_err(|e| crate::Error::Storage(e.to_string()))?;
+            pipe.lpush(&history_key, entry).ignore();
+            pipe.ltrim(&history_key, 0, PLAYER_GAMES_MAX_LEN - 1).ignore();
+        }
+
+        // 当天的精简对局记录，由汇总任务次日汇总为每日统计
+        let record = GameRecord {
+            timestamp: result.timestamp,
+            winner: result.winner,
+            duration_ms: result.clock.total_ms,
+            players: history_players.to_vec(),
+        };
+        let record_key = GameRecord::key(result.timestamp.date_naive());
+        let record = serde_json::to_string(&record).map_err(|e| crate::Error::Storage(e.to_string()))?;
+        pipe.rpush(&record_key, record).ignore();
+        pipe.expire(&record_key, GAME_RECORD_TTL_SECS).ignore();
+
+        // 按日、周和全部时间累加的对局计数与去重玩家数
+        let winner_field = match result.winner {
+            Role::Civilian => "civilian_wins",
+            _ => "undercover_wins",
+        };
+        let retention_days = crate::config::Config::get().stats.retention_days.max(1) as i64;
+        for period in [LeaderboardPeriod::Daily, LeaderboardPeriod::Weekly, LeaderboardPeriod::All] {
+            let counters_key = stats_counters_key(period, result.timestamp);
+            let players_key = stats_players_key(period, result.timestamp);
+            pipe.hincr(&counters_key, "games", 1).ignore();
+            pipe.hincr(&counters_key, winner_field, 1).ignore();
+            if !history_players.is_empty() {
+                pipe.pfadd(&players_key, history_players).ignore();
+            }
+            let ttl = match period {
+                LeaderboardPeriod::Daily => Some(retention_days * 86400),
+                LeaderboardPeriod::Weekly => Some((retention_days + 7) * 86400),
+                LeaderboardPeriod::All => None,
+            };
+            if let Some(ttl) = ttl {
+                pipe.expire(&counters_key, ttl).ignore();
+                pipe.expire(&players_key, ttl).ignore();
+            }
+        }
+
+        self.write::<()>(pipe).await?;
         Ok(())
     }
 
-    pub async fn get_game_history<T: for<'de> Deserialize<'de>>(
-        &self,
-        room_id: &str,
-    ) -> Result<Vec<T>> {
+    /// 获取房间归档的历史对局结果（按时间顺序）
+    async fn get_room_results(&self, room_id: &str) -> Result<Vec<GameResult>> {
+        let key = format!("room:{}:results", room_id);
         let mut conn = self.manager.lock().await;
-        let history: Vec<String> = conn
-            .lrange(format!("game_history:{}", room_id), 0, -1)
-            .await?;
+        let values: Vec<String> = conn
+            .lrange(&key, 0, -1)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
 
-        let mut states = Vec::new();
-        for json in history {
-            states.push(serde_json::from_str(&json)?);
-        }
-        Ok(states)
+        Ok(values
+            .iter()
+            .filter_map(|value| serde_json::from_str(value).ok())
+            .collect())
     }
 
-    // 连接状态管理
-    pub async fn update_connection_status(&self, player_id: &str, status: &str) -> Result<()> {
+    /// 读取玩家最近的对局历史，按时间倒序，`offset` 为跳过的条数
+    async fn get_player_games(&self, player_id: &str, offset: usize, limit: usize) -> Result<Vec<PlayerGameEntry>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
         let mut conn = self.manager.lock().await;
-        let key = format!("connection:{}", player_id);
-        conn.hset::<_, _, _, ()>(&key, "status", status).await?;
-        conn.hset::<_, _, _, ()>(&key, "last_seen", chrono::Utc::now().timestamp())
+        let entries: Vec<String> = conn
+            .lrange(
+                player_games_key(player_id),
+                offset as isize,
+                (offset + limit) as isize - 1,
+            )
             .await?;
-        Ok(())
+        Ok(entries
+            .iter()
+            .filter_map(|entry| serde_json::from_str(entry).ok())
+            .collect())
     }
 
-    pub async fn get_connection_status(&self, player_id: &str) -> Result<Option<String>> {
+    /// 当天截至目前的统计，直接由当天的精简记录计算
+    async fn get_today_stats(&self) -> Result<DailyStats> {
+        let today = Utc::now().date_naive();
+        Ok(DailyStats::from_records(today, &self.get_game_records(today).await?))
+    }
+
+    /// 读取最近若干天（不含当天）的每日统计，按日期从近到远排列，尚未汇总的日期会被跳过
+    async fn get_daily_stats(&self, days: u32) -> Result<Vec<DailyStats>> {
+        let today = Utc::now().date_naive();
+        let fields: Vec<String> = (1..=days as u64)
+            .filter_map(|offset| today.checked_sub_days(chrono::Days::new(offset)))
+            .map(|date| date.to_string())
+            .collect();
+        if fields.is_empty() {
+            return Ok(Vec::new());
+        }
         let mut conn = self.manager.lock().await;
-        let status: Option<String> = conn
-            .hget(format!("connection:{}", player_id), "status")
+        let values: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(DAILY_STATS_KEY)
+            .arg(&fields)
+            .query_async(&mut *conn)
             .await?;
-        Ok(status)
+        Ok(values
+            .into_iter()
+            .flatten()
+            .filter_map(|data| serde_json::from_str(&data).ok())
+            .collect())
     }
-}
 
-pub struct Storage {
-    manager: Arc<Mutex<ConnectionManager>>,
-}
+    /// 读取最近若干个周期（含当前周期）的对局汇总，按时间从近到远排列
+    async fn get_stats_summary(&self, period: LeaderboardPeriod, count: u32) -> Result<Vec<StatsSummary>> {
+        let now = Utc::now();
+        let step = match period {
+            LeaderboardPeriod::Daily => 1,
+            LeaderboardPeriod::Weekly => 7,
+            LeaderboardPeriod::All => 0,
+        };
+        let count = if period == LeaderboardPeriod::All { 1 } else { count.max(1) };
+        let instants: Vec<DateTime<Utc>> = (0..count as i64)
+            .map(|offset| now - chrono::Duration::days(offset * step))
+            .collect();
 
-impl Storage {
-    pub async fn new(redis_url: &str) -> Result<Self> {
-        let client = Client::open(redis_url)?;
-        let manager = ConnectionManager::new(client).await?;
-        Ok(Storage {
-            manager: Arc::new(Mutex::new(manager)),
-        })
+        let mut pipe = redis::pipe();
+        for at in &instants {
+            pipe.cmd("HMGET")
+                .arg(stats_counters_key(period, *at))
+                .arg(&["games", "civilian_wins", "undercover_wins"])
+                .pfcount(stats_players_key(period, *at));
+        }
+        let mut conn = self.manager.lock().await;
+        let values: Vec<redis::Value> = pipe.query_async(&mut *conn).await?;
+        drop(conn);
+
+        // 每个周期对应 HMGET 和 PFCOUNT 两条回复
+        let mut summary = Vec::with_capacity(instants.len());
+        for (at, replies) in instants.iter().zip(values.chunks_exact(2)) {
+            let counters: Vec<Option<u64>> = redis::from_redis_value(&replies[0])?;
+            let unique_players: u64 = redis::from_redis_value(&replies[1])?;
+            let counter = |index: usize| counters.get(index).copied().flatten().unwrap_or(0);
+            let (games, civilian_wins, undercover_wins) = (counter(0), counter(1), counter(2));
+            let rate = |wins: u64| if games == 0 { 0.0 } else { wins as f64 / games as f64 };
+            summary.push(StatsSummary {
+                period: stats_period_label(period, *at),
+                games,
+                unique_players,
+                civilian_wins,
+                undercover_wins,
+                civilian_win_rate: rate(civilian_wins),
+                undercover_win_rate: rate(undercover_wins),
+            });
+        }
+        Ok(summary)
     }
 
-    /// 保存房间状态
-    pub async fn save_room_state(&self, room_id: String, state: &GameState) -> Result<()> {
-        let key = format!("room:{}:state", room_id);
+    /// 追加一条对局回放事件
+    async fn append_replay_event(&self, room_id: &str, game_id: &str, entry: &ReplayEntry) -> Result<()> {
+        let key = format!("replay:{}:{}", room_id, game_id);
         let value =
-            serde_json::to_string(state).map_err(|e| crate::Error::Storage(e.to_string()))?;
+            serde_json::to_string(entry).map_err(|e| crate::Error::Storage(e.to_string()))?;
 
-        let mut conn = self.manager.lock().await;
-        conn.set_ex::<_, _, ()>(&key, &value, 3600)
-            .await
-            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        let mut pipe = redis::pipe();
+        pipe.rpush(&key, &value).ignore();
+        pipe.expire(&key, REPLAY_TTL_SECS).ignore();
+        // 第一条事件时记录对局所在房间，按对局ID查询回放时使用
+        if entry.seq == 0 {
+            pipe.set_ex(format!("replay_index:{}", game_id), room_id, REPLAY_TTL_SECS as u64)
+                .ignore();
+        }
+        self.write::<()>(pipe).await?;
 
         Ok(())
     }
 
-    /// 加载房间状态
-    pub async fn load_room_state(&self, room_id: String) -> Result<Option<GameState>> {
-        let key = format!("room:{}:state", room_id);
+    /// 按对局ID获取完整回放，回放不存在或已过期时返回 None
+    async fn get_replay(&self, game_id: &str) -> Result<Option<Replay>> {
         let mut conn = self.manager.lock().await;
-        let value: Option<String> = conn
-            .get(&key)
+        let room_id: Option<String> = conn
+            .get(format!("replay_index:{}", game_id))
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        let Some(room_id) = room_id else {
+            return Ok(None);
+        };
 
-        match value {
-            Some(json) => {
-                let state: GameState = serde_json::from_str(&json)
-                    .map_err(|e| crate::Error::Storage(e.to_string()))?;
-                Ok(Some(state))
-            }
-            None => Ok(None),
+        let values: Vec<String> = conn
+            .lrange(format!("replay:{}:{}", room_id, game_id), 0, -1)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        if values.is_empty() {
+            return Ok(None);
         }
-    }
 
-    /// 保存游戏结果
-    pub async fn save_game_result(
-        &self,
-        room_id: String,
-        winner: Role,
-        players: &[Player],
-    ) -> Result<()> {
-        let key = format!("game:{}:result", room_id);
-        let result = GameResult {
+        let events = values
+            .iter()
+            .map(|value| serde_json::from_str(value))
+            .collect::<std::result::Result<Vec<ReplayEntry>, _>>()
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        Ok(Some(Replay {
+            game_id: game_id.to_string(),
             room_id,
-            winner,
-            players: players.to_vec(),
-            timestamp: Utc::now(),
-        };
+            events,
+        }))
+    }
 
-        let value =
-            serde_json::to_string(&result).map_err(|e| crate::Error::Storage(e.to_string()))?;
+    /// 分页获取排行榜，返回榜单总人数和本页条目
+    async fn get_leaderboard(
+        &self,
+        metric: LeaderboardMetric,
+        period: LeaderboardPeriod,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(usize, Vec<LeaderboardEntry>)> {
+        if metric == LeaderboardMetric::Rating && period != LeaderboardPeriod::All {
+            return Err(crate::Error::Storage("等级分排行榜只有总榜".to_string()).into());
+        }
 
+        let key = metric.key(period, Utc::now());
         let mut conn = self.manager.lock().await;
-        conn.set_ex::<_, _, ()>(&key, &value, 86400)
+        let total: usize = conn
+            .zcard(&key)
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        if limit == 0 || offset >= total {
+            return Ok((total, Vec::new()));
+        }
 
-        Ok(())
-    }
-
-    pub async fn get_game_history(&self, limit: usize) -> Result<Vec<GameResult>> {
-        let mut conn = self.manager.lock().await;
-        let history_key = "game_history";
-        let keys: Vec<String> = conn.lrange(history_key, 0, limit as isize - 1).await?;
-
-        let mut results = Vec::new();
-        for key in keys {
-            if let Some(data) = conn.get::<_, Option<String>>(&key).await? {
-                if let Ok(result) = serde_json::from_str::<GameResult>(&data) {
-                    results.push(result);
-                }
-            }
+        let scores: Vec<(String, f64)> = conn
+            .zrevrange_withscores(&key, offset as isize, (offset + limit) as isize - 1)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        if scores.is_empty() {
+            return Ok((total, Vec::new()));
         }
 
-        Ok(results)
+        let ids: Vec<&String> = scores.iter().map(|(id, _)| id).collect();
+        let names: Vec<Option<String>> = redis::cmd("HMGET")
+            .arg(LEADERBOARD_NAMES_KEY)
+            .arg(&ids)
+            .query_async(&mut *conn)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        let entries = scores
+            .into_iter()
+            .zip(names)
+            .enumerate()
+            .map(|(index, ((player_id, score), name))| LeaderboardEntry {
+                rank: offset + index + 1,
+                player_name: name.unwrap_or_else(|| player_id.clone()),
+                player_id,
+                score: score as i64,
+            })
+            .collect();
+
+        Ok((total, entries))
     }
 
     /// 获取玩家统计信息
-    pub async fn get_player_stats(&self, player_id: Uuid) -> Result<PlayerStats> {
+    async fn get_player_stats(&self, player_id: &str) -> Result<PlayerStats> {
         let mut conn = self.manager.lock().await;
         let key = format!("player_stats:{}", player_id);
 
@@ -249,89 +1823,148 @@ impl Storage {
         }
     }
 
-    /// 检查玩家是否已在其他房间，返回当前房间ID
-    pub async fn get_player_current_room(&self, player_id: &str) -> Result<Option<String>> {
+    async fn update_player_stats(&self, player_id: &str, stats: &PlayerStats) -> Result<()> {
+        let key = format!("player_stats:{}", player_id);
+        let data = serde_json::to_string(stats)?;
+        let mut pipe = redis::pipe();
+        pipe.set(&key, data).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
+
+    /// 读取玩家的个人资料设置，未设置过时返回默认值
+    async fn get_profile_settings(&self, player_id: &str) -> Result<ProfileSettings> {
         let mut conn = self.manager.lock().await;
-        let key = format!("player:{}", player_id);
-        let stored_room_id: Option<String> = conn.hget(&key, "room_id").await?;
-        Ok(stored_room_id)
+        let data: Option<String> = conn.get(profile_settings_key(player_id)).await?;
+        Ok(data
+            .map(|data| serde_json::from_str(&data))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    /// 保存玩家的个人资料设置
+    async fn save_profile_settings(&self, player_id: &str, settings: &ProfileSettings) -> Result<()> {
+        let data = serde_json::to_string(settings)?;
+        let mut pipe = redis::pipe();
+        pipe.set(profile_settings_key(player_id), data).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
     }
 
-    /// 检查玩家是否已在其他房间（保持向后兼容）
-    pub async fn is_player_in_other_room(&self, player_id: &str, current_room_id: &str) -> Result<bool> {
-        if let Some(room_id) = self.get_player_current_room(player_id).await? {
-            Ok(room_id != current_room_id)
-        } else {
-            Ok(false) // 玩家没有房间信息，说明不在任何房间
+    /// 检查玩家是否已在其他房间，返回当前房间ID
+    ///
+    /// Redis 不可用时无法得知，视为不在其他房间
+    async fn get_player_current_room(&self, player_id: &str) -> Result<Option<String>> {
+        if self.is_degraded() {
+            return Ok(None);
         }
-    }
-
-    /// 清理玩家的房间信息（当玩家离开房间时调用）
-    pub async fn clear_player_room_info(&self, player_id: &str) -> Result<()> {
         let mut conn = self.manager.lock().await;
         let key = format!("player:{}", player_id);
-        conn.del::<_, ()>(&key).await?;
-        Ok(())
+        match conn.hget(&key, "room_id").await {
+            Ok(stored_room_id) => Ok(stored_room_id),
+            Err(e) if self.observe_error(&e) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
     }
 
     /// 保存玩家房间信息
-    pub async fn save_player_room_info(&self, player_id: &str, name: &str, room_id: &str) -> Result<()> {
-        let mut conn = self.manager.lock().await;
+    async fn save_player_room_info(&self, player_id: &str, name: &str, room_id: &str) -> Result<()> {
         let key = format!("player:{}", player_id);
-        conn.hset::<_, _, _, ()>(&key, "name", name).await?;
-        conn.hset::<_, _, _, ()>(&key, "room_id", room_id).await?;
-        conn.hset::<_, _, _, ()>(&key, "last_active", chrono::Utc::now().timestamp()).await?;
+        let mut pipe = redis::pipe();
+        pipe.hset(&key, "name", name).ignore();
+        pipe.hset(&key, "room_id", room_id).ignore();
+        pipe.hset(&key, "last_active", chrono::Utc::now().timestamp()).ignore();
+        self.write::<()>(pipe).await?;
         Ok(())
     }
 
-    pub async fn update_player_stats(&self, player_id: Uuid, stats: &PlayerStats) -> Result<()> {
-        let mut conn = self.manager.lock().await;
-        let key = format!("player_stats:{}", player_id);
-        let data = serde_json::to_string(stats)?;
-        conn.set::<_, _, ()>(&key, data).await?;
-        Ok(())
+    /// 仅当玩家记录的房间仍是指定房间时才清理，避免误删玩家在新房间的信息
+    async fn clear_player_room_info_if(&self, player_id: &str, room_id: &str) -> Result<bool> {
+        const SCRIPT: &str = r"if redis.call('HGET', KEYS[1], 'room_id') == ARGV[1] then
+                return redis.call('DEL', KEYS[1])
+            end
+            return 0";
+        let key = format!("player:{}", player_id);
+        // 以 EVAL 执行，降级期间缓存后也能原样补写
+        let mut pipe = redis::pipe();
+        pipe.cmd("EVAL").arg(SCRIPT).arg(1).arg(&key).arg(room_id);
+        let removed: Option<(i64,)> = self.write(pipe).await?;
+        Ok(removed.is_some_and(|(removed,)| removed > 0))
     }
 
-    pub async fn save_checkpoint(&self, room_id: Uuid, state: &GameState) -> Result<()> {
+    /// 尝试获取玩家房间归属的租约，成功时返回租约令牌
+    ///
+    /// 租约带有过期时间，持有者异常退出时也会自动释放。
+    /// Redis 不可用时无法跨房间协调，直接视为获取成功。
+    async fn try_acquire_player_lease(&self, player_id: &str, ttl_ms: u64) -> Result<Option<String>> {
+        let token = Uuid::new_v4().to_string();
+        if self.is_degraded() {
+            return Ok(Some(token));
+        }
         let mut conn = self.manager.lock().await;
-        let key = format!("checkpoint:{}", room_id);
-        let data = serde_json::to_string(state)?;
-        conn.set::<_, _, ()>(&key, data).await?;
-        Ok(())
+        let key = format!("player:{}:lease", player_id);
+        let acquired: Option<String> = match redis::cmd("SET")
+            .arg(&key)
+            .arg(&token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut *conn)
+            .await
+        {
+            Ok(acquired) => acquired,
+            Err(e) if self.observe_error(&e) => return Ok(Some(token)),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(acquired.map(|_| token))
     }
 
-    pub async fn load_checkpoint(&self, room_id: Uuid) -> Result<Option<GameState>> {
+    /// 释放玩家房间归属的租约，只有令牌匹配时才会删除
+    async fn release_player_lease(&self, player_id: &str, token: &str) -> Result<()> {
+        let script = redis::Script::new(
+            r"if redis.call('GET', KEYS[1]) == ARGV[1] then
+                return redis.call('DEL', KEYS[1])
+            end
+            return 0",
+        );
+        if self.is_degraded() {
+            return Ok(());
+        }
         let mut conn = self.manager.lock().await;
-        let key = format!("checkpoint:{}", room_id);
-        let data: Option<String> = conn.get(&key).await?;
-
-        match data {
-            Some(data) => {
-                let state = serde_json::from_str(&data)?;
-                Ok(Some(state))
-            }
-            None => Ok(None),
+        let key = format!("player:{}:lease", player_id);
+        match script.key(&key).arg(token).invoke_async::<i64>(&mut *conn).await {
+            Ok(_) => Ok(()),
+            // 租约会自然过期，Redis 不可用时无需补写
+            Err(e) if self.observe_error(&e) => Ok(()),
+            Err(e) => Err(e.into()),
         }
     }
 
-    /// 保存用户会话
-    pub async fn save_session(&self, session: &UserSession) -> Result<()> {
+    /// 保存用户会话，同时登记到用户的会话索引
+    async fn save_session(&self, session: &UserSession) -> Result<()> {
         let key = format!("session:{}", session.session_id);
         let session_json = serde_json::to_string(session)
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
-        
-        let mut conn = self.manager.lock().await;
+
         // 设置过期时间（秒）
         let ttl = (session.expires_at - Utc::now()).num_seconds().max(0) as u64;
-        conn.set_ex::<_, _, ()>(&key, &session_json, ttl)
+        let index_key = user_sessions_key(&session.user_id);
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set_ex(&key, &session_json, ttl).ignore()
+            .zadd(&index_key, session.session_id.to_string(), session.expires_at.timestamp()).ignore()
+            .expire(&index_key, USER_SESSIONS_INDEX_TTL_SECS).ignore();
+
+        let mut conn = self.manager.lock().await;
+        pipe.query_async::<()>(&mut *conn)
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
-        
+
         Ok(())
     }
 
     /// 获取用户会话
-    pub async fn get_session(&self, session_id: &Uuid) -> Result<Option<UserSession>> {
+    async fn get_session(&self, session_id: &Uuid) -> Result<Option<UserSession>> {
         let key = format!("session:{}", session_id);
         let mut conn = self.manager.lock().await;
         
@@ -350,20 +1983,68 @@ impl Storage {
         }
     }
 
-    /// 删除用户会话
-    pub async fn delete_session(&self, session_id: &Uuid) -> Result<()> {
+    /// 删除用户会话，同时从用户的会话索引中移除
+    async fn delete_session(&self, session_id: &Uuid) -> Result<()> {
+        let session = self.get_session(session_id).await?;
         let key = format!("session:{}", session_id);
+
+        let mut pipe = redis::pipe();
+        pipe.atomic().del(&key).ignore();
+        if let Some(session) = session {
+            pipe.zrem(user_sessions_key(&session.user_id), session_id.to_string())
+                .ignore();
+        }
+
         let mut conn = self.manager.lock().await;
-        
-        conn.del::<_, ()>(&key)
+        pipe.query_async::<()>(&mut *conn)
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
         
         Ok(())
     }
 
-    /// 更新会话过期时间
-    pub async fn extend_session(&self, session_id: &Uuid, new_expires_at: DateTime<Utc>) -> Result<()> {
+    /// 列出用户当前有效的会话，按创建时间排列，顺带清理索引中已过期或已删除的会话
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>> {
+        let index_key = user_sessions_key(user_id);
+        let mut conn = self.manager.lock().await;
+
+        conn.zrembyscore::<_, _, _, ()>(&index_key, "-inf", Utc::now().timestamp())
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        let session_ids: Vec<String> = conn
+            .zrange(&index_key, 0, -1)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        if session_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys: Vec<String> = session_ids.iter().map(|id| format!("session:{}", id)).collect();
+        let values: Vec<Option<String>> = conn
+            .mget(&keys)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        let mut sessions = Vec::new();
+        let mut stale = Vec::new();
+        for (session_id, value) in session_ids.into_iter().zip(values) {
+            match value.and_then(|json| serde_json::from_str::<UserSession>(&json).ok()) {
+                Some(session) => sessions.push(session),
+                None => stale.push(session_id),
+            }
+        }
+        if !stale.is_empty() {
+            conn.zrem::<_, _, ()>(&index_key, stale)
+                .await
+                .map_err(|e| crate::Error::Storage(e.to_string()))?;
+        }
+
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    /// 更新会话过期时间和最后使用时间
+    async fn extend_session(&self, session_id: &Uuid, new_expires_at: DateTime<Utc>) -> Result<()> {
         let key = format!("session:{}", session_id);
         let mut conn = self.manager.lock().await;
         
@@ -379,12 +2060,19 @@ impl Storage {
             
             // 更新过期时间
             session.expires_at = new_expires_at;
+            session.last_used_at = Utc::now();
             let updated_json = serde_json::to_string(&session)
                 .map_err(|e| crate::Error::Storage(e.to_string()))?;
             
             // 重新保存，设置新的过期时间
             let ttl = (new_expires_at - Utc::now()).num_seconds().max(0) as u64;
-            conn.set_ex::<_, _, ()>(&key, &updated_json, ttl)
+            let index_key = user_sessions_key(&session.user_id);
+            let mut pipe = redis::pipe();
+            pipe.atomic()
+                .set_ex(&key, &updated_json, ttl).ignore()
+                .zadd(&index_key, session_id.to_string(), new_expires_at.timestamp()).ignore()
+                .expire(&index_key, USER_SESSIONS_INDEX_TTL_SECS).ignore();
+            pipe.query_async::<()>(&mut *conn)
                 .await
                 .map_err(|e| crate::Error::Storage(e.to_string()))?;
         }
@@ -393,7 +2081,7 @@ impl Storage {
     }
 
     /// 保存用户信息
-    pub async fn save_user(&self, user: &User) -> Result<()> {
+    async fn save_user(&self, user: &User) -> Result<()> {
         let key = format!("user:{}", user.id);
         let user_json = serde_json::to_string(user)
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
@@ -407,8 +2095,22 @@ impl Storage {
         Ok(())
     }
 
+    /// 保存游客信息，与游客会话同时过期
+    async fn save_guest_user(&self, user: &User, ttl_secs: u64) -> Result<()> {
+        let key = format!("user:{}", user.id);
+        let user_json = serde_json::to_string(user)
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        let mut conn = self.manager.lock().await;
+        conn.set_ex::<_, _, ()>(&key, &user_json, ttl_secs)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        Ok(())
+    }
+
     /// 获取用户信息
-    pub async fn get_user(&self, user_id: &str) -> Result<Option<User>> {
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>> {
         let key = format!("user:{}", user_id);
         let mut conn = self.manager.lock().await;
         
@@ -427,153 +2129,299 @@ impl Storage {
         }
     }
 
-    /// 删除用户信息
-    pub async fn delete_user(&self, user_id: &str) -> Result<()> {
-        let key = format!("user:{}", user_id);
+    /// 保存管理员令牌，到期后自动删除
+    async fn save_admin_token(&self, token: &AdminToken) -> Result<()> {
+        let key = format!("admin_token:{}", token.token);
+        let token_json = serde_json::to_string(token)
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
         let mut conn = self.manager.lock().await;
-        
-        conn.del::<_, ()>(&key)
+        let ttl = (token.expires_at - Utc::now()).num_seconds().max(1) as u64;
+        conn.set_ex::<_, _, ()>(&key, &token_json, ttl)
             .await
             .map_err(|e| crate::Error::Storage(e.to_string()))?;
-        
+
         Ok(())
     }
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct GameResult {
-    pub room_id: String,
-    pub winner: Role,
-    pub players: Vec<Player>,
-    pub timestamp: DateTime<Utc>,
-}
+    /// 获取管理员令牌
+    async fn get_admin_token(&self, token: &str) -> Result<Option<AdminToken>> {
+        let key = format!("admin_token:{}", token);
+        let mut conn = self.manager.lock().await;
 
-#[derive(Debug, Serialize, Deserialize, Default)]
-pub struct PlayerStats {
-    pub games_played: u32,
-    pub games_won: u32,
-    pub games_as_undercover: u32,
-    pub games_won_as_undercover: u32,
-    pub games_as_civilian: u32,
-    pub games_won_as_civilian: u32,
-    pub total_votes_received: u32,
-    pub total_votes_cast: u32,
-    pub correct_votes: u32,
-    pub last_played: Option<DateTime<Utc>>,
-}
+        let token_json: Option<String> = conn
+            .get(&key)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Checkpoint {
-    pub state: GameState,
-    pub version: u64,
-    pub timestamp: DateTime<Utc>,
-    pub is_consistent: bool,
-}
+        match token_json {
+            Some(json) => {
+                let token: AdminToken = serde_json::from_str(&json)
+                    .map_err(|e| crate::Error::Storage(e.to_string()))?;
+                Ok(Some(token))
+            }
+            None => Ok(None),
+        }
+    }
 
-impl Storage {
-    pub async fn create_checkpoint(
+    async fn delete_admin_token(&self, token: &str) -> Result<()> {
+        let key = format!("admin_token:{}", token);
+        let mut conn = self.manager.lock().await;
+
+        conn.del::<_, ()>(&key)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        Ok(())
+    }
+
+    /// 追加一条管理操作审计记录到 Redis Stream，近似保留最近 max_len 条
+    async fn append_audit_record(&self, record: &AuditRecord, max_len: usize) -> Result<()> {
+        let data = serde_json::to_string(record)?;
+        let mut pipe = redis::pipe();
+        pipe.xadd_maxlen(
+            ADMIN_AUDIT_KEY,
+            redis::streams::StreamMaxlen::Approx(max_len),
+            "*",
+            &[("data", data)],
+        )
+        .ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
+
+    /// 从新到旧分页读取审计记录，before 为上一页最后一条记录的ID（不含）
+    async fn get_audit_records(
         &self,
-        room_id: Uuid,
-        state: &GameState,
-        version: u64,
-    ) -> Result<()> {
-        let checkpoint = Checkpoint {
-            state: state.clone(),
-            version,
-            timestamp: Utc::now(),
-            is_consistent: true,
+        before: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<(String, AuditRecord)>> {
+        let end = match before {
+            Some(id) => format!("({}", id),
+            None => "+".to_string(),
         };
+        let mut conn = self.manager.lock().await;
+        let reply: redis::streams::StreamRangeReply =
+            conn.xrevrange_count(ADMIN_AUDIT_KEY, end, "-", limit).await?;
+
+        Ok(reply
+            .ids
+            .into_iter()
+            .filter_map(|entry| {
+                let data: String = entry.get("data")?;
+                let record = serde_json::from_str(&data).ok()?;
+                Some((entry.id, record))
+            })
+            .collect())
+    }
 
+    /// 获取玩家等级分，没有记录时返回初始分
+    async fn get_rating(&self, player_id: &str) -> Result<PlayerRating> {
+        let key = format!("player_rating:{}", player_id);
         let mut conn = self.manager.lock().await;
-        let key = format!("checkpoint:{}", room_id);
-        let data = serde_json::to_string(&checkpoint)?;
 
-        // 使用事务确保原子性
+        let rating_json: Option<String> = conn
+            .get(&key)
+            .await
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
+        match rating_json {
+            Some(json) => serde_json::from_str(&json)
+                .map_err(|e| crate::Error::Storage(e.to_string()).into()),
+            None => Ok(PlayerRating::new(player_id)),
+        }
+    }
+
+    /// 保存玩家等级分（长期保存，不设置过期时间）
+    async fn save_rating(&self, rating: &PlayerRating) -> Result<()> {
+        let key = format!("player_rating:{}", rating.player_id);
+        let rating_json = serde_json::to_string(rating)
+            .map_err(|e| crate::Error::Storage(e.to_string()))?;
+
         let mut pipe = redis::pipe();
-        pipe.atomic().set(&key, &data).expire(&key, 24 * 60 * 60); // 24小时过期
+        pipe.set(&key, &rating_json).ignore();
+        // 同步等级分排行榜
+        pipe.zadd(
+            LeaderboardMetric::Rating.key(LeaderboardPeriod::All, Utc::now()),
+            &rating.player_id,
+            rating.rating,
+        )
+        .ignore();
+        self.write::<()>(pipe).await?;
 
-        let _: () = pipe.query_async(&mut *conn).await?;
+        Ok(())
+    }
 
+    /// 累加玩家当天的对局数和游戏时长
+    async fn record_playtime(&self, player_id: &str, seconds: u64) -> Result<()> {
+        let key = Self::playtime_key(player_id);
+        let mut pipe = redis::pipe();
+        pipe.hincr(&key, "games", 1).ignore();
+        pipe.hincr(&key, "seconds", seconds).ignore();
+        pipe.expire(&key, PLAYTIME_TTL_SECS).ignore();
+        self.write::<()>(pipe).await?;
         Ok(())
     }
 
-    pub async fn load_latest_checkpoint(&self, room_id: Uuid) -> Result<Option<Checkpoint>> {
+    /// 读取玩家当天的对局数和游戏时长
+    async fn get_daily_playtime(&self, player_id: &str) -> Result<DailyPlaytime> {
         let mut conn = self.manager.lock().await;
-        let key = format!("checkpoint:{}", room_id);
-        let data: Option<String> = conn.get(&key).await?;
+        let (games, seconds): (Option<u32>, Option<u64>) = redis::cmd("HMGET")
+            .arg(Self::playtime_key(player_id))
+            .arg("games")
+            .arg("seconds")
+            .query_async(&mut *conn)
+            .await?;
+        Ok(DailyPlaytime {
+            games: games.unwrap_or(0),
+            seconds: seconds.unwrap_or(0),
+        })
+    }
 
+    /// 读取玩家的每日游戏上限
+    async fn get_play_limits(&self, player_id: &str) -> Result<PlayLimits> {
+        let mut conn = self.manager.lock().await;
+        let data: Option<String> = conn.get(format!("play_limits:{}", player_id)).await?;
         match data {
-            Some(data) => {
-                let checkpoint = serde_json::from_str(&data)?;
-                Ok(Some(checkpoint))
-            }
-            None => Ok(None),
+            Some(data) => Ok(serde_json::from_str(&data)?),
+            None => Ok(PlayLimits::default()),
         }
     }
 
-    pub async fn verify_state_consistency(&self, room_id: Uuid, state: &GameState) -> Result<bool> {
-        let mut conn = self.manager.lock().await;
-        let key = format!("state_verification:{}", room_id);
+    /// 保存玩家的每日游戏上限
+    async fn set_play_limits(&self, player_id: &str, limits: &PlayLimits) -> Result<()> {
+        let data = serde_json::to_string(limits)?;
+        let mut pipe = redis::pipe();
+        pipe.set(format!("play_limits:{}", player_id), data).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
 
-        // 计算状态哈希
-        let state_hash = self.calculate_state_hash(state).await?;
+    /// 保存全服禁言，过期时间与禁言到期时间一致
+    async fn set_global_mute(&self, mute: &GlobalMute) -> Result<()> {
+        let data = serde_json::to_string(mute)?;
+        let ttl = (mute.expires_at - Utc::now()).num_seconds().max(1) as u64;
+        let mut pipe = redis::pipe();
+        pipe.set_ex(Self::global_mute_key(&mute.user_id), data, ttl).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
 
-        // 获取之前的状态哈希
-        let prev_hash: Option<String> = conn.get(&key).await?;
+    /// 读取用户当前生效的全服禁言
+    async fn get_global_mute(&self, user_id: &str) -> Result<Option<GlobalMute>> {
+        let mut conn = self.manager.lock().await;
+        let data: Option<String> = conn.get(Self::global_mute_key(user_id)).await?;
+        let mute = data
+            .map(|data| serde_json::from_str::<GlobalMute>(&data))
+            .transpose()?;
+        Ok(mute.filter(|mute| mute.expires_at > Utc::now()))
+    }
 
-        // 更新状态哈希
-        conn.set::<_, _, ()>(&key, &state_hash).await?;
+    /// 解除全服禁言
+    async fn clear_global_mute(&self, user_id: &str) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.del(Self::global_mute_key(user_id)).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
 
-        // 验证一致性
-        Ok(prev_hash.map_or(true, |h| h == state_hash))
+    /// 缓存用户头像的内容类型和图片数据
+    async fn save_avatar(&self, user_id: &str, content_type: &str, data: &[u8], ttl_secs: u64) -> Result<()> {
+        let key = Self::avatar_key(user_id);
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hset(&key, "content_type", content_type)
+            .ignore()
+            .hset(&key, "data", data)
+            .ignore()
+            .expire(&key, ttl_secs.max(1) as i64)
+            .ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
     }
 
-    async fn calculate_state_hash(&self, state: &GameState) -> Result<String> {
-        let state_json = serde_json::to_string(state)?;
-        let hash = Sha256::digest(state_json.as_bytes());
-        Ok(hex::encode(hash))
+    /// 读取缓存的用户头像，返回内容类型和图片数据
+    async fn get_avatar(&self, user_id: &str) -> Result<Option<(String, Vec<u8>)>> {
+        let mut conn = self.manager.lock().await;
+        let (content_type, data): (Option<String>, Option<Vec<u8>>) = redis::pipe()
+            .hget(Self::avatar_key(user_id), "content_type")
+            .hget(Self::avatar_key(user_id), "data")
+            .query_async(&mut *conn)
+            .await?;
+        Ok(content_type.zip(data))
     }
 
-    pub async fn recover_from_crash(&self, room_id: Uuid) -> Result<Option<GameState>> {
-        // 1. 尝试加载最新的检查点
-        if let Some(checkpoint) = self.load_latest_checkpoint(room_id).await? {
-            // 2. 验证检查点的一致性
-            if checkpoint.is_consistent {
-                return Ok(Some(checkpoint.state));
-            }
-        }
+    /// 删除缓存的用户头像，头像地址变化后重新下载
+    async fn delete_avatar(&self, user_id: &str) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.del(Self::avatar_key(user_id)).ignore();
+        self.write::<()>(pipe).await?;
+        Ok(())
+    }
 
-        // 3. 如果没有有效的检查点，尝试从游戏历史恢复
+    /// 尝试持有或续约主实例租约，成功时表示当前实例为主实例
+    async fn try_hold_active_lease(&self, instance_id: &str, ttl_secs: u64) -> Result<bool> {
+        let script = redis::Script::new(
+            r"if redis.call('GET', KEYS[1]) == ARGV[1] then
+                redis.call('EXPIRE', KEYS[1], ARGV[2])
+                return 1
+            end
+            if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'EX', ARGV[2]) then
+                return 1
+            end
+            return 0",
+        );
         let mut conn = self.manager.lock().await;
-        let history_key = format!("game_history:{}", room_id);
-        let latest_state: Option<String> = conn.lindex(&history_key, 0).await?;
-
-        match latest_state {
-            Some(state_json) => {
-                let state = serde_json::from_str(&state_json)?;
-                Ok(Some(state))
+        match script
+            .key("cluster:active")
+            .arg(instance_id)
+            .arg(ttl_secs.max(1))
+            .invoke_async::<i64>(&mut *conn)
+            .await
+        {
+            Ok(held) => Ok(held > 0),
+            Err(e) => {
+                self.observe_error(&e);
+                Err(e.into())
             }
-            None => Ok(None),
         }
     }
 
-    pub async fn cleanup_old_data(&self) -> Result<()> {
-        let mut conn = self.manager.lock().await;
-
-        // 清理过期的检查点
-        let pattern = "checkpoint:*";
-        let keys: Vec<String> = conn.keys(pattern).await?;
-        for key in keys {
-            let _: i32 = conn.del(&key).await?;
+    /// 保存房间检查点，Redis 不可用时跳过，下一轮会重新写入最新状态
+    async fn save_room_checkpoint(&self, room_id: &str, checkpoint: &RoomCheckpoint) -> Result<()> {
+        if self.is_degraded() {
+            return Ok(());
         }
-
-        // 清理过期的游戏历史
-        let pattern = "game_history:*";
-        let keys: Vec<String> = conn.keys(pattern).await?;
-        for key in keys {
-            let _: bool = conn.ltrim(&key, 0, 999).await?; // 只保留最近1000条记录
+        let data = serde_json::to_string(checkpoint)?;
+        let mut conn = self.manager.lock().await;
+        match conn.hset::<_, _, _, ()>("cluster:rooms", room_id, data).await {
+            Ok(()) => Ok(()),
+            Err(e) if self.observe_error(&e) => Ok(()),
+            Err(e) => Err(e.into()),
         }
+    }
 
+    /// 删除房间检查点
+    async fn delete_room_checkpoint(&self, room_id: &str) -> Result<()> {
+        let mut pipe = redis::pipe();
+        pipe.hdel("cluster:rooms", room_id).ignore();
+        self.write::<()>(pipe).await?;
         Ok(())
     }
+
+    /// 加载所有房间检查点，无法解析的检查点会被跳过
+    async fn load_room_checkpoints(&self) -> Result<Vec<RoomCheckpoint>> {
+        let mut conn = self.manager.lock().await;
+        let entries: std::collections::HashMap<String, String> = conn.hgetall("cluster:rooms").await?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(room_id, data)| match serde_json::from_str(&data) {
+                Ok(checkpoint) => Some(checkpoint),
+                Err(e) => {
+                    warn!("房间 {} 的检查点无法解析: {}", room_id, e);
+                    None
+                }
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,225 @@
+//! 存储后端的公共测试：同一组用例分别在内存和 SQLite 后端上运行
+
+use super::{GameResult, GlobalMute, LeaderboardMetric, LeaderboardPeriod, MemoryStorage, PlayerRating, SqliteStorage, StorageBackend};
+use crate::game::{GameClockStats, Player, Role};
+use crate::user::UserSession;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use uuid::Uuid;
+
+fn player(id: &str, role: Role) -> Player {
+    Player {
+        id: id.to_string(),
+        name: format!("玩家{}", id),
+        role: Some(role),
+        word: Some("苹果".to_string()),
+        is_alive: true,
+        last_action: Utc::now(),
+    }
+}
+
+/// 三人对局：a、c 是平民，b 是卧底
+pub(super) fn result(room_id: &str, winner: Role, timestamp: DateTime<Utc>) -> GameResult {
+    GameResult {
+        room_id: room_id.to_string(),
+        winner,
+        players: vec![player("a", Role::Civilian), player("b", Role::Undercover), player("c", Role::Civilian)],
+        timestamp,
+        conceded_by: None,
+        clock: GameClockStats {
+            total_ms: 60_000,
+            ..Default::default()
+        },
+        actions: Vec::new(),
+        game_id: Some(Uuid::new_v4().to_string()),
+    }
+}
+
+pub(super) fn ids(ids: &[&str]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn session(user_id: &str, expires_in_secs: i64) -> UserSession {
+    let now = Utc::now();
+    UserSession {
+        session_id: Uuid::new_v4(),
+        user_id: user_id.to_string(),
+        created_at: now,
+        expires_at: now + chrono::Duration::seconds(expires_in_secs),
+        last_used_at: now,
+    }
+}
+
+/// 为每个用例分别生成内存后端和 SQLite 后端的测试
+macro_rules! backend_suite {
+    ($($case:ident),* $(,)?) => {
+        mod memory_backend {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    super::$case(&super::MemoryStorage::new()).await;
+                }
+            )*
+        }
+
+        mod sqlite_backend {
+            $(
+                #[tokio::test]
+                async fn $case() {
+                    super::$case(&super::SqliteStorage::in_memory().await).await;
+                }
+            )*
+        }
+    };
+}
+
+backend_suite!(
+    save_game_result_records_history_and_stats,
+    leaderboards_rank_wins_and_ratings,
+    sessions_expire_and_extend,
+    player_lease_is_exclusive_until_released,
+    expired_player_lease_can_be_taken_over,
+    global_mute_expires_and_clears,
+);
+
+async fn save_game_result_records_history_and_stats(storage: &dyn StorageBackend) {
+    let now = Utc::now();
+    storage
+        .save_game_result(&result("room1", Role::Civilian, now), &ids(&["a", "b"]), &ids(&["a", "b", "c"]))
+        .await
+        .unwrap();
+    storage
+        .save_game_result(&result("room1", Role::Undercover, now), &ids(&["a", "b"]), &ids(&["a", "b"]))
+        .await
+        .unwrap();
+
+    assert_eq!(storage.get_room_results("room1").await.unwrap().len(), 2);
+    assert!(storage.get_room_results("room2").await.unwrap().is_empty());
+
+    let games = storage.get_player_games("a", 0, 10).await.unwrap();
+    assert_eq!(games.len(), 2);
+    assert!(!games[0].won);
+    assert!(games[1].won);
+    assert_eq!(games[1].player_count, 3);
+    assert_eq!(storage.get_player_games("c", 0, 10).await.unwrap().len(), 1);
+
+    let today = storage.get_today_stats().await.unwrap();
+    assert_eq!(today.games, 2);
+    assert_eq!((today.civilian_wins, today.undercover_wins), (1, 1));
+    assert_eq!(today.games_per_hour.iter().sum::<u32>(), 2);
+    assert_eq!(today.average_duration_ms, 60_000);
+    assert_eq!(today.active_users, 3);
+
+    let summary = storage.get_stats_summary(LeaderboardPeriod::All, 1).await.unwrap();
+    assert_eq!(summary[0].games, 2);
+    assert_eq!(summary[0].unique_players, 3);
+    assert_eq!(summary[0].civilian_win_rate, 0.5);
+}
+
+async fn leaderboards_rank_wins_and_ratings(storage: &dyn StorageBackend) {
+    let now = Utc::now();
+    for winner in [Role::Civilian, Role::Civilian, Role::Undercover] {
+        // c 不在榜单玩家中，胜场不计入
+        storage
+            .save_game_result(&result("room1", winner, now), &ids(&["a", "b"]), &ids(&["a", "b", "c"]))
+            .await
+            .unwrap();
+    }
+
+    let (total, entries) = storage
+        .get_leaderboard(LeaderboardMetric::Wins, LeaderboardPeriod::Daily, 0, 10)
+        .await
+        .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!((entries[0].player_id.as_str(), entries[0].player_name.as_str()), ("a", "玩家a"));
+    assert_eq!((entries[0].rank, entries[0].score), (1, 2));
+    assert_eq!((entries[1].player_id.as_str(), entries[1].rank, entries[1].score), ("b", 2, 1));
+
+    let (total, entries) = storage
+        .get_leaderboard(LeaderboardMetric::Wins, LeaderboardPeriod::All, 1, 10)
+        .await
+        .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(entries.len(), 1);
+    assert_eq!((entries[0].player_id.as_str(), entries[0].rank), ("b", 2));
+
+    for (player_id, rating) in [("a", 1100), ("b", 1200)] {
+        storage
+            .save_rating(&PlayerRating {
+                rating,
+                ..PlayerRating::new(player_id)
+            })
+            .await
+            .unwrap();
+    }
+    let (total, entries) = storage
+        .get_leaderboard(LeaderboardMetric::Rating, LeaderboardPeriod::All, 0, 10)
+        .await
+        .unwrap();
+    assert_eq!(total, 2);
+    assert_eq!((entries[0].player_id.as_str(), entries[0].score), ("b", 1200));
+    assert!(storage
+        .get_leaderboard(LeaderboardMetric::Rating, LeaderboardPeriod::Daily, 0, 10)
+        .await
+        .is_err());
+}
+
+async fn sessions_expire_and_extend(storage: &dyn StorageBackend) {
+    let active = session("u1", 3600);
+    let expired = session("u1", -1);
+    storage.save_session(&active).await.unwrap();
+    storage.save_session(&expired).await.unwrap();
+    storage.save_session(&session("u2", 3600)).await.unwrap();
+
+    assert!(storage.get_session(&active.session_id).await.unwrap().is_some());
+    assert!(storage.get_session(&expired.session_id).await.unwrap().is_none());
+    let listed = storage.list_user_sessions("u1").await.unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].session_id, active.session_id);
+
+    let new_expires_at = Utc::now() + chrono::Duration::days(7);
+    storage.extend_session(&active.session_id, new_expires_at).await.unwrap();
+    let extended = storage.get_session(&active.session_id).await.unwrap().unwrap();
+    assert_eq!(extended.expires_at, new_expires_at);
+
+    storage.delete_session(&active.session_id).await.unwrap();
+    assert!(storage.get_session(&active.session_id).await.unwrap().is_none());
+}
+
+async fn player_lease_is_exclusive_until_released(storage: &dyn StorageBackend) {
+    let token = storage.try_acquire_player_lease("p1", 10_000).await.unwrap().unwrap();
+    assert!(storage.try_acquire_player_lease("p1", 10_000).await.unwrap().is_none());
+    assert!(storage.try_acquire_player_lease("p2", 10_000).await.unwrap().is_some());
+
+    // 令牌不匹配时不释放
+    storage.release_player_lease("p1", "other").await.unwrap();
+    assert!(storage.try_acquire_player_lease("p1", 10_000).await.unwrap().is_none());
+
+    storage.release_player_lease("p1", &token).await.unwrap();
+    assert!(storage.try_acquire_player_lease("p1", 10_000).await.unwrap().is_some());
+}
+
+async fn expired_player_lease_can_be_taken_over(storage: &dyn StorageBackend) {
+    storage.try_acquire_player_lease("p1", 1).await.unwrap().unwrap();
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    assert!(storage.try_acquire_player_lease("p1", 10_000).await.unwrap().is_some());
+}
+
+async fn global_mute_expires_and_clears(storage: &dyn StorageBackend) {
+    let now = Utc::now();
+    let mute = |user_id: &str, expires_in_secs: i64| GlobalMute {
+        user_id: user_id.to_string(),
+        admin: "admin".to_string(),
+        reason: "刷屏".to_string(),
+        muted_at: now,
+        expires_at: now + chrono::Duration::seconds(expires_in_secs),
+    };
+    storage.set_global_mute(&mute("u1", 3600)).await.unwrap();
+    storage.set_global_mute(&mute("u2", -1)).await.unwrap();
+
+    assert_eq!(storage.get_global_mute("u1").await.unwrap().unwrap().reason, "刷屏");
+    assert!(storage.get_global_mute("u2").await.unwrap().is_none());
+
+    storage.clear_global_mute("u1").await.unwrap();
+    assert!(storage.get_global_mute("u1").await.unwrap().is_none());
+}
@@ -0,0 +1,745 @@
+use super::{
+    CHAT_LOG_MAX_LEN, CHAT_LOG_TTL_SECS, ChatChannel, DailyPlaytime, DailyStats, GAME_EVENT_LOG_MAX_LEN,
+    GAME_EVENT_LOG_TTL_SECS, GameRecord, GameResult, GlobalMute, LeaderboardEntry, LeaderboardMetric,
+    LeaderboardPeriod, PLAYER_GAMES_MAX_LEN, PLAYTIME_TTL_SECS, ROOM_RESULTS_MAX_LEN, PlayLimits, PlayerGameEntry, PlayerRating,
+    PlayerStats, ProfileSettings, REPLAY_TTL_SECS, Replay, ReplayEntry, StateLogEntry, StatsSummary,
+    StorageBackend, StorageHealthSnapshot, stats_counters_key, stats_period_label,
+};
+use crate::admin::{AdminToken, AuditRecord};
+use crate::game::{ChatMessage, Role, StateRecord};
+use crate::room::RoomCheckpoint;
+use crate::user::{User, UserSession};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use dashmap::DashMap;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 房间归档对局结果的保存时间（秒），与 Redis 实现一致
+const ROOM_RESULTS_TTL_SECS: i64 = 86400;
+
+/// 带过期时间的值，过期后读取时视为不存在，由清理任务定期删除
+struct Expiring<T> {
+    value: T,
+    expires_at: Option<Instant>,
+}
+
+impl<T> Expiring<T> {
+    /// ttl_secs 为 None 时永不过期
+    fn new(value: T, ttl_secs: Option<i64>) -> Self {
+        Expiring {
+            value,
+            expires_at: ttl_secs.map(expires_in),
+        }
+    }
+
+    fn is_alive(&self) -> bool {
+        self.expires_at.is_none_or(|at| at > Instant::now())
+    }
+}
+
+fn expires_in(ttl_secs: i64) -> Instant {
+    Instant::now() + Duration::from_secs(ttl_secs.max(1) as u64)
+}
+
+/// 读取未过期的值
+fn live<T: Clone>(map: &DashMap<String, Expiring<T>>, key: &str) -> Option<T> {
+    map.get(key)
+        .filter(|entry| entry.is_alive())
+        .map(|entry| entry.value.clone())
+}
+
+/// 追加到有长度上限的列表末尾，并刷新过期时间
+fn append_capped<T>(map: &DashMap<String, Expiring<VecDeque<T>>>, key: String, value: T, max_len: isize, ttl_secs: i64) {
+    let mut entry = map
+        .entry(key)
+        .or_insert_with(|| Expiring::new(VecDeque::new(), Some(ttl_secs)));
+    if !entry.is_alive() {
+        entry.value.clear();
+    }
+    entry.value.push_back(value);
+    while entry.value.len() > max_len as usize {
+        entry.value.pop_front();
+    }
+    entry.expires_at = Some(expires_in(ttl_secs));
+}
+
+/// 一个统计周期内的对局计数与参与玩家
+#[derive(Default)]
+struct PeriodCounters {
+    games: u64,
+    civilian_wins: u64,
+    undercover_wins: u64,
+    players: HashSet<String>,
+}
+
+/// 纯内存存储后端：所有数据保存在进程内，重启后丢失，不能在多个实例间共享
+///
+/// 用于测试和单机演示部署，过期规则与 Redis 实现保持一致
+pub struct MemoryStorage {
+    state_logs: DashMap<String, Expiring<VecDeque<StateLogEntry>>>,
+    chat_logs: DashMap<String, Expiring<VecDeque<ChatMessage>>>,
+    room_results: DashMap<String, Expiring<Vec<GameResult>>>,
+    player_games: DashMap<String, VecDeque<PlayerGameEntry>>,
+    /// 按日期保存的精简对局记录，超过统计保留天数后删除
+    game_records: Mutex<BTreeMap<NaiveDate, Vec<GameRecord>>>,
+    /// 按日、周和全部时间累加的对局计数，键与 Redis 实现相同
+    stats_counters: DashMap<String, Expiring<PeriodCounters>>,
+    /// 对局ID到所在房间和回放事件
+    replays: DashMap<String, Expiring<(String, Vec<ReplayEntry>)>>,
+    /// 榜单键到玩家分数，键与 Redis 实现相同
+    leaderboards: DashMap<String, Expiring<HashMap<String, i64>>>,
+    leaderboard_names: DashMap<String, String>,
+    player_stats: DashMap<String, PlayerStats>,
+    profile_settings: DashMap<String, ProfileSettings>,
+    player_rooms: DashMap<String, String>,
+    player_leases: DashMap<String, Expiring<String>>,
+    sessions: DashMap<Uuid, UserSession>,
+    users: DashMap<String, Expiring<User>>,
+    admin_tokens: DashMap<String, AdminToken>,
+    audit_log: Mutex<VecDeque<(u64, AuditRecord)>>,
+    next_audit_id: AtomicU64,
+    ratings: DashMap<String, PlayerRating>,
+    /// 玩家ID和日期到当天的游戏时长
+    playtime: DashMap<String, Expiring<DailyPlaytime>>,
+    play_limits: DashMap<String, PlayLimits>,
+    global_mutes: DashMap<String, GlobalMute>,
+    avatars: DashMap<String, Expiring<(String, Vec<u8>)>>,
+    active_lease: Mutex<Option<(String, Instant)>>,
+    room_checkpoints: DashMap<String, RoomCheckpoint>,
+    /// 启动日期，之前的日期没有每日统计
+    started_on: NaiveDate,
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        MemoryStorage {
+            state_logs: DashMap::new(),
+            chat_logs: DashMap::new(),
+            room_results: DashMap::new(),
+            player_games: DashMap::new(),
+            game_records: Mutex::new(BTreeMap::new()),
+            stats_counters: DashMap::new(),
+            replays: DashMap::new(),
+            leaderboards: DashMap::new(),
+            leaderboard_names: DashMap::new(),
+            player_stats: DashMap::new(),
+            profile_settings: DashMap::new(),
+            player_rooms: DashMap::new(),
+            player_leases: DashMap::new(),
+            sessions: DashMap::new(),
+            users: DashMap::new(),
+            admin_tokens: DashMap::new(),
+            audit_log: Mutex::new(VecDeque::new()),
+            next_audit_id: AtomicU64::new(1),
+            ratings: DashMap::new(),
+            playtime: DashMap::new(),
+            play_limits: DashMap::new(),
+            global_mutes: DashMap::new(),
+            avatars: DashMap::new(),
+            active_lease: Mutex::new(None),
+            room_checkpoints: DashMap::new(),
+            started_on: Utc::now().date_naive(),
+        }
+    }
+
+    /// 删除所有已过期的数据
+    pub fn purge_expired(&self) {
+        let now = Utc::now();
+        self.state_logs.retain(|_, entry| entry.is_alive());
+        self.chat_logs.retain(|_, entry| entry.is_alive());
+        self.room_results.retain(|_, entry| entry.is_alive());
+        self.stats_counters.retain(|_, entry| entry.is_alive());
+        self.replays.retain(|_, entry| entry.is_alive());
+        self.leaderboards.retain(|_, entry| entry.is_alive());
+        self.player_leases.retain(|_, entry| entry.is_alive());
+        self.sessions.retain(|_, session| session.expires_at > now);
+        self.users.retain(|_, entry| entry.is_alive());
+        self.admin_tokens.retain(|_, token| !token.is_expired());
+        self.playtime.retain(|_, entry| entry.is_alive());
+        self.global_mutes.retain(|_, mute| mute.expires_at > now);
+        self.avatars.retain(|_, entry| entry.is_alive());
+    }
+
+    /// 启动后台任务，定期删除过期数据
+    pub fn spawn_sweeper(self: &Arc<Self>, interval: Duration) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                storage.purge_expired();
+            }
+        });
+    }
+
+    fn playtime_key(player_id: &str) -> String {
+        format!("{}:{}", player_id, Utc::now().date_naive())
+    }
+
+    fn records_of(&self, date: NaiveDate) -> DailyStats {
+        let records = self.game_records.lock().unwrap_or_else(|e| e.into_inner());
+        DailyStats::from_records(date, records.get(&date).map_or(&[][..], Vec::as_slice))
+    }
+}
+
+#[axum::async_trait]
+impl StorageBackend for MemoryStorage {
+    fn is_degraded(&self) -> bool {
+        false
+    }
+
+    fn health(&self) -> StorageHealthSnapshot {
+        StorageHealthSnapshot {
+            degraded: false,
+            degraded_since: None,
+            pending_writes: 0,
+            dropped_writes: 0,
+        }
+    }
+
+    async fn append_state_record(&self, room_id: &str, record: &StateRecord) -> Result<()> {
+        let entry = StateLogEntry {
+            timestamp: Utc::now(),
+            record: record.clone(),
+        };
+        if matches!(record, StateRecord::Snapshot(_)) {
+            self.state_logs.remove(room_id);
+        }
+        append_capped(&self.state_logs, room_id.to_string(), entry, GAME_EVENT_LOG_MAX_LEN, GAME_EVENT_LOG_TTL_SECS);
+        Ok(())
+    }
+
+    async fn delete_room_state(&self, room_id: &str) -> Result<()> {
+        self.state_logs.remove(room_id);
+        Ok(())
+    }
+
+    async fn load_state_log(&self, room_id: &str) -> Result<Vec<StateLogEntry>> {
+        Ok(live(&self.state_logs, room_id).map(Vec::from).unwrap_or_default())
+    }
+
+    async fn list_saved_room_ids(&self) -> Result<Vec<String>> {
+        Ok(self
+            .state_logs
+            .iter()
+            .filter(|entry| entry.is_alive())
+            .map(|entry| entry.key().clone())
+            .collect())
+    }
+
+    async fn append_chat_message(&self, room_id: &str, channel: ChatChannel, message: &ChatMessage) -> Result<()> {
+        append_capped(&self.chat_logs, channel.key(room_id), message.clone(), CHAT_LOG_MAX_LEN, CHAT_LOG_TTL_SECS);
+        Ok(())
+    }
+
+    async fn clear_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<()> {
+        self.chat_logs.remove(&channel.key(room_id));
+        Ok(())
+    }
+
+    async fn load_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<Vec<ChatMessage>> {
+        Ok(live(&self.chat_logs, &channel.key(room_id))
+            .map(Vec::from)
+            .unwrap_or_default())
+    }
+
+    async fn save_game_result(
+        &self,
+        result: &GameResult,
+        leaderboard_players: &[String],
+        history_players: &[String],
+    ) -> Result<()> {
+        {
+            let mut results = self
+                .room_results
+                .entry(result.room_id.clone())
+                .or_insert_with(|| Expiring::new(Vec::new(), Some(ROOM_RESULTS_TTL_SECS)));
+            if !results.is_alive() {
+                results.value.clear();
+            }
+            results.value.push(result.clone());
+            let excess = results.value.len().saturating_sub(ROOM_RESULTS_MAX_LEN as usize);
+            results.value.drain(..excess);
+            results.expires_at = Some(expires_in(ROOM_RESULTS_TTL_SECS));
+        }
+
+        // 胜场排行榜只保留当前周期，读取时也只读当前周期
+        let now = result.timestamp;
+        for player in result.players.iter().filter(|p| leaderboard_players.contains(&p.id)) {
+            self.leaderboard_names.insert(player.id.clone(), player.name.clone());
+
+            if !player.role.is_some_and(|role| role.wins_with(result.winner)) {
+                continue;
+            }
+            for period in [LeaderboardPeriod::Daily, LeaderboardPeriod::Weekly, LeaderboardPeriod::All] {
+                let mut board = self
+                    .leaderboards
+                    .entry(LeaderboardMetric::Wins.key(period, now))
+                    .or_insert_with(|| Expiring::new(HashMap::new(), period.ttl_secs()));
+                *board.value.entry(player.id.clone()).or_insert(0) += 1;
+            }
+        }
+
+        for player_id in history_players {
+            let Some(entry) = PlayerGameEntry::from_result(player_id, result) else {
+                continue;
+            };
+            let mut games = self.player_games.entry(player_id.clone()).or_default();
+            games.push_front(entry);
+            games.truncate(PLAYER_GAMES_MAX_LEN as usize);
+        }
+
+        // 未加载配置时（测试）使用默认保留天数
+        let retention_days = crate::config::Config::try_get()
+            .map_or_else(|| crate::config::StatsConfig::default().retention_days, |config| config.stats.retention_days)
+            .max(1) as i64;
+        {
+            let mut records = self.game_records.lock().unwrap_or_else(|e| e.into_inner());
+            records
+                .entry(result.timestamp.date_naive())
+                .or_default()
+                .push(GameRecord {
+                    timestamp: result.timestamp,
+                    winner: result.winner,
+                    duration_ms: result.clock.total_ms,
+                    players: history_players.to_vec(),
+                });
+            let cutoff = Utc::now().date_naive() - chrono::Duration::days(retention_days);
+            records.retain(|date, _| *date >= cutoff);
+        }
+
+        for period in [LeaderboardPeriod::Daily, LeaderboardPeriod::Weekly, LeaderboardPeriod::All] {
+            let ttl = match period {
+                LeaderboardPeriod::Daily => Some(retention_days * 86400),
+                LeaderboardPeriod::Weekly => Some((retention_days + 7) * 86400),
+                LeaderboardPeriod::All => None,
+            };
+            let mut counters = self
+                .stats_counters
+                .entry(stats_counters_key(period, result.timestamp))
+                .or_insert_with(|| Expiring::new(PeriodCounters::default(), ttl));
+            counters.value.games += 1;
+            match result.winner {
+                Role::Civilian => counters.value.civilian_wins += 1,
+                _ => counters.value.undercover_wins += 1,
+            }
+            counters.value.players.extend(history_players.iter().cloned());
+            counters.expires_at = ttl.map(expires_in);
+        }
+
+        Ok(())
+    }
+
+    async fn get_room_results(&self, room_id: &str) -> Result<Vec<GameResult>> {
+        Ok(live(&self.room_results, room_id).unwrap_or_default())
+    }
+
+    async fn get_player_games(&self, player_id: &str, offset: usize, limit: usize) -> Result<Vec<PlayerGameEntry>> {
+        Ok(self
+            .player_games
+            .get(player_id)
+            .map(|games| games.iter().skip(offset).take(limit).cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn get_today_stats(&self) -> Result<DailyStats> {
+        Ok(self.records_of(Utc::now().date_naive()))
+    }
+
+    async fn get_daily_stats(&self, days: u32) -> Result<Vec<DailyStats>> {
+        let today = Utc::now().date_naive();
+        Ok((1..=days as u64)
+            .filter_map(|offset| today.checked_sub_days(chrono::Days::new(offset)))
+            .filter(|date| *date >= self.started_on)
+            .map(|date| self.records_of(date))
+            .collect())
+    }
+
+    async fn get_stats_summary(&self, period: LeaderboardPeriod, count: u32) -> Result<Vec<StatsSummary>> {
+        let now = Utc::now();
+        let step = match period {
+            LeaderboardPeriod::Daily => 1,
+            LeaderboardPeriod::Weekly => 7,
+            LeaderboardPeriod::All => 0,
+        };
+        let count = if period == LeaderboardPeriod::All { 1 } else { count.max(1) };
+
+        Ok((0..count as i64)
+            .map(|offset| now - chrono::Duration::days(offset * step))
+            .map(|at| {
+                let counters = self
+                    .stats_counters
+                    .get(&stats_counters_key(period, at))
+                    .filter(|entry| entry.is_alive());
+                let (games, civilian_wins, undercover_wins, unique_players) = counters
+                    .map(|entry| {
+                        let counters = &entry.value;
+                        (
+                            counters.games,
+                            counters.civilian_wins,
+                            counters.undercover_wins,
+                            counters.players.len() as u64,
+                        )
+                    })
+                    .unwrap_or_default();
+                let rate = |wins: u64| if games == 0 { 0.0 } else { wins as f64 / games as f64 };
+                StatsSummary {
+                    period: stats_period_label(period, at),
+                    games,
+                    unique_players,
+                    civilian_wins,
+                    undercover_wins,
+                    civilian_win_rate: rate(civilian_wins),
+                    undercover_win_rate: rate(undercover_wins),
+                }
+            })
+            .collect())
+    }
+
+    async fn append_replay_event(&self, room_id: &str, game_id: &str, entry: &ReplayEntry) -> Result<()> {
+        let mut replay = self
+            .replays
+            .entry(game_id.to_string())
+            .or_insert_with(|| Expiring::new((room_id.to_string(), Vec::new()), Some(REPLAY_TTL_SECS)));
+        if !replay.is_alive() {
+            replay.value.1.clear();
+        }
+        replay.value.1.push(entry.clone());
+        replay.expires_at = Some(expires_in(REPLAY_TTL_SECS));
+        Ok(())
+    }
+
+    async fn get_replay(&self, game_id: &str) -> Result<Option<Replay>> {
+        Ok(live(&self.replays, game_id)
+            .filter(|(_, events)| !events.is_empty())
+            .map(|(room_id, events)| Replay {
+                game_id: game_id.to_string(),
+                room_id,
+                events,
+            }))
+    }
+
+    async fn get_leaderboard(
+        &self,
+        metric: LeaderboardMetric,
+        period: LeaderboardPeriod,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(usize, Vec<LeaderboardEntry>)> {
+        if metric == LeaderboardMetric::Rating && period != LeaderboardPeriod::All {
+            return Err(crate::Error::Storage("等级分排行榜只有总榜".to_string()).into());
+        }
+
+        let mut scores: Vec<(String, i64)> = live(&self.leaderboards, &metric.key(period, Utc::now()))
+            .map(|board| board.into_iter().collect())
+            .unwrap_or_default();
+        // 与 Redis 有序集合的倒序一致：分数相同时按玩家ID倒序
+        scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+
+        let total = scores.len();
+        let entries = scores
+            .into_iter()
+            .enumerate()
+            .skip(offset)
+            .take(limit)
+            .map(|(index, (player_id, score))| LeaderboardEntry {
+                rank: index + 1,
+                player_name: self
+                    .leaderboard_names
+                    .get(&player_id)
+                    .map(|name| name.clone())
+                    .unwrap_or_else(|| player_id.clone()),
+                player_id,
+                score,
+            })
+            .collect();
+
+        Ok((total, entries))
+    }
+
+    async fn get_player_stats(&self, player_id: &str) -> Result<PlayerStats> {
+        Ok(self
+            .player_stats
+            .get(player_id)
+            .map(|stats| stats.clone())
+            .unwrap_or_default())
+    }
+
+    async fn update_player_stats(&self, player_id: &str, stats: &PlayerStats) -> Result<()> {
+        self.player_stats.insert(player_id.to_string(), stats.clone());
+        Ok(())
+    }
+
+    async fn get_profile_settings(&self, player_id: &str) -> Result<ProfileSettings> {
+        Ok(self
+            .profile_settings
+            .get(player_id)
+            .map(|settings| *settings)
+            .unwrap_or_default())
+    }
+
+    async fn save_profile_settings(&self, player_id: &str, settings: &ProfileSettings) -> Result<()> {
+        self.profile_settings.insert(player_id.to_string(), *settings);
+        Ok(())
+    }
+
+    async fn get_player_current_room(&self, player_id: &str) -> Result<Option<String>> {
+        Ok(self.player_rooms.get(player_id).map(|room_id| room_id.clone()))
+    }
+
+    async fn save_player_room_info(&self, player_id: &str, _name: &str, room_id: &str) -> Result<()> {
+        self.player_rooms.insert(player_id.to_string(), room_id.to_string());
+        Ok(())
+    }
+
+    async fn clear_player_room_info_if(&self, player_id: &str, room_id: &str) -> Result<bool> {
+        Ok(self
+            .player_rooms
+            .remove_if(player_id, |_, stored| stored == room_id)
+            .is_some())
+    }
+
+    async fn try_acquire_player_lease(&self, player_id: &str, ttl_ms: u64) -> Result<Option<String>> {
+        let token = Uuid::new_v4().to_string();
+        let lease = Expiring {
+            value: token.clone(),
+            expires_at: Some(Instant::now() + Duration::from_millis(ttl_ms)),
+        };
+        match self.player_leases.entry(player_id.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(mut entry) => {
+                if entry.get().is_alive() {
+                    return Ok(None);
+                }
+                entry.insert(lease);
+            }
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(lease);
+            }
+        }
+        Ok(Some(token))
+    }
+
+    async fn release_player_lease(&self, player_id: &str, token: &str) -> Result<()> {
+        self.player_leases.remove_if(player_id, |_, lease| lease.value == token);
+        Ok(())
+    }
+
+    async fn save_session(&self, session: &UserSession) -> Result<()> {
+        self.sessions.insert(session.session_id, session.clone());
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &Uuid) -> Result<Option<UserSession>> {
+        Ok(self
+            .sessions
+            .get(session_id)
+            .filter(|session| session.expires_at > Utc::now())
+            .map(|session| session.clone()))
+    }
+
+    async fn delete_session(&self, session_id: &Uuid) -> Result<()> {
+        self.sessions.remove(session_id);
+        Ok(())
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>> {
+        let now = Utc::now();
+        let mut sessions: Vec<UserSession> = self
+            .sessions
+            .iter()
+            .filter(|session| session.user_id == user_id && session.expires_at > now)
+            .map(|session| session.clone())
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn extend_session(&self, session_id: &Uuid, new_expires_at: DateTime<Utc>) -> Result<()> {
+        if let Some(mut session) = self.sessions.get_mut(session_id) {
+            session.expires_at = new_expires_at;
+            session.last_used_at = Utc::now();
+        }
+        Ok(())
+    }
+
+    async fn save_user(&self, user: &User) -> Result<()> {
+        self.users.insert(user.id.clone(), Expiring::new(user.clone(), None));
+        Ok(())
+    }
+
+    async fn save_guest_user(&self, user: &User, ttl_secs: u64) -> Result<()> {
+        self.users
+            .insert(user.id.clone(), Expiring::new(user.clone(), Some(ttl_secs as i64)));
+        Ok(())
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>> {
+        Ok(live(&self.users, user_id))
+    }
+
+    async fn save_admin_token(&self, token: &AdminToken) -> Result<()> {
+        self.admin_tokens.insert(token.token.clone(), token.clone());
+        Ok(())
+    }
+
+    async fn get_admin_token(&self, token: &str) -> Result<Option<AdminToken>> {
+        Ok(self
+            .admin_tokens
+            .get(token)
+            .filter(|token| !token.is_expired())
+            .map(|token| token.clone()))
+    }
+
+    async fn delete_admin_token(&self, token: &str) -> Result<()> {
+        self.admin_tokens.remove(token);
+        Ok(())
+    }
+
+    async fn append_audit_record(&self, record: &AuditRecord, max_len: usize) -> Result<()> {
+        let id = self.next_audit_id.fetch_add(1, Ordering::Relaxed);
+        let mut log = self.audit_log.lock().unwrap_or_else(|e| e.into_inner());
+        log.push_back((id, record.clone()));
+        while log.len() > max_len {
+            log.pop_front();
+        }
+        Ok(())
+    }
+
+    async fn get_audit_records(&self, before: Option<&str>, limit: usize) -> Result<Vec<(String, AuditRecord)>> {
+        let before = before
+            .map(|id| id.parse::<u64>())
+            .transpose()
+            .map_err(|_| crate::Error::Storage("无效的审计记录ID".to_string()))?;
+        let log = self.audit_log.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(log
+            .iter()
+            .rev()
+            .filter(|(id, _)| before.is_none_or(|before| *id < before))
+            .take(limit)
+            .map(|(id, record)| (id.to_string(), record.clone()))
+            .collect())
+    }
+
+    async fn get_rating(&self, player_id: &str) -> Result<PlayerRating> {
+        Ok(self
+            .ratings
+            .get(player_id)
+            .map(|rating| rating.clone())
+            .unwrap_or_else(|| PlayerRating::new(player_id)))
+    }
+
+    async fn save_rating(&self, rating: &PlayerRating) -> Result<()> {
+        self.ratings.insert(rating.player_id.clone(), rating.clone());
+        self.leaderboards
+            .entry(LeaderboardMetric::Rating.key(LeaderboardPeriod::All, Utc::now()))
+            .or_insert_with(|| Expiring::new(HashMap::new(), None))
+            .value
+            .insert(rating.player_id.clone(), rating.rating as i64);
+        Ok(())
+    }
+
+    async fn record_playtime(&self, player_id: &str, seconds: u64) -> Result<()> {
+        let mut usage = self
+            .playtime
+            .entry(Self::playtime_key(player_id))
+            .or_insert_with(|| Expiring::new(DailyPlaytime::default(), Some(PLAYTIME_TTL_SECS)));
+        usage.value.games += 1;
+        usage.value.seconds += seconds;
+        usage.expires_at = Some(expires_in(PLAYTIME_TTL_SECS));
+        Ok(())
+    }
+
+    async fn get_daily_playtime(&self, player_id: &str) -> Result<DailyPlaytime> {
+        Ok(live(&self.playtime, &Self::playtime_key(player_id)).unwrap_or_default())
+    }
+
+    async fn get_play_limits(&self, player_id: &str) -> Result<PlayLimits> {
+        Ok(self
+            .play_limits
+            .get(player_id)
+            .map(|limits| *limits)
+            .unwrap_or_default())
+    }
+
+    async fn set_play_limits(&self, player_id: &str, limits: &PlayLimits) -> Result<()> {
+        self.play_limits.insert(player_id.to_string(), *limits);
+        Ok(())
+    }
+
+    async fn set_global_mute(&self, mute: &GlobalMute) -> Result<()> {
+        self.global_mutes.insert(mute.user_id.clone(), mute.clone());
+        Ok(())
+    }
+
+    async fn get_global_mute(&self, user_id: &str) -> Result<Option<GlobalMute>> {
+        Ok(self
+            .global_mutes
+            .get(user_id)
+            .filter(|mute| mute.expires_at > Utc::now())
+            .map(|mute| mute.clone()))
+    }
+
+    async fn clear_global_mute(&self, user_id: &str) -> Result<()> {
+        self.global_mutes.remove(user_id);
+        Ok(())
+    }
+
+    async fn save_avatar(&self, user_id: &str, content_type: &str, data: &[u8], ttl_secs: u64) -> Result<()> {
+        self.avatars.insert(
+            user_id.to_string(),
+            Expiring::new((content_type.to_string(), data.to_vec()), Some(ttl_secs as i64)),
+        );
+        Ok(())
+    }
+
+    async fn get_avatar(&self, user_id: &str) -> Result<Option<(String, Vec<u8>)>> {
+        Ok(live(&self.avatars, user_id))
+    }
+
+    async fn delete_avatar(&self, user_id: &str) -> Result<()> {
+        self.avatars.remove(user_id);
+        Ok(())
+    }
+
+    async fn try_hold_active_lease(&self, instance_id: &str, ttl_secs: u64) -> Result<bool> {
+        let mut lease = self.active_lease.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let held_by_other = lease
+            .as_ref()
+            .is_some_and(|(holder, expires_at)| holder != instance_id && *expires_at > now);
+        if held_by_other {
+            return Ok(false);
+        }
+        *lease = Some((instance_id.to_string(), now + Duration::from_secs(ttl_secs.max(1))));
+        Ok(true)
+    }
+
+    async fn save_room_checkpoint(&self, room_id: &str, checkpoint: &RoomCheckpoint) -> Result<()> {
+        self.room_checkpoints.insert(room_id.to_string(), checkpoint.clone());
+        Ok(())
+    }
+
+    async fn delete_room_checkpoint(&self, room_id: &str) -> Result<()> {
+        self.room_checkpoints.remove(room_id);
+        Ok(())
+    }
+
+    async fn load_room_checkpoints(&self) -> Result<Vec<RoomCheckpoint>> {
+        Ok(self
+            .room_checkpoints
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect())
+    }
+}
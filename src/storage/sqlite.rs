@@ -0,0 +1,983 @@
+use super::{
+    ChatChannel, DailyPlaytime, DailyStats, GameResult, GlobalMute, LeaderboardEntry,
+    LeaderboardMetric, LeaderboardPeriod, MemoryStorage, PLAYTIME_TTL_SECS, PlayLimits, PlayerGameEntry,
+    PlayerRating, PlayerStats, ProfileSettings, REPLAY_TTL_SECS, ROOM_RESULTS_MAX_LEN, Replay, ReplayEntry, StateLogEntry,
+    StatsSummary, StorageBackend, StorageHealthSnapshot, stats_period_label,
+};
+use crate::admin::{AdminToken, AuditRecord};
+use crate::game::{ChatMessage, Role, StateRecord};
+use crate::room::RoomCheckpoint;
+use crate::user::{User, UserSession};
+use crate::word_bank::WordBankData;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc};
+use serde::de::DeserializeOwned;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::error;
+use uuid::Uuid;
+
+/// 连接池大小，SQLite 同一时间只有一个写连接，读连接多了也没有意义
+const SQLITE_MAX_CONNECTIONS: u32 = 4;
+
+/// 房间归档对局结果的查询范围（秒），与 Redis 实现的保存时间一致
+const ROOM_RESULTS_WINDOW_SECS: i64 = 86400;
+
+const SCHEMA: &str = r"
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    expires_at INTEGER
+);
+CREATE TABLE IF NOT EXISTS sessions (
+    session_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS sessions_user_id ON sessions (user_id);
+CREATE TABLE IF NOT EXISTS admin_tokens (
+    token TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS audit_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS game_results (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    room_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    winner TEXT NOT NULL,
+    player_count INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS game_results_room_id ON game_results (room_id, timestamp);
+CREATE INDEX IF NOT EXISTS game_results_timestamp ON game_results (timestamp);
+CREATE TABLE IF NOT EXISTS player_games (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    player_id TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    won INTEGER NOT NULL,
+    ranked INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS player_games_player_id ON player_games (player_id, id);
+CREATE INDEX IF NOT EXISTS player_games_ranked_wins ON player_games (ranked, won, timestamp);
+CREATE TABLE IF NOT EXISTS player_names (
+    player_id TEXT PRIMARY KEY,
+    name TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS player_stats (
+    player_id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS ratings (
+    player_id TEXT PRIMARY KEY,
+    rating INTEGER NOT NULL,
+    data TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS ratings_rating ON ratings (rating);
+CREATE TABLE IF NOT EXISTS profile_settings (
+    player_id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS play_limits (
+    player_id TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS playtime (
+    player_id TEXT NOT NULL,
+    date TEXT NOT NULL,
+    games INTEGER NOT NULL,
+    seconds INTEGER NOT NULL,
+    PRIMARY KEY (player_id, date)
+);
+CREATE TABLE IF NOT EXISTS global_mutes (
+    user_id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    expires_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS replay_events (
+    game_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    room_id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (game_id, seq)
+);
+CREATE TABLE IF NOT EXISTS word_bank (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    data TEXT NOT NULL
+);
+";
+
+fn decode<T: DeserializeOwned>(data: &str) -> Result<T> {
+    serde_json::from_str(data).map_err(|e| crate::Error::Storage(e.to_string()).into())
+}
+
+fn encode(value: &impl serde::Serialize) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| crate::Error::Storage(e.to_string()).into())
+}
+
+/// 胜方在 `game_results.winner` 列中的取值，与 JSON 序列化的名称一致
+fn role_name(role: Role) -> &'static str {
+    match role {
+        Role::Undercover => "Undercover",
+        Role::Civilian => "Civilian",
+        Role::Blank => "Blank",
+    }
+}
+
+/// 统计时间范围的起止时间戳，不限时取 i64 的边界值
+fn timestamp_range(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> (i64, i64) {
+    (
+        start.map_or(i64::MIN, |start| start.timestamp()),
+        end.map_or(i64::MAX, |end| end.timestamp()),
+    )
+}
+
+/// 指定时间所在统计周期（按UTC自然日、ISO周划分）的起止时间，全部时间为 None
+fn period_range(period: LeaderboardPeriod, at: DateTime<Utc>) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let date = at.date_naive();
+    let (start, days) = match period {
+        LeaderboardPeriod::Daily => (date, 1),
+        LeaderboardPeriod::Weekly => (
+            date - chrono::Days::new(date.weekday().num_days_from_monday() as u64),
+            7,
+        ),
+        LeaderboardPeriod::All => return None,
+    };
+    let start = start.and_time(NaiveTime::MIN).and_utc();
+    Some((start, start + chrono::Duration::days(days)))
+}
+
+/// SQLite 存储后端：对局结果、玩家统计与等级分、会话和用户、词库等长期数据保存在单个数据库文件中
+///
+/// 适合不想部署 Redis 的小规模自建服务。进行中房间的状态事件、聊天记录、房间归属租约和头像缓存
+/// 只保存在内存中，服务重启后进行中的房间不会恢复；不支持主备部署。
+pub struct SqliteStorage {
+    pool: SqlitePool,
+    /// 不需要长期保存的数据
+    ephemeral: MemoryStorage,
+}
+
+impl SqliteStorage {
+    /// 打开（不存在时创建）数据库文件并建表
+    pub async fn open(path: &str) -> Result<Self> {
+        if let Some(parent) = std::path::Path::new(path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+        Self::connect(options, SQLITE_MAX_CONNECTIONS).await
+    }
+
+    /// 测试用的内存数据库，内存数据库只在单个连接内可见，连接池只保留一个连接
+    #[cfg(test)]
+    pub(super) async fn in_memory() -> Self {
+        Self::connect("sqlite::memory:".parse().unwrap(), 1).await.unwrap()
+    }
+
+    /// 按连接参数建立连接池并建表
+    async fn connect(options: SqliteConnectOptions, max_connections: u32) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .connect_with(options)
+            .await?;
+        sqlx::raw_sql(SCHEMA).execute(&pool).await?;
+
+        Ok(SqliteStorage {
+            pool,
+            ephemeral: MemoryStorage::new(),
+        })
+    }
+
+    /// 删除已过期的会话、游客、令牌、禁言和回放
+    pub async fn purge_expired(&self) -> Result<()> {
+        let now = Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM users WHERE expires_at IS NOT NULL AND expires_at <= ?")
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM admin_tokens WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM global_mutes WHERE expires_at <= ?")
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM replay_events WHERE created_at <= ?")
+            .bind(now - REPLAY_TTL_SECS)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM playtime WHERE date < ?")
+            .bind((Utc::now() - chrono::Duration::seconds(PLAYTIME_TTL_SECS)).date_naive().to_string())
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.ephemeral.purge_expired();
+        Ok(())
+    }
+
+    /// 启动后台任务，定期删除过期数据
+    pub fn spawn_sweeper(self: &Arc<Self>, interval: Duration) {
+        let storage = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = storage.purge_expired().await {
+                    error!("清理 SQLite 中的过期数据失败: {}", e);
+                }
+            }
+        });
+    }
+
+    /// 按UTC日期汇总时间范围内的对局，没有对局的日期不在结果中；`end` 为 None 时不限结束时间
+    async fn daily_stats(
+        &self,
+        start: Option<DateTime<Utc>>,
+        end: Option<DateTime<Utc>>,
+    ) -> Result<BTreeMap<NaiveDate, DailyStats>> {
+        let (start, end) = timestamp_range(start, end);
+        let rows: Vec<(String, i64, i64, i64, i64)> = sqlx::query_as(
+            "SELECT date(timestamp, 'unixepoch') AS day, CAST(strftime('%H', timestamp, 'unixepoch') AS INTEGER) AS hour,
+                    COUNT(*), SUM(winner = ?), SUM(duration_ms)
+             FROM game_results WHERE timestamp >= ? AND timestamp < ? GROUP BY day, hour",
+        )
+        .bind(role_name(Role::Civilian))
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+        let users: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT date(timestamp, 'unixepoch') AS day, COUNT(DISTINCT player_id)
+             FROM player_games WHERE timestamp >= ? AND timestamp < ? GROUP BY day",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut days: BTreeMap<NaiveDate, (DailyStats, u64)> = BTreeMap::new();
+        for (day, hour, games, civilian_wins, duration_ms) in rows {
+            let date: NaiveDate = day.parse()?;
+            let (stats, total_duration) = days
+                .entry(date)
+                .or_insert_with(|| (DailyStats::from_records(date, &[]), 0));
+            stats.games += games as u32;
+            stats.games_per_hour[hour as usize % 24] += games as u32;
+            stats.civilian_wins += civilian_wins as u32;
+            stats.undercover_wins += (games - civilian_wins) as u32;
+            *total_duration += duration_ms as u64;
+        }
+        for (day, active_users) in users {
+            if let Some((stats, _)) = days.get_mut(&day.parse()?) {
+                stats.active_users = active_users as u32;
+            }
+        }
+
+        Ok(days
+            .into_iter()
+            .map(|(date, (mut stats, total_duration))| {
+                stats.average_duration_ms = total_duration / stats.games.max(1) as u64;
+                (date, stats)
+            })
+            .collect())
+    }
+}
+
+#[axum::async_trait]
+impl StorageBackend for SqliteStorage {
+    fn is_degraded(&self) -> bool {
+        false
+    }
+
+    fn health(&self) -> StorageHealthSnapshot {
+        self.ephemeral.health()
+    }
+
+    async fn append_state_record(&self, room_id: &str, record: &StateRecord) -> Result<()> {
+        self.ephemeral.append_state_record(room_id, record).await
+    }
+
+    async fn delete_room_state(&self, room_id: &str) -> Result<()> {
+        self.ephemeral.delete_room_state(room_id).await
+    }
+
+    async fn load_state_log(&self, room_id: &str) -> Result<Vec<StateLogEntry>> {
+        self.ephemeral.load_state_log(room_id).await
+    }
+
+    async fn list_saved_room_ids(&self) -> Result<Vec<String>> {
+        self.ephemeral.list_saved_room_ids().await
+    }
+
+    async fn append_chat_message(&self, room_id: &str, channel: ChatChannel, message: &ChatMessage) -> Result<()> {
+        self.ephemeral.append_chat_message(room_id, channel, message).await
+    }
+
+    async fn clear_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<()> {
+        self.ephemeral.clear_chat_log(room_id, channel).await
+    }
+
+    async fn load_chat_log(&self, room_id: &str, channel: ChatChannel) -> Result<Vec<ChatMessage>> {
+        self.ephemeral.load_chat_log(room_id, channel).await
+    }
+
+    async fn save_game_result(
+        &self,
+        result: &GameResult,
+        leaderboard_players: &[String],
+        history_players: &[String],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO game_results (room_id, timestamp, winner, player_count, duration_ms, data) \
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&result.room_id)
+        .bind(result.timestamp.timestamp())
+        .bind(role_name(result.winner))
+        .bind(result.players.len() as i64)
+        .bind(result.clock.total_ms as i64)
+        .bind(encode(result)?)
+        .execute(&mut *tx)
+        .await?;
+
+        // 计入排行榜的玩家都会记入对局历史，胜场排行榜由对局历史按周期统计
+        for player_id in history_players {
+            let Some(entry) = PlayerGameEntry::from_result(player_id, result) else {
+                continue;
+            };
+            let ranked = leaderboard_players.contains(player_id);
+            sqlx::query("INSERT INTO player_games (player_id, timestamp, won, ranked, data) VALUES (?, ?, ?, ?, ?)")
+                .bind(player_id)
+                .bind(entry.timestamp.timestamp())
+                .bind(entry.won)
+                .bind(ranked)
+                .bind(encode(&entry)?)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for player in result.players.iter().filter(|p| leaderboard_players.contains(&p.id)) {
+            sqlx::query("INSERT OR REPLACE INTO player_names (player_id, name) VALUES (?, ?)")
+                .bind(&player.id)
+                .bind(&player.name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_room_results(&self, room_id: &str) -> Result<Vec<GameResult>> {
+        let results: Vec<String> = sqlx::query_scalar(
+            "SELECT data FROM (SELECT id, data FROM game_results WHERE room_id = ? AND timestamp > ? \
+             ORDER BY id DESC LIMIT ?) ORDER BY id",
+        )
+        .bind(room_id)
+        .bind(Utc::now().timestamp() - ROOM_RESULTS_WINDOW_SECS)
+        .bind(ROOM_RESULTS_MAX_LEN as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(results.iter().filter_map(|result| decode(result).ok()).collect())
+    }
+
+    async fn get_player_games(&self, player_id: &str, offset: usize, limit: usize) -> Result<Vec<PlayerGameEntry>> {
+        let entries: Vec<String> =
+            sqlx::query_scalar("SELECT data FROM player_games WHERE player_id = ? ORDER BY id DESC LIMIT ? OFFSET ?")
+                .bind(player_id)
+                .bind(limit as i64)
+                .bind(offset as i64)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(entries.iter().filter_map(|entry| decode(entry).ok()).collect())
+    }
+
+    async fn get_today_stats(&self) -> Result<DailyStats> {
+        let today = Utc::now().date_naive();
+        let (start, end) = period_range(LeaderboardPeriod::Daily, Utc::now()).unzip();
+        Ok(self
+            .daily_stats(start, end)
+            .await?
+            .remove(&today)
+            .unwrap_or_else(|| DailyStats::from_records(today, &[])))
+    }
+
+    async fn get_daily_stats(&self, days: u32) -> Result<Vec<DailyStats>> {
+        let today = Utc::now().date_naive();
+        let Some(first) = today.checked_sub_days(chrono::Days::new(days as u64)) else {
+            return Ok(Vec::new());
+        };
+        let mut by_date = self
+            .daily_stats(
+                Some(first.and_time(NaiveTime::MIN).and_utc()),
+                Some(today.and_time(NaiveTime::MIN).and_utc()),
+            )
+            .await?;
+
+        // 与每日汇总一致：最早一局之前的日期没有统计，之后没有对局的日期记为0局
+        let Some(earliest) = by_date.keys().next().copied() else {
+            return Ok(Vec::new());
+        };
+        Ok((1..=days as u64)
+            .filter_map(|offset| today.checked_sub_days(chrono::Days::new(offset)))
+            .filter(|date| *date >= earliest)
+            .map(|date| by_date.remove(&date).unwrap_or_else(|| DailyStats::from_records(date, &[])))
+            .collect())
+    }
+
+    async fn get_stats_summary(&self, period: LeaderboardPeriod, count: u32) -> Result<Vec<StatsSummary>> {
+        let now = Utc::now();
+        let step = match period {
+            LeaderboardPeriod::Daily => 1,
+            LeaderboardPeriod::Weekly => 7,
+            LeaderboardPeriod::All => 0,
+        };
+        let count = if period == LeaderboardPeriod::All { 1 } else { count.max(1) };
+
+        let mut summary = Vec::with_capacity(count as usize);
+        for offset in 0..count as i64 {
+            let at = now - chrono::Duration::days(offset * step);
+            let (start, end) = period_range(period, at).unzip();
+            let (start, end) = timestamp_range(start, end);
+            let (games, civilian_wins): (i64, i64) = sqlx::query_as(
+                "SELECT COUNT(*), COALESCE(SUM(winner = ?), 0) FROM game_results WHERE timestamp >= ? AND timestamp < ?",
+            )
+            .bind(role_name(Role::Civilian))
+            .bind(start)
+            .bind(end)
+            .fetch_one(&self.pool)
+            .await?;
+            let unique_players: i64 = sqlx::query_scalar(
+                "SELECT COUNT(DISTINCT player_id) FROM player_games WHERE timestamp >= ? AND timestamp < ?",
+            )
+            .bind(start)
+            .bind(end)
+            .fetch_one(&self.pool)
+            .await?;
+
+            let (games, civilian_wins, unique_players) = (games as u64, civilian_wins as u64, unique_players as u64);
+            let undercover_wins = games - civilian_wins;
+            let rate = |wins: u64| if games == 0 { 0.0 } else { wins as f64 / games as f64 };
+            summary.push(StatsSummary {
+                period: stats_period_label(period, at),
+                games,
+                unique_players,
+                civilian_wins,
+                undercover_wins,
+                civilian_win_rate: rate(civilian_wins),
+                undercover_win_rate: rate(undercover_wins),
+            });
+        }
+        Ok(summary)
+    }
+
+    async fn append_replay_event(&self, room_id: &str, game_id: &str, entry: &ReplayEntry) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO replay_events (game_id, seq, room_id, data, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(game_id)
+        .bind(entry.seq as i64)
+        .bind(room_id)
+        .bind(encode(entry)?)
+        .bind(Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_replay(&self, game_id: &str) -> Result<Option<Replay>> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT room_id, data FROM replay_events WHERE game_id = ? AND created_at > ? ORDER BY seq")
+                .bind(game_id)
+                .bind(Utc::now().timestamp() - REPLAY_TTL_SECS)
+                .fetch_all(&self.pool)
+                .await?;
+        let Some((room_id, _)) = rows.first() else {
+            return Ok(None);
+        };
+
+        Ok(Some(Replay {
+            game_id: game_id.to_string(),
+            room_id: room_id.clone(),
+            events: rows
+                .iter()
+                .map(|(_, data)| decode(data))
+                .collect::<Result<Vec<ReplayEntry>>>()?,
+        }))
+    }
+
+    async fn get_leaderboard(
+        &self,
+        metric: LeaderboardMetric,
+        period: LeaderboardPeriod,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(usize, Vec<LeaderboardEntry>)> {
+        let (total, rows): (i64, Vec<(String, i64, Option<String>)>) = match metric {
+            LeaderboardMetric::Rating => {
+                if period != LeaderboardPeriod::All {
+                    return Err(crate::Error::Storage("等级分排行榜只有总榜".to_string()).into());
+                }
+                let total = sqlx::query_scalar("SELECT COUNT(*) FROM ratings")
+                    .fetch_one(&self.pool)
+                    .await?;
+                let rows = sqlx::query_as(
+                    "SELECT r.player_id, r.rating, n.name FROM ratings r
+                     LEFT JOIN player_names n ON n.player_id = r.player_id
+                     ORDER BY r.rating DESC, r.player_id DESC LIMIT ? OFFSET ?",
+                )
+                .bind(limit as i64)
+                .bind(offset as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                (total, rows)
+            }
+            LeaderboardMetric::Wins => {
+                let since = period_range(period, Utc::now()).map_or(i64::MIN, |(start, _)| start.timestamp());
+                let total = sqlx::query_scalar(
+                    "SELECT COUNT(DISTINCT player_id) FROM player_games WHERE ranked = 1 AND won = 1 AND timestamp >= ?",
+                )
+                .bind(since)
+                .fetch_one(&self.pool)
+                .await?;
+                let rows = sqlx::query_as(
+                    "SELECT g.player_id, COUNT(*) AS wins, n.name FROM player_games g
+                     LEFT JOIN player_names n ON n.player_id = g.player_id
+                     WHERE g.ranked = 1 AND g.won = 1 AND g.timestamp >= ?
+                     GROUP BY g.player_id ORDER BY wins DESC, g.player_id DESC LIMIT ? OFFSET ?",
+                )
+                .bind(since)
+                .bind(limit as i64)
+                .bind(offset as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                (total, rows)
+            }
+        };
+
+        let entries = rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, (player_id, score, name))| LeaderboardEntry {
+                rank: offset + index + 1,
+                player_name: name.unwrap_or_else(|| player_id.clone()),
+                player_id,
+                score,
+            })
+            .collect();
+        Ok((total as usize, entries))
+    }
+
+    async fn get_player_stats(&self, player_id: &str) -> Result<PlayerStats> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM player_stats WHERE player_id = ?")
+            .bind(player_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(data.map(|data| decode(&data)).transpose()?.unwrap_or_default())
+    }
+
+    async fn update_player_stats(&self, player_id: &str, stats: &PlayerStats) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO player_stats (player_id, data) VALUES (?, ?)")
+            .bind(player_id)
+            .bind(encode(stats)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_profile_settings(&self, player_id: &str) -> Result<ProfileSettings> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM profile_settings WHERE player_id = ?")
+            .bind(player_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(data.map(|data| decode(&data)).transpose()?.unwrap_or_default())
+    }
+
+    async fn save_profile_settings(&self, player_id: &str, settings: &ProfileSettings) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO profile_settings (player_id, data) VALUES (?, ?)")
+            .bind(player_id)
+            .bind(encode(settings)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_player_current_room(&self, player_id: &str) -> Result<Option<String>> {
+        self.ephemeral.get_player_current_room(player_id).await
+    }
+
+    async fn save_player_room_info(&self, player_id: &str, name: &str, room_id: &str) -> Result<()> {
+        self.ephemeral.save_player_room_info(player_id, name, room_id).await
+    }
+
+    async fn clear_player_room_info_if(&self, player_id: &str, room_id: &str) -> Result<bool> {
+        self.ephemeral.clear_player_room_info_if(player_id, room_id).await
+    }
+
+    async fn try_acquire_player_lease(&self, player_id: &str, ttl_ms: u64) -> Result<Option<String>> {
+        self.ephemeral.try_acquire_player_lease(player_id, ttl_ms).await
+    }
+
+    async fn release_player_lease(&self, player_id: &str, token: &str) -> Result<()> {
+        self.ephemeral.release_player_lease(player_id, token).await
+    }
+
+    async fn save_session(&self, session: &UserSession) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO sessions (session_id, user_id, data, created_at, expires_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(session.session_id.to_string())
+        .bind(&session.user_id)
+        .bind(encode(session)?)
+        .bind(session.created_at.timestamp())
+        .bind(session.expires_at.timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_session(&self, session_id: &Uuid) -> Result<Option<UserSession>> {
+        let data: Option<String> =
+            sqlx::query_scalar("SELECT data FROM sessions WHERE session_id = ? AND expires_at > ?")
+                .bind(session_id.to_string())
+                .bind(Utc::now().timestamp())
+                .fetch_optional(&self.pool)
+                .await?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    async fn delete_session(&self, session_id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE session_id = ?")
+            .bind(session_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_user_sessions(&self, user_id: &str) -> Result<Vec<UserSession>> {
+        let sessions: Vec<String> = sqlx::query_scalar(
+            "SELECT data FROM sessions WHERE user_id = ? AND expires_at > ? ORDER BY created_at",
+        )
+        .bind(user_id)
+        .bind(Utc::now().timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(sessions.iter().filter_map(|session| decode(session).ok()).collect())
+    }
+
+    async fn extend_session(&self, session_id: &Uuid, new_expires_at: DateTime<Utc>) -> Result<()> {
+        let Some(mut session) = self.get_session(session_id).await? else {
+            return Ok(());
+        };
+        session.expires_at = new_expires_at;
+        session.last_used_at = Utc::now();
+        sqlx::query("UPDATE sessions SET data = ?, expires_at = ? WHERE session_id = ?")
+            .bind(encode(&session)?)
+            .bind(new_expires_at.timestamp())
+            .bind(session_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_user(&self, user: &User) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO users (id, data, expires_at) VALUES (?, ?, NULL)")
+            .bind(&user.id)
+            .bind(encode(user)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_guest_user(&self, user: &User, ttl_secs: u64) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO users (id, data, expires_at) VALUES (?, ?, ?)")
+            .bind(&user.id)
+            .bind(encode(user)?)
+            .bind(Utc::now().timestamp() + ttl_secs as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_user(&self, user_id: &str) -> Result<Option<User>> {
+        let data: Option<String> =
+            sqlx::query_scalar("SELECT data FROM users WHERE id = ? AND (expires_at IS NULL OR expires_at > ?)")
+                .bind(user_id)
+                .bind(Utc::now().timestamp())
+                .fetch_optional(&self.pool)
+                .await?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    async fn save_admin_token(&self, token: &AdminToken) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO admin_tokens (token, data, expires_at) VALUES (?, ?, ?)")
+            .bind(&token.token)
+            .bind(encode(token)?)
+            .bind(token.expires_at.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_admin_token(&self, token: &str) -> Result<Option<AdminToken>> {
+        let data: Option<String> =
+            sqlx::query_scalar("SELECT data FROM admin_tokens WHERE token = ? AND expires_at > ?")
+                .bind(token)
+                .bind(Utc::now().timestamp())
+                .fetch_optional(&self.pool)
+                .await?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    async fn delete_admin_token(&self, token: &str) -> Result<()> {
+        sqlx::query("DELETE FROM admin_tokens WHERE token = ?")
+            .bind(token)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn append_audit_record(&self, record: &AuditRecord, max_len: usize) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("INSERT INTO audit_log (data) VALUES (?)")
+            .bind(encode(record)?)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM audit_log WHERE id <= (SELECT MAX(id) FROM audit_log) - ?")
+            .bind(max_len as i64)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_audit_records(&self, before: Option<&str>, limit: usize) -> Result<Vec<(String, AuditRecord)>> {
+        let before = match before {
+            Some(id) => id
+                .parse::<i64>()
+                .map_err(|_| crate::Error::Storage("无效的审计记录ID".to_string()))?,
+            None => i64::MAX,
+        };
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, data FROM audit_log WHERE id < ? ORDER BY id DESC LIMIT ?")
+                .bind(before)
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(id, data)| Some((id.to_string(), decode(&data).ok()?)))
+            .collect())
+    }
+
+    async fn get_rating(&self, player_id: &str) -> Result<PlayerRating> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM ratings WHERE player_id = ?")
+            .bind(player_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match data {
+            Some(data) => decode(&data),
+            None => Ok(PlayerRating::new(player_id)),
+        }
+    }
+
+    async fn save_rating(&self, rating: &PlayerRating) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO ratings (player_id, rating, data) VALUES (?, ?, ?)")
+            .bind(&rating.player_id)
+            .bind(rating.rating)
+            .bind(encode(rating)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn record_playtime(&self, player_id: &str, seconds: u64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO playtime (player_id, date, games, seconds) VALUES (?, ?, 1, ?)
+             ON CONFLICT (player_id, date) DO UPDATE SET games = games + 1, seconds = seconds + excluded.seconds",
+        )
+        .bind(player_id)
+        .bind(Utc::now().date_naive().to_string())
+        .bind(seconds as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_daily_playtime(&self, player_id: &str) -> Result<DailyPlaytime> {
+        let usage: Option<(i64, i64)> =
+            sqlx::query_as("SELECT games, seconds FROM playtime WHERE player_id = ? AND date = ?")
+                .bind(player_id)
+                .bind(Utc::now().date_naive().to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(usage
+            .map(|(games, seconds)| DailyPlaytime {
+                games: games as u32,
+                seconds: seconds as u64,
+            })
+            .unwrap_or_default())
+    }
+
+    async fn get_play_limits(&self, player_id: &str) -> Result<PlayLimits> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM play_limits WHERE player_id = ?")
+            .bind(player_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(data.map(|data| decode(&data)).transpose()?.unwrap_or_default())
+    }
+
+    async fn set_play_limits(&self, player_id: &str, limits: &PlayLimits) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO play_limits (player_id, data) VALUES (?, ?)")
+            .bind(player_id)
+            .bind(encode(limits)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn set_global_mute(&self, mute: &GlobalMute) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO global_mutes (user_id, data, expires_at) VALUES (?, ?, ?)")
+            .bind(&mute.user_id)
+            .bind(encode(mute)?)
+            .bind(mute.expires_at.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn get_global_mute(&self, user_id: &str) -> Result<Option<GlobalMute>> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM global_mutes WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        let mute = data.map(|data| decode::<GlobalMute>(&data)).transpose()?;
+        Ok(mute.filter(|mute| mute.expires_at > Utc::now()))
+    }
+
+    async fn clear_global_mute(&self, user_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM global_mutes WHERE user_id = ?")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_avatar(&self, user_id: &str, content_type: &str, data: &[u8], ttl_secs: u64) -> Result<()> {
+        self.ephemeral.save_avatar(user_id, content_type, data, ttl_secs).await
+    }
+
+    async fn get_avatar(&self, user_id: &str) -> Result<Option<(String, Vec<u8>)>> {
+        self.ephemeral.get_avatar(user_id).await
+    }
+
+    async fn delete_avatar(&self, user_id: &str) -> Result<()> {
+        self.ephemeral.delete_avatar(user_id).await
+    }
+
+    async fn try_hold_active_lease(&self, instance_id: &str, ttl_secs: u64) -> Result<bool> {
+        self.ephemeral.try_hold_active_lease(instance_id, ttl_secs).await
+    }
+
+    async fn save_room_checkpoint(&self, room_id: &str, checkpoint: &RoomCheckpoint) -> Result<()> {
+        self.ephemeral.save_room_checkpoint(room_id, checkpoint).await
+    }
+
+    async fn delete_room_checkpoint(&self, room_id: &str) -> Result<()> {
+        self.ephemeral.delete_room_checkpoint(room_id).await
+    }
+
+    async fn load_room_checkpoints(&self) -> Result<Vec<RoomCheckpoint>> {
+        self.ephemeral.load_room_checkpoints().await
+    }
+
+    async fn load_word_bank(&self) -> Result<Option<WordBankData>> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM word_bank WHERE id = 1")
+            .fetch_optional(&self.pool)
+            .await?;
+        data.map(|data| decode(&data)).transpose()
+    }
+
+    async fn save_word_bank(&self, data: &WordBankData) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO word_bank (id, data) VALUES (1, ?)")
+            .bind(encode(data)?)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::backend_tests::{ids, result};
+
+    #[tokio::test]
+    async fn schema_is_created_idempotently() {
+        let storage = SqliteStorage::in_memory().await;
+        sqlx::raw_sql(SCHEMA).execute(&storage.pool).await.unwrap();
+
+        let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&storage.pool)
+            .await
+            .unwrap();
+        for table in ["users", "sessions", "game_results", "player_games", "ratings", "global_mutes", "word_bank"] {
+            assert!(tables.iter().any(|name| name == table), "缺少表 {}", table);
+        }
+    }
+
+    #[tokio::test]
+    async fn game_result_row_stores_summary_columns() {
+        let storage = SqliteStorage::in_memory().await;
+        storage
+            .save_game_result(&result("room1", Role::Civilian, Utc::now()), &ids(&["a", "b"]), &ids(&["a", "b", "c"]))
+            .await
+            .unwrap();
+
+        let (winner, player_count, duration_ms): (String, i64, i64) =
+            sqlx::query_as("SELECT winner, player_count, duration_ms FROM game_results ORDER BY id LIMIT 1")
+                .fetch_one(&storage.pool)
+                .await
+                .unwrap();
+        assert_eq!((winner.as_str(), player_count, duration_ms), ("Civilian", 3, 60_000));
+    }
+
+    /// SQLite 按对局时间统计每日数据，补写的历史对局计入对局当天
+    #[tokio::test]
+    async fn daily_stats_start_from_earliest_game() {
+        let storage = SqliteStorage::in_memory().await;
+        let three_days_ago = Utc::now() - chrono::Duration::days(3);
+        storage
+            .save_game_result(&result("room1", Role::Undercover, three_days_ago), &[], &ids(&["a"]))
+            .await
+            .unwrap();
+
+        let daily = storage.get_daily_stats(7).await.unwrap();
+        assert_eq!(daily.len(), 3);
+        assert_eq!(daily[0].games, 0);
+        assert_eq!(daily[2].date, three_days_ago.date_naive());
+        assert_eq!((daily[2].games, daily[2].undercover_wins, daily[2].active_users), (1, 1, 1));
+        assert_eq!(storage.get_today_stats().await.unwrap().games, 0);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::game::{PlayerId, Role};
+use crate::message::{ClientMessage, SequencedMessage, ServerMessage};
+use crate::room::Room;
+use rand::prelude::*;
+use std::collections::HashSet;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+/// 教程机器人名称
+const TUTORIAL_BOT_NAMES: [&str; 3] = ["教程机器人·小明", "教程机器人·小红", "教程机器人·小刚"];
+
+/// 机器人每次行动前的停顿，让新手有时间阅读提示
+const BOT_THINK_TIME: Duration = Duration::from_secs(2);
+
+/// 教程房间所需玩家数（3个机器人 + 1名新手）
+pub const TUTORIAL_PLAYER_COUNT: usize = TUTORIAL_BOT_NAMES.len() + 1;
+
+/// 教程机器人ID
+fn bot_id(room_id: &str, index: usize) -> PlayerId {
+    format!("tutorial-bot-{}-{}", room_id, index)
+}
+
+/// 向教程房间加入脚本机器人，机器人通过与真实玩家相同的消息通道与房间交互
+pub async fn spawn_tutorial_bots(room: &Arc<Room>) {
+    for (index, name) in TUTORIAL_BOT_NAMES.iter().enumerate() {
+        let id = bot_id(room.id(), index);
+        let (tx, rx) = mpsc::channel::<SequencedMessage>(100);
+        let rx = room.player_feed(id.clone(), &tx, rx);
+        room.register_bot(id.clone());
+
+        let join = ClientMessage::Join {
+            player_name: name.to_string(),
+            capabilities: None,
+            invite_code: None,
+        };
+        if let Err(e) = room.handle_message(id.clone(), join, Some(tx)).await {
+            error!("教程机器人 {} 加入房间失败: {}", name, e);
+            continue;
+        }
+
+        // 第一个机器人同时负责向新手推送教程步骤
+        let bot = TutorialBot {
+            id,
+            room: Arc::downgrade(room),
+            is_narrator: index == 0,
+            ready_sent: false,
+            described: false,
+            voted: false,
+            sent_steps: HashSet::new(),
+        };
+        tokio::spawn(bot.run(rx));
+    }
+}
+
+/// 按脚本行动的教程机器人
+struct TutorialBot {
+    id: PlayerId,
+    room: Weak<Room>,
+    is_narrator: bool,
+    ready_sent: bool,
+    described: bool,
+    voted: bool,
+    sent_steps: HashSet<String>,
+}
+
+impl TutorialBot {
+    async fn run(mut self, mut rx: mpsc::Receiver<SequencedMessage>) {
+        while let Some(SequencedMessage { message, .. }) = rx.recv().await {
+            if message.type_ != "state_update" {
+                continue;
+            }
+            let Some(room) = self.room.upgrade() else {
+                break;
+            };
+            self.on_state_update(&room, &message.data).await;
+        }
+        debug!("教程机器人 {} 已退出", self.id);
+    }
+
+    async fn on_state_update(&mut self, room: &Arc<Room>, data: &serde_json::Value) {
+        let state = data["state"].as_str().unwrap_or_default().to_string();
+        let me = data["players"]
+            .as_array()
+            .and_then(|players| players.iter().find(|p| p["id"] == self.id.as_str()))
+            .cloned()
+            .unwrap_or_default();
+
+        if state != "Lobby" {
+            self.ready_sent = false;
+        }
+        if state != "DescribePhase" {
+            self.described = false;
+        }
+        if state != "VotePhase" {
+            self.voted = false;
+        }
+
+        if self.is_narrator {
+            self.narrate(room, &state, data).await;
+        }
+
+        match state.as_str() {
+            "Lobby" if !self.ready_sent && !me["is_ready"].as_bool().unwrap_or(false) => {
+                self.ready_sent = true;
+                self.act(room, ClientMessage::Ready {});
+            }
+            "DescribePhase" if !self.described && data["current_player"] == self.id.as_str() => {
+                self.described = true;
+                let word = me["word"].as_str().unwrap_or_default().to_string();
+                let content = scripted_description(&word);
+                self.act(room, ClientMessage::Describe { content });
+            }
+            "VotePhase" if !self.voted && me["is_alive"].as_bool().unwrap_or(false) => {
+                self.voted = true;
+                if let Some(target) = self.choose_vote_target(room).await {
+                    self.act(room, ClientMessage::Vote { target_id: target });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// 稍作停顿后以普通玩家消息的形式执行操作
+    fn act(&self, room: &Arc<Room>, message: ClientMessage) {
+        let room = room.clone();
+        let bot_id = self.id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(BOT_THINK_TIME).await;
+            if let Err(e) = room.handle_message(bot_id.clone(), message, None).await {
+                debug!("教程机器人 {} 操作失败: {}", bot_id, e);
+            }
+        });
+    }
+
+    /// 脚本投票：平民机器人投给卧底，卧底机器人投给任意一名平民机器人
+    async fn choose_vote_target(&self, room: &Arc<Room>) -> Option<PlayerId> {
+        let players = room.players_with_roles().await;
+        let my_role = players.iter().find(|p| p.id == self.id)?.role;
+        let alive = players.iter().filter(|p| p.is_alive && p.id != self.id);
+
+        let candidates: Vec<PlayerId> = match my_role {
+            Some(Role::Undercover) => alive
+                .filter(|p| p.role == Some(Role::Civilian) && room.is_bot(&p.id))
+                .map(|p| p.id.clone())
+                .collect(),
+            _ => alive
+                .filter(|p| p.role == Some(Role::Undercover))
+                .map(|p| p.id.clone())
+                .collect(),
+        };
+
+        let mut rng = rand::rng();
+        candidates.choose(&mut rng).cloned()
+    }
+
+    /// 根据游戏进度向新手推送教程步骤，每个步骤只推送一次
+    async fn narrate(&mut self, room: &Arc<Room>, state: &str, data: &serde_json::Value) {
+        let learner = room.get_host().await;
+        let learner_data = data["players"]
+            .as_array()
+            .and_then(|players| players.iter().find(|p| p["id"] == learner.as_str()))
+            .cloned()
+            .unwrap_or_default();
+
+        let step = match state {
+            "Lobby" if learner_data.is_null() => None,
+            "Lobby" => Some((
+                "welcome",
+                "欢迎来到新手教程".to_string(),
+                "这里有3个教程机器人陪你练习。点击「准备」，等所有人都准备好后，作为房主的你就可以点击「开始游戏」了。"
+                    .to_string(),
+            )),
+            "DescribePhase" if data["current_player"] == learner.as_str() => Some((
+                "your_turn",
+                "轮到你描述了".to_string(),
+                "用一句话描述你的词语，但不要直接说出它。描述太具体会暴露给卧底，太模糊又会被怀疑。"
+                    .to_string(),
+            )),
+            "DescribePhase" => {
+                let word = learner_data["word"].as_str().unwrap_or("（未知）");
+                Some((
+                    "describe",
+                    "描述阶段".to_string(),
+                    format!(
+                        "你的词语是「{}」。大部分人拿到的是平民词，少数人拿到的是相近的卧底词。大家按顺序各说一句描述，仔细听谁的描述和别人不太一样。",
+                        word
+                    ),
+                ))
+            }
+            "VotePhase" => Some((
+                "vote",
+                "投票阶段".to_string(),
+                "根据大家的描述，投票给你认为是卧底的玩家。得票最多的玩家会被淘汰。".to_string(),
+            )),
+            "ResultPhase" => Some((
+                "result",
+                "投票结果".to_string(),
+                "得票最多的玩家被淘汰；如果平票则无人淘汰。卧底全部出局时平民获胜，卧底人数追上平民时卧底获胜。"
+                    .to_string(),
+            )),
+            "GameOver" => Some((
+                "finished",
+                "教程完成".to_string(),
+                "恭喜你完成了新手教程！现在可以去创建或加入真实房间，和摸鱼派的鱼油们一起玩了。"
+                    .to_string(),
+            )),
+            _ => None,
+        };
+
+        let Some((step, title, message)) = step else {
+            return;
+        };
+
+        // 描述和投票每轮都会出现，按轮次区分
+        let round = data["players"]
+            .as_array()
+            .map(|players| players.iter().filter(|p| p["is_alive"] == false).count())
+            .unwrap_or(0);
+        let key = format!("{}:{}", step, round);
+        if !self.sent_steps.insert(key) {
+            return;
+        }
+
+        room.send_to_player(
+            &learner,
+            ServerMessage::TutorialStep {
+                step: step.to_string(),
+                title,
+                message,
+            },
+        )
+        .await;
+    }
+}
+
+/// 根据词语生成不直接暴露词语的脚本描述
+fn scripted_description(word: &str) -> String {
+    let templates = [
+        "这个东西在日常生活中挺常见的",
+        "我觉得大部分人都接触过它",
+        "它和我们的生活息息相关",
+        "说起它，我第一反应是很实用",
+    ];
+    let mut rng = rand::rng();
+    let template = templates.choose(&mut rng).copied().unwrap_or(templates[0]);
+    let length = word.chars().count();
+    if length > 0 {
+        format!("{}，它有{}个字", template, length)
+    } else {
+        template.to_string()
+    }
+}
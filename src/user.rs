@@ -1,11 +1,14 @@
 use crate::Result;
-use crate::storage::Storage;
+use crate::auth::{AuthProvider, ExternalIdentity, FISHPI_PROVIDER_ID, FishpiProvider, OAuth2Provider};
+use crate::client_ip::IpRateLimiter;
+use crate::storage::StorageBackend;
 use chrono::{DateTime, Utc};
+use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use serde_json;
 use std::collections::HashMap;
-use tracing::debug;
-use url::Url;
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::{debug, warn};
 use uuid::Uuid;
 
 /// 用户信息
@@ -15,10 +18,37 @@ pub struct User {
     pub username: String,
     pub nickname: Option<String>,
     pub avatar: Option<String>,
+    /// 外部头像的原始地址，启用头像代理时 `avatar` 为代理地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avatar_source: Option<String>,
     pub created_at: DateTime<Utc>,
     pub last_login: DateTime<Utc>,
 }
 
+/// 游客用户ID的前缀，摸鱼派用户ID为纯数字，不会与之冲突
+pub const GUEST_ID_PREFIX: &str = "guest-";
+
+/// 用户ID是否属于游客
+pub fn is_guest_id(user_id: &str) -> bool {
+    user_id.starts_with(GUEST_ID_PREFIX)
+}
+
+impl User {
+    /// 是否为未登录摸鱼派的游客
+    pub fn is_guest(&self) -> bool {
+        is_guest_id(&self.id)
+    }
+
+    /// 头像代理下载时使用的外部地址，兼容启用代理前保存的用户信息
+    pub fn avatar_origin(&self) -> Option<&str> {
+        self.avatar_source
+            .as_deref()
+            .or(self.avatar.as_deref())
+            .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            .filter(|url| *url != crate::avatar::proxied_url(&self.id))
+    }
+}
+
 /// 用户会话
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSession {
@@ -26,228 +56,189 @@ pub struct UserSession {
     pub user_id: String,
     pub created_at: DateTime<Utc>,
     pub expires_at: DateTime<Utc>,
+    /// 最后一次使用会话的时间，旧会话缺少该字段时取读取时刻
+    #[serde(default = "Utc::now")]
+    pub last_used_at: DateTime<Utc>,
 }
 
-/// 摸鱼派用户信息响应
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct FishpiUserInfoResponse {
-    pub msg: String,
-    pub code: i32,
-    pub data: FishpiUserInfoData,
-}
+/// 登录状态的有效期，超过后回调不再被接受
+const LOGIN_STATE_TTL_SECS: i64 = 600;
 
-#[derive(Debug, Deserialize)]
-struct FishpiUserInfoData {
-    #[serde(rename = "userAvatarURL")]
-    pub user_avatar_url: Option<String>,
-    #[serde(rename = "userNickname")]
-    pub user_nickname: Option<String>,
-    #[serde(rename = "userName")]
-    pub user_name: String,
+/// 同时进行中的登录数上限，超出后拒绝新的登录，防止未认证的请求撑大内存
+const PENDING_LOGINS_MAX: usize = 10_000;
+
+/// 已发起但尚未完成的登录
+struct PendingLogin {
+    provider: String,
+    /// 登录成功后跳转的前端地址
+    callback_url: Option<String>,
+    created_at: DateTime<Utc>,
 }
 
-/// 用户管理器 - 只负责业务逻辑，数据存储交给Storage
+/// 用户管理器 - 只负责业务逻辑，数据存储交给存储后端
 pub struct UserManager {
-    storage: Storage,
-    fishpi_base_url: String,
+    storage: Arc<dyn StorageBackend>,
+    /// 可用的登录方式，第一个为摸鱼派
+    providers: Vec<Arc<dyn AuthProvider>>,
+    /// 按 state 记录发起中的登录，回调时校验并取回跳转地址
+    pending_logins: DashMap<String, PendingLogin>,
+    /// 最近验证成功的会话对应的用户，Redis 不可用时用于继续识别已登录玩家
+    session_cache: DashMap<Uuid, User>,
+    /// 按客户端IP限制游客身份的创建频率
+    guest_limiter: IpRateLimiter,
+    /// 按客户端IP限制发起登录的频率
+    login_limiter: IpRateLimiter,
 }
 
 impl UserManager {
-    /// 创建新的用户管理器
-    pub fn new(storage: Storage) -> Self {
+    /// 创建新的用户管理器，登录方式按配置注册
+    pub fn new(storage: Arc<dyn StorageBackend>) -> Self {
+        let mut providers: Vec<Arc<dyn AuthProvider>> = vec![Arc::new(FishpiProvider::new())];
+        for config in &crate::config::Config::get().auth.providers {
+            let valid_id = !config.id.is_empty()
+                && config
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+            let reserved = config.id == FISHPI_PROVIDER_ID || config.id == "guest" || config.id == "bot";
+            if !valid_id || reserved || providers.iter().any(|provider| provider.id() == config.id) {
+                warn!("忽略无效或重复的登录方式: {}", config.id);
+                continue;
+            }
+            providers.push(Arc::new(OAuth2Provider::new(config.clone())));
+        }
+
+        let auth = &crate::config::Config::get().auth;
         UserManager {
             storage,
-            fishpi_base_url: "https://fishpi.cn".to_string(),
+            providers,
+            pending_logins: DashMap::new(),
+            session_cache: DashMap::new(),
+            guest_limiter: IpRateLimiter::new(
+                std::time::Duration::from_secs(auth.guest_rate_limit_window),
+                auth.guest_rate_limit_max,
+            ),
+            login_limiter: IpRateLimiter::new(
+                std::time::Duration::from_secs(auth.login_rate_limit_window),
+                auth.login_rate_limit_max,
+            ),
         }
     }
 
-    /// 生成摸鱼派登录URL
-    pub fn generate_login_url(&self, return_url: &str, realm: &str) -> Result<String> {
-        let mut url = Url::parse(&format!("{}/openid/login", self.fishpi_base_url))
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        // 使用url crate的query_pairs_mut来确保正确的URL编码
-        let mut query_pairs = url.query_pairs_mut();
-
-        // 按照文档要求的顺序添加参数，确保正确编码
-        query_pairs.append_pair("openid.ns", "http://specs.openid.net/auth/2.0");
-        query_pairs.append_pair("openid.mode", "checkid_setup");
-        query_pairs.append_pair("openid.return_to", return_url);
-        query_pairs.append_pair("openid.realm", realm);
-        query_pairs.append_pair(
-            "openid.claimed_id",
-            "http://specs.openid.net/auth/2.0/identifier_select",
-        );
-        query_pairs.append_pair(
-            "openid.identity",
-            "http://specs.openid.net/auth/2.0/identifier_select",
-        );
-
-        // 释放query_pairs以完成URL构建
-        drop(query_pairs);
-
-        debug!("生成的登录URL: {}", url);
-
-        Ok(url.to_string())
+    /// 可用的登录方式
+    pub fn providers(&self) -> &[Arc<dyn AuthProvider>] {
+        &self.providers
     }
 
-    /// 验证摸鱼派OpenID响应
-    pub async fn verify_openid_response(
-        &self,
-        openid_params: &HashMap<String, String>,
-    ) -> Result<String> {
-        // 检查是否是成功的响应
-        if let Some(mode) = openid_params.get("openid.mode") {
-            if mode == "id_res" {
-                // 检查response_nonce的有效期
-                if let Some(response_nonce) = openid_params.get("openid.response_nonce") {
-                    if !self.is_response_nonce_valid(response_nonce)? {
-                        return Err(crate::Error::Auth("response_nonce已过期或无效".to_string()));
-                    }
-                } else {
-                    return Err(crate::Error::Auth("缺少response_nonce参数".to_string()));
-                }
-
-                // 进行签名校验
-                self.verify_signature(openid_params).await?;
-
-                // 提取用户ID
-                if let Some(claimed_id) = openid_params.get("openid.claimed_id") {
-                    // /openid/id/123456
-                    if let Some(user_id) = claimed_id.split('/').last() {
-                        return Ok(user_id.to_string());
-                    } else {
-                        return Err(crate::Error::Auth(
-                            "无法从claimed_id中提取用户ID".to_string(),
-                        ));
-                    }
-                } else {
-                    return Err(crate::Error::Auth("缺少openid.claimed_id参数".to_string()));
-                }
-            } else {
-                return Err(crate::Error::Auth("无效的OpenID模式".to_string()));
-            }
-        } else {
-            return Err(crate::Error::Auth("缺少openid.mode参数".to_string()));
-        }
+    fn provider(&self, provider_id: &str) -> Result<&Arc<dyn AuthProvider>> {
+        self.providers
+            .iter()
+            .find(|provider| provider.id() == provider_id)
+            .ok_or_else(|| crate::Error::Auth(format!("未知的登录方式: {}", provider_id)))
     }
 
-    /// 检查response_nonce是否有效
-    fn is_response_nonce_valid(&self, response_nonce: &str) -> Result<bool> {
-        // response_nonce格式: 2025-06-19T03:52:20Z8241ed4a70
-        if let Some(timestamp_str) = response_nonce.split('Z').next() {
-            if let Ok(timestamp) = DateTime::parse_from_rfc3339(&format!("{}Z", timestamp_str)) {
-                let now = Utc::now();
-                let diff = now.signed_duration_since(timestamp.naive_utc().and_utc());
-
-                // 检查是否在5分钟内
-                if diff.num_minutes() <= 5 {
-                    debug!("response_nonce时间有效，距离现在{}分钟", diff.num_minutes());
-                    return Ok(true);
-                } else {
-                    debug!("response_nonce已过期，距离现在{}分钟", diff.num_minutes());
-                    return Ok(false);
-                }
-            }
-        }
-
-        debug!("无法解析response_nonce时间戳: {}", response_nonce);
-        Ok(false)
+    /// 登录方式的回调地址，需要在外部登录方式中登记
+    pub fn redirect_uri(provider_id: &str) -> String {
+        format!("{}/auth/callback/{}", crate::config::Config::get().auth.domain, provider_id)
     }
 
-    /// 验证OpenID签名
-    async fn verify_signature(&self, params: &HashMap<String, String>) -> Result<()> {
-        // 构建验证请求参数
-        let mut verify_params = HashMap::new();
-
-        // 获取openid.signed参数，确定哪些参数被签名了
-        let signed_params = if let Some(signed_str) = params.get("openid.signed") {
-            signed_str.split(',').collect::<Vec<&str>>()
-        } else {
-            return Err(crate::Error::Auth("缺少openid.signed参数".to_string()));
-        };
+    /// 发起登录，返回外部登录页地址；同一IP发起过于频繁或进行中的登录过多时拒绝
+    pub fn begin_login(&self, provider_id: &str, callback_url: Option<String>, client_ip: IpAddr) -> Result<String> {
+        let provider = self.provider(provider_id)?;
+        if !self.login_limiter.check(client_ip) {
+            warn!("客户端 {} 发起登录过于频繁", client_ip);
+            return Err(crate::Error::Auth("登录请求过于频繁，请稍后再试".to_string()));
+        }
+        let now = Utc::now();
+        self.pending_logins
+            .retain(|_, pending| (now - pending.created_at).num_seconds() <= LOGIN_STATE_TTL_SECS);
+        if self.pending_logins.len() >= PENDING_LOGINS_MAX {
+            warn!("进行中的登录已达上限 {}，拒绝新的登录", PENDING_LOGINS_MAX);
+            return Err(crate::Error::Auth("登录请求过多，请稍后再试".to_string()));
+        }
 
-        // 添加所有必需的参数
-        verify_params.insert(
-            "openid.ns".to_string(),
-            "http://specs.openid.net/auth/2.0".to_string(),
-        );
-        verify_params.insert(
-            "openid.mode".to_string(),
-            "check_authentication".to_string(),
+        let state = Uuid::new_v4().simple().to_string();
+        let url = provider.login_url(&Self::redirect_uri(provider_id), &state)?;
+        self.pending_logins.insert(
+            state,
+            PendingLogin {
+                provider: provider_id.to_string(),
+                callback_url,
+                created_at: now,
+            },
         );
+        Ok(url)
+    }
 
-        // 添加所有被签名的参数
-        for (key, value) in params {
-            if key == "openid.signed" || key == "openid.sig" {
-                verify_params.insert(key.clone(), value.clone());
-            } else if key == "openid.mode" {
-                // 跳过，已经设置为check_authentication
-                continue;
-            } else {
-                // 检查这个参数是否在签名列表中
-                let param_name = key.strip_prefix("openid.").unwrap_or(key);
-                if signed_params.contains(&param_name) {
-                    verify_params.insert(key.clone(), value.clone());
-                }
+    /// 完成登录：校验 state 和回调参数，登记用户并创建会话，同时返回发起登录时指定的跳转地址
+    ///
+    /// 摸鱼派的旧版回调地址不带 state，此时跳转地址取自回调参数中的 callback_url。
+    pub async fn complete_login(
+        &self,
+        provider_id: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<(Uuid, User, Option<String>)> {
+        let provider = self.provider(provider_id)?.clone();
+        let callback_url = match params.get("state") {
+            Some(state) => {
+                let (_, pending) = self
+                    .pending_logins
+                    .remove(state)
+                    .filter(|(_, pending)| {
+                        pending.provider == provider_id
+                            && (Utc::now() - pending.created_at).num_seconds() <= LOGIN_STATE_TTL_SECS
+                    })
+                    .ok_or_else(|| crate::Error::Auth("登录请求已过期，请重新登录".to_string()))?;
+                pending.callback_url
             }
-        }
-
-        // 发送验证请求到摸鱼派
-        let verify_url = format!("{}/openid/verify", self.fishpi_base_url);
-
-        // 构建请求体，使用JSON格式
-        let query_string = serde_json::to_string(&verify_params)
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        // 创建HTTP客户端
-        let client = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36")
-            .build()
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        // 发送POST请求
-        let response = client
-            .post(&verify_url)
-            .header("Content-Type", "application/json")
-            .body(query_string)
-            .send()
-            .await
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        if !response.status().is_success() {
-            return Err(crate::Error::Auth(format!(
-                "验证请求失败，状态码: {}",
-                response.status()
-            )));
-        }
+            None if provider_id == FISHPI_PROVIDER_ID => params.get("callback_url").cloned(),
+            None => return Err(crate::Error::Auth("缺少state参数".to_string())),
+        };
 
-        let response_text = response
-            .text()
-            .await
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
+        let identity = provider
+            .verify_callback(params, &Self::redirect_uri(provider_id))
+            .await?;
+        let user = self.save_identity(provider.as_ref(), identity).await?;
+        let session_id = self.create_session(&user.id).await?;
 
-        // 解析响应
-        let lines: Vec<&str> = response_text.lines().collect();
-        let mut is_valid = false;
+        Ok((session_id, user, callback_url))
+    }
 
-        for line in lines {
-            if line.starts_with("is_valid:") {
-                let valid_str = line.split(':').nth(1).unwrap_or("false").trim();
-                is_valid = valid_str == "true";
-                break;
+    /// 将外部身份登记为本地用户，保留首次登录时间；启用头像代理且头像地址允许代理时改写头像地址
+    async fn save_identity(&self, provider: &dyn AuthProvider, identity: ExternalIdentity) -> Result<User> {
+        let id = provider.local_user_id(&identity.external_id);
+        let now = Utc::now();
+        let previous = self.storage.get_user(&id).await.ok().flatten();
+        let avatar = match &identity.avatar {
+            Some(url) if crate::config::Config::get().avatar.enabled && crate::avatar::is_allowed(url) => {
+                Some(crate::avatar::proxied_url(&id))
             }
-        }
+            avatar => avatar.clone(),
+        };
+        let user = User {
+            id,
+            username: provider.local_username(&identity.username),
+            nickname: identity.nickname,
+            avatar,
+            avatar_source: identity.avatar,
+            created_at: previous.as_ref().map_or(now, |user| user.created_at),
+            last_login: now,
+        };
 
-        if is_valid {
-            Ok(())
-        } else {
-            Err(crate::Error::Auth("签名验证失败".to_string()))
+        // 头像地址变化后丢弃旧的头像缓存
+        if previous.is_some_and(|previous| previous.avatar_origin() != user.avatar_origin())
+            && let Err(e) = self.storage.delete_avatar(&user.id).await
+        {
+            warn!("清除用户 {} 的头像缓存失败: {}", user.id, e);
         }
+
+        // 保存到Storage缓存
+        self.storage.save_user(&user).await?;
+        debug!("用户信息已保存到缓存: {}", user.id);
+        Ok(user)
     }
 
-    /// 获取用户信息（从Storage或API）
+    /// 获取用户信息（从Storage或登录方式）
     pub async fn get_user_info(&self, user_id: &str) -> Result<User> {
         // 先从Storage获取缓存
         if let Some(user) = self.storage.get_user(user_id).await? {
@@ -255,69 +246,40 @@ impl UserManager {
             return Ok(user);
         }
 
-        // 缓存中没有，从摸鱼派API获取用户信息
-        let url = format!(
-            "{}/api/user/getInfoById?userId={}",
-            self.fishpi_base_url, user_id
-        );
-
-        // 创建新的HTTP客户端
-        let client = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/137.0.0.0 Safari/537.36")
-            .build()
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        let response = client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        let status = response.status();
-        let response_text = response
-            .text()
-            .await
-            .map_err(|e| crate::Error::Network(anyhow::anyhow!(e)))?;
-
-        if status.is_success() {
-            let fishpi_user: FishpiUserInfoResponse = serde_json::from_str(&response_text)
-                .map_err(|e| {
-                    println!("JSON解析错误: {}", e);
-                    crate::Error::Network(anyhow::anyhow!(e))
-                })?;
-
-            let user = User {
-                id: user_id.to_string(),
-                username: fishpi_user.data.user_name.clone(),
-                nickname: fishpi_user.data.user_nickname
-                    .filter(|nickname| !nickname.trim().is_empty())
-                    .or(Some(fishpi_user.data.user_name.clone())),
-                avatar: fishpi_user.data.user_avatar_url,
-                created_at: Utc::now(),
-                last_login: Utc::now(),
-            };
-
-            // 保存到Storage缓存
-            self.storage.save_user(&user).await?;
-            debug!("用户信息已保存到缓存: {}", user_id);
-
-            Ok(user)
-        } else {
-            Err(crate::Error::Auth("获取用户信息失败".to_string()))
+        // 游客信息只保存在本地，过期后无法从摸鱼派找回
+        if is_guest_id(user_id) {
+            return Err(crate::Error::Auth("游客身份已过期，请重新进入".to_string()));
         }
+
+        // 缓存中没有，向用户所属的登录方式重新获取；带前缀的ID属于其他登录方式，否则为摸鱼派用户
+        let (provider, external_id) = match user_id.split_once(':') {
+            Some((provider_id, external_id)) => (self.provider(provider_id)?, external_id),
+            None => (self.provider(FISHPI_PROVIDER_ID)?, user_id),
+        };
+        let identity = provider
+            .fetch_identity(external_id)
+            .await?
+            .ok_or_else(|| crate::Error::Auth("用户信息已过期，请重新登录".to_string()))?;
+        self.save_identity(provider.as_ref(), identity).await
     }
 
     /// 创建用户会话
     pub async fn create_session(&self, user_id: &str) -> Result<Uuid> {
+        // 30天过期
+        self.create_session_until(user_id, Utc::now() + chrono::Duration::days(30)).await
+    }
+
+    /// 创建在指定时间过期的用户会话
+    async fn create_session_until(&self, user_id: &str, expires_at: DateTime<Utc>) -> Result<Uuid> {
         let session_id = Uuid::new_v4();
         let now = Utc::now();
-        let expires_at = now + chrono::Duration::days(30); // 30天过期
 
         let session = UserSession {
             session_id,
             user_id: user_id.to_string(),
             created_at: now,
             expires_at,
+            last_used_at: now,
         };
 
         // 保存会话到Storage
@@ -344,15 +306,44 @@ impl UserManager {
     }
 
     /// 获取会话对应的用户
+    ///
+    /// 存储不可用时回退到最近一次验证成功的缓存，已登录的玩家仍可加入房间
     pub async fn get_user_by_session(&self, session_id: &Uuid) -> Result<User> {
+        match self.load_user_by_session(session_id).await {
+            Ok(user) => {
+                self.session_cache.insert(*session_id, user.clone());
+                Ok(user)
+            }
+            Err(e @ crate::Error::Auth(_)) => {
+                self.session_cache.remove(session_id);
+                Err(e)
+            }
+            Err(e) => match self.session_cache.get(session_id) {
+                Some(user) => {
+                    warn!("读取会话 {} 失败，使用缓存的用户信息: {}", session_id, e);
+                    Ok(user.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    async fn load_user_by_session(&self, session_id: &Uuid) -> Result<User> {
         // 先验证会话并获取用户ID
         let user_id = {
             let session = self.validate_session(session_id).await?;
             session.user_id.clone()
         };
 
-        // 验证成功，延长会话有效期
-        self.extend_session(session_id).await?;
+        // 游客身份有固定的有效期，不随使用延长
+        if is_guest_id(&user_id) {
+            if !crate::config::Config::get().auth.guest_enabled {
+                return Err(crate::Error::Auth("游客模式已关闭，请登录摸鱼派账号".to_string()));
+            }
+        } else {
+            // 验证成功，延长会话有效期
+            self.extend_session(session_id).await?;
+        }
 
         // 获取用户信息
         self.get_user_info(&user_id).await
@@ -360,11 +351,35 @@ impl UserManager {
 
     /// 删除会话
     pub async fn remove_session(&self, session_id: &Uuid) -> Result<()> {
+        self.session_cache.remove(session_id);
         self.storage.delete_session(session_id).await?;
         debug!("删除用户会话: {}", session_id);
         Ok(())
     }
 
+    /// 列出用户当前有效的会话
+    pub async fn list_sessions(&self, user_id: &str) -> Result<Vec<UserSession>> {
+        Ok(self.storage.list_user_sessions(user_id).await?)
+    }
+
+    /// 删除用户的所有会话，在所有设备上退出登录，返回删除的会话数
+    pub async fn remove_all_sessions(&self, user_id: &str) -> Result<usize> {
+        self.remove_sessions_except(user_id, None).await
+    }
+
+    /// 删除用户除指定会话外的所有会话，在其他设备上退出登录，返回删除的会话数
+    pub async fn remove_sessions_except(&self, user_id: &str, keep: Option<&Uuid>) -> Result<usize> {
+        let sessions = self.storage.list_user_sessions(user_id).await?;
+        let mut removed = 0;
+        for session in sessions.iter().filter(|session| Some(&session.session_id) != keep) {
+            self.session_cache.remove(&session.session_id);
+            self.storage.delete_session(&session.session_id).await?;
+            removed += 1;
+        }
+        debug!("删除用户 {} 的 {} 个会话", user_id, removed);
+        Ok(removed)
+    }
+
     /// 延长会话有效期
     pub async fn extend_session(&self, session_id: &Uuid) -> Result<()> {
         let new_expires_at = Utc::now() + chrono::Duration::days(5); // 延长30天
@@ -373,19 +388,29 @@ impl UserManager {
         Ok(())
     }
 
-    /// 处理登录流程
-    pub async fn handle_login(
-        &self,
-        openid_params: &HashMap<String, String>,
-    ) -> Result<(Uuid, User)> {
-        // 验证OpenID响应
-        let user_id = self.verify_openid_response(openid_params).await?;
-
-        // 获取用户信息
-        let user = self.get_user_info(&user_id).await?;
+    /// 创建游客身份和会话，游客信息与会话同时过期；同一IP创建过于频繁时拒绝
+    pub async fn create_guest(&self, nickname: &str, client_ip: IpAddr) -> Result<(Uuid, User)> {
+        if !self.guest_limiter.check(client_ip) {
+            warn!("客户端 {} 创建游客身份过于频繁", client_ip);
+            return Err(crate::Error::Auth("创建游客身份过于频繁，请稍后再试".to_string()));
+        }
+        let hours = crate::config::Config::get().auth.guest_session_hours.max(1);
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::hours(hours as i64);
+        let id = format!("{}{}", GUEST_ID_PREFIX, &Uuid::new_v4().simple().to_string()[..12]);
+        let user = User {
+            id: id.clone(),
+            username: id.clone(),
+            nickname: Some(nickname.to_string()),
+            avatar: None,
+            avatar_source: None,
+            created_at: now,
+            last_login: now,
+        };
 
-        // 创建会话
-        let session_id = self.create_session(&user_id).await?;
+        self.storage.save_guest_user(&user, hours * 3600).await?;
+        let session_id = self.create_session_until(&id, expires_at).await?;
+        debug!("创建游客身份: {} ({})", nickname, id);
 
         Ok((session_id, user))
     }
@@ -393,9 +418,8 @@ impl UserManager {
 
 impl Default for UserManager {
     fn default() -> Self {
-        // 这里需要传入Storage实例，但在Default实现中无法创建
-        // 移除Default实现，或者使用Option<Storage>
-        panic!("UserManager需要Storage实例，不能使用Default实现")
+        // 这里需要传入存储后端，但在Default实现中无法创建
+        panic!("UserManager需要存储后端，不能使用Default实现")
     }
 }
 
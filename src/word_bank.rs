@@ -1,8 +1,12 @@
 use crate::Result;
+use crate::storage::StorageBackend;
 use anyhow::Context;
+use chrono::{DateTime, Utc};
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tracing::warn;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WordPair {
@@ -12,6 +16,16 @@ pub struct WordPair {
     pub difficulty: Difficulty,
 }
 
+impl WordPair {
+    /// 平民词与卧底词的字数差
+    pub fn length_difference(&self) -> usize {
+        self.civilian_word
+            .chars()
+            .count()
+            .abs_diff(self.undercover_word.chars().count())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Difficulty {
     #[serde(rename = "easy")]
@@ -22,14 +36,17 @@ pub enum Difficulty {
     Hard,
 }
 
-impl Difficulty {
-    pub fn from_str(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
+impl std::str::FromStr for Difficulty {
+    type Err = std::convert::Infallible;
+
+    /// 解析难度，无法识别时默认为中等
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
             "easy" => Difficulty::Easy,
             "medium" => Difficulty::Medium,
             "hard" => Difficulty::Hard,
             _ => Difficulty::Medium,
-        }
+        })
     }
 }
 
@@ -47,19 +64,30 @@ pub struct WordBankData {
 #[derive(Debug, Clone)]
 pub struct WordBank {
     categories: HashMap<String, Vec<WordPair>>,
-    all_words: Vec<WordPair>,
+    /// 所有词对及其所属分类
+    all_words: Vec<(String, WordPair)>,
     config: crate::config::WordBankConfig,
+    /// 全服最近抽取过的分类，按时间先后排列，用于降低热门分类的抽取权重
+    recent_usage: Arc<Mutex<CategoryUsage>>,
 }
 
+/// 分类抽取记录：(抽取时间, 分类名)
+type CategoryUsage = VecDeque<(DateTime<Utc>, String)>;
+
 impl WordBank {
-    pub fn new() -> Self {
-        let config = crate::config::Config::get().word_bank.clone();
-        let file_path = config.file_path.clone();
-        let mut word_bank = WordBank {
+    /// 按配置创建空词库
+    fn empty() -> Self {
+        WordBank {
             categories: HashMap::new(),
             all_words: Vec::new(),
-            config,
-        };
+            config: crate::config::Config::get().word_bank.clone(),
+            recent_usage: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn new() -> Self {
+        let mut word_bank = WordBank::empty();
+        let file_path = word_bank.config.file_path.clone();
 
         // 尝试从文件加载，如果失败则使用默认词库
         if let Err(e) = word_bank.load_from_file(&file_path) {
@@ -70,6 +98,48 @@ impl WordBank {
         word_bank
     }
 
+    /// 加载词库并与存储后端同步：词库文件可用时以文件为准并在存储后端保存一份，
+    /// 文件不可用时使用存储后端保存的词库，都没有时使用默认词库
+    pub async fn load(storage: &dyn StorageBackend) -> Self {
+        let mut word_bank = WordBank::empty();
+        let file_path = word_bank.config.file_path.clone();
+
+        let file_error = match word_bank.load_from_file(&file_path) {
+            Ok(()) => {
+                if let Err(e) = storage.save_word_bank(&word_bank.data()).await {
+                    warn!("保存词库到存储后端失败: {}", e);
+                }
+                return word_bank;
+            }
+            Err(e) => e,
+        };
+
+        match storage.load_word_bank().await {
+            Ok(Some(data)) => {
+                warn!("无法加载词库文件: {}, 使用存储后端保存的词库", file_error);
+                word_bank.categories = data.categories;
+                word_bank.update_all_words();
+            }
+            Ok(None) => {
+                eprintln!("无法加载词库文件: {}, 使用默认词库", file_error);
+                word_bank.load_default_words();
+            }
+            Err(e) => {
+                warn!("无法加载词库文件: {}, 读取存储后端的词库也失败: {}, 使用默认词库", file_error, e);
+                word_bank.load_default_words();
+            }
+        }
+
+        word_bank
+    }
+
+    /// 当前词库内容
+    pub fn data(&self) -> WordBankData {
+        WordBankData {
+            categories: self.categories.clone(),
+        }
+    }
+
     /// 从文件加载词库
     pub fn load_from_file(&mut self, path: &str) -> Result<()> {
         let content =
@@ -86,11 +156,7 @@ impl WordBank {
 
     /// 保存词库到文件
     pub fn save_to_file(&self, path: &str) -> Result<()> {
-        let data = WordBankData {
-            categories: self.categories.clone(),
-        };
-
-        let content = serde_json::to_string_pretty(&data).with_context(|| "无法序列化词库")?;
+        let content = serde_json::to_string_pretty(&self.data()).with_context(|| "无法序列化词库")?;
 
         std::fs::write(path, content).with_context(|| format!("无法写入词库文件: {}", path))?;
 
@@ -145,49 +211,138 @@ impl WordBank {
     /// 更新所有词语列表
     fn update_all_words(&mut self) {
         self.all_words.clear();
-        for words in self.categories.values() {
-            self.all_words.extend(words.clone());
+        for (category, words) in &self.categories {
+            self.all_words
+                .extend(words.iter().map(|word| (category.clone(), word.clone())));
         }
     }
 
-    /// 获取随机词对
-    pub fn get_random_word_pair(&self) -> Option<&WordPair> {
+    /// 记录一次分类抽取
+    fn record_usage(&self, category: &str) {
+        let mut usage = self.recent_usage.lock().unwrap_or_else(|e| e.into_inner());
+        usage.push_back((Utc::now(), category.to_string()));
+        self.prune_usage(&mut usage);
+    }
+
+    /// 清理统计窗口之外的抽取记录
+    fn prune_usage(&self, usage: &mut CategoryUsage) {
+        let cutoff = Utc::now() - chrono::Duration::hours(self.config.usage_window_hours as i64);
+        while usage.front().is_some_and(|(at, _)| *at < cutoff) {
+            usage.pop_front();
+        }
+    }
+
+    /// 统计窗口内各分类被抽取的次数
+    pub fn get_recent_usage(&self) -> HashMap<String, usize> {
+        let mut usage = self.recent_usage.lock().unwrap_or_else(|e| e.into_inner());
+        self.prune_usage(&mut usage);
+
+        let mut counts = HashMap::new();
+        for (_, category) in usage.iter() {
+            *counts.entry(category.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// 各分类当前的词对抽取权重，最近被抽取得越多权重越低
+    pub fn get_category_weights(&self) -> HashMap<String, f64> {
+        let usage = self.get_recent_usage();
+        self.categories
+            .keys()
+            .map(|category| {
+                let uses = usage.get(category).copied().unwrap_or(0) as f64;
+                (category.clone(), 1.0 / (1.0 + self.config.usage_penalty * uses))
+            })
+            .collect()
+    }
+
+    /// 按分类权重从候选词对中抽取一个并记录分类使用
+    fn choose_weighted<'a>(&self, candidates: Vec<&'a (String, WordPair)>) -> Option<&'a WordPair> {
+        let weights = self.get_category_weights();
         let mut rng = rand::rng();
-        self.all_words.choose(&mut rng)
+        let (category, pair) = *candidates
+            .choose_weighted(&mut rng, |(category, _)| {
+                weights.get(category).copied().unwrap_or(1.0)
+            })
+            .ok()?;
+        self.record_usage(category);
+        Some(pair)
+    }
+
+    /// 获取随机词对，最近全服频繁抽到的分类会被降低权重
+    pub fn get_random_word_pair(&self) -> Option<&WordPair> {
+        self.choose_weighted(self.all_words.iter().collect())
     }
 
     /// 根据相似度获取词对
     pub fn get_word_pair_by_similarity(&self, min_similarity: f32) -> Option<&WordPair> {
-        let mut rng = rand::rng();
-        self.all_words
-            .iter()
-            .filter(|pair| pair.similarity >= min_similarity)
-            .collect::<Vec<_>>()
-            .choose(&mut rng)
-            .copied()
+        self.choose_weighted(
+            self.all_words
+                .iter()
+                .filter(|(_, pair)| pair.similarity >= min_similarity)
+                .collect(),
+        )
+    }
+
+    /// 获取字数差不超过指定值的词对，避免玩家通过词语长度推测身份
+    pub fn get_word_pair_by_length_diff(&self, max_diff: usize) -> Option<&WordPair> {
+        self.choose_weighted(
+            self.all_words
+                .iter()
+                .filter(|(_, pair)| pair.length_difference() <= max_diff)
+                .collect(),
+        )
     }
 
     /// 根据难度获取词对
     pub fn get_word_pair_by_difficulty(&self, difficulty: Difficulty) -> Option<&WordPair> {
-        let mut rng = rand::rng();
-        self.all_words
-            .iter()
-            .filter(|pair| pair.difficulty == difficulty)
-            .collect::<Vec<_>>()
-            .choose(&mut rng)
-            .copied()
+        self.choose_weighted(
+            self.all_words
+                .iter()
+                .filter(|(_, pair)| pair.difficulty == difficulty)
+                .collect(),
+        )
+    }
+
+    /// 获取同时满足分类、难度和字数差条件的词对，未设置的条件不限制
+    pub fn get_word_pair_matching(
+        &self,
+        category: Option<&str>,
+        difficulty: Option<&Difficulty>,
+        max_length_diff: Option<usize>,
+    ) -> Option<&WordPair> {
+        self.choose_weighted(
+            self.all_words
+                .iter()
+                .filter(|(pair_category, pair)| {
+                    category.is_none_or(|category| pair_category == category)
+                        && difficulty.is_none_or(|difficulty| pair.difficulty == *difficulty)
+                        && max_length_diff.is_none_or(|max_diff| pair.length_difference() <= max_diff)
+                })
+                .collect(),
+        )
     }
 
     /// 从指定分类获取词对
     pub fn get_word_pair_from_category(&self, category: &str) -> Option<&WordPair> {
         if let Some(words) = self.categories.get(category) {
             let mut rng = rand::rng();
-            words.choose(&mut rng)
+            let pair = words.choose(&mut rng)?;
+            self.record_usage(category);
+            Some(pair)
         } else {
             None
         }
     }
 
+    /// 查找词语所属的分类（平民词或卧底词均可）
+    pub fn category_of(&self, word: &str) -> Option<&str> {
+        self.all_words
+            .iter()
+            .find(|(_, pair)| pair.civilian_word == word || pair.undercover_word == word)
+            .map(|(category, _)| category.as_str())
+    }
+
     /// 获取所有分类
     pub fn get_categories(&self) -> Vec<&String> {
         self.categories.keys().collect()
@@ -205,7 +360,7 @@ impl WordBank {
     pub fn add_word_pair(&mut self, category: &str, word_pair: WordPair) {
         self.categories
             .entry(category.to_string())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(word_pair);
         self.update_all_words();
     }
@@ -229,7 +384,7 @@ impl WordBank {
         let total_categories = self.categories.len();
 
         let mut difficulty_stats = HashMap::new();
-        for word in &self.all_words {
+        for (_, word) in &self.all_words {
             *difficulty_stats.entry(word.difficulty.clone()).or_insert(0) += 1;
         }
 
@@ -243,6 +398,8 @@ impl WordBank {
             total_categories,
             difficulty_stats,
             category_stats,
+            recent_usage: self.get_recent_usage(),
+            category_weights: self.get_category_weights(),
         }
     }
 
@@ -290,6 +447,10 @@ pub struct WordBankStats {
     pub total_categories: usize,
     pub difficulty_stats: HashMap<Difficulty, usize>,
     pub category_stats: HashMap<String, usize>,
+    /// 统计窗口内各分类被抽取的次数
+    pub recent_usage: HashMap<String, usize>,
+    /// 各分类当前的抽取权重
+    pub category_weights: HashMap<String, f64>,
 }
 
 impl Default for WordBank {